@@ -19,17 +19,22 @@
 use anyhow::Result;
 use capsule::packets::ip::v4::Ipv4;
 use capsule::packets::ip::v6::{Ipv6, SegmentRouting};
-use capsule::packets::{Ethernet, Packet, Udp4};
+use capsule::packets::{parse_batch, Ethernet, Packet, Udp4};
+use capsule::testils::byte_arrays::IPV4_UDP_PACKET;
 use capsule::testils::criterion::BencherExt;
 use capsule::testils::proptest::*;
 use capsule::testils::{PacketExt, Rvg};
 use capsule::{fieldmap, Mbuf};
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
 use proptest::prelude::*;
 use std::net::Ipv6Addr;
 
 const BATCH_SIZE: usize = 500;
 
+// the size of a receive burst, and how many bursts fit in the mempool.
+const BURST_SIZE: usize = 32;
+const BURSTS: u64 = 15;
+
 fn single_parse_udp(ipv4: Ipv4) -> Udp4 {
     ipv4.parse::<Udp4>().unwrap()
 }
@@ -92,6 +97,47 @@ fn multi_peek_vs_parse(c: &mut Criterion) {
     group.finish()
 }
 
+fn udp_burst() -> Vec<Mbuf> {
+    (0..BURST_SIZE)
+        .map(|_| Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap())
+        .collect()
+}
+
+fn parse_udp(mbuf: Mbuf) -> Result<Udp4> {
+    mbuf.parse::<Ethernet>()?.parse::<Ipv4>()?.parse::<Udp4>()
+}
+
+fn naive_parse_udp_burst(mbufs: Vec<Mbuf>) -> Vec<Result<Udp4>> {
+    mbufs.into_iter().map(parse_udp).collect()
+}
+
+fn batch_parse_udp_burst(mbufs: Vec<Mbuf>) -> Vec<Result<Udp4>> {
+    parse_batch(mbufs, parse_udp)
+}
+
+#[capsule::bench(mempool_capacity = 511)]
+fn batch_vs_naive_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("packets::batch_vs_naive_parse_on_udp_burst");
+
+    group.bench_function("packets::naive_parse_udp_burst", |b| {
+        b.iter_batched(
+            udp_burst,
+            naive_parse_udp_burst,
+            BatchSize::NumIterations(BURSTS),
+        )
+    });
+
+    group.bench_function("packets::batch_parse_udp_burst", |b| {
+        b.iter_batched(
+            udp_burst,
+            batch_parse_udp_burst,
+            BatchSize::NumIterations(BURSTS),
+        )
+    });
+
+    group.finish()
+}
+
 fn single_parse_srh(ipv6: Ipv6) -> SegmentRouting<Ipv6> {
     ipv6.parse::<SegmentRouting<Ipv6>>().unwrap()
 }
@@ -274,6 +320,7 @@ criterion_group! {
     config=bench_config();
     targets=single_peek_vs_parse,
             multi_peek_vs_parse,
+            batch_vs_naive_parse,
             single_parse_srh_segments_sizes,
             multi_parse_upto_variable_srh,
             set_srh_segments_sizes,
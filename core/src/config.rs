@@ -46,7 +46,7 @@
 //! [`pktdump`]: https://github.com/capsule-rs/capsule/tree/master/examples/pktdump
 
 use crate::dpdk::CoreId;
-use crate::net::{Ipv4Cidr, Ipv6Cidr, MacAddr};
+use crate::net::{Ipv4Cidr, Ipv6Cidr};
 use anyhow::Result;
 use clap::{clap_app, crate_version};
 use regex::Regex;
@@ -67,17 +67,6 @@ impl<'de> Deserialize<'de> for CoreId {
     }
 }
 
-// make `MacAddr` serde deserializable.
-impl<'de> Deserialize<'de> for MacAddr {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let s = String::deserialize(deserializer)?;
-        MacAddr::from_str(&s).map_err(de::Error::custom)
-    }
-}
-
 // make `Ipv4Cidr` serde deserializable.
 impl<'de> Deserialize<'de> for Ipv4Cidr {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
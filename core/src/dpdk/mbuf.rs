@@ -19,10 +19,19 @@
 use super::MEMPOOL;
 use crate::dpdk::{DpdkError, MempoolError};
 use crate::ffi::{self, ToResult};
-use crate::packets::{Internal, Packet};
-use crate::{ensure, trace};
+use crate::packets::ip::extract_flow;
+use crate::packets::{EtherTypes, Ethernet, Internal, Packet, PacketError};
+use crate::pcap::{LinkType, LinkTypes};
+use crate::{ensure, rss, trace};
 use anyhow::Result;
+use capsule_headers::ETH_HEADER_SIZE;
+use std::any::TypeId;
+#[cfg(target_arch = "x86")]
+use std::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
 use std::fmt;
+use std::marker::PhantomData;
 use std::mem;
 use std::os::raw;
 use std::ptr::{self, NonNull};
@@ -47,40 +56,65 @@ use thiserror::Error;
 /// }
 /// ```
 pub trait SizeOf {
+    /// The size of the type in bytes.
+    ///
+    /// Unlike [`size_of`], the constant can be used in const contexts, for
+    /// example to size a stack-allocated buffer.
+    ///
+    /// [`size_of`]: SizeOf::size_of
+    const SIZE: usize;
+
     /// Returns the size of a type in bytes.
-    fn size_of() -> usize;
+    #[inline]
+    fn size_of() -> usize {
+        Self::SIZE
+    }
 }
 
 impl SizeOf for () {
-    fn size_of() -> usize {
-        std::mem::size_of::<()>()
-    }
+    const SIZE: usize = std::mem::size_of::<()>();
 }
 
 impl SizeOf for u8 {
-    fn size_of() -> usize {
-        std::mem::size_of::<u8>()
-    }
+    const SIZE: usize = std::mem::size_of::<u8>();
 }
 
 impl SizeOf for [u8; 2] {
-    fn size_of() -> usize {
-        std::mem::size_of::<[u8; 2]>()
-    }
+    const SIZE: usize = std::mem::size_of::<[u8; 2]>();
 }
 
 impl SizeOf for [u8; 16] {
-    fn size_of() -> usize {
-        std::mem::size_of::<[u8; 16]>()
-    }
+    const SIZE: usize = std::mem::size_of::<[u8; 16]>();
 }
 
 impl SizeOf for ::std::net::Ipv6Addr {
-    fn size_of() -> usize {
-        std::mem::size_of::<std::net::Ipv6Addr>()
-    }
+    const SIZE: usize = std::mem::size_of::<std::net::Ipv6Addr>();
 }
 
+/// Size of the private area reserved after each `Mbuf` for the packet
+/// metadata. The size must be a multiple of `RTE_MBUF_PRIV_ALIGN`.
+pub(crate) const MBUF_PRIV_SIZE: u16 = 64;
+
+/// The largest capacity of a message buffer created from a byte array,
+/// the length of the largest IP packet. A larger buffer is a chain of
+/// segments.
+pub const MAX_MBUF_CAPACITY: usize = 65_535;
+
+/// Offload flag of an indirect buffer attached to the data of another
+/// buffer.
+const IND_ATTACHED_MBUF: u64 = 1 << 62;
+
+/// Offload flag of a buffer attached to an external data buffer.
+const EXT_ATTACHED_MBUF: u64 = 1 << 61;
+
+/// Type tag stored at the beginning of the private area, identifying the
+/// type of the metadata stored after it.
+type MetadataTag = Option<TypeId>;
+
+/// Flow hash cached in the private area of the buffer.
+#[derive(Clone, Copy)]
+struct FlowHash(u32);
+
 /// Error indicating buffer access failures.
 #[derive(Debug, Error)]
 pub(crate) enum BufferError {
@@ -95,15 +129,28 @@ pub(crate) enum BufferError {
     /// The struct size exceeds the remaining buffer length.
     #[error("Struct size {0} exceeds the remaining buffer length {1}.")]
     OutOfBuffer(usize, usize),
+
+    /// The struct spans across the segments of a chained buffer.
+    #[error("Struct at offset {0} spans across buffer segments. Linearize the buffer first.")]
+    NotContiguous(usize),
+
+    /// The metadata size exceeds the private area of the buffer.
+    #[error("Metadata size {0} exceeds the private area size {1}.")]
+    MetadataTooLarge(usize, usize),
 }
 
 /// A DPDK message buffer that carries the network packet.
 ///
 /// # Remarks
 ///
-/// Multi-segment Mbuf is not supported. It's the application's responsibilty
-/// to ensure that the ethernet device's MTU is less than the default size
-/// of a single Mbuf segment (`RTE_MBUF_DEFAULT_DATAROOM` = 2048).
+/// Multi-segment Mbuf has limited support. Packet data is only read from
+/// and written to the first segment. A multi-segment Mbuf should be
+/// collapsed into a single segment with [`linearize`] before parsing. It's
+/// the application's responsibilty to ensure that the ethernet device's MTU
+/// is less than the default size of a single Mbuf segment
+/// (`RTE_MBUF_DEFAULT_DATAROOM` = 2048).
+///
+/// [`linearize`]: Mbuf::linearize
 pub struct Mbuf {
     inner: MbufInner,
 }
@@ -151,32 +198,127 @@ impl Mbuf {
         let raw =
             unsafe { ffi::_rte_pktmbuf_alloc(mempool).into_result(|_| MempoolError::Exhausted)? };
 
-        Ok(Mbuf {
+        let mut mbuf = Mbuf {
             inner: MbufInner::Original(raw),
-        })
+        };
+        mbuf.clear_metadata();
+        Ok(mbuf)
     }
 
     /// Creates a new message buffer from a byte array.
     ///
+    /// A byte array larger than a single segment is copied into a chain of
+    /// segments.
+    ///
     /// # Errors
     ///
-    /// Returns `MempoolError::Exhausted` if the allocation of mbuf fails.
-    /// Returns `BufferError::NotResized` if the byte array is larger than
-    /// the maximum mbuf size.
+    /// Returns `PacketError::Empty` if the byte array is empty.
+    /// Returns `PacketError::AllocFailed` if the allocation of mbuf fails.
+    /// Returns `PacketError::TooLarge` if the byte array is larger than
+    /// [`MAX_MBUF_CAPACITY`].
     #[inline]
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
-        let mut mbuf = Mbuf::new()?;
-        mbuf.extend(0, data.len())?;
-        mbuf.write_data_slice(0, data)?;
+        Mbuf::from_bytes_with_capacity(data, data.len())
+    }
+
+    /// Creates a new message buffer from a byte array, with room for at
+    /// least `capacity` bytes of data.
+    ///
+    /// If `capacity` fits in a single segment, the space after the copied
+    /// bytes is left as tailroom, so the buffer can grow up to `capacity`
+    /// bytes with later [`extend`] calls or pushed headers. Otherwise, the
+    /// buffer is a chain of segments with room for `capacity` bytes in
+    /// total, for example for a jumbo frame. The bytes fill the segments
+    /// in order, and the segments past the bytes are left empty. Only the
+    /// tailroom of the first segment can be grown into with [`extend`].
+    ///
+    /// [`extend`]: Mbuf::extend
+    ///
+    /// # Errors
+    ///
+    /// Returns `PacketError::Empty` if the byte array is empty.
+    /// Returns `PacketError::CapacityTooSmall` if `capacity` is smaller
+    /// than the byte array.
+    /// Returns `PacketError::TooLarge` if `capacity` is larger than
+    /// [`MAX_MBUF_CAPACITY`].
+    /// Returns `PacketError::AllocFailed` if the allocation of mbuf fails.
+    #[inline]
+    pub fn from_bytes_with_capacity(data: &[u8], capacity: usize) -> Result<Self> {
+        ensure!(!data.is_empty(), PacketError::Empty);
+        ensure!(
+            data.len() <= capacity,
+            PacketError::CapacityTooSmall {
+                len: data.len(),
+                capacity
+            }
+        );
+        ensure!(
+            capacity <= MAX_MBUF_CAPACITY,
+            PacketError::TooLarge {
+                len: capacity,
+                max: MAX_MBUF_CAPACITY
+            }
+        );
+
+        let mut mbuf = Mbuf::new().map_err(|_| PacketError::AllocFailed)?;
+        let mut room = mbuf.tailroom();
+        let (head, mut rest) = data.split_at(data.len().min(room));
+        mbuf.append(head.len())?.copy_from_slice(head);
+
+        // chains more segments until there's room for the capacity.
+        while room < capacity {
+            let mut segment = Mbuf::new().map_err(|_| PacketError::AllocFailed)?;
+            room += segment.tailroom();
+
+            let len = rest.len().min(segment.tailroom());
+            if len > 0 {
+                segment.append(len)?.copy_from_slice(&rest[..len]);
+                rest = &rest[len..];
+            }
+
+            mbuf.chain(segment)?;
+        }
+
+        Ok(mbuf)
+    }
+
+    /// Creates a new message buffer with `len` bytes of zeroed data.
+    ///
+    /// The data can be written directly, or used as the payload of the
+    /// packet headers pushed in front of it. A `len` of 0 returns an empty
+    /// buffer, same as [`new`].
+    ///
+    /// [`new`]: Mbuf::new
+    ///
+    /// # Errors
+    ///
+    /// Returns `PacketError::AllocFailed` if the allocation of mbuf fails.
+    /// Returns `PacketError::TooLarge` if `len` is larger than the maximum
+    /// mbuf size.
+    #[inline]
+    pub fn alloc(len: usize) -> Result<Self> {
+        let mut mbuf = Mbuf::new().map_err(|_| PacketError::AllocFailed)?;
+        let max = mbuf.tailroom();
+        ensure!(len <= max, PacketError::TooLarge { len, max });
+
+        if len > 0 {
+            mbuf.extend(0, len)?;
+            unsafe {
+                ptr::write_bytes(mbuf.data_address(0), 0, len);
+            }
+        }
+
         Ok(mbuf)
     }
 
     /// Creates a new `Mbuf` from a raw pointer.
     #[inline]
     pub(crate) unsafe fn from_ptr(ptr: *mut ffi::rte_mbuf) -> Self {
-        Mbuf {
+        let mut mbuf = Mbuf {
             inner: MbufInner::Original(NonNull::new_unchecked(ptr)),
-        }
+        };
+        mbuf.clear_metadata();
+        mbuf
     }
 
     /// Returns the raw struct needed for FFI calls.
@@ -197,6 +339,61 @@ impl Mbuf {
         self.raw().data_len as usize
     }
 
+    /// Returns the total amount of data stored in all the segments of the
+    /// buffer.
+    #[inline]
+    pub fn pkt_len(&self) -> usize {
+        self.raw().pkt_len as usize
+    }
+
+    /// Returns whether the buffer is a chain of multiple segments.
+    #[inline]
+    pub fn is_segmented(&self) -> bool {
+        self.raw().nb_segs > 1
+    }
+
+    /// Chains the tail buffer to the end of this buffer, creating a
+    /// multi-segment buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::NotResized` if the chain exceeds the maximum
+    /// number of segments. The tail buffer is freed.
+    #[inline]
+    pub fn chain(&mut self, tail: Mbuf) -> Result<()> {
+        let tail = tail.into_ptr();
+
+        unsafe {
+            if ffi::_rte_pktmbuf_chain(self.raw_mut(), tail) != 0 {
+                // takes back the ownership so the tail is freed.
+                let _ = Mbuf::from_ptr(tail);
+                return Err(BufferError::NotResized.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Collapses a multi-segment buffer into a single segment.
+    ///
+    /// The data of the subsequent segments is moved to the first segment,
+    /// and the subsequent segments are freed. The call is a no-op if the
+    /// buffer is not segmented.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::NotResized` if the first segment does not have
+    /// enough tailroom to hold the entire packet.
+    #[inline]
+    pub fn linearize(&mut self) -> Result<()> {
+        if self.is_segmented() {
+            let ret = unsafe { ffi::_rte_pktmbuf_linearize(self.raw_mut()) };
+            ensure!(ret == 0, BufferError::NotResized);
+        }
+
+        Ok(())
+    }
+
     /// Returns the raw pointer from the offset
     #[inline]
     pub(crate) unsafe fn data_address(&self, offset: usize) -> *mut u8 {
@@ -204,6 +401,27 @@ impl Mbuf {
         (raw.buf_addr as *mut u8).offset(raw.data_off as isize + offset as isize)
     }
 
+    /// Prefetches the cache line of the data at offset into all levels of
+    /// the cache hierarchy.
+    ///
+    /// The prefetch is only a hint to the CPU. It is a no-op on targets
+    /// without a prefetch instruction, or if the offset is out of bound.
+    #[inline]
+    pub(crate) fn prefetch_data(&self, offset: usize) {
+        if offset < self.data_len() {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            unsafe {
+                _mm_prefetch(self.data_address(offset) as *const i8, _MM_HINT_T0);
+            }
+        }
+    }
+
+    /// Returns the amount of bytes available in front of the data.
+    #[inline]
+    fn headroom(&self) -> usize {
+        self.raw().data_off as usize
+    }
+
     /// Returns the amount of bytes left in the buffer.
     #[inline]
     fn tailroom(&self) -> usize {
@@ -211,6 +429,264 @@ impl Mbuf {
         (raw.buf_len - raw.data_off - raw.data_len) as usize
     }
 
+    /// Returns the offload feature flags of the buffer.
+    #[inline]
+    pub fn ol_flags(&self) -> u64 {
+        self.raw().ol_flags
+    }
+
+    /// Returns the receive timestamp of the buffer.
+    ///
+    /// Returns `None` if the `PKT_RX_TIMESTAMP` offload flag is not set,
+    /// i.e. the timestamp was not recorded by the device or the driver.
+    /// The unit and the time reference are device specific.
+    #[inline]
+    pub fn timestamp(&self) -> Option<u64> {
+        let raw = self.raw();
+        if raw.ol_flags & u64::from(ffi::PKT_RX_TIMESTAMP) != 0 {
+            Some(raw.timestamp)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the timestamp of the buffer and the `PKT_RX_TIMESTAMP` offload
+    /// flag.
+    #[inline]
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        let raw = self.raw_mut();
+        raw.timestamp = timestamp;
+        raw.ol_flags |= u64::from(ffi::PKT_RX_TIMESTAMP);
+    }
+
+    /// Returns the RSS hash of the packet computed by the device.
+    ///
+    /// Returns `None` if the `PKT_RX_RSS_HASH` offload flag is not set. The
+    /// hash can be used instead of recomputing it in software with
+    /// [`rss::toeplitz`].
+    ///
+    /// [`rss::toeplitz`]: crate::rss::toeplitz
+    #[inline]
+    pub fn rss_hash(&self) -> Option<u32> {
+        let raw = self.raw();
+        if raw.ol_flags & u64::from(ffi::PKT_RX_RSS_HASH) != 0 {
+            Some(unsafe { raw.__bindgen_anon_4.hash.rss })
+        } else {
+            None
+        }
+    }
+
+    /// Sets the RSS hash of the packet and the `PKT_RX_RSS_HASH` offload
+    /// flag.
+    #[inline]
+    pub fn set_rss_hash(&mut self, hash: u32) {
+        let raw = self.raw_mut();
+        raw.__bindgen_anon_4.hash.rss = hash;
+        raw.ol_flags |= u64::from(ffi::PKT_RX_RSS_HASH);
+    }
+
+    /// Computes the Toeplitz hash of the packet's 5-tuple and caches it in
+    /// the metadata of the buffer.
+    ///
+    /// Later pipeline stages can read the hash with [`cached_flow_hash`]
+    /// instead of extracting the 5-tuple and hashing it again. The hash
+    /// stays with the buffer when the packet is parsed. Because the buffer
+    /// holds only one metadata value, caching the hash replaces any
+    /// metadata set before, and setting other metadata discards the hash.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the packet is not a TCP or UDP packet over IP.
+    /// Returns `BufferError::MetadataTooLarge` if the private area cannot
+    /// hold the hash.
+    ///
+    /// [`cached_flow_hash`]: Mbuf::cached_flow_hash
+    #[inline]
+    pub fn cache_flow_hash(&mut self, key: &[u8]) -> Result<u32> {
+        let flow = extract_flow(self)?;
+        let hash = rss::toeplitz(key, &flow);
+        self.set_metadata(FlowHash(hash))?;
+        Ok(hash)
+    }
+
+    /// Returns the flow hash cached by [`cache_flow_hash`].
+    ///
+    /// Returns `None` if the hash is not cached.
+    ///
+    /// [`cache_flow_hash`]: Mbuf::cache_flow_hash
+    #[inline]
+    pub fn cached_flow_hash(&self) -> Option<u32> {
+        self.get_metadata::<FlowHash>().map(|hash| hash.0)
+    }
+
+    /// Parses the buffer as an IP packet without link-layer framing.
+    ///
+    /// Packets from a tun device or a pcap file of [`LinkTypes::Raw`] begin
+    /// with the IP header. The version in the first nibble determines if
+    /// the packet is IPv4 or IPv6. Because both IP packet types are
+    /// enveloped in Ethernet, an Ethernet header with zero addresses and the
+    /// matching ether type is prepended to the buffer in the headroom. The
+    /// returned frame is then parsed as either [`Ipv4`] or [`Ipv6`] as
+    /// usual.
+    ///
+    /// The buffer is rewritten, not just reinterpreted. The IP data is not
+    /// moved, but the buffer now starts with the made-up Ethernet header,
+    /// and is 14 bytes longer. A packet written back to a raw device or to
+    /// a pcap file of [`LinkTypes::Raw`] should have the header [`remove`]d
+    /// first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PacketError::Empty` if the buffer is empty. Returns
+    /// `PacketError::UnknownIpVersion` if the version is neither 4 nor 6.
+    /// Returns `BufferError::NotResized` if the buffer does not have enough
+    /// headroom for the Ethernet header. The buffer is freed on error.
+    ///
+    /// [`LinkTypes::Raw`]: crate::pcap::LinkTypes::Raw
+    /// [`Ipv4`]: crate::packets::ip::v4::Ipv4
+    /// [`Ipv6`]: crate::packets::ip::v6::Ipv6
+    /// [`remove`]: Packet::remove
+    #[inline]
+    pub fn parse_l3(mut self) -> Result<Ethernet> {
+        ensure!(self.data_len() > 0, PacketError::Empty);
+
+        let version = unsafe { *self.read_data::<u8>(0)?.as_ref() } >> 4;
+        let ether_type = match version {
+            4 => EtherTypes::Ipv4,
+            6 => EtherTypes::Ipv6,
+            _ => return Err(PacketError::UnknownIpVersion(version).into()),
+        };
+
+        self.prepend(ETH_HEADER_SIZE)?
+            .copy_from_slice(&[0; ETH_HEADER_SIZE]);
+        let mut ethernet = self.parse::<Ethernet>()?;
+        ethernet.set_ether_type(ether_type);
+        Ok(ethernet)
+    }
+
+    /// Parses the buffer as a frame of the link-layer header type.
+    ///
+    /// Frames of [`LinkTypes::Ethernet`] are parsed as Ethernet, and frames
+    /// of [`LinkTypes::Raw`] are parsed with [`parse_l3`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `PacketError::UnsupportedLinkType` if the link-layer header
+    /// type is neither. Returns an error if the frame fails to parse.
+    ///
+    /// [`LinkTypes::Ethernet`]: crate::pcap::LinkTypes::Ethernet
+    /// [`LinkTypes::Raw`]: crate::pcap::LinkTypes::Raw
+    /// [`parse_l3`]: Mbuf::parse_l3
+    #[inline]
+    pub fn parse_link(self, link_type: LinkType) -> Result<Ethernet> {
+        match link_type {
+            LinkTypes::Ethernet => self.parse::<Ethernet>(),
+            LinkTypes::Raw => self.parse_l3(),
+            _ => Err(PacketError::UnsupportedLinkType(link_type).into()),
+        }
+    }
+
+    /// Returns the address of the private area that follows the raw struct,
+    /// or `None` if the area cannot hold the type tag.
+    #[inline]
+    fn priv_area(&self) -> Option<*mut u8> {
+        let raw = self.raw();
+        if (raw.priv_size as usize) < mem::size_of::<MetadataTag>() {
+            None
+        } else {
+            let ptr = raw as *const ffi::rte_mbuf as *mut u8;
+            Some(unsafe { ptr.add(mem::size_of::<ffi::rte_mbuf>()) })
+        }
+    }
+
+    /// Stores the metadata in the private area of the buffer.
+    ///
+    /// The metadata is carried alongside the packet between pipeline stages
+    /// and replaces any metadata stored before. Only one value can be
+    /// stored at a time. The metadata is cleared when the buffer is
+    /// allocated or received.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::MetadataTooLarge` if the metadata does not fit
+    /// in the private area.
+    #[inline]
+    pub fn set_metadata<T: Copy + 'static>(&mut self, metadata: T) -> Result<()> {
+        let tag_len = mem::size_of::<MetadataTag>();
+        let room = (self.raw().priv_size as usize).saturating_sub(tag_len);
+        ensure!(
+            mem::size_of::<T>() <= room,
+            BufferError::MetadataTooLarge(mem::size_of::<T>(), room)
+        );
+
+        // the size check guarantees the private area holds the tag.
+        let ptr = self.priv_area().unwrap();
+        unsafe {
+            ptr::write_unaligned(ptr.add(tag_len) as *mut T, metadata);
+            ptr::write_unaligned(ptr as *mut MetadataTag, Some(TypeId::of::<T>()));
+        }
+        Ok(())
+    }
+
+    /// Returns the metadata stored in the private area of the buffer.
+    ///
+    /// Returns `None` if there is no metadata stored, or the stored
+    /// metadata is not of type `T`.
+    #[inline]
+    pub fn get_metadata<T: Copy + 'static>(&self) -> Option<T> {
+        let ptr = self.priv_area()?;
+        unsafe {
+            if ptr::read_unaligned(ptr as *const MetadataTag) == Some(TypeId::of::<T>()) {
+                let tag_len = mem::size_of::<MetadataTag>();
+                Some(ptr::read_unaligned(ptr.add(tag_len) as *const T))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Clears the metadata stored in the private area of the buffer.
+    #[inline]
+    pub fn clear_metadata(&mut self) {
+        if let Some(ptr) = self.priv_area() {
+            unsafe {
+                ptr::write_unaligned(ptr as *mut MetadataTag, None);
+            }
+        }
+    }
+
+    /// Returns the length of the layer-2 header used by the transmit
+    /// offloads.
+    #[inline]
+    pub fn l2_len(&self) -> usize {
+        unsafe { self.raw().__bindgen_anon_6.__bindgen_anon_1.l2_len() as usize }
+    }
+
+    /// Returns the length of the layer-3 header used by the transmit
+    /// offloads.
+    #[inline]
+    pub fn l3_len(&self) -> usize {
+        unsafe { self.raw().__bindgen_anon_6.__bindgen_anon_1.l3_len() as usize }
+    }
+
+    /// Requests transmit offloads from the network device.
+    ///
+    /// `flags` are added to the existing offload flags. The header lengths
+    /// tell the device where the layer-3 and layer-4 headers begin.
+    #[inline]
+    pub(crate) fn set_tx_offload(&mut self, flags: u64, l2_len: usize, l3_len: usize) {
+        let raw = self.raw_mut();
+        raw.ol_flags |= flags;
+        unsafe {
+            raw.__bindgen_anon_6
+                .__bindgen_anon_1
+                .set_l2_len(l2_len as u64);
+            raw.__bindgen_anon_6
+                .__bindgen_anon_1
+                .set_l3_len(l3_len as u64);
+        }
+    }
+
     /// Extends the data buffer at offset by `len` bytes.
     ///
     /// If the offset is not at the end of the data. The data after the
@@ -225,7 +701,7 @@ impl Mbuf {
     pub fn extend(&mut self, offset: usize, len: usize) -> Result<()> {
         ensure!(len > 0, BufferError::NotResized);
         ensure!(offset <= self.data_len(), BufferError::NotResized);
-        ensure!(len < self.tailroom(), BufferError::NotResized);
+        ensure!(len <= self.tailroom(), BufferError::NotResized);
 
         // shifts down data to make room
         let to_copy = self.data_len() - offset;
@@ -274,6 +750,52 @@ impl Mbuf {
         Ok(())
     }
 
+    /// Prepends `len` bytes to the front of the data buffer and returns
+    /// them as a writable slice.
+    ///
+    /// The bytes are taken from the buffer's headroom, so no data is moved.
+    /// This is the equivalent of DPDK's `rte_pktmbuf_prepend`. The content
+    /// of the returned slice is uninitialized.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::NotResized` if the length to prepend is either
+    /// 0 or exceeds the available headroom.
+    #[inline]
+    pub fn prepend(&mut self, len: usize) -> Result<&mut [u8]> {
+        ensure!(len > 0, BufferError::NotResized);
+        ensure!(len <= self.headroom(), BufferError::NotResized);
+
+        self.raw_mut().data_off -= len as u16;
+        self.raw_mut().data_len += len as u16;
+        self.raw_mut().pkt_len += len as u32;
+
+        unsafe { Ok(slice::from_raw_parts_mut(self.data_address(0), len)) }
+    }
+
+    /// Appends `len` bytes to the end of the data buffer and returns them
+    /// as a writable slice.
+    ///
+    /// The bytes are taken from the buffer's tailroom. This is the
+    /// equivalent of DPDK's `rte_pktmbuf_append`. The content of the
+    /// returned slice is uninitialized.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::NotResized` if the length to append is either
+    /// 0 or exceeds the available tailroom.
+    #[inline]
+    pub fn append(&mut self, len: usize) -> Result<&mut [u8]> {
+        ensure!(len > 0, BufferError::NotResized);
+        ensure!(len <= self.tailroom(), BufferError::NotResized);
+
+        let offset = self.data_len();
+        self.raw_mut().data_len += len as u16;
+        self.raw_mut().pkt_len += len as u32;
+
+        unsafe { Ok(slice::from_raw_parts_mut(self.data_address(offset), len)) }
+    }
+
     /// Resizes the data buffer.
     ///
     /// Delegates to either `extend` or `shrink`.
@@ -306,11 +828,17 @@ impl Mbuf {
     ///
     /// # Errors
     ///
+    /// Returns `BufferError::NotContiguous` if the buffer is segmented and
+    /// `T` does not fit within the first segment.
     /// Returns `BufferError::BadOffset` if the offset is out of bound.
     /// Returns `BufferError::OutOfBuffer` if the size of `T` exceeds the
     /// size of the data stored at offset.
     #[inline]
     pub fn read_data<T: SizeOf>(&self, offset: usize) -> Result<NonNull<T>> {
+        ensure!(
+            !self.is_segmented() || offset + T::size_of() <= self.data_len(),
+            BufferError::NotContiguous(offset)
+        );
         ensure!(
             offset < self.data_len(),
             BufferError::BadOffset(offset, self.data_len())
@@ -358,11 +886,17 @@ impl Mbuf {
     ///
     /// # Errors
     ///
+    /// Returns `BufferError::NotContiguous` if the buffer is segmented and
+    /// the `T` slice does not fit within the first segment.
     /// Returns `BufferError::BadOffset` if the offset is out of bound.
     /// Returns `BufferError::OutOfBuffer` if the size of `T` slice exceeds
     /// the size of the data stored at offset.
     #[inline]
     pub fn read_data_slice<T: SizeOf>(&self, offset: usize, count: usize) -> Result<NonNull<[T]>> {
+        ensure!(
+            !self.is_segmented() || offset + T::size_of() * count <= self.data_len(),
+            BufferError::NotContiguous(offset)
+        );
         ensure!(
             offset < self.data_len(),
             BufferError::BadOffset(offset, self.data_len())
@@ -412,6 +946,172 @@ impl Mbuf {
         self.read_data_slice(offset, count)
     }
 
+    /// Returns an iterator over the data of every segment in the buffer
+    /// chain.
+    #[inline]
+    fn segments(&self) -> Segments<'_> {
+        Segments {
+            next: self.raw(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the contiguous slices of data starting at
+    /// offset, one slice per segment in the buffer chain.
+    #[inline]
+    pub(crate) fn data_chunks(&self, offset: usize) -> DataChunks<'_> {
+        DataChunks {
+            segments: self.segments(),
+            skip: offset,
+        }
+    }
+
+    /// Copies the data starting at offset into a vector.
+    ///
+    /// If the buffer is a chain of segments, the data of all the segments
+    /// is concatenated.
+    pub(crate) fn copy_data(&self, offset: usize) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.pkt_len().saturating_sub(offset));
+
+        for chunk in self.data_chunks(offset) {
+            data.extend_from_slice(chunk);
+        }
+
+        data
+    }
+
+    /// Creates a deep copy of the message buffer.
+    ///
+    /// Unlike a reference counted clone that shares the packet data, the
+    /// data of all the segments is copied into a newly allocated buffer,
+    /// along with the offload flags, the timestamp, the RSS hash and the
+    /// metadata. The copy is fully independent of the original, so both
+    /// can be modified and transmitted on different queues.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PacketError::AllocFailed` if the allocation of mbuf fails.
+    /// Returns `BufferError::NotResized` if the copy exceeds the maximum
+    /// number of segments.
+    #[inline]
+    pub fn clone_packet(&self) -> Result<Self> {
+        let mut copy = Mbuf::new().map_err(|_| PacketError::AllocFailed)?;
+        let room = copy.tailroom();
+
+        // copies the data segment by segment. a segment attached to a
+        // larger external buffer is split across several segments.
+        for data in self.segments() {
+            for chunk in data.chunks(room) {
+                if copy.pkt_len() == 0 {
+                    copy.append(chunk.len())?.copy_from_slice(chunk);
+                } else {
+                    let mut segment = Mbuf::new().map_err(|_| PacketError::AllocFailed)?;
+                    segment.append(chunk.len())?.copy_from_slice(chunk);
+                    copy.chain(segment)?;
+                }
+            }
+        }
+
+        let src = self.raw();
+        let dst = copy.raw_mut();
+        // the copy owns its data, even if the original is attached.
+        dst.ol_flags = src.ol_flags & !(IND_ATTACHED_MBUF | EXT_ATTACHED_MBUF);
+        dst.timestamp = src.timestamp;
+        dst.__bindgen_anon_4 = src.__bindgen_anon_4;
+        dst.__bindgen_anon_6 = src.__bindgen_anon_6;
+
+        if let (Some(src), Some(dst)) = (self.priv_area(), copy.priv_area()) {
+            let len = self.raw().priv_size.min(copy.raw().priv_size);
+            unsafe {
+                ptr::copy_nonoverlapping(src, dst, len as usize);
+            }
+        }
+
+        Ok(copy)
+    }
+
+    /// Creates a reference counted clone of the message buffer.
+    ///
+    /// Unlike [`clone_packet`], the packet data is not copied. The clone is
+    /// an indirect buffer attached to the data of the original, so both are
+    /// [`shared`] until one of them is freed. The clone does not carry the
+    /// metadata of the original.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MempoolError::Exhausted` if the allocation of mbuf fails.
+    ///
+    /// [`clone_packet`]: Mbuf::clone_packet
+    /// [`shared`]: Mbuf::is_shared
+    #[inline]
+    pub fn share(&self) -> Result<Self> {
+        let mempool = MEMPOOL.with(|tls| tls.get());
+        let ptr = self.inner.ptr().as_ptr();
+        let raw = unsafe {
+            ffi::_rte_pktmbuf_clone(ptr, mempool).into_result(|_| MempoolError::Exhausted)?
+        };
+
+        Ok(unsafe { Mbuf::from_ptr(raw.as_ptr()) })
+    }
+
+    /// Returns the reference count of the buffer.
+    #[inline]
+    pub fn refcnt(&self) -> u16 {
+        unsafe { self.raw().__bindgen_anon_2.refcnt }
+    }
+
+    /// Returns whether the packet data may be shared with other buffers.
+    ///
+    /// The data is shared if any segment is referenced more than once, is
+    /// attached to the data of another buffer, or is attached to an
+    /// external buffer. Shared data must not be written to.
+    #[inline]
+    pub fn is_shared(&self) -> bool {
+        let mut next = self.raw() as *const ffi::rte_mbuf;
+        while let Some(segment) = unsafe { next.as_ref() } {
+            let refcnt = unsafe { segment.__bindgen_anon_2.refcnt };
+            if refcnt > 1 || segment.ol_flags & (IND_ATTACHED_MBUF | EXT_ATTACHED_MBUF) != 0 {
+                return true;
+            }
+            next = segment.next;
+        }
+
+        false
+    }
+
+    /// Ensures the packet data can be written to without affecting other
+    /// buffers.
+    ///
+    /// If the data is [`shared`], the data is copied with [`clone_packet`]
+    /// into a newly allocated buffer, which takes the place of this one in
+    /// place, and the reference to the shared data is released. Otherwise
+    /// the call is a no-op.
+    ///
+    /// Any packet parsed from the buffer still points into the shared
+    /// data, so this must not be called through [`mbuf_mut`] of a parsed
+    /// packet. To make a parsed packet writable, [`reset`] it first and
+    /// parse the buffer again.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`clone_packet`] if the copy fails. The buffer
+    /// is left unchanged and still shared.
+    ///
+    /// [`shared`]: Mbuf::is_shared
+    /// [`clone_packet`]: Mbuf::clone_packet
+    /// [`mbuf_mut`]: Packet::mbuf_mut
+    /// [`reset`]: Packet::reset
+    #[inline]
+    pub fn make_writable(&mut self) -> Result<()> {
+        if self.is_shared() {
+            let mut copy = self.clone_packet()?;
+            // the original is freed when the copy goes out of scope.
+            mem::swap(&mut self.inner, &mut copy.inner);
+        }
+
+        Ok(())
+    }
+
     /// Acquires the underlying raw struct pointer.
     ///
     /// The `Mbuf` is consumed. It is the caller's the responsibility to
@@ -452,6 +1152,55 @@ impl Mbuf {
     }
 }
 
+/// An iterator over the data of the segments in a chained buffer.
+struct Segments<'a> {
+    next: *const ffi::rte_mbuf,
+    phantom: PhantomData<&'a Mbuf>,
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            self.next.as_ref().map(|segment| {
+                self.next = segment.next;
+                let data = (segment.buf_addr as *const u8).offset(segment.data_off as isize);
+                slice::from_raw_parts(data, segment.data_len as usize)
+            })
+        }
+    }
+}
+
+/// An iterator over the contiguous slices of data in a chained buffer,
+/// starting at an offset.
+///
+/// Each slice covers the data of one segment. No data is copied, so the
+/// slices can be fed to a streaming parser without linearizing the buffer.
+#[allow(missing_debug_implementations)]
+pub struct DataChunks<'a> {
+    segments: Segments<'a>,
+    skip: usize,
+}
+
+impl<'a> Iterator for DataChunks<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for segment in &mut self.segments {
+            if self.skip >= segment.len() {
+                self.skip -= segment.len();
+            } else {
+                let chunk = &segment[self.skip..];
+                self.skip = 0;
+                return Some(chunk);
+            }
+        }
+
+        None
+    }
+}
+
 impl fmt::Debug for Mbuf {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let raw = self.raw();
@@ -558,6 +1307,10 @@ impl Packet for Mbuf {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::{Tcp6, Udp4};
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, IPV6_TCP_PACKET};
 
     const BUFFER: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
 
@@ -570,6 +1323,176 @@ mod tests {
         assert_eq!(BUFFER, slice);
     }
 
+    #[capsule::test]
+    fn data_and_pkt_len() {
+        let mbuf = Mbuf::from_bytes(&BUFFER).unwrap();
+        assert_eq!(16, mbuf.data_len());
+        assert_eq!(16, mbuf.pkt_len());
+        assert_eq!(16, mbuf.len());
+    }
+
+    #[capsule::test]
+    fn new_from_empty_bytes() {
+        let err = Mbuf::from_bytes(&[]).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PacketError>(),
+            Some(PacketError::Empty)
+        ));
+    }
+
+    #[capsule::test]
+    fn new_from_too_many_bytes() {
+        let err = Mbuf::from_bytes(&vec![0; 999_999]).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PacketError>(),
+            Some(PacketError::TooLarge { len: 999_999, .. })
+        ));
+    }
+
+    #[capsule::test]
+    fn new_from_bytes_with_capacity() {
+        let mut mbuf = Mbuf::from_bytes_with_capacity(&BUFFER, 64).unwrap();
+        assert_eq!(16, mbuf.data_len());
+        assert_eq!(BUFFER.to_vec(), mbuf.to_vec());
+
+        assert!(mbuf.extend(16, 48).is_ok());
+        assert_eq!(64, mbuf.data_len());
+    }
+
+    #[capsule::test]
+    fn new_from_bytes_with_small_capacity() {
+        let err = Mbuf::from_bytes_with_capacity(&BUFFER, 8).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PacketError>(),
+            Some(PacketError::CapacityTooSmall {
+                len: 16,
+                capacity: 8
+            })
+        ));
+
+        let err = Mbuf::from_bytes_with_capacity(&BUFFER, 999_999).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PacketError>(),
+            Some(PacketError::TooLarge { len: 999_999, .. })
+        ));
+    }
+
+    #[capsule::test]
+    fn new_from_bytes_with_jumbo_capacity() {
+        let capacity = ffi::RTE_MBUF_DEFAULT_BUF_SIZE as usize * 4;
+        let data = (0..8000).map(|i| i as u8).collect::<Vec<_>>();
+        let mbuf = Mbuf::from_bytes_with_capacity(&data, capacity).unwrap();
+
+        assert!(mbuf.is_segmented());
+        assert_eq!(8000, mbuf.pkt_len());
+        assert_eq!(data, mbuf.copy_data(0));
+
+        // the segments have room for the capacity in total.
+        let mut room = 0;
+        let mut segment = mbuf.raw() as *const ffi::rte_mbuf;
+        while !segment.is_null() {
+            let raw = unsafe { &*segment };
+            room += (raw.buf_len - raw.data_off) as usize;
+            segment = raw.next;
+        }
+        assert!(room >= capacity);
+    }
+
+    #[capsule::test]
+    fn new_from_jumbo_bytes() {
+        let data = vec![0xab; 9000];
+        let mbuf = Mbuf::from_bytes(&data).unwrap();
+
+        assert!(mbuf.is_segmented());
+        assert_eq!(9000, mbuf.pkt_len());
+        assert_eq!(data, mbuf.copy_data(0));
+    }
+
+    #[capsule::test]
+    fn alloc_with_len() {
+        let mbuf = Mbuf::alloc(20).unwrap();
+        assert_eq!(20, mbuf.data_len());
+        assert_eq!(vec![0; 20], mbuf.to_vec());
+
+        let ethernet = mbuf.push::<Ethernet>().unwrap();
+        assert_eq!(14, ethernet.header_len());
+        assert_eq!(20, ethernet.payload_len());
+        assert_eq!(34, ethernet.mbuf().data_len());
+
+        assert!(Mbuf::alloc(999_999).is_err());
+    }
+
+    #[capsule::test]
+    fn clone_packet_is_independent() {
+        let mut mbuf = Mbuf::from_bytes(&BUFFER).unwrap();
+        mbuf.set_rss_hash(42);
+        mbuf.set_metadata(7u16).unwrap();
+
+        let mut copy = mbuf.clone_packet().unwrap();
+        assert_eq!(BUFFER.to_vec(), copy.to_vec());
+        assert_eq!(Some(42), copy.rss_hash());
+        assert_eq!(Some(7u16), copy.get_metadata::<u16>());
+
+        copy.write_data(0, &0xffu8).unwrap();
+        copy.set_metadata(8u16).unwrap();
+        assert_eq!(0xff, copy.to_vec()[0]);
+        assert_eq!(BUFFER.to_vec(), mbuf.to_vec());
+        assert_eq!(Some(7u16), mbuf.get_metadata::<u16>());
+    }
+
+    #[capsule::test]
+    fn clone_empty_packet() {
+        let mbuf = Mbuf::new().unwrap();
+        let copy = mbuf.clone_packet().unwrap();
+        assert_eq!(0, copy.pkt_len());
+
+        // a shared empty buffer can be made writable.
+        let mut shared = mbuf.share().unwrap();
+        assert!(shared.is_shared());
+        shared.make_writable().unwrap();
+        assert!(!shared.is_shared());
+        assert_eq!(0, shared.pkt_len());
+    }
+
+    #[capsule::test]
+    fn clone_chained_packet() {
+        let mut mbuf = Mbuf::from_bytes(&BUFFER[..8]).unwrap();
+        mbuf.chain(Mbuf::from_bytes(&BUFFER[8..]).unwrap()).unwrap();
+
+        let copy = mbuf.clone_packet().unwrap();
+        assert!(copy.is_segmented());
+        assert_eq!(8, copy.data_len());
+        assert_eq!(BUFFER.to_vec(), copy.to_vec());
+    }
+
+    #[capsule::test]
+    fn make_shared_buffer_writable() {
+        let mut mbuf = Mbuf::from_bytes(&BUFFER).unwrap();
+        assert_eq!(1, mbuf.refcnt());
+        assert!(!mbuf.is_shared());
+
+        let mut shared = mbuf.share().unwrap();
+        assert_eq!(2, mbuf.refcnt());
+        assert!(mbuf.is_shared());
+        assert!(shared.is_shared());
+        assert_eq!(BUFFER.to_vec(), shared.to_vec());
+
+        shared.make_writable().unwrap();
+        assert!(!shared.is_shared());
+        assert_eq!(1, mbuf.refcnt());
+        assert!(!mbuf.is_shared());
+
+        shared.write_data(0, &0xffu8).unwrap();
+        assert_eq!(0xff, shared.to_vec()[0]);
+        assert_eq!(BUFFER.to_vec(), mbuf.to_vec());
+
+        // the buffer is left as is when not shared.
+        let raw = mbuf.raw() as *const ffi::rte_mbuf;
+        mbuf.make_writable().unwrap();
+        assert_eq!(raw, mbuf.raw() as *const ffi::rte_mbuf);
+        assert_eq!(BUFFER.to_vec(), mbuf.to_vec());
+    }
+
     #[capsule::test]
     fn extend_data_buffer_tail() {
         let mut mbuf = Mbuf::new().unwrap();
@@ -665,6 +1588,52 @@ mod tests {
         assert!(mbuf.shrink(150, 100).is_err());
     }
 
+    #[capsule::test]
+    fn prepend_data_buffer() {
+        let mut mbuf = Mbuf::from_bytes(&BUFFER).unwrap();
+
+        let front = mbuf.prepend(4).unwrap();
+        assert_eq!(4, front.len());
+        front.copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(20, mbuf.data_len());
+
+        // make sure data is untouched
+        let slice = mbuf.read_data_slice::<u8>(0, 20).unwrap();
+        let slice = unsafe { slice.as_ref() };
+        assert_eq!([0xde, 0xad, 0xbe, 0xef], slice[..4]);
+        assert_eq!(BUFFER, slice[4..]);
+    }
+
+    #[capsule::test]
+    fn prepend_data_buffer_too_much() {
+        let mut mbuf = Mbuf::new().unwrap();
+        assert!(mbuf.prepend(0).is_err());
+        assert!(mbuf.prepend(999_999).is_err());
+    }
+
+    #[capsule::test]
+    fn append_data_buffer() {
+        let mut mbuf = Mbuf::from_bytes(&BUFFER).unwrap();
+
+        let back = mbuf.append(4).unwrap();
+        assert_eq!(4, back.len());
+        back.copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(20, mbuf.data_len());
+
+        // make sure data is untouched
+        let slice = mbuf.read_data_slice::<u8>(0, 20).unwrap();
+        let slice = unsafe { slice.as_ref() };
+        assert_eq!(BUFFER, slice[..16]);
+        assert_eq!([0xde, 0xad, 0xbe, 0xef], slice[16..]);
+    }
+
+    #[capsule::test]
+    fn append_data_buffer_too_much() {
+        let mut mbuf = Mbuf::new().unwrap();
+        assert!(mbuf.append(0).is_err());
+        assert!(mbuf.append(999_999).is_err());
+    }
+
     #[capsule::test]
     fn truncate_data_buffer() {
         let mut mbuf = Mbuf::new().unwrap();
@@ -725,6 +1694,48 @@ mod tests {
         assert!(mbuf.read_data_slice::<u8>(10, 16).is_err());
     }
 
+    #[capsule::test]
+    fn prefetch_data_buffer() {
+        let mbuf = Mbuf::from_bytes(&BUFFER).unwrap();
+
+        // prefetch is a hint, make sure out of bound offsets are ignored
+        mbuf.prefetch_data(0);
+        mbuf.prefetch_data(15);
+        mbuf.prefetch_data(16);
+        mbuf.prefetch_data(999_999);
+        mbuf.prefetch();
+    }
+
+    #[capsule::test]
+    fn linearize_segmented_buffer() {
+        // splits the packet in the middle of the UDP header
+        let mut packet = Mbuf::from_bytes(&IPV4_UDP_PACKET[..36]).unwrap();
+        let tail = Mbuf::from_bytes(&IPV4_UDP_PACKET[36..]).unwrap();
+        assert!(!packet.is_segmented());
+        assert!(packet.chain(tail).is_ok());
+
+        assert!(packet.is_segmented());
+        assert_eq!(36, packet.data_len());
+        assert_eq!(52, packet.pkt_len());
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), packet.to_vec());
+
+        // the UDP header crosses the segments
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert!(ipv4.peek::<Udp4>().is_err());
+
+        let mut packet = ipv4.reset();
+        assert!(packet.linearize().is_ok());
+        assert!(!packet.is_segmented());
+        assert_eq!(52, packet.data_len());
+
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp4>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(1087, udp.dst_port());
+    }
+
     #[capsule::test]
     fn alloc_bulk() {
         let mbufs = Mbuf::alloc_bulk(8).unwrap();
@@ -734,4 +1745,134 @@ mod tests {
             assert_eq!(0, mbuf.data_len());
         }
     }
+
+    #[capsule::test]
+    fn set_and_get_metadata() {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        struct Tag {
+            flow_id: u32,
+            decision: u8,
+        }
+
+        let mut mbuf = Mbuf::new().unwrap();
+        assert_eq!(None, mbuf.get_metadata::<Tag>());
+
+        let tag = Tag {
+            flow_id: 42,
+            decision: 1,
+        };
+        assert!(mbuf.set_metadata(tag).is_ok());
+        assert_eq!(Some(tag), mbuf.get_metadata::<Tag>());
+
+        // reading back as a different type is not allowed.
+        assert_eq!(None, mbuf.get_metadata::<u32>());
+
+        // too big for the private area.
+        assert!(mbuf.set_metadata([0u8; 128]).is_err());
+        assert_eq!(Some(tag), mbuf.get_metadata::<Tag>());
+
+        mbuf.clear_metadata();
+        assert_eq!(None, mbuf.get_metadata::<Tag>());
+    }
+
+    #[capsule::test]
+    fn cache_flow_hash_across_parse() {
+        let mut mbuf = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        assert_eq!(None, mbuf.cached_flow_hash());
+
+        let hash = mbuf.cache_flow_hash(&rss::DEFAULT_KEY).unwrap();
+        assert_eq!(Some(hash), mbuf.cached_flow_hash());
+
+        let ethernet = mbuf.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp4>().unwrap();
+        assert_eq!(rss::toeplitz(&rss::DEFAULT_KEY, &udp.flow()), hash);
+        assert_eq!(Some(hash), udp.mbuf().cached_flow_hash());
+
+        // not a TCP or UDP packet.
+        let mut mbuf = Mbuf::from_bytes(&BUFFER).unwrap();
+        assert!(mbuf.cache_flow_hash(&rss::DEFAULT_KEY).is_err());
+        assert_eq!(None, mbuf.cached_flow_hash());
+    }
+
+    #[capsule::test]
+    fn parse_raw_ip_frames() {
+        // the IPv4 packet without the Ethernet header.
+        let mbuf = Mbuf::from_bytes(&IPV4_UDP_PACKET[14..]).unwrap();
+        let ethernet = mbuf.parse_link(LinkTypes::Raw).unwrap();
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp4>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(&IPV4_UDP_PACKET[14..], &udp.mbuf().to_vec()[14..]);
+
+        let mbuf = Mbuf::from_bytes(&IPV6_TCP_PACKET[14..]).unwrap();
+        let ethernet = mbuf.parse_l3().unwrap();
+        assert_eq!(EtherTypes::Ipv6, ethernet.ether_type());
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        assert!(ipv6.parse::<Tcp6>().is_ok());
+
+        // not an IP packet.
+        let mbuf = Mbuf::from_bytes(&BUFFER).unwrap();
+        assert!(mbuf.parse_l3().is_err());
+    }
+
+    #[capsule::test]
+    fn parse_raw_ip_frame_rewrites_buffer() {
+        let mbuf = Mbuf::from_bytes(&IPV4_UDP_PACKET[14..]).unwrap();
+        let ethernet = mbuf.parse_l3().unwrap();
+        let data = ethernet.mbuf().to_vec();
+        assert_eq!(IPV4_UDP_PACKET.len(), data.len());
+        assert_eq!(&[0; 12], &data[..12]);
+        assert_eq!(&IPV4_UDP_PACKET[12..], &data[12..]);
+
+        // the made-up header can be removed before writing it back out.
+        let mbuf = ethernet.remove().unwrap();
+        assert_eq!(&IPV4_UDP_PACKET[14..], &mbuf.to_vec()[..]);
+
+        // uses up all the headroom.
+        let mut mbuf = Mbuf::new().unwrap();
+        let headroom = mbuf.headroom();
+        mbuf.prepend(headroom).unwrap();
+        mbuf.shrink(0, headroom).unwrap();
+        let payload = &IPV4_UDP_PACKET[14..];
+        mbuf.append(payload.len()).unwrap().copy_from_slice(payload);
+
+        let err = mbuf.parse_l3().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<BufferError>(),
+            Some(BufferError::NotResized)
+        ));
+    }
+
+    #[capsule::test]
+    fn parse_ethernet_frame_by_link_type() {
+        let mbuf = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = mbuf.parse_link(LinkTypes::Ethernet).unwrap();
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        assert_eq!(IPV4_UDP_PACKET.len(), ethernet.len());
+
+        let mbuf = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        assert!(mbuf.parse_link(LinkTypes::LinuxSll).is_err());
+    }
+
+    #[capsule::test]
+    fn set_and_get_timestamp() {
+        let mut mbuf = Mbuf::new().unwrap();
+        assert_eq!(None, mbuf.timestamp());
+
+        mbuf.set_timestamp(1_234_567_890);
+        assert_eq!(Some(1_234_567_890), mbuf.timestamp());
+        assert_ne!(0, mbuf.ol_flags() & u64::from(ffi::PKT_RX_TIMESTAMP));
+    }
+
+    #[capsule::test]
+    fn set_and_get_rss_hash() {
+        let mut mbuf = Mbuf::new().unwrap();
+        assert_eq!(None, mbuf.rss_hash());
+
+        mbuf.set_rss_hash(0x323e_8fc2);
+        assert_eq!(Some(0x323e_8fc2), mbuf.rss_hash());
+        assert_ne!(0, mbuf.ol_flags() & u64::from(ffi::PKT_RX_RSS_HASH));
+    }
 }
@@ -62,7 +62,7 @@ impl Mempool {
                 name.clone().into_cstring().as_ptr(),
                 capacity as raw::c_uint,
                 cache_size as raw::c_uint,
-                0,
+                super::MBUF_PRIV_SIZE,
                 ffi::RTE_MBUF_DEFAULT_BUF_SIZE as u16,
                 socket_id.raw(),
             )
@@ -83,8 +83,10 @@
 //! ## Feature flags
 //!
 //! - `default`: Enables metrics by default.
+//! - `json`: Enables serializing parsed packets to JSON with [`json`].
 //! - `metrics`: Enables automatic [`metrics`] collection.
-//! - `pcap-dump`: Enables capturing port traffic to `pcap` files.
+//! - `pcap-dump`: Enables capturing port traffic to `pcap` files. Reading
+//!   and writing `pcap` files with [`pcap`] is always available.
 //! - `testils`: Enables utilities for unit testing and benchmarking.
 //! - `full`: Enables all features.
 //!
@@ -110,6 +112,7 @@
 //! [README]: https://github.com/capsule-rs/capsule/blob/master/README.md
 //! [sandbox repo]: https://github.com/capsule-rs/sandbox
 //! [`metrics`]: crate::metrics
+//! [`json`]: crate::packets::json
 //! [kni]: https://github.com/capsule-rs/capsule/tree/master/examples/kni
 //! [nat64]: https://github.com/capsule-rs/capsule/tree/master/examples/nat64
 //! [ping4d]: https://github.com/capsule-rs/capsule/tree/master/examples/ping4d
@@ -131,15 +134,15 @@ mod macros;
 pub mod metrics;
 pub mod net;
 pub mod packets;
-#[cfg(feature = "pcap-dump")]
-#[cfg_attr(docsrs, doc(cfg(feature = "pcap-dump")))]
-mod pcap;
+pub mod pcap;
+pub mod reorder;
+pub mod rss;
 mod runtime;
 #[cfg(any(test, feature = "testils"))]
 #[cfg_attr(docsrs, doc(cfg(feature = "testils")))]
 pub mod testils;
 
-pub use self::dpdk::{KniRx, KniTxQueue, Mbuf, PortQueue, SizeOf};
+pub use self::dpdk::{DataChunks, KniRx, KniTxQueue, Mbuf, PortQueue, SizeOf, MAX_MBUF_CAPACITY};
 pub use self::runtime::{Runtime, UnixSignal};
 pub use capsule_macros::SizeOf;
 #[cfg(any(test, feature = "testils"))]
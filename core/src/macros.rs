@@ -104,3 +104,162 @@ macro_rules! trace {
         ::tracing::trace!($($arg)+);
     )
 }
+
+/// Generates the getters and the setters of the named bit ranges within a
+/// header field.
+///
+/// The field is either a `u8` or a big-endian integer. Bit ranges are
+/// numbered from the least significant bit of the field in host order,
+/// and the bit range of a getter or a setter must be narrower than the
+/// field. The values are converted to and from the return type with `as`.
+/// The setter discards the value's bits that don't fit in the range. The
+/// enclosing type must have the `header` and `header_mut` accessors.
+///
+/// # Example
+///
+/// ```
+/// impl Ipv4 {
+///     bitfield! {
+///         version_ihl: u8 {
+///             /// Returns the protocol version.
+///             pub get version -> u8 = 4..8;
+///             /// Sets the protocol version.
+///             pub set set_version(u8) = 4..8;
+///         }
+///     }
+/// }
+/// ```
+#[doc(hidden)]
+#[macro_export]
+macro_rules! bitfield {
+    ($field:ident: $int:ty {}) => {};
+    ($field:ident: $int:ty {
+        $(#[$meta:meta])*
+        $vis:vis get $name:ident -> $ret:ty = $lo:literal..$hi:literal;
+        $($rest:tt)*
+    }) => {
+        $(#[$meta])*
+        #[allow(clippy::identity_op, clippy::unnecessary_cast, clippy::useless_conversion)]
+        $vis fn $name(&self) -> $ret {
+            let mask: $int = ((1 as $int) << ($hi - $lo)) - 1;
+            let bits: $int = self.header().$field.into();
+            ((bits >> $lo) & mask) as $ret
+        }
+
+        $crate::bitfield!($field: $int { $($rest)* });
+    };
+    ($field:ident: $int:ty {
+        $(#[$meta:meta])*
+        $vis:vis set $name:ident($ret:ty) = $lo:literal..$hi:literal;
+        $($rest:tt)*
+    }) => {
+        $(#[$meta])*
+        #[allow(clippy::identity_op, clippy::unnecessary_cast, clippy::useless_conversion)]
+        $vis fn $name(&mut self, value: $ret) {
+            let mask: $int = (((1 as $int) << ($hi - $lo)) - 1) << $lo;
+            let bits: $int = self.header().$field.into();
+            let bits = (bits & !mask) | (((value as $int) << $lo) & mask);
+            self.header_mut().$field = bits.into();
+        }
+
+        $crate::bitfield!($field: $int { $($rest)* });
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packets::types::{u16be, u32be};
+
+    #[derive(Default)]
+    struct Header {
+        flags: u8,
+        word: u16be,
+        dword: u32be,
+    }
+
+    #[derive(Default)]
+    struct Fields {
+        header: Header,
+    }
+
+    impl Fields {
+        fn header(&self) -> &Header {
+            &self.header
+        }
+
+        fn header_mut(&mut self) -> &mut Header {
+            &mut self.header
+        }
+
+        bitfield! {
+            flags: u8 {
+                get high_nibble -> u8 = 4..8;
+                set set_high_nibble(u8) = 4..8;
+                get low_bit -> u8 = 0..1;
+            }
+        }
+
+        bitfield! {
+            word: u16 {
+                get high_byte -> u8 = 8..16;
+                set set_high_byte(u8) = 8..16;
+                get middle -> u8 = 4..12;
+                set set_middle(u8) = 4..12;
+                get offset -> u16 = 0..13;
+            }
+        }
+
+        bitfield! {
+            dword: u32 {
+                get class -> u8 = 20..28;
+                set set_class(u8) = 20..28;
+                get label -> u32 = 0..20;
+            }
+        }
+    }
+
+    #[test]
+    fn get_bits_within_byte() {
+        let mut fields = Fields::default();
+        fields.header.flags = 0xa5;
+
+        assert_eq!(0x0a, fields.high_nibble());
+        assert_eq!(1, fields.low_bit());
+
+        fields.set_high_nibble(0x03);
+        assert_eq!(0x35, fields.header.flags);
+
+        // the bits that don't fit are discarded.
+        fields.set_high_nibble(0xff);
+        assert_eq!(0xf5, fields.header.flags);
+    }
+
+    #[test]
+    fn get_bits_at_byte_boundary() {
+        let mut fields = Fields::default();
+        fields.header.word = 0x1234.into();
+
+        assert_eq!(0x12, fields.high_byte());
+        assert_eq!(0x1234, fields.offset());
+
+        fields.set_high_byte(0xab);
+        assert_eq!(0xab34, u16::from(fields.header.word));
+    }
+
+    #[test]
+    fn get_bits_across_bytes() {
+        let mut fields = Fields::default();
+        fields.header.word = 0x1234.into();
+        fields.header.dword = 0x0fab_cdef.into();
+
+        assert_eq!(0x23, fields.middle());
+        assert_eq!(0xfa, fields.class());
+        assert_eq!(0xb_cdef, fields.label());
+
+        fields.set_middle(0xff);
+        assert_eq!(0x1ff4, u16::from(fields.header.word));
+
+        fields.set_class(0x12);
+        assert_eq!(0x012b_cdef, u32::from(fields.header.dword));
+    }
+}
@@ -24,6 +24,9 @@ pub use self::v4::Ipv4Cidr;
 #[allow(unreachable_pub)]
 pub use self::v6::Ipv6Cidr;
 
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
 use thiserror::Error;
 
 /// Error indicating that a CIDR range cannot be parsed or is handled with an invalid prefix length.
@@ -73,3 +76,141 @@ pub trait Cidr: Sized {
     /// Returns a new CIDR range from a netmask.
     fn with_netmask(address: Self::Addr, netmask: Self::Addr) -> Result<Self, CidrError>;
 }
+
+/// CIDR range for either IPv4 or IPv6 addresses.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IpCidr {
+    /// An IPv4 CIDR range.
+    V4(Ipv4Cidr),
+    /// An IPv6 CIDR range.
+    V6(Ipv6Cidr),
+}
+
+impl IpCidr {
+    /// Returns a new CIDR range from a prefix length.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CidrError::Malformed` if the length is greater than the
+    /// number of bits in the address.
+    pub fn new(address: IpAddr, length: usize) -> Result<Self, CidrError> {
+        match address {
+            IpAddr::V4(address) => Ipv4Cidr::new(address, length).map(IpCidr::V4),
+            IpAddr::V6(address) => Ipv6Cidr::new(address, length).map(IpCidr::V6),
+        }
+    }
+
+    /// Returns the IP address prefix.
+    pub fn address(&self) -> IpAddr {
+        match self {
+            IpCidr::V4(cidr) => cidr.address().into(),
+            IpCidr::V6(cidr) => cidr.address().into(),
+        }
+    }
+
+    /// Returns the CIDR prefix length.
+    pub fn length(&self) -> usize {
+        match self {
+            IpCidr::V4(cidr) => cidr.length(),
+            IpCidr::V6(cidr) => cidr.length(),
+        }
+    }
+
+    /// Checks whether an address is contained within the CIDR range.
+    ///
+    /// An IPv4 address is never contained in an IPv6 range, and vice versa.
+    pub fn contains(&self, address: IpAddr) -> bool {
+        match (self, address) {
+            (IpCidr::V4(cidr), IpAddr::V4(address)) => cidr.contains(address),
+            (IpCidr::V6(cidr), IpAddr::V6(address)) => cidr.contains(address),
+            _ => false,
+        }
+    }
+}
+
+impl From<Ipv4Cidr> for IpCidr {
+    fn from(cidr: Ipv4Cidr) -> Self {
+        IpCidr::V4(cidr)
+    }
+}
+
+impl From<Ipv6Cidr> for IpCidr {
+    fn from(cidr: Ipv6Cidr) -> Self {
+        IpCidr::V6(cidr)
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = CidrError;
+
+    fn from_str(s: &str) -> Result<Self, CidrError> {
+        if s.contains(':') {
+            Ipv6Cidr::from_str(s).map(IpCidr::V6)
+        } else {
+            Ipv4Cidr::from_str(s).map(IpCidr::V4)
+        }
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpCidr::V4(cidr) => write!(f, "{}", cidr),
+            IpCidr::V6(cidr) => write!(f, "{}", cidr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ip_cidr() {
+        let cidr = IpCidr::from_str("10.0.0.0/8").unwrap();
+        assert_eq!(IpCidr::V4(Ipv4Cidr::from_str("10.0.0.0/8").unwrap()), cidr);
+        assert_eq!("10.0.0.0/8", cidr.to_string());
+
+        let cidr = IpCidr::from_str("2001:db8::/32").unwrap();
+        assert_eq!(32, cidr.length());
+        assert_eq!("2001:db8::/32", cidr.to_string());
+    }
+
+    #[test]
+    fn parse_ip_cidr_bad_length() {
+        assert!(IpCidr::from_str("10.0.0.0/33").is_err());
+        assert!(IpCidr::from_str("2001:db8::/129").is_err());
+        assert!(IpCidr::new("10.0.0.0".parse().unwrap(), 33).is_err());
+    }
+
+    #[test]
+    fn ipv4_cidr_boundaries() {
+        let cidr = IpCidr::from_str("192.168.1.0/24").unwrap();
+        assert!(!cidr.contains("192.168.0.255".parse().unwrap()));
+        assert!(cidr.contains("192.168.1.0".parse().unwrap()));
+        assert!(cidr.contains("192.168.1.255".parse().unwrap()));
+        assert!(!cidr.contains("192.168.2.0".parse().unwrap()));
+        assert!(!cidr.contains("::ffff:192.168.1.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn ipv6_cidr_boundaries() {
+        let cidr = IpCidr::from_str("2001:db8:0:1::/64").unwrap();
+        assert!(!cidr.contains("2001:db8:0:0:ffff:ffff:ffff:ffff".parse().unwrap()));
+        assert!(cidr.contains("2001:db8:0:1::".parse().unwrap()));
+        assert!(cidr.contains("2001:db8:0:1:ffff:ffff:ffff:ffff".parse().unwrap()));
+        assert!(!cidr.contains("2001:db8:0:2::".parse().unwrap()));
+        assert!(!cidr.contains("192.168.1.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn zero_length_cidr_contains_all() {
+        let cidr = IpCidr::from_str("0.0.0.0/0").unwrap();
+        assert_eq!(0, cidr.length());
+        assert!(cidr.contains("255.255.255.255".parse().unwrap()));
+
+        let cidr = IpCidr::from_str("::/0").unwrap();
+        assert_eq!(0, cidr.length());
+        assert!(cidr.contains("2001:db8::1".parse().unwrap()));
+    }
+}
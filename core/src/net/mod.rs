@@ -19,7 +19,6 @@
 //! Common network utilities.
 
 mod cidr;
-mod mac;
 
-pub use self::cidr::{Cidr, CidrError, Ipv4Cidr, Ipv6Cidr};
-pub use self::mac::{MacAddr, MacParseError};
+pub use self::cidr::{Cidr, CidrError, IpCidr, Ipv4Cidr, Ipv6Cidr};
+pub use capsule_headers::{MacAddr, MacParseError};
@@ -0,0 +1,441 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! IP Authentication Header.
+
+use crate::packets::checksum::PseudoHeader;
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::{Ipv6, Ipv6Packet};
+use crate::packets::ip::{IpPacket, ProtocolNumber, ProtocolNumbers};
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{Internal, Packet};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::IpAddr;
+use std::ptr::NonNull;
+
+/// IP Authentication Header packet based on [IETF RFC 4302].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | Next Header   |  Payload Len  |          RESERVED             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 Security Parameters Index (SPI)               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                    Sequence Number Field                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// +                Integrity Check Value-ICV (variable)           |
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Next Header*: (8 bits)
+///      Identifies the type of the next payload after the Authentication
+///      Header, using the IP protocol numbers.
+///
+/// - *Payload Length*: (8 bits)
+///      The length of AH in 32-bit words (4-byte units), minus 2.
+///
+/// - *Security Parameters Index*: (32 bits)
+///      An arbitrary value used by a receiver to identify the security
+///      association to which an incoming packet is bound.
+///
+/// - *Sequence Number*: (32 bits)
+///      A monotonically increasing counter value used for anti-replay.
+///
+/// - *Integrity Check Value*: (variable)
+///      A variable-length field that contains the ICV for this packet.
+///
+/// As the payload is not encrypted, AH is both a packet and an envelope
+/// of the next protocol, for example `Udp<Ah<Ipv4>>`.
+///
+/// [IETF RFC 4302]: https://tools.ietf.org/html/rfc4302#section-2
+pub struct Ah<E: IpPacket> {
+    envelope: E,
+    header: NonNull<AhHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Ah<E> {
+    #[inline]
+    fn header(&self) -> &AhHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut AhHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the protocol number of the next header.
+    #[inline]
+    pub fn next_header(&self) -> ProtocolNumber {
+        ProtocolNumber::new(self.header().next_header)
+    }
+
+    /// Sets the protocol number of the next header.
+    #[inline]
+    pub fn set_next_header(&mut self, next_header: ProtocolNumber) {
+        self.header_mut().next_header = next_header.0;
+    }
+
+    /// Returns the payload length field, the length of AH in 4-byte units
+    /// minus 2.
+    #[inline]
+    pub fn payload_len_field(&self) -> u8 {
+        self.header().payload_len
+    }
+
+    /// Returns the security parameters index.
+    #[inline]
+    pub fn spi(&self) -> u32 {
+        self.header().spi.into()
+    }
+
+    /// Sets the security parameters index.
+    #[inline]
+    pub fn set_spi(&mut self, spi: u32) {
+        self.header_mut().spi = spi.into();
+    }
+
+    /// Returns the sequence number.
+    #[inline]
+    pub fn sequence(&self) -> u32 {
+        self.header().sequence.into()
+    }
+
+    /// Sets the sequence number.
+    #[inline]
+    pub fn set_sequence(&mut self, sequence: u32) {
+        self.header_mut().sequence = sequence.into();
+    }
+
+    /// Returns the integrity check value.
+    #[inline]
+    pub fn icv(&self) -> &[u8] {
+        let len = self.header_len() - AhHeader::size_of();
+        if len > 0 {
+            // the ICV length is checked when parsed.
+            let data = self
+                .mbuf()
+                .read_data_slice::<u8>(self.offset + AhHeader::size_of(), len)
+                .unwrap();
+            unsafe { &*data.as_ptr() }
+        } else {
+            &[]
+        }
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Ah<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ah")
+            .field("next_header", &format!("{}", self.next_header()))
+            .field("payload_len", &self.payload_len_field())
+            .field("spi", &format!("0x{:08x}", self.spi()))
+            .field("sequence", &self.sequence())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Ah<E> {
+    /// The preceding type for an AH packet can be either an [IPv4] packet,
+    /// an [IPv6] packet, or any IPv6 extension packets.
+    ///
+    /// [IPv4]: crate::packets::ip::v4::Ipv4
+    /// [IPv6]: crate::packets::ip::v6::Ipv6
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of AH, including the ICV.
+    #[inline]
+    fn header_len(&self) -> usize {
+        (self.payload_len_field() as usize + 2) * 4
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Ah::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an AH packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the envelope's [`next_protocol`] is not
+    /// [`ProtocolNumbers::Ah`]. Returns an error if the payload does not
+    /// have sufficient data for the header and the ICV.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Ah,
+            anyhow!("not an AH packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data::<AhHeader>(offset)?;
+
+        let payload_len = unsafe { header.as_ref() }.payload_len;
+        ensure!(
+            payload_len >= 1,
+            anyhow!("invalid AH payload length {}.", payload_len)
+        );
+
+        let packet = Ah {
+            envelope,
+            header,
+            offset,
+        };
+
+        // makes sure the ICV is in the buffer.
+        let _ = packet
+            .mbuf()
+            .read_data_slice::<u8>(offset, packet.header_len())?;
+
+        Ok(packet)
+    }
+
+    /// Prepends an AH packet without ICV to the beginning of the envelope's
+    /// payload.
+    ///
+    /// [`next_header`] is set to the value of the envelope's
+    /// [`next_protocol`], and the envelope is set to [`ProtocolNumbers::Ah`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`next_header`]: Ah::next_header
+    /// [`next_protocol`]: IpPacket::next_protocol
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, AhHeader::size_of())?;
+        let header = mbuf.write_data(offset, &AhHeader::default())?;
+
+        let mut packet = Ah {
+            envelope,
+            header,
+            offset,
+        };
+
+        packet.set_next_header(packet.envelope().next_protocol());
+        packet.envelope_mut().set_next_protocol(ProtocolNumbers::Ah);
+
+        Ok(packet)
+    }
+
+    /// Removes AH packet from the message buffer.
+    ///
+    /// The envelope's [`next_protocol`] is set to the value of the
+    /// `next_header` field on the AH packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have sufficient data to
+    /// remove.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    #[inline]
+    fn remove(mut self) -> Result<Self::Envelope> {
+        let offset = self.offset();
+        let len = self.header_len();
+        let next_header = self.next_header();
+        self.mbuf_mut().shrink(offset, len)?;
+        self.envelope_mut().set_next_protocol(next_header);
+        Ok(self.envelope)
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl<E: IpPacket> IpPacket for Ah<E> {
+    #[inline]
+    fn next_protocol(&self) -> ProtocolNumber {
+        self.next_header()
+    }
+
+    #[inline]
+    fn set_next_protocol(&mut self, proto: ProtocolNumber) {
+        self.set_next_header(proto);
+    }
+
+    #[inline]
+    fn src(&self) -> IpAddr {
+        self.envelope().src()
+    }
+
+    #[inline]
+    fn set_src(&mut self, src: IpAddr) -> Result<()> {
+        self.envelope_mut().set_src(src)
+    }
+
+    #[inline]
+    fn dst(&self) -> IpAddr {
+        self.envelope().dst()
+    }
+
+    #[inline]
+    fn set_dst(&mut self, dst: IpAddr) -> Result<()> {
+        self.envelope_mut().set_dst(dst)
+    }
+
+    #[inline]
+    fn pseudo_header(&self, packet_len: u16, protocol: ProtocolNumber) -> PseudoHeader {
+        self.envelope().pseudo_header(packet_len, protocol)
+    }
+
+    #[inline]
+    fn truncate(&mut self, mtu: usize) -> Result<()> {
+        self.envelope_mut().truncate(mtu)
+    }
+}
+
+impl<E: Ipv6Packet> Ipv6Packet for Ah<E> {
+    #[inline]
+    fn next_header(&self) -> ProtocolNumber {
+        ProtocolNumber::new(self.header().next_header)
+    }
+
+    #[inline]
+    fn set_next_header(&mut self, next_header: ProtocolNumber) {
+        self.header_mut().next_header = next_header.0;
+    }
+}
+
+/// A type alias for an IPv4 AH packet.
+pub type Ah4 = Ah<Ipv4>;
+
+/// A type alias for an IPv6 AH packet.
+pub type Ah6 = Ah<Ipv6>;
+
+/// AH header without the ICV.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct AhHeader {
+    next_header: u8,
+    payload_len: u8,
+    reserved: u16be,
+    spi: u32be,
+    sequence: u32be,
+}
+
+impl Default for AhHeader {
+    fn default() -> AhHeader {
+        AhHeader {
+            next_header: 0,
+            // an AH without ICV is 3 words long.
+            payload_len: 1,
+            reserved: u16be::default(),
+            spi: u32be::default(),
+            sequence: u32be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::{Ethernet, Udp};
+    use crate::testils::byte_arrays::{AH_PACKET, IPV4_UDP_PACKET};
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_ah_header() {
+        assert_eq!(12, AhHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_ah_packet() {
+        let packet = Mbuf::from_bytes(&AH_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let ah = ipv4.parse::<Ah4>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Udp, ah.next_header());
+        assert_eq!(4, ah.payload_len_field());
+        assert_eq!(24, ah.header_len());
+        assert_eq!(0x1234, ah.spi());
+        assert_eq!(1, ah.sequence());
+        assert_eq!(&[0xbb; 12], ah.icv());
+
+        let udp = ah.parse::<Udp<Ah4>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(1087, udp.dst_port());
+        assert_eq!(13, udp.length());
+    }
+
+    #[capsule::test]
+    fn parse_non_ah_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Ah4>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_and_remove_ah_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let ah = ipv4.push::<Ah4>().unwrap();
+        assert_eq!(12, ah.len() - ah.payload_len());
+        assert_eq!(ProtocolNumbers::Udp, ah.next_header());
+        assert_eq!(ProtocolNumbers::Ah, ah.envelope().protocol());
+        assert!(ah.icv().is_empty());
+
+        let ipv4 = ah.remove().unwrap();
+        assert_eq!(ProtocolNumbers::Udp, ipv4.protocol());
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), ipv4.reset().to_vec());
+    }
+}
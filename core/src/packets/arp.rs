@@ -21,11 +21,13 @@
 use crate::net::MacAddr;
 use crate::packets::types::u16be;
 use crate::packets::{EtherTypes, Ethernet, Internal, Packet};
-use crate::{ensure, SizeOf};
+use crate::{ensure, Mbuf, SizeOf};
 use anyhow::{anyhow, Result};
+use std::collections::HashMap;
 use std::fmt;
 use std::net::Ipv4Addr;
 use std::ptr::NonNull;
+use std::time::{Duration, Instant};
 
 /// Address Resolution Protocol packet based on [IETF RFC 826].
 ///
@@ -534,9 +536,7 @@ pub trait HardwareAddr: SizeOf + Copy + fmt::Display {
 }
 
 impl SizeOf for MacAddr {
-    fn size_of() -> usize {
-        6
-    }
+    const SIZE: usize = 6;
 }
 
 impl HardwareAddr for MacAddr {
@@ -562,9 +562,7 @@ pub trait ProtocolAddr: SizeOf + Copy + fmt::Display {
 }
 
 impl SizeOf for Ipv4Addr {
-    fn size_of() -> usize {
-        4
-    }
+    const SIZE: usize = 4;
 }
 
 impl ProtocolAddr for Ipv4Addr {
@@ -580,6 +578,140 @@ impl ProtocolAddr for Ipv4Addr {
 /// A type alias for an IPv4 ARP packet.
 pub type Arp4 = Arp<MacAddr, Ipv4Addr>;
 
+impl Arp<MacAddr, Ipv4Addr> {
+    /// Creates a gratuitous ARP announcing that `ip` is at `mac`.
+    ///
+    /// The packet is a broadcast request with both the sender and the
+    /// target protocol addresses set to `ip`, the sender hardware address
+    /// set to `mac`, and the target hardware address zeroed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the allocation of the buffer fails.
+    pub fn gratuitous(mac: MacAddr, ip: Ipv4Addr) -> Result<Self> {
+        let mut ethernet = Mbuf::new()?.push::<Ethernet>()?;
+        ethernet.set_src(mac);
+        ethernet.set_dst(MacAddr::BROADCAST);
+
+        let mut arp = ethernet.push::<Arp4>()?;
+        arp.set_operation_code(OperationCodes::Request);
+        arp.set_sender_hardware_addr(mac);
+        arp.set_sender_protocol_addr(ip);
+        arp.set_target_hardware_addr(MacAddr::UNSPECIFIED);
+        arp.set_target_protocol_addr(ip);
+
+        Ok(arp)
+    }
+
+    /// Returns whether the packet is a gratuitous ARP request or reply.
+    ///
+    /// A gratuitous ARP has the same sender and target protocol addresses.
+    /// An ARP probe, which has an unspecified sender protocol address, is
+    /// not gratuitous.
+    #[inline]
+    pub fn is_gratuitous(&self) -> bool {
+        let ip = self.sender_protocol_addr();
+        let op = self.operation_code();
+        (op == OperationCodes::Request || op == OperationCodes::Reply)
+            && !ip.is_unspecified()
+            && ip == self.target_protocol_addr()
+    }
+}
+
+/// A cache of resolved IPv4 to MAC address mappings.
+///
+/// Each entry expires `ttl` after it is last inserted or learned. Expired
+/// entries are ignored by [`lookup`], and are removed with
+/// [`expire_older_than`].
+///
+/// The cache is not synchronized. It's meant to be owned by a single
+/// pipeline on one core. To share it across cores, wrap it in a lock.
+///
+/// [`lookup`]: ArpCache::lookup
+/// [`expire_older_than`]: ArpCache::expire_older_than
+#[derive(Debug)]
+pub struct ArpCache {
+    entries: HashMap<Ipv4Addr, ArpCacheEntry>,
+    ttl: Duration,
+}
+
+#[derive(Debug)]
+struct ArpCacheEntry {
+    mac: MacAddr,
+    updated: Instant,
+}
+
+impl ArpCache {
+    /// Creates a new empty cache whose entries expire after `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        ArpCache {
+            entries: HashMap::new(),
+            ttl,
+        }
+    }
+
+    /// Returns the number of entries in the cache, including the expired
+    /// entries not removed yet.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the MAC address of `ip` if the entry has not expired.
+    pub fn lookup(&self, ip: Ipv4Addr) -> Option<MacAddr> {
+        self.entries
+            .get(&ip)
+            .filter(|entry| entry.updated.elapsed() <= self.ttl)
+            .map(|entry| entry.mac)
+    }
+
+    /// Inserts or refreshes the MAC address of `ip`.
+    ///
+    /// Returns the previous MAC address if there was one.
+    pub fn insert(&mut self, ip: Ipv4Addr, mac: MacAddr) -> Option<MacAddr> {
+        let entry = ArpCacheEntry {
+            mac,
+            updated: Instant::now(),
+        };
+        self.entries.insert(ip, entry).map(|entry| entry.mac)
+    }
+
+    /// Removes `ip` from the cache.
+    ///
+    /// Returns the MAC address if there was one.
+    pub fn remove(&mut self, ip: Ipv4Addr) -> Option<MacAddr> {
+        self.entries.remove(&ip).map(|entry| entry.mac)
+    }
+
+    /// Removes the entries not refreshed in the last `age`.
+    ///
+    /// Returns the number of entries removed.
+    pub fn expire_older_than(&mut self, age: Duration) -> usize {
+        let len = self.entries.len();
+        self.entries
+            .retain(|_, entry| entry.updated.elapsed() <= age);
+        len - self.entries.len()
+    }
+
+    /// Learns the sender's addresses from an ARP reply.
+    ///
+    /// Requests and replies with an unspecified sender protocol address
+    /// are ignored. Returns whether an entry is inserted or refreshed.
+    pub fn learn(&mut self, arp: &Arp4) -> bool {
+        let ip = arp.sender_protocol_addr();
+        if arp.operation_code() == OperationCodes::Reply && !ip.is_unspecified() {
+            self.insert(ip, arp.sender_hardware_addr());
+            true
+        } else {
+            false
+        }
+    }
+}
+
 /// ARP header.
 #[allow(missing_debug_implementations)]
 #[derive(Copy, SizeOf)]
@@ -632,7 +764,6 @@ impl<H: HardwareAddr, P: ProtocolAddr> Default for ArpHeader<H, P> {
 mod tests {
     use super::*;
     use crate::testils::byte_arrays::ARP4_PACKET;
-    use crate::Mbuf;
 
     #[test]
     fn size_of_arp_header() {
@@ -685,4 +816,93 @@ mod tests {
         // make sure the ether type is fixed
         assert_eq!(EtherTypes::Arp, arp4.envelope().ether_type());
     }
+
+    #[capsule::test]
+    fn gratuitous_arp() {
+        let mac = MacAddr::new(0, 0, 0, 0, 0, 1);
+        let ip = Ipv4Addr::new(10, 0, 0, 1);
+        let arp4 = Arp4::gratuitous(mac, ip).unwrap();
+
+        assert_eq!(MacAddr::BROADCAST, arp4.envelope().dst());
+        assert_eq!(mac, arp4.envelope().src());
+        assert_eq!(OperationCodes::Request, arp4.operation_code());
+        assert_eq!(mac, arp4.sender_hardware_addr());
+        assert_eq!(ip, arp4.sender_protocol_addr());
+        assert_eq!(MacAddr::UNSPECIFIED, arp4.target_hardware_addr());
+        assert_eq!(ip, arp4.target_protocol_addr());
+        assert!(arp4.is_gratuitous());
+    }
+
+    #[capsule::test]
+    fn detect_gratuitous_arp() {
+        let packet = Mbuf::from_bytes(&ARP4_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut arp4 = ethernet.parse::<Arp4>().unwrap();
+        assert!(!arp4.is_gratuitous());
+
+        arp4.set_target_protocol_addr(arp4.sender_protocol_addr());
+        assert!(arp4.is_gratuitous());
+
+        // an ARP probe is not gratuitous.
+        arp4.set_sender_protocol_addr(Ipv4Addr::UNSPECIFIED);
+        arp4.set_target_protocol_addr(Ipv4Addr::UNSPECIFIED);
+        assert!(!arp4.is_gratuitous());
+    }
+
+    #[test]
+    fn insert_and_lookup_arp_cache() {
+        let mut cache = ArpCache::new(Duration::from_secs(60));
+        let ip = Ipv4Addr::new(10, 0, 0, 1);
+        let mac = MacAddr::new(0, 0, 0, 0, 0, 1);
+
+        assert!(cache.is_empty());
+        assert_eq!(None, cache.insert(ip, mac));
+        assert_eq!(Some(mac), cache.lookup(ip));
+        assert_eq!(None, cache.lookup(Ipv4Addr::new(10, 0, 0, 2)));
+
+        let new_mac = MacAddr::new(0, 0, 0, 0, 0, 2);
+        assert_eq!(Some(mac), cache.insert(ip, new_mac));
+        assert_eq!(Some(new_mac), cache.lookup(ip));
+        assert_eq!(1, cache.len());
+
+        assert_eq!(Some(new_mac), cache.remove(ip));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn expire_arp_cache_entries() {
+        let mut cache = ArpCache::new(Duration::from_millis(10));
+        let ip = Ipv4Addr::new(10, 0, 0, 1);
+        cache.insert(ip, MacAddr::new(0, 0, 0, 0, 0, 1));
+        assert_eq!(0, cache.expire_older_than(Duration::from_secs(60)));
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        // expired entries are not returned but still in the cache.
+        assert_eq!(None, cache.lookup(ip));
+        assert_eq!(1, cache.len());
+
+        cache.insert(Ipv4Addr::new(10, 0, 0, 2), MacAddr::new(0, 0, 0, 0, 0, 2));
+        assert_eq!(1, cache.expire_older_than(Duration::from_millis(10)));
+        assert_eq!(1, cache.len());
+    }
+
+    #[capsule::test]
+    fn learn_from_arp_reply() {
+        let packet = Mbuf::from_bytes(&ARP4_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut arp4 = ethernet.parse::<Arp4>().unwrap();
+        let mut cache = ArpCache::new(Duration::from_secs(60));
+
+        // doesn't learn from requests.
+        assert!(!cache.learn(&arp4));
+        assert!(cache.is_empty());
+
+        arp4.set_operation_code(OperationCodes::Reply);
+        assert!(cache.learn(&arp4));
+        assert_eq!(
+            Some(MacAddr::new(0, 0, 0, 0, 0, 1)),
+            cache.lookup(Ipv4Addr::new(139, 133, 217, 110))
+        );
+    }
 }
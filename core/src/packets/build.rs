@@ -0,0 +1,216 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Builder for composing packets layer by layer.
+
+use crate::net::MacAddr;
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::IpPacket;
+use crate::packets::{Ethernet, Packet, Tcp, Udp};
+use crate::Mbuf;
+use anyhow::Result;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// A builder that composes a packet one layer at a time.
+///
+/// Each layer is pushed onto the message buffer with the addressing fields
+/// set. Other fields of the outermost layer can be changed with [`with`].
+/// [`build`] reconciles the derivable fields of all the layers, such as
+/// the lengths and the checksums, and returns the finished packet.
+///
+/// # Example
+///
+/// ```
+/// let udp = PacketBuilder::ethernet(src_mac, dst_mac)?
+///     .ipv4(src_ip, dst_ip)?
+///     .with(|ipv4| ipv4.set_ttl(32))
+///     .udp(src_port, dst_port)?
+///     .payload(b"hello")?
+///     .build();
+/// ```
+///
+/// [`with`]: PacketBuilder::with
+/// [`build`]: PacketBuilder::build
+#[derive(Debug)]
+pub struct PacketBuilder<T: Packet> {
+    packet: T,
+}
+
+impl PacketBuilder<Ethernet> {
+    /// Starts a new packet with an Ethernet frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the allocation of mbuf fails.
+    pub fn ethernet(src: MacAddr, dst: MacAddr) -> Result<Self> {
+        let mut ethernet = Mbuf::new()?.push::<Ethernet>()?;
+        ethernet.set_src(src);
+        ethernet.set_dst(dst);
+        Ok(PacketBuilder { packet: ethernet })
+    }
+
+    /// Adds an IPv4 packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    pub fn ipv4(self, src: Ipv4Addr, dst: Ipv4Addr) -> Result<PacketBuilder<Ipv4>> {
+        let mut ipv4 = self.packet.push::<Ipv4>()?;
+        ipv4.set_src(src);
+        ipv4.set_dst(dst);
+        Ok(PacketBuilder { packet: ipv4 })
+    }
+
+    /// Adds an IPv6 packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    pub fn ipv6(self, src: Ipv6Addr, dst: Ipv6Addr) -> Result<PacketBuilder<Ipv6>> {
+        let mut ipv6 = self.packet.push::<Ipv6>()?;
+        ipv6.set_src(src);
+        ipv6.set_dst(dst);
+        Ok(PacketBuilder { packet: ipv6 })
+    }
+}
+
+impl<E: IpPacket> PacketBuilder<E> {
+    /// Adds an UDP packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    pub fn udp(self, src_port: u16, dst_port: u16) -> Result<PacketBuilder<Udp<E>>> {
+        let mut udp = self.packet.push::<Udp<E>>()?;
+        udp.set_src_port(src_port);
+        udp.set_dst_port(dst_port);
+        Ok(PacketBuilder { packet: udp })
+    }
+
+    /// Adds a TCP packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    pub fn tcp(self, src_port: u16, dst_port: u16) -> Result<PacketBuilder<Tcp<E>>> {
+        let mut tcp = self.packet.push::<Tcp<E>>()?;
+        tcp.set_src_port(src_port);
+        tcp.set_dst_port(dst_port);
+        Ok(PacketBuilder { packet: tcp })
+    }
+}
+
+impl<T: Packet> PacketBuilder<T> {
+    /// Changes the fields of the outermost layer with the closure.
+    pub fn with<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut T),
+    {
+        f(&mut self.packet);
+        self
+    }
+
+    /// Appends the payload to the end of the packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    pub fn payload(mut self, data: &[u8]) -> Result<Self> {
+        if !data.is_empty() {
+            let offset = self.packet.mbuf().data_len();
+            let mbuf = self.packet.mbuf_mut();
+            mbuf.extend(offset, data.len())?;
+            mbuf.write_data_slice(offset, data)?;
+        }
+
+        Ok(self)
+    }
+
+    /// Reconciles all the layers and returns the finished packet.
+    pub fn build(mut self) -> T {
+        self.packet.reconcile_all();
+        self.packet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::ProtocolNumbers;
+    use crate::packets::EtherTypes;
+    use crate::testils::byte_arrays::IPV4_UDP_PACKET;
+
+    #[capsule::test]
+    fn build_udp_packet() {
+        let udp = PacketBuilder::ethernet(
+            MacAddr::new(0, 0, 0, 0, 0, 2),
+            MacAddr::new(0, 0, 0, 0, 0, 1),
+        )
+        .unwrap()
+        .ipv4(
+            Ipv4Addr::new(139, 133, 217, 110),
+            Ipv4Addr::new(139, 133, 233, 2),
+        )
+        .unwrap()
+        .with(|ipv4| {
+            ipv4.set_identification(43849);
+            ipv4.set_dont_fragment();
+            ipv4.set_ttl(255);
+        })
+        .udp(39376, 1087)
+        .unwrap()
+        .payload(b"hellohello")
+        .unwrap()
+        .build();
+
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), udp.mbuf().to_vec());
+
+        // parses the packet back
+        let packet = udp.reset();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert_eq!(MacAddr::new(0, 0, 0, 0, 0, 2), ethernet.src());
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert_eq!(ProtocolNumbers::Udp, ipv4.protocol());
+        assert_eq!(38, ipv4.total_length());
+        assert_eq!(0xf700, ipv4.checksum());
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(1087, udp.dst_port());
+        assert_eq!(18, udp.length());
+        assert_eq!(0x7228, udp.checksum());
+    }
+
+    #[capsule::test]
+    fn build_tcp_packet() {
+        let tcp = PacketBuilder::ethernet(MacAddr::UNSPECIFIED, MacAddr::UNSPECIFIED)
+            .unwrap()
+            .ipv6(Ipv6Addr::LOCALHOST, Ipv6Addr::LOCALHOST)
+            .unwrap()
+            .tcp(1024, 80)
+            .unwrap()
+            .with(|tcp| tcp.set_syn())
+            .build();
+
+        assert_eq!(1024, tcp.src_port());
+        assert_eq!(80, tcp.dst_port());
+        assert!(tcp.syn());
+        assert_eq!(20, tcp.envelope().payload_length());
+    }
+}
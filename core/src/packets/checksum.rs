@@ -52,6 +52,39 @@ pub enum PseudoHeader {
 }
 
 impl PseudoHeader {
+    /// Creates a pseudo header for the upper-layer protocol carried in an
+    /// IP packet between the source and destination addresses.
+    ///
+    /// `packet_len` is the length of the upper-layer packet, including its
+    /// header. The same pseudo header serves every protocol whose checksum
+    /// covers it, for example TCP, UDP and ICMPv6.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the addresses are not of the same address family.
+    pub fn new(
+        src: IpAddr,
+        dst: IpAddr,
+        packet_len: u16,
+        protocol: ProtocolNumber,
+    ) -> Result<Self> {
+        match (src, dst) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => Ok(PseudoHeader::V4 {
+                src,
+                dst,
+                packet_len,
+                protocol,
+            }),
+            (IpAddr::V6(src), IpAddr::V6(dst)) => Ok(PseudoHeader::V6 {
+                src,
+                dst,
+                packet_len,
+                protocol,
+            }),
+            _ => Err(anyhow!("cannot mix IPv4 and IPv6 addresses.")),
+        }
+    }
+
     /// Calculates the upper-layer checksum based on the psuedo header.
     pub fn sum(&self) -> u16 {
         let mut sum = match *self {
@@ -140,12 +173,60 @@ fn v6_csum(src: Ipv6Addr, dst: Ipv6Addr, packet_len: u16, protocol: ProtocolNumb
 /// set of octets, including the checksum field.  If the result is all 1 bits
 /// (-0 in 1's complement arithmetic), the check succeeds.
 ///
+/// On x86 targets, the sum is vectorized with AVX2 or SSE2 when the CPU
+/// supports it, and falls back to the scalar implementation otherwise.
+///
 /// [IETF RFC 1071]: https://tools.ietf.org/html/rfc1071
-#[allow(clippy::cast_ptr_alignment)]
 pub fn compute(pseudo_header_sum: u16, payload: &[u8]) -> u16 {
+    let mut checksum = u32::from(pseudo_header_sum) + u32::from(sum(payload));
+
+    while checksum >> 16 != 0 {
+        checksum = (checksum >> 16) + (checksum & 0xFFFF);
+    }
+
+    !(checksum as u16)
+}
+
+/// Computes the Internet checksum of the payload as if the 16-bit checksum
+/// field at offset `field` were cleared, without modifying the payload.
+///
+/// The field must start at an even offset, which is the case for all the
+/// checksum fields of the Internet protocols.
+///
+/// # Panics
+///
+/// Panics if the field is beyond the end of the payload.
+pub fn compute_cleared(pseudo_header_sum: u16, payload: &[u8], field: usize) -> u16 {
+    debug_assert!(field % 2 == 0);
+    let partial_sum = !compute(pseudo_header_sum, &payload[..field]);
+    compute(partial_sum, &payload[field + 2..])
+}
+
+/// Returns the folded 1's complement sum of the payload, using the fastest
+/// implementation the CPU supports.
+#[inline]
+fn sum(payload: &[u8]) -> u16 {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { simd::sum_avx2(payload) };
+        }
+
+        if is_x86_feature_detected!("sse2") {
+            return unsafe { simd::sum_sse2(payload) };
+        }
+    }
+
+    sum_scalar(payload)
+}
+
+/// Returns the folded 1's complement sum of the payload one 16-bit word
+/// at a time.
+#[allow(clippy::cast_ptr_alignment)]
+fn sum_scalar(payload: &[u8]) -> u16 {
     let len = payload.len();
     let mut data = payload;
-    let mut checksum = u32::from(pseudo_header_sum);
+    let mut checksum = 0u32;
 
     // odd # of bytes, we add the last byte with padding separately
     if len % 2 > 0 {
@@ -164,7 +245,108 @@ pub fn compute(pseudo_header_sum: u16, payload: &[u8]) -> u16 {
         checksum = (checksum >> 16) + (checksum & 0xFFFF);
     }
 
-    !(checksum as u16)
+    checksum as u16
+}
+
+/// Vectorized 1's complement sums.
+///
+/// The 16-bit words are summed in native byte order and the folded sum is
+/// swapped back to network byte order at the end. The 1's complement sum
+/// is independent of byte order, as noted in [IETF RFC 1071], so the result
+/// is identical to the scalar sum.
+///
+/// [IETF RFC 1071]: https://tools.ietf.org/html/rfc1071#section-2
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[allow(clippy::cast_ptr_alignment)]
+mod simd {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    // Each 32-bit lane receives two 16-bit words per iteration. Flushing
+    // the lanes before 32768 iterations guarantees they never overflow.
+    const FLUSH_INTERVAL: usize = 32_767;
+
+    /// Sums the payload 32 bytes at a time.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn sum_avx2(payload: &[u8]) -> u16 {
+        let zero = _mm256_setzero_si256();
+        let mut acc = zero;
+        let mut total = 0u64;
+        let mut chunks = payload.chunks_exact(32);
+
+        for (i, chunk) in (&mut chunks).enumerate() {
+            let words = _mm256_loadu_si256(chunk.as_ptr() as *const __m256i);
+            acc = _mm256_add_epi32(acc, _mm256_unpacklo_epi16(words, zero));
+            acc = _mm256_add_epi32(acc, _mm256_unpackhi_epi16(words, zero));
+
+            if (i + 1) % FLUSH_INTERVAL == 0 {
+                total += drain_avx2(acc);
+                acc = zero;
+            }
+        }
+
+        total += drain_avx2(acc);
+        finish(total, chunks.remainder())
+    }
+
+    /// Sums the payload 16 bytes at a time.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn sum_sse2(payload: &[u8]) -> u16 {
+        let zero = _mm_setzero_si128();
+        let mut acc = zero;
+        let mut total = 0u64;
+        let mut chunks = payload.chunks_exact(16);
+
+        for (i, chunk) in (&mut chunks).enumerate() {
+            let words = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            acc = _mm_add_epi32(acc, _mm_unpacklo_epi16(words, zero));
+            acc = _mm_add_epi32(acc, _mm_unpackhi_epi16(words, zero));
+
+            if (i + 1) % FLUSH_INTERVAL == 0 {
+                total += drain_sse2(acc);
+                acc = zero;
+            }
+        }
+
+        total += drain_sse2(acc);
+        finish(total, chunks.remainder())
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn drain_avx2(acc: __m256i) -> u64 {
+        let mut lanes = [0u32; 8];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, acc);
+        lanes.iter().map(|&x| u64::from(x)).sum()
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn drain_sse2(acc: __m128i) -> u64 {
+        let mut lanes = [0u32; 4];
+        _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, acc);
+        lanes.iter().map(|&x| u64::from(x)).sum()
+    }
+
+    /// Adds the tail bytes that don't fill a vector, folds the total and
+    /// swaps the sum back to network byte order.
+    fn finish(mut total: u64, tail: &[u8]) -> u16 {
+        let mut words = tail.chunks_exact(2);
+        for word in &mut words {
+            total += u64::from(u16::from_ne_bytes([word[0], word[1]]));
+        }
+
+        // odd # of bytes, the last byte is padded with zero
+        if let [last] = words.remainder() {
+            total += u64::from(u16::from_ne_bytes([*last, 0]));
+        }
+
+        while total >> 16 != 0 {
+            total = (total >> 16) + (total & 0xFFFF);
+        }
+
+        u16::from_be(total as u16)
+    }
 }
 
 /// Computes the Internet checksum via incremental update as defined in
@@ -219,12 +401,188 @@ pub fn compute_with_ipaddr(
     }
 }
 
+/// Lookup table for the reflected CRC-32 polynomial `0xEDB88320`.
+const CRC32_TABLE: [u32; 256] = crc32_table(0xEDB8_8320);
+
+/// Lookup table for the reflected CRC32c polynomial `0x82F63B78`.
+const CRC32C_TABLE: [u32; 256] = crc32_table(0x82F6_3B78);
+
+const fn crc32_table(polynomial: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ polynomial
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+fn crc32_with(table: &[u32; 256], data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        table[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Computes the CRC-32 checksum as defined in [IEEE 802.3].
+///
+/// CRC-32 is used as the frame check sequence of Ethernet frames.
+///
+/// [IEEE 802.3]: https://standards.ieee.org/standard/802_3-2018.html
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_with(&CRC32_TABLE, data)
+}
+
+/// Computes the CRC32c (Castagnoli) checksum as defined in [IETF RFC 3309].
+///
+/// CRC32c is used by SCTP instead of the Internet checksum.
+///
+/// [IETF RFC 3309]: https://tools.ietf.org/html/rfc3309
+pub fn crc32c(data: &[u8]) -> u32 {
+    crc32_with(&CRC32C_TABLE, data)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::packets::ip::ProtocolNumbers;
+    use crate::testils::byte_arrays::{IPV4_TCP_PACKET, IPV4_UDP_PACKET, IPV6_TCP_PACKET};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn compute_crc32() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+        assert_eq!(0, crc32(&[]));
+    }
+
+    #[test]
+    fn compute_crc32c() {
+        // test vectors from IETF RFC 3720, appendix B.4.
+        assert_eq!(0x8a91_36aa, crc32c(&[0; 32]));
+        assert_eq!(0x62a8_ab43, crc32c(&[0xff; 32]));
+        assert_eq!(0xe306_9283, crc32c(b"123456789"));
+        assert_eq!(0, crc32c(&[]));
+    }
 
     #[test]
     fn compute_checksum_incrementally() {
         assert_eq!(0x0000, compute_inc(0xdd2f, &[0x5555], &[0x3285]));
     }
+
+    #[test]
+    fn compute_checksum_of_ipv4_header() {
+        let header = [
+            0x45, 0x00, 0x00, 0x26, 0xab, 0x49, 0x40, 0x00, 0xff, 0x11, 0x00, 0x00, 0x8b, 0x85,
+            0xd9, 0x6e, 0x8b, 0x85, 0xe9, 0x02,
+        ];
+        assert_eq!(0xf700, compute(0, &header));
+    }
+
+    /// Computes the checksum of the upper-layer packet over the pseudo
+    /// header, with the checksum field at `field` cleared.
+    fn upper_layer_checksum(pseudo_header: &PseudoHeader, packet: &[u8], field: usize) -> u16 {
+        let mut packet = packet.to_vec();
+        packet[field] = 0;
+        packet[field + 1] = 0;
+        compute(pseudo_header.sum(), &packet)
+    }
+
+    #[test]
+    fn compute_checksum_with_field_cleared() {
+        let header = [
+            0x45, 0x00, 0x00, 0x26, 0xab, 0x49, 0x40, 0x00, 0xff, 0x11, 0xf7, 0x00, 0x8b, 0x85,
+            0xd9, 0x6e, 0x8b, 0x85, 0xe9, 0x02,
+        ];
+        assert_eq!(0xf700, compute_cleared(0, &header, 10));
+
+        let src = "139.133.217.110".parse().unwrap();
+        let dst = "139.133.233.2".parse().unwrap();
+
+        let udp = &IPV4_UDP_PACKET[34..];
+        let pseudo_header =
+            PseudoHeader::new(src, dst, udp.len() as u16, ProtocolNumbers::Udp).unwrap();
+        assert_eq!(0x7228, compute_cleared(pseudo_header.sum(), udp, 6));
+    }
+
+    #[test]
+    fn compute_checksum_with_ipv4_pseudo_header() {
+        let src = "139.133.217.110".parse().unwrap();
+        let dst = "139.133.233.2".parse().unwrap();
+
+        let udp = &IPV4_UDP_PACKET[34..];
+        let pseudo_header =
+            PseudoHeader::new(src, dst, udp.len() as u16, ProtocolNumbers::Udp).unwrap();
+        assert_eq!(0x7228, upper_layer_checksum(&pseudo_header, udp, 6));
+
+        let tcp = &IPV4_TCP_PACKET[34..];
+        let pseudo_header =
+            PseudoHeader::new(src, dst, tcp.len() as u16, ProtocolNumbers::Tcp).unwrap();
+        assert_eq!(0xa92c, upper_layer_checksum(&pseudo_header, tcp, 16));
+    }
+
+    #[test]
+    fn compute_checksum_with_ipv6_pseudo_header() {
+        let src = "2001:db8:85a3::1".parse().unwrap();
+        let dst = "2001:db8:85a3::8a2e:370:7334".parse().unwrap();
+
+        // the UDP datagram of `IPV4_UDP_PACKET` sent over IPv6.
+        let udp = &IPV4_UDP_PACKET[34..];
+        let pseudo_header =
+            PseudoHeader::new(src, dst, udp.len() as u16, ProtocolNumbers::Udp).unwrap();
+        assert_eq!(0xe417, upper_layer_checksum(&pseudo_header, udp, 6));
+
+        let tcp = &IPV6_TCP_PACKET[54..];
+        let pseudo_header =
+            PseudoHeader::new(src, dst, tcp.len() as u16, ProtocolNumbers::Tcp).unwrap();
+        assert_eq!(0x1b1c, upper_layer_checksum(&pseudo_header, tcp, 16));
+    }
+
+    #[test]
+    fn cannot_mix_address_families_in_pseudo_header() {
+        let src = "139.133.217.110".parse().unwrap();
+        let dst = "2001:db8:85a3::1".parse().unwrap();
+        assert!(PseudoHeader::new(src, dst, 8, ProtocolNumbers::Udp).is_err());
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn simd_sum_matches_scalar_for_short_payloads() {
+        let data = (0..64u8)
+            .map(|x| x.wrapping_mul(37) ^ 0xa5)
+            .collect::<Vec<_>>();
+
+        for len in 0..64 {
+            let expected = sum_scalar(&data[..len]);
+            if is_x86_feature_detected!("sse2") {
+                assert_eq!(expected, unsafe { simd::sum_sse2(&data[..len]) });
+            }
+            if is_x86_feature_detected!("avx2") {
+                assert_eq!(expected, unsafe { simd::sum_avx2(&data[..len]) });
+            }
+        }
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    proptest! {
+        #[test]
+        fn simd_sum_matches_scalar(data in vec(any::<u8>(), 0..4096)) {
+            let expected = sum_scalar(&data);
+            if is_x86_feature_detected!("sse2") {
+                prop_assert_eq!(expected, unsafe { simd::sum_sse2(&data) });
+            }
+            if is_x86_feature_detected!("avx2") {
+                prop_assert_eq!(expected, unsafe { simd::sum_avx2(&data) });
+            }
+        }
+    }
 }
@@ -0,0 +1,925 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Dynamic Host Configuration Protocol.
+
+use crate::net::MacAddr;
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{Internal, Packet, Udp};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::ptr::NonNull;
+
+/// The UDP port of DHCP servers.
+pub const SERVER_PORT: u16 = 67;
+
+/// The UDP port of DHCP clients.
+pub const CLIENT_PORT: u16 = 68;
+
+/// The magic cookie that precedes the DHCP options.
+pub const MAGIC_COOKIE: u32 = 0x6382_5363;
+
+// Ethernet hardware address type.
+const HTYPE_ETHERNET: u8 = 1;
+
+// Broadcast flag.
+const FLAGS_BROADCAST: u16be = u16be(u16::to_be(0b1000_0000_0000_0000));
+
+/// Dynamic Host Configuration Protocol message based on [IETF RFC 2131].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     op (1)    |   htype (1)   |   hlen (1)    |   hops (1)    |
+/// +---------------+---------------+---------------+---------------+
+/// |                            xid (4)                            |
+/// +-------------------------------+-------------------------------+
+/// |           secs (2)            |           flags (2)           |
+/// +-------------------------------+-------------------------------+
+/// |                          ciaddr  (4)                          |
+/// +---------------------------------------------------------------+
+/// |                          yiaddr  (4)                          |
+/// +---------------------------------------------------------------+
+/// |                          siaddr  (4)                          |
+/// +---------------------------------------------------------------+
+/// |                          giaddr  (4)                          |
+/// +---------------------------------------------------------------+
+/// |                          chaddr  (16)                         |
+/// +---------------------------------------------------------------+
+/// |                          sname   (64)                         |
+/// +---------------------------------------------------------------+
+/// |                          file    (128)                        |
+/// +---------------------------------------------------------------+
+/// |                          options (variable)                   |
+/// +---------------------------------------------------------------+
+/// ```
+///
+/// - *op*: (8 bits)
+///      Message op code, 1 = BOOTREQUEST, 2 = BOOTREPLY.
+///
+/// - *htype*: (8 bits)
+///      Hardware address type, 1 = 10Mb Ethernet.
+///
+/// - *hlen*: (8 bits)
+///      Hardware address length, 6 for Ethernet.
+///
+/// - *hops*: (8 bits)
+///      Incremented by relay agents.
+///
+/// - *xid*: (32 bits)
+///      Transaction ID chosen by the client, used to associate messages
+///      and responses.
+///
+/// - *secs*: (16 bits)
+///      Seconds elapsed since client began address acquisition or renewal
+///      process.
+///
+/// - *flags*: (16 bits)
+///      The left-most bit is the broadcast flag.
+///
+/// - *ciaddr*: (32 bits)
+///      Client IP address, only filled in if client is in BOUND, RENEW or
+///      REBINDING state.
+///
+/// - *yiaddr*: (32 bits)
+///      'your' (client) IP address.
+///
+/// - *siaddr*: (32 bits)
+///      IP address of next server to use in bootstrap.
+///
+/// - *giaddr*: (32 bits)
+///      Relay agent IP address.
+///
+/// - *chaddr*: (128 bits)
+///      Client hardware address.
+///
+/// - *sname*: (512 bits)
+///      Optional server host name, null terminated string.
+///
+/// - *file*: (1024 bits)
+///      Boot file name, null terminated string.
+///
+/// - *options*: (variable)
+///      Optional parameters field, starting with the magic cookie
+///      `99.130.83.99`.
+///
+/// [IETF RFC 2131]: https://tools.ietf.org/html/rfc2131
+pub struct Dhcp {
+    envelope: Udp<Ipv4>,
+    header: NonNull<DhcpHeader>,
+    offset: usize,
+}
+
+impl Dhcp {
+    #[inline]
+    fn header(&self) -> &DhcpHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut DhcpHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the message op code.
+    #[inline]
+    pub fn op(&self) -> OpCode {
+        OpCode::new(self.header().op)
+    }
+
+    /// Sets the message op code.
+    #[inline]
+    pub fn set_op(&mut self, op: OpCode) {
+        self.header_mut().op = op.0
+    }
+
+    /// Returns the hardware address type.
+    #[inline]
+    pub fn htype(&self) -> u8 {
+        self.header().htype
+    }
+
+    /// Returns the hardware address length.
+    #[inline]
+    pub fn hlen(&self) -> u8 {
+        self.header().hlen
+    }
+
+    /// Returns the number of relay agent hops.
+    #[inline]
+    pub fn hops(&self) -> u8 {
+        self.header().hops
+    }
+
+    /// Sets the number of relay agent hops.
+    #[inline]
+    pub fn set_hops(&mut self, hops: u8) {
+        self.header_mut().hops = hops
+    }
+
+    /// Returns the transaction ID.
+    #[inline]
+    pub fn xid(&self) -> u32 {
+        self.header().xid.into()
+    }
+
+    /// Sets the transaction ID.
+    #[inline]
+    pub fn set_xid(&mut self, xid: u32) {
+        self.header_mut().xid = xid.into()
+    }
+
+    /// Returns the seconds elapsed since the client began the address
+    /// acquisition.
+    #[inline]
+    pub fn secs(&self) -> u16 {
+        self.header().secs.into()
+    }
+
+    /// Sets the seconds elapsed.
+    #[inline]
+    pub fn set_secs(&mut self, secs: u16) {
+        self.header_mut().secs = secs.into()
+    }
+
+    /// Returns a flag indicating whether the reply should be broadcast.
+    #[inline]
+    pub fn broadcast(&self) -> bool {
+        self.header().flags & FLAGS_BROADCAST != u16be::MIN
+    }
+
+    /// Sets the broadcast flag.
+    #[inline]
+    pub fn set_broadcast(&mut self) {
+        self.header_mut().flags |= FLAGS_BROADCAST
+    }
+
+    /// Returns the client IP address.
+    #[inline]
+    pub fn ciaddr(&self) -> Ipv4Addr {
+        self.header().ciaddr
+    }
+
+    /// Sets the client IP address.
+    #[inline]
+    pub fn set_ciaddr(&mut self, addr: Ipv4Addr) {
+        self.header_mut().ciaddr = addr
+    }
+
+    /// Returns the 'your' (client) IP address.
+    #[inline]
+    pub fn yiaddr(&self) -> Ipv4Addr {
+        self.header().yiaddr
+    }
+
+    /// Sets the 'your' (client) IP address.
+    #[inline]
+    pub fn set_yiaddr(&mut self, addr: Ipv4Addr) {
+        self.header_mut().yiaddr = addr
+    }
+
+    /// Returns the IP address of the next server to use in bootstrap.
+    #[inline]
+    pub fn siaddr(&self) -> Ipv4Addr {
+        self.header().siaddr
+    }
+
+    /// Sets the IP address of the next server to use in bootstrap.
+    #[inline]
+    pub fn set_siaddr(&mut self, addr: Ipv4Addr) {
+        self.header_mut().siaddr = addr
+    }
+
+    /// Returns the relay agent IP address.
+    #[inline]
+    pub fn giaddr(&self) -> Ipv4Addr {
+        self.header().giaddr
+    }
+
+    /// Sets the relay agent IP address.
+    #[inline]
+    pub fn set_giaddr(&mut self, addr: Ipv4Addr) {
+        self.header_mut().giaddr = addr
+    }
+
+    /// Returns the client hardware address field.
+    #[inline]
+    pub fn chaddr(&self) -> [u8; 16] {
+        self.header().chaddr
+    }
+
+    /// Returns the client MAC address.
+    ///
+    /// Returns `None` if the hardware address is not a 6-byte Ethernet
+    /// address.
+    #[inline]
+    pub fn client_mac(&self) -> Option<MacAddr> {
+        if self.hlen() == 6 {
+            let chaddr = self.chaddr();
+            let mut octets = [0; 6];
+            octets.copy_from_slice(&chaddr[..6]);
+            Some(octets.into())
+        } else {
+            None
+        }
+    }
+
+    /// Sets the client hardware address to the MAC address.
+    #[inline]
+    pub fn set_client_mac(&mut self, mac: MacAddr) {
+        let header = self.header_mut();
+        header.htype = HTYPE_ETHERNET;
+        header.hlen = 6;
+        header.chaddr = [0; 16];
+        header.chaddr[..6].copy_from_slice(&mac.octets());
+    }
+
+    /// Returns an iterator over the options.
+    ///
+    /// The options end with the UDP payload. The data past the UDP length,
+    /// such as the Ethernet padding, is not parsed as options.
+    #[inline]
+    pub fn options(&self) -> DhcpOptionsIterator<'_> {
+        let udp = self.envelope();
+        let pkt_len = self.mbuf().pkt_len();
+
+        // a zero length is a pushed header not reconciled yet.
+        let end = match udp.length() as usize {
+            0 => pkt_len,
+            len => (udp.offset() + len).min(pkt_len),
+        };
+
+        DhcpOptionsIterator {
+            mbuf: self.mbuf(),
+            offset: self.payload_offset(),
+            end,
+        }
+    }
+
+    /// Returns the data of the first option with the option code.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an option is malformed.
+    pub fn option(&self, code: DhcpOptionCode) -> Result<Option<&[u8]>> {
+        let mut iter = self.options();
+        while let Some(option) = iter.next()? {
+            if option.code() == code {
+                return Ok(Some(option.data()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the DHCP message type, option 53.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an option is malformed.
+    pub fn message_type(&self) -> Result<Option<MessageType>> {
+        match self.option(DhcpOptionCodes::MessageType)? {
+            Some(&[value]) => Ok(Some(MessageType::new(value))),
+            Some(_) => Err(anyhow!("invalid DHCP message type option.")),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the requested IP address, option 50.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an option is malformed.
+    pub fn requested_ip(&self) -> Result<Option<Ipv4Addr>> {
+        self.ipv4_option(DhcpOptionCodes::RequestedIpAddress)
+    }
+
+    /// Returns the server identifier, option 54.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an option is malformed.
+    pub fn server_identifier(&self) -> Result<Option<Ipv4Addr>> {
+        self.ipv4_option(DhcpOptionCodes::ServerIdentifier)
+    }
+
+    fn ipv4_option(&self, code: DhcpOptionCode) -> Result<Option<Ipv4Addr>> {
+        match self.option(code)? {
+            Some(&[a, b, c, d]) => Ok(Some(Ipv4Addr::new(a, b, c, d))),
+            Some(_) => Err(anyhow!("invalid DHCP option {} length.", code)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl fmt::Debug for Dhcp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("dhcp")
+            .field("op", &format!("{}", self.op()))
+            .field("htype", &self.htype())
+            .field("hlen", &self.hlen())
+            .field("hops", &self.hops())
+            .field("xid", &format!("0x{:08x}", self.xid()))
+            .field("secs", &self.secs())
+            .field("broadcast", &self.broadcast())
+            .field("ciaddr", &self.ciaddr())
+            .field("yiaddr", &self.yiaddr())
+            .field("siaddr", &self.siaddr())
+            .field("giaddr", &self.giaddr())
+            .field("chaddr", &self.chaddr())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Dhcp {
+    /// The preceding type for DHCP must be an IPv4 `Udp`.
+    type Envelope = Udp<Ipv4>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the fixed fields and the magic cookie. The
+    /// options are the payload.
+    #[inline]
+    fn header_len(&self) -> usize {
+        DhcpHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Dhcp {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP payload as a DHCP message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither the source nor the destination port is
+    /// a DHCP port. Returns an error if the payload does not have sufficient
+    /// data for the fixed fields, or the magic cookie does not match.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let is_dhcp_port = |port| port == SERVER_PORT || port == CLIENT_PORT;
+        ensure!(
+            is_dhcp_port(envelope.src_port()) || is_dhcp_port(envelope.dst_port()),
+            anyhow!("not a DHCP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Dhcp {
+            envelope,
+            header,
+            offset,
+        };
+
+        let cookie = u32::from(packet.header().magic_cookie);
+        ensure!(
+            cookie == MAGIC_COOKIE,
+            anyhow!("invalid DHCP magic cookie 0x{:08x}.", cookie)
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends a DHCP message to the beginning of the UDP's payload.
+    ///
+    /// The magic cookie is set and the client hardware address type is
+    /// set to Ethernet. The message has no options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, DhcpHeader::size_of())?;
+        let header = mbuf.write_data(offset, &DhcpHeader::default())?;
+
+        Ok(Dhcp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// DHCP message op code.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct OpCode(pub u8);
+
+impl OpCode {
+    /// Creates a new op code.
+    pub fn new(value: u8) -> Self {
+        OpCode(value)
+    }
+}
+
+/// Supported op codes.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod OpCodes {
+    use super::OpCode;
+
+    /// Message sent from a client to a server.
+    pub const BootRequest: OpCode = OpCode(1);
+    /// Message sent from a server to a client.
+    pub const BootReply: OpCode = OpCode(2);
+}
+
+impl fmt::Display for OpCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                OpCodes::BootRequest => "BOOTREQUEST".to_string(),
+                OpCodes::BootReply => "BOOTREPLY".to_string(),
+                _ => {
+                    let op = self.0;
+                    format!("{}", op)
+                }
+            }
+        )
+    }
+}
+
+/// [IANA] assigned DHCP message type, the value of option 53.
+///
+/// [IANA]: https://www.iana.org/assignments/bootp-dhcp-parameters/bootp-dhcp-parameters.xhtml#message-type-53
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct MessageType(pub u8);
+
+impl MessageType {
+    /// Creates a new message type.
+    pub fn new(value: u8) -> Self {
+        MessageType(value)
+    }
+}
+
+/// Supported message types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod MessageTypes {
+    use super::MessageType;
+
+    /// DHCPDISCOVER.
+    pub const Discover: MessageType = MessageType(1);
+    /// DHCPOFFER.
+    pub const Offer: MessageType = MessageType(2);
+    /// DHCPREQUEST.
+    pub const Request: MessageType = MessageType(3);
+    /// DHCPDECLINE.
+    pub const Decline: MessageType = MessageType(4);
+    /// DHCPACK.
+    pub const Ack: MessageType = MessageType(5);
+    /// DHCPNAK.
+    pub const Nak: MessageType = MessageType(6);
+    /// DHCPRELEASE.
+    pub const Release: MessageType = MessageType(7);
+    /// DHCPINFORM.
+    pub const Inform: MessageType = MessageType(8);
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                MessageTypes::Discover => "DHCPDISCOVER".to_string(),
+                MessageTypes::Offer => "DHCPOFFER".to_string(),
+                MessageTypes::Request => "DHCPREQUEST".to_string(),
+                MessageTypes::Decline => "DHCPDECLINE".to_string(),
+                MessageTypes::Ack => "DHCPACK".to_string(),
+                MessageTypes::Nak => "DHCPNAK".to_string(),
+                MessageTypes::Release => "DHCPRELEASE".to_string(),
+                MessageTypes::Inform => "DHCPINFORM".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("{}", t)
+                }
+            }
+        )
+    }
+}
+
+/// [IANA] assigned DHCP option code.
+///
+/// [IANA]: https://www.iana.org/assignments/bootp-dhcp-parameters/bootp-dhcp-parameters.xhtml#options
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct DhcpOptionCode(pub u8);
+
+impl DhcpOptionCode {
+    /// Creates a new option code.
+    pub fn new(value: u8) -> Self {
+        DhcpOptionCode(value)
+    }
+}
+
+/// Supported option codes.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod DhcpOptionCodes {
+    use super::DhcpOptionCode;
+
+    /// Pad, used to align the subsequent options.
+    pub const Pad: DhcpOptionCode = DhcpOptionCode(0);
+    /// Subnet mask.
+    pub const SubnetMask: DhcpOptionCode = DhcpOptionCode(1);
+    /// Router.
+    pub const Router: DhcpOptionCode = DhcpOptionCode(3);
+    /// Domain name server.
+    pub const DomainNameServer: DhcpOptionCode = DhcpOptionCode(6);
+    /// Host name.
+    pub const HostName: DhcpOptionCode = DhcpOptionCode(12);
+    /// Requested IP address.
+    pub const RequestedIpAddress: DhcpOptionCode = DhcpOptionCode(50);
+    /// IP address lease time.
+    pub const LeaseTime: DhcpOptionCode = DhcpOptionCode(51);
+    /// DHCP message type.
+    pub const MessageType: DhcpOptionCode = DhcpOptionCode(53);
+    /// Server identifier.
+    pub const ServerIdentifier: DhcpOptionCode = DhcpOptionCode(54);
+    /// Parameter request list.
+    pub const ParameterRequestList: DhcpOptionCode = DhcpOptionCode(55);
+    /// Client identifier.
+    pub const ClientIdentifier: DhcpOptionCode = DhcpOptionCode(61);
+    /// End, marks the end of the options.
+    pub const End: DhcpOptionCode = DhcpOptionCode(255);
+}
+
+impl fmt::Display for DhcpOptionCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                DhcpOptionCodes::Pad => "Pad".to_string(),
+                DhcpOptionCodes::SubnetMask => "Subnet Mask".to_string(),
+                DhcpOptionCodes::Router => "Router".to_string(),
+                DhcpOptionCodes::DomainNameServer => "Domain Name Server".to_string(),
+                DhcpOptionCodes::HostName => "Host Name".to_string(),
+                DhcpOptionCodes::RequestedIpAddress => "Requested IP Address".to_string(),
+                DhcpOptionCodes::LeaseTime => "Lease Time".to_string(),
+                DhcpOptionCodes::MessageType => "Message Type".to_string(),
+                DhcpOptionCodes::ServerIdentifier => "Server Identifier".to_string(),
+                DhcpOptionCodes::ParameterRequestList => "Parameter Request List".to_string(),
+                DhcpOptionCodes::ClientIdentifier => "Client Identifier".to_string(),
+                DhcpOptionCodes::End => "End".to_string(),
+                _ => {
+                    let code = self.0;
+                    format!("{}", code)
+                }
+            }
+        )
+    }
+}
+
+/// A DHCP option.
+pub struct DhcpOption<'a> {
+    code: DhcpOptionCode,
+    data: &'a [u8],
+}
+
+impl<'a> DhcpOption<'a> {
+    /// Returns the option code.
+    #[inline]
+    pub fn code(&self) -> DhcpOptionCode {
+        self.code
+    }
+
+    /// Returns the option data, excluding the code and length.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl fmt::Debug for DhcpOption<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DhcpOption")
+            .field("code", &format!("{}", self.code()))
+            .field("data", &self.data())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the DHCP options.
+///
+/// Pad options are skipped and the iteration stops at the end option.
+/// An option that continues past the first segment of a chained buffer
+/// is returned as an error.
+pub struct DhcpOptionsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> DhcpOptionsIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<DhcpOption<'a>>> {
+        while self.end > self.offset {
+            let code = unsafe { *self.mbuf.read_data::<u8>(self.offset)?.as_ref() };
+            let code = DhcpOptionCode(code);
+
+            match code {
+                DhcpOptionCodes::Pad => self.offset += 1,
+                DhcpOptionCodes::End => {
+                    self.offset = self.end;
+                }
+                _ => {
+                    let len = unsafe { *self.mbuf.read_data::<u8>(self.offset + 1)?.as_ref() };
+                    let len = len as usize;
+                    ensure!(
+                        self.offset + 2 + len <= self.end,
+                        anyhow!("DHCP option {} is past the end of the UDP payload.", code)
+                    );
+                    let data: &'a [u8] = if len > 0 {
+                        let data = self.mbuf.read_data_slice::<u8>(self.offset + 2, len)?;
+                        unsafe { &*data.as_ptr() }
+                    } else {
+                        &[]
+                    };
+
+                    self.offset += 2 + len;
+                    return Ok(Some(DhcpOption { code, data }));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl fmt::Debug for DhcpOptionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DhcpOptionsIterator")
+            .field("offset", &self.offset)
+            .field("end", &self.end)
+            .finish()
+    }
+}
+
+/// DHCP fixed fields and the magic cookie.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct DhcpHeader {
+    op: u8,
+    htype: u8,
+    hlen: u8,
+    hops: u8,
+    xid: u32be,
+    secs: u16be,
+    flags: u16be,
+    ciaddr: Ipv4Addr,
+    yiaddr: Ipv4Addr,
+    siaddr: Ipv4Addr,
+    giaddr: Ipv4Addr,
+    chaddr: [u8; 16],
+    sname: [u8; 64],
+    file: [u8; 128],
+    magic_cookie: u32be,
+}
+
+impl Default for DhcpHeader {
+    fn default() -> DhcpHeader {
+        DhcpHeader {
+            op: OpCodes::BootRequest.0,
+            htype: HTYPE_ETHERNET,
+            hlen: 6,
+            hops: 0,
+            xid: u32be::default(),
+            secs: u16be::default(),
+            flags: u16be::default(),
+            ciaddr: Ipv4Addr::UNSPECIFIED,
+            yiaddr: Ipv4Addr::UNSPECIFIED,
+            siaddr: Ipv4Addr::UNSPECIFIED,
+            giaddr: Ipv4Addr::UNSPECIFIED,
+            chaddr: [0; 16],
+            sname: [0; 64],
+            file: [0; 128],
+            magic_cookie: MAGIC_COOKIE.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{DHCP_DISCOVER_PACKET, IPV4_UDP_PACKET};
+
+    #[test]
+    fn size_of_dhcp_header() {
+        assert_eq!(240, DhcpHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_dhcp_discover() {
+        let packet = Mbuf::from_bytes(&DHCP_DISCOVER_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let dhcp = udp.parse::<Dhcp>().unwrap();
+
+        assert_eq!(OpCodes::BootRequest, dhcp.op());
+        assert_eq!(1, dhcp.htype());
+        assert_eq!(6, dhcp.hlen());
+        assert_eq!(0x3903_f326, dhcp.xid());
+        assert_eq!(Ipv4Addr::UNSPECIFIED, dhcp.ciaddr());
+        assert_eq!(
+            Some(MacAddr::new(0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42)),
+            dhcp.client_mac()
+        );
+
+        assert_eq!(Some(MessageTypes::Discover), dhcp.message_type().unwrap());
+        assert_eq!(Some(Ipv4Addr::UNSPECIFIED), dhcp.requested_ip().unwrap());
+        assert_eq!(None, dhcp.server_identifier().unwrap());
+    }
+
+    #[capsule::test]
+    fn iterate_dhcp_options() {
+        let packet = Mbuf::from_bytes(&DHCP_DISCOVER_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let dhcp = udp.parse::<Dhcp>().unwrap();
+
+        let mut codes = vec![];
+        let mut iter = dhcp.options();
+        while let Some(option) = iter.next().unwrap() {
+            codes.push(option.code());
+        }
+
+        assert_eq!(
+            vec![
+                DhcpOptionCodes::MessageType,
+                DhcpOptionCodes::ClientIdentifier,
+                DhcpOptionCodes::RequestedIpAddress,
+                DhcpOptionCodes::ParameterRequestList,
+            ],
+            codes
+        );
+
+        let params = dhcp.option(DhcpOptionCodes::ParameterRequestList).unwrap();
+        assert_eq!(Some(&[1, 3, 6, 42][..]), params);
+    }
+
+    #[capsule::test]
+    fn stop_dhcp_options_at_end_of_udp_payload() {
+        // drops the end option and pads the frame with what looks like
+        // another option.
+        let mut bytes = DHCP_DISCOVER_PACKET[..306].to_vec();
+        // UDP length = 272
+        bytes[38] = 0x01;
+        bytes[39] = 0x10;
+        bytes.extend_from_slice(&[0x35, 0x01, 0x05, 0x00]);
+
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let dhcp = udp.parse::<Dhcp>().unwrap();
+
+        let mut codes = vec![];
+        let mut iter = dhcp.options();
+        while let Some(option) = iter.next().unwrap() {
+            codes.push(option.code());
+        }
+
+        assert_eq!(
+            vec![
+                DhcpOptionCodes::MessageType,
+                DhcpOptionCodes::ClientIdentifier,
+                DhcpOptionCodes::RequestedIpAddress,
+                DhcpOptionCodes::ParameterRequestList,
+            ],
+            codes
+        );
+    }
+
+    #[capsule::test]
+    fn parse_non_dhcp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Dhcp>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_dhcp_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        udp.set_src_port(CLIENT_PORT);
+        udp.set_dst_port(SERVER_PORT);
+        let mut dhcp = udp.push::<Dhcp>().unwrap();
+        dhcp.set_client_mac(MacAddr::new(0, 0, 0, 0, 0, 1));
+
+        assert_eq!(240, dhcp.len());
+        assert_eq!(OpCodes::BootRequest, dhcp.op());
+        assert_eq!(Some(MacAddr::new(0, 0, 0, 0, 0, 1)), dhcp.client_mac());
+        assert_eq!(None, dhcp.message_type().unwrap());
+
+        // parses back the pushed packet
+        let udp = dhcp.deparse();
+        assert!(udp.peek::<Dhcp>().is_ok());
+    }
+}
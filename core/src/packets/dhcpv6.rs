@@ -0,0 +1,601 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Dynamic Host Configuration Protocol for IPv6.
+
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::types::u16be;
+use crate::packets::{Internal, Packet, Udp};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The UDP port of DHCPv6 clients.
+pub const CLIENT_PORT: u16 = 546;
+
+/// The UDP port of DHCPv6 servers and relay agents.
+pub const SERVER_PORT: u16 = 547;
+
+/// Dynamic Host Configuration Protocol for IPv6 message based on
+/// [IETF RFC 8415].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |    msg-type   |               transaction-id                  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// .                            options                            .
+/// .                 (variable number and length)                  .
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *msg-type*: (8 bits)
+///      Identifies the DHCP message type.
+///
+/// - *transaction-id*: (24 bits)
+///      The transaction ID for this message exchange.
+///
+/// - *options*: (variable)
+///      Options carried in this message. Each option starts with a 16-bit
+///      option code and a 16-bit option length.
+///
+/// Relay-forward and relay-reply messages use a different layout and are
+/// not supported.
+///
+/// [IETF RFC 8415]: https://tools.ietf.org/html/rfc8415
+pub struct Dhcpv6 {
+    envelope: Udp<Ipv6>,
+    header: NonNull<Dhcpv6Header>,
+    offset: usize,
+}
+
+impl Dhcpv6 {
+    #[inline]
+    fn header(&self) -> &Dhcpv6Header {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut Dhcpv6Header {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the message type.
+    #[inline]
+    pub fn msg_type(&self) -> MessageType {
+        MessageType::new(self.header().msg_type)
+    }
+
+    /// Sets the message type.
+    #[inline]
+    pub fn set_msg_type(&mut self, msg_type: MessageType) {
+        self.header_mut().msg_type = msg_type.0;
+    }
+
+    /// Returns the 24-bit transaction ID.
+    #[inline]
+    pub fn transaction_id(&self) -> u32 {
+        let [a, b, c] = self.header().transaction_id;
+        u32::from_be_bytes([0, a, b, c])
+    }
+
+    /// Sets the transaction ID. Only the lower 24 bits are used.
+    #[inline]
+    pub fn set_transaction_id(&mut self, transaction_id: u32) {
+        let [_, a, b, c] = transaction_id.to_be_bytes();
+        self.header_mut().transaction_id = [a, b, c];
+    }
+
+    /// Returns an iterator over the options.
+    #[inline]
+    pub fn options(&self) -> Dhcpv6OptionsIterator<'_> {
+        Dhcpv6OptionsIterator {
+            mbuf: self.mbuf(),
+            offset: self.payload_offset(),
+        }
+    }
+
+    /// Returns the data of the first option with the option code.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an option is malformed.
+    pub fn option(&self, code: Dhcpv6OptionCode) -> Result<Option<&[u8]>> {
+        let mut iter = self.options();
+        while let Some(option) = iter.next()? {
+            if option.code() == code {
+                return Ok(Some(option.data()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the DUID of the client, option 1.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an option is malformed.
+    pub fn client_duid(&self) -> Result<Option<&[u8]>> {
+        self.option(Dhcpv6OptionCodes::ClientId)
+    }
+
+    /// Returns the DUID of the server, option 2.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an option is malformed.
+    pub fn server_duid(&self) -> Result<Option<&[u8]>> {
+        self.option(Dhcpv6OptionCodes::ServerId)
+    }
+}
+
+impl fmt::Debug for Dhcpv6 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("dhcpv6")
+            .field("msg_type", &format!("{}", self.msg_type()))
+            .field(
+                "transaction_id",
+                &format!("0x{:06x}", self.transaction_id()),
+            )
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Dhcpv6 {
+    /// The preceding type for DHCPv6 must be an IPv6 `Udp`.
+    type Envelope = Udp<Ipv6>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the message type and the transaction ID. The
+    /// options are the payload.
+    #[inline]
+    fn header_len(&self) -> usize {
+        Dhcpv6Header::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Dhcpv6 {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP payload as a DHCPv6 message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither the source nor the destination port is
+    /// a DHCPv6 port. Returns an error if the message is a relay message,
+    /// or the payload does not have sufficient data for the header.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let is_dhcpv6_port = |port| port == SERVER_PORT || port == CLIENT_PORT;
+        ensure!(
+            is_dhcpv6_port(envelope.src_port()) || is_dhcpv6_port(envelope.dst_port()),
+            anyhow!("not a DHCPv6 packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Dhcpv6 {
+            envelope,
+            header,
+            offset,
+        };
+
+        let msg_type = packet.msg_type();
+        ensure!(
+            msg_type != MessageTypes::RelayForw && msg_type != MessageTypes::RelayRepl,
+            anyhow!("DHCPv6 relay messages are not supported.")
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends a DHCPv6 message to the beginning of the UDP's payload.
+    ///
+    /// The message type is set to solicit. The message has no options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, Dhcpv6Header::size_of())?;
+        let header = mbuf.write_data(offset, &Dhcpv6Header::default())?;
+
+        Ok(Dhcpv6 {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// [IANA] assigned DHCPv6 message type.
+///
+/// [IANA]: https://www.iana.org/assignments/dhcpv6-parameters/dhcpv6-parameters.xhtml#dhcpv6-parameters-1
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct MessageType(pub u8);
+
+impl MessageType {
+    /// Creates a new message type.
+    pub fn new(value: u8) -> Self {
+        MessageType(value)
+    }
+}
+
+/// Supported message types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod MessageTypes {
+    use super::MessageType;
+
+    /// SOLICIT.
+    pub const Solicit: MessageType = MessageType(1);
+    /// ADVERTISE.
+    pub const Advertise: MessageType = MessageType(2);
+    /// REQUEST.
+    pub const Request: MessageType = MessageType(3);
+    /// CONFIRM.
+    pub const Confirm: MessageType = MessageType(4);
+    /// RENEW.
+    pub const Renew: MessageType = MessageType(5);
+    /// REBIND.
+    pub const Rebind: MessageType = MessageType(6);
+    /// REPLY.
+    pub const Reply: MessageType = MessageType(7);
+    /// RELEASE.
+    pub const Release: MessageType = MessageType(8);
+    /// DECLINE.
+    pub const Decline: MessageType = MessageType(9);
+    /// RECONFIGURE.
+    pub const Reconfigure: MessageType = MessageType(10);
+    /// INFORMATION-REQUEST.
+    pub const InformationRequest: MessageType = MessageType(11);
+    /// RELAY-FORW.
+    pub const RelayForw: MessageType = MessageType(12);
+    /// RELAY-REPL.
+    pub const RelayRepl: MessageType = MessageType(13);
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                MessageTypes::Solicit => "SOLICIT".to_string(),
+                MessageTypes::Advertise => "ADVERTISE".to_string(),
+                MessageTypes::Request => "REQUEST".to_string(),
+                MessageTypes::Confirm => "CONFIRM".to_string(),
+                MessageTypes::Renew => "RENEW".to_string(),
+                MessageTypes::Rebind => "REBIND".to_string(),
+                MessageTypes::Reply => "REPLY".to_string(),
+                MessageTypes::Release => "RELEASE".to_string(),
+                MessageTypes::Decline => "DECLINE".to_string(),
+                MessageTypes::Reconfigure => "RECONFIGURE".to_string(),
+                MessageTypes::InformationRequest => "INFORMATION-REQUEST".to_string(),
+                MessageTypes::RelayForw => "RELAY-FORW".to_string(),
+                MessageTypes::RelayRepl => "RELAY-REPL".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("{}", t)
+                }
+            }
+        )
+    }
+}
+
+/// [IANA] assigned DHCPv6 option code.
+///
+/// [IANA]: https://www.iana.org/assignments/dhcpv6-parameters/dhcpv6-parameters.xhtml#dhcpv6-parameters-2
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct Dhcpv6OptionCode(pub u16);
+
+impl Dhcpv6OptionCode {
+    /// Creates a new option code.
+    pub fn new(value: u16) -> Self {
+        Dhcpv6OptionCode(value)
+    }
+}
+
+/// Supported option codes.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod Dhcpv6OptionCodes {
+    use super::Dhcpv6OptionCode;
+
+    /// Client Identifier.
+    pub const ClientId: Dhcpv6OptionCode = Dhcpv6OptionCode(1);
+    /// Server Identifier.
+    pub const ServerId: Dhcpv6OptionCode = Dhcpv6OptionCode(2);
+    /// Identity Association for Non-temporary Addresses.
+    pub const IaNa: Dhcpv6OptionCode = Dhcpv6OptionCode(3);
+    /// Identity Association for Temporary Addresses.
+    pub const IaTa: Dhcpv6OptionCode = Dhcpv6OptionCode(4);
+    /// IA Address.
+    pub const IaAddr: Dhcpv6OptionCode = Dhcpv6OptionCode(5);
+    /// Option Request.
+    pub const Oro: Dhcpv6OptionCode = Dhcpv6OptionCode(6);
+    /// Preference.
+    pub const Preference: Dhcpv6OptionCode = Dhcpv6OptionCode(7);
+    /// Elapsed Time.
+    pub const ElapsedTime: Dhcpv6OptionCode = Dhcpv6OptionCode(8);
+    /// Status Code.
+    pub const StatusCode: Dhcpv6OptionCode = Dhcpv6OptionCode(13);
+    /// Rapid Commit.
+    pub const RapidCommit: Dhcpv6OptionCode = Dhcpv6OptionCode(14);
+    /// DNS Recursive Name Server.
+    pub const DnsServers: Dhcpv6OptionCode = Dhcpv6OptionCode(23);
+    /// Domain Search List.
+    pub const DomainList: Dhcpv6OptionCode = Dhcpv6OptionCode(24);
+    /// Identity Association for Prefix Delegation.
+    pub const IaPd: Dhcpv6OptionCode = Dhcpv6OptionCode(25);
+    /// IA Prefix.
+    pub const IaPrefix: Dhcpv6OptionCode = Dhcpv6OptionCode(26);
+}
+
+impl fmt::Display for Dhcpv6OptionCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Dhcpv6OptionCodes::ClientId => "Client Identifier".to_string(),
+                Dhcpv6OptionCodes::ServerId => "Server Identifier".to_string(),
+                Dhcpv6OptionCodes::IaNa => "IA_NA".to_string(),
+                Dhcpv6OptionCodes::IaTa => "IA_TA".to_string(),
+                Dhcpv6OptionCodes::IaAddr => "IA Address".to_string(),
+                Dhcpv6OptionCodes::Oro => "Option Request".to_string(),
+                Dhcpv6OptionCodes::Preference => "Preference".to_string(),
+                Dhcpv6OptionCodes::ElapsedTime => "Elapsed Time".to_string(),
+                Dhcpv6OptionCodes::StatusCode => "Status Code".to_string(),
+                Dhcpv6OptionCodes::RapidCommit => "Rapid Commit".to_string(),
+                Dhcpv6OptionCodes::DnsServers => "DNS Recursive Name Server".to_string(),
+                Dhcpv6OptionCodes::DomainList => "Domain Search List".to_string(),
+                Dhcpv6OptionCodes::IaPd => "IA_PD".to_string(),
+                Dhcpv6OptionCodes::IaPrefix => "IA Prefix".to_string(),
+                _ => {
+                    let code = self.0;
+                    format!("{}", code)
+                }
+            }
+        )
+    }
+}
+
+/// A DHCPv6 option.
+pub struct Dhcpv6Option<'a> {
+    code: Dhcpv6OptionCode,
+    data: &'a [u8],
+}
+
+impl<'a> Dhcpv6Option<'a> {
+    /// Returns the option code.
+    #[inline]
+    pub fn code(&self) -> Dhcpv6OptionCode {
+        self.code
+    }
+
+    /// Returns the option data, excluding the code and length.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl fmt::Debug for Dhcpv6Option<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dhcpv6Option")
+            .field("code", &format!("{}", self.code()))
+            .field("data", &self.data())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the top-level DHCPv6 options.
+///
+/// Options encapsulated in another option, for example the IA addresses
+/// of an IA_NA, are part of the data of the enclosing option.
+pub struct Dhcpv6OptionsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+}
+
+impl<'a> Dhcpv6OptionsIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<Dhcpv6Option<'a>>> {
+        if self.mbuf.data_len() <= self.offset {
+            return Ok(None);
+        }
+
+        let code = unsafe { *self.mbuf.read_data::<u16be>(self.offset)?.as_ref() };
+        let code = Dhcpv6OptionCode(code.into());
+        let len = unsafe { *self.mbuf.read_data::<u16be>(self.offset + 2)?.as_ref() };
+        let len = u16::from(len) as usize;
+        let data: &'a [u8] = if len > 0 {
+            let data = self.mbuf.read_data_slice::<u8>(self.offset + 4, len)?;
+            unsafe { &*data.as_ptr() }
+        } else {
+            &[]
+        };
+
+        self.offset += 4 + len;
+        Ok(Some(Dhcpv6Option { code, data }))
+    }
+}
+
+impl fmt::Debug for Dhcpv6OptionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dhcpv6OptionsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// DHCPv6 message type and transaction ID.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct Dhcpv6Header {
+    msg_type: u8,
+    transaction_id: [u8; 3],
+}
+
+impl Default for Dhcpv6Header {
+    fn default() -> Dhcpv6Header {
+        Dhcpv6Header {
+            msg_type: MessageTypes::Solicit.0,
+            transaction_id: [0; 3],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::DHCPV6_SOLICIT_PACKET;
+
+    #[test]
+    fn size_of_dhcpv6_header() {
+        assert_eq!(4, Dhcpv6Header::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_dhcpv6_solicit() {
+        let packet = Mbuf::from_bytes(&DHCPV6_SOLICIT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let udp = ipv6.parse::<Udp<Ipv6>>().unwrap();
+        let dhcpv6 = udp.parse::<Dhcpv6>().unwrap();
+
+        assert_eq!(MessageTypes::Solicit, dhcpv6.msg_type());
+        assert_eq!(0x10_0874, dhcpv6.transaction_id());
+
+        // DUID-LLT, ethernet, time = 0x1c39cf88, mac = 08:00:27:2f:e8:f9
+        let duid = [
+            0x00, 0x01, 0x00, 0x01, 0x1c, 0x39, 0xcf, 0x88, 0x08, 0x00, 0x27, 0x2f, 0xe8, 0xf9,
+        ];
+        assert_eq!(Some(&duid[..]), dhcpv6.client_duid().unwrap());
+        assert_eq!(None, dhcpv6.server_duid().unwrap());
+    }
+
+    #[capsule::test]
+    fn iterate_dhcpv6_options() {
+        let packet = Mbuf::from_bytes(&DHCPV6_SOLICIT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let udp = ipv6.parse::<Udp<Ipv6>>().unwrap();
+        let dhcpv6 = udp.parse::<Dhcpv6>().unwrap();
+
+        let mut codes = vec![];
+        let mut iter = dhcpv6.options();
+        while let Some(option) = iter.next().unwrap() {
+            codes.push(option.code());
+        }
+
+        assert_eq!(
+            vec![
+                Dhcpv6OptionCodes::ClientId,
+                Dhcpv6OptionCodes::ElapsedTime,
+                Dhcpv6OptionCodes::IaNa,
+                Dhcpv6OptionCodes::Oro,
+            ],
+            codes
+        );
+
+        let oro = dhcpv6.option(Dhcpv6OptionCodes::Oro).unwrap();
+        assert_eq!(Some(&[0x00, 0x17, 0x00, 0x18][..]), oro);
+    }
+
+    #[capsule::test]
+    fn parse_non_dhcpv6_packet() {
+        // same message but sent between non-DHCPv6 ports.
+        let mut bytes = DHCPV6_SOLICIT_PACKET;
+        bytes[55] = 0x30;
+        bytes[57] = 0x31;
+
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let udp = ipv6.parse::<Udp<Ipv6>>().unwrap();
+
+        assert!(udp.parse::<Dhcpv6>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_dhcpv6_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut udp = ipv6.push::<Udp<Ipv6>>().unwrap();
+        udp.set_src_port(CLIENT_PORT);
+        udp.set_dst_port(SERVER_PORT);
+        let mut dhcpv6 = udp.push::<Dhcpv6>().unwrap();
+        dhcpv6.set_transaction_id(0xab_cdef);
+
+        assert_eq!(4, dhcpv6.len());
+        assert_eq!(MessageTypes::Solicit, dhcpv6.msg_type());
+        assert_eq!(0xab_cdef, dhcpv6.transaction_id());
+        assert_eq!(None, dhcpv6.client_duid().unwrap());
+
+        // parses back the pushed packet
+        let udp = dhcpv6.deparse();
+        assert!(udp.peek::<Dhcpv6>().is_ok());
+    }
+}
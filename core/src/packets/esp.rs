@@ -0,0 +1,365 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! IP Encapsulating Security Payload.
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::{IpPacket, ProtocolNumber, ProtocolNumbers};
+use crate::packets::types::u32be;
+use crate::packets::{Internal, Packet};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// IP Encapsulating Security Payload packet based on [IETF RFC 4303].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |               Security Parameters Index (SPI)                 |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                      Sequence Number                          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                    Payload Data (variable)                    |
+/// ~                                                               ~
+/// |                                                               |
+/// +               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |               |     Padding (0-255 bytes)                     |
+/// +-+-+-+-+-+-+-+-+               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                               |  Pad Length   | Next Header   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |         Integrity Check Value-ICV   (variable)                |
+/// ~                                                               ~
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Security Parameters Index*: (32 bits)
+///      An arbitrary value used by a receiver to identify the security
+///      association to which an incoming packet is bound.
+///
+/// - *Sequence Number*: (32 bits)
+///      A monotonically increasing counter value used for anti-replay.
+///
+/// - *Payload Data*: (variable)
+///      The data described by the next header field, usually encrypted.
+///
+/// - *Padding, Pad Length, Next Header*: (variable)
+///      The trailer of the payload, usually encrypted with the payload
+///      data.
+///
+/// - *Integrity Check Value*: (variable)
+///      A variable-length field computed over the ESP header, payload and
+///      trailer. Its length is determined by the integrity algorithm of
+///      the security association.
+///
+/// Everything following the header is opaque, see [`payload`]. The
+/// trailer can only be read when the packet has been decrypted or uses
+/// NULL encryption, and the caller must supply the length of the ICV.
+///
+/// [IETF RFC 4303]: https://tools.ietf.org/html/rfc4303#section-2
+/// [`payload`]: Esp::payload
+pub struct Esp<E: IpPacket> {
+    envelope: E,
+    header: NonNull<EspHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Esp<E> {
+    #[inline]
+    fn header(&self) -> &EspHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut EspHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the security parameters index.
+    #[inline]
+    pub fn spi(&self) -> u32 {
+        self.header().spi.into()
+    }
+
+    /// Sets the security parameters index.
+    #[inline]
+    pub fn set_spi(&mut self, spi: u32) {
+        self.header_mut().spi = spi.into();
+    }
+
+    /// Returns the sequence number.
+    #[inline]
+    pub fn sequence(&self) -> u32 {
+        self.header().sequence.into()
+    }
+
+    /// Sets the sequence number.
+    #[inline]
+    pub fn set_sequence(&mut self, sequence: u32) {
+        self.header_mut().sequence = sequence.into();
+    }
+
+    /// Returns the opaque payload following the header, including the
+    /// trailer and the ICV.
+    #[inline]
+    pub fn payload(&self) -> &[u8] {
+        if self.payload_len() > 0 {
+            // the payload is in the buffer, should never run out.
+            let data = self
+                .mbuf()
+                .read_data_slice::<u8>(self.payload_offset(), self.payload_len())
+                .unwrap();
+            unsafe { &*data.as_ptr() }
+        } else {
+            &[]
+        }
+    }
+
+    /// Returns the byte at `back` bytes before the ICV.
+    #[inline]
+    fn trailer_byte(&self, icv_len: usize, back: usize) -> Result<u8> {
+        let payload = self.payload();
+        ensure!(
+            payload.len() >= icv_len + back,
+            anyhow!("ESP payload is too short for the trailer.")
+        );
+        Ok(payload[payload.len() - icv_len - back])
+    }
+
+    /// Returns the pad length of the trailer in the clear.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload is too short for the trailer and an
+    /// ICV of `icv_len` bytes.
+    #[inline]
+    pub fn pad_length(&self, icv_len: usize) -> Result<u8> {
+        self.trailer_byte(icv_len, 2)
+    }
+
+    /// Returns the next header of the trailer in the clear.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload is too short for the trailer and an
+    /// ICV of `icv_len` bytes.
+    #[inline]
+    pub fn next_header(&self, icv_len: usize) -> Result<ProtocolNumber> {
+        self.trailer_byte(icv_len, 1).map(ProtocolNumber::new)
+    }
+
+    /// Returns the payload data in the clear, without the padding, the
+    /// trailer and the ICV.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload is too short for the padding, the
+    /// trailer and an ICV of `icv_len` bytes.
+    #[inline]
+    pub fn payload_data(&self, icv_len: usize) -> Result<&[u8]> {
+        let pad_length = self.pad_length(icv_len)? as usize;
+        let payload = self.payload();
+        ensure!(
+            payload.len() >= icv_len + 2 + pad_length,
+            anyhow!("ESP payload is too short for the padding.")
+        );
+        Ok(&payload[..payload.len() - icv_len - 2 - pad_length])
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Esp<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("esp")
+            .field("spi", &format!("0x{:08x}", self.spi()))
+            .field("sequence", &self.sequence())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Esp<E> {
+    /// The preceding type for an ESP packet can be either an [IPv4] packet,
+    /// an [IPv6] packet, or any IPv6 extension packets.
+    ///
+    /// [IPv4]: crate::packets::ip::v4::Ipv4
+    /// [IPv6]: crate::packets::ip::v6::Ipv6
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        EspHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Esp::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as an ESP packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the envelope's [`next_protocol`] is not
+    /// [`ProtocolNumbers::Esp`]. Returns an error if the payload does not
+    /// have sufficient data for the ESP header.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Esp,
+            anyhow!("not an ESP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        Ok(Esp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an ESP header to the beginning of the envelope's payload.
+    ///
+    /// The envelope's [`next_protocol`] is set to [`ProtocolNumbers::Esp`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, EspHeader::size_of())?;
+        let header = mbuf.write_data(offset, &EspHeader::default())?;
+
+        envelope.set_next_protocol(ProtocolNumbers::Esp);
+
+        Ok(Esp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// A type alias for an IPv4 ESP packet.
+pub type Esp4 = Esp<Ipv4>;
+
+/// A type alias for an IPv6 ESP packet.
+pub type Esp6 = Esp<Ipv6>;
+
+/// ESP header.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct EspHeader {
+    spi: u32be,
+    sequence: u32be,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{ESP_PACKET, IPV4_UDP_PACKET};
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_esp_header() {
+        assert_eq!(8, EspHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_esp_packet() {
+        let packet = Mbuf::from_bytes(&ESP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let esp = ipv4.parse::<Esp4>().unwrap();
+
+        assert_eq!(0x1234, esp.spi());
+        assert_eq!(1, esp.sequence());
+        assert_eq!(28, esp.payload().len());
+
+        // NULL encryption with a 12-byte ICV.
+        assert_eq!(1, esp.pad_length(12).unwrap());
+        assert_eq!(ProtocolNumbers::Udp, esp.next_header(12).unwrap());
+        assert_eq!(&ESP_PACKET[42..55], esp.payload_data(12).unwrap());
+        assert!(esp.next_header(64).is_err());
+    }
+
+    #[capsule::test]
+    fn parse_non_esp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Esp4>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_esp_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut esp = ipv4.push::<Esp4>().unwrap();
+        esp.set_spi(0x1234);
+        esp.set_sequence(7);
+
+        assert_eq!(8, esp.len());
+        assert_eq!(0x1234, esp.spi());
+        assert_eq!(7, esp.sequence());
+        assert!(esp.payload().is_empty());
+        assert_eq!(ProtocolNumbers::Esp, esp.envelope().next_protocol());
+    }
+}
@@ -18,18 +18,16 @@
 
 use crate::dpdk::BufferError;
 use crate::net::MacAddr;
-use crate::packets::types::u16be;
+use crate::packets::checksum;
+use crate::packets::llc::MAX_802_3_LENGTH;
 use crate::packets::{Internal, Packet};
 use crate::{ensure, Mbuf, SizeOf};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use capsule_headers::{EthernetHeader, ETH_HEADER_SIZE, VLAN_802_1Q, VLAN_TAG_SIZE};
 use std::fmt;
 use std::ptr::NonNull;
 
-const ETH_HEADER_SIZE: usize = 14;
-
-// Tag protocol identifiers.
-const VLAN_802_1Q: u16 = 0x8100;
-const VLAN_802_1AD: u16 = 0x88a8;
+pub use capsule_headers::{EtherType, EtherTypes, Vlan};
 
 /// Ethernet II frame.
 ///
@@ -138,69 +136,163 @@ impl Ethernet {
     /// Returns the source MAC address.
     #[inline]
     pub fn src(&self) -> MacAddr {
-        self.header().src
+        self.header().src()
     }
 
     /// Sets the source MAC address.
     #[inline]
     pub fn set_src(&mut self, src: MacAddr) {
-        self.header_mut().src = src
+        self.header_mut().set_src(src)
     }
 
     /// Returns the destination MAC address.
     #[inline]
     pub fn dst(&self) -> MacAddr {
-        self.header().dst
+        self.header().dst()
     }
 
     /// Sets the destination MAC address.
     #[inline]
     pub fn set_dst(&mut self, dst: MacAddr) {
-        self.header_mut().dst = dst
-    }
-
-    /// Returns the marker that indicates whether the frame is VLAN.
-    #[inline]
-    fn vlan_marker(&self) -> u16 {
-        unsafe { self.header().chunk.ether_type.into() }
+        self.header_mut().set_dst(dst)
     }
 
     /// Returns the protocol identifier of the payload.
     #[inline]
     pub fn ether_type(&self) -> EtherType {
-        let header = self.header();
-        let ether_type = unsafe {
-            match self.vlan_marker() {
-                VLAN_802_1Q => header.chunk.dot1q.ether_type,
-                VLAN_802_1AD => header.chunk.qinq.ether_type,
-                _ => header.chunk.ether_type,
-            }
-        };
-
-        EtherType::new(ether_type.into())
+        self.header().ether_type()
     }
 
     /// Sets the protocol identifier of the payload.
     #[inline]
     pub fn set_ether_type(&mut self, ether_type: EtherType) {
-        let ether_type = ether_type.0.into();
-        match self.vlan_marker() {
-            VLAN_802_1Q => self.header_mut().chunk.dot1q.ether_type = ether_type,
-            VLAN_802_1AD => self.header_mut().chunk.qinq.ether_type = ether_type,
-            _ => self.header_mut().chunk.ether_type = ether_type,
-        }
+        self.header_mut().set_ether_type(ether_type)
+    }
+
+    /// Returns the protocol identifier of the payload, the same as
+    /// [`ether_type`].
+    ///
+    /// Mirrors [`IpPacket::next_protocol`] so a dispatch loop can decide
+    /// how to parse the payload before parsing it.
+    ///
+    /// [`ether_type`]: Ethernet::ether_type
+    /// [`IpPacket::next_protocol`]: crate::packets::ip::IpPacket::next_protocol
+    #[inline]
+    pub fn next_protocol(&self) -> EtherType {
+        self.ether_type()
     }
 
     /// Returns whether the frame is VLAN Dot1q (802.1Q) tagged.
     #[inline]
     pub fn is_dot1q(&self) -> bool {
-        self.vlan_marker() == VLAN_802_1Q
+        self.header().is_dot1q()
     }
 
     /// Returns whether the frame is VLAN QinQ (802.1ad) tagged.
     #[inline]
     pub fn is_qinq(&self) -> bool {
-        self.vlan_marker() == VLAN_802_1AD
+        self.header().is_qinq()
+    }
+
+    /// Returns the VLAN tag of a Dot1q frame, or the outer S-TAG of a QinQ
+    /// frame.
+    #[inline]
+    pub fn vlan(&self) -> Option<&Vlan> {
+        self.header().vlan()
+    }
+
+    /// Returns the mutable VLAN tag of a Dot1q frame, or the outer S-TAG of
+    /// a QinQ frame.
+    #[inline]
+    pub fn vlan_mut(&mut self) -> Option<&mut Vlan> {
+        self.header_mut().vlan_mut()
+    }
+
+    /// Returns the inner C-TAG of a QinQ frame.
+    #[inline]
+    pub fn inner_vlan(&self) -> Option<&Vlan> {
+        self.header().inner_vlan()
+    }
+
+    /// Returns the mutable inner C-TAG of a QinQ frame.
+    #[inline]
+    pub fn inner_vlan_mut(&mut self) -> Option<&mut Vlan> {
+        self.header_mut().inner_vlan_mut()
+    }
+
+    /// Returns whether the frame is an Ethernet II frame, where the
+    /// Ethernet type field identifies the payload protocol.
+    #[inline]
+    pub fn is_ethernet2(&self) -> bool {
+        !self.is_802_3()
+    }
+
+    /// Returns whether the frame is an IEEE 802.3 frame, where the
+    /// Ethernet type field is the length of the payload, and the payload
+    /// begins with an LLC header.
+    ///
+    /// Use [`Llc`] or [`LlcSnap`] to parse the payload of the frame.
+    ///
+    /// [`Llc`]: crate::packets::llc::Llc
+    /// [`LlcSnap`]: crate::packets::llc::LlcSnap
+    #[inline]
+    pub fn is_802_3(&self) -> bool {
+        self.ether_type().0 <= MAX_802_3_LENGTH
+    }
+
+    /// Inserts an 802.1Q VLAN tag after the source MAC address.
+    ///
+    /// The tag carries the VLAN identifier and the priority code point,
+    /// and the existing ether type is moved into the tag. The payload is
+    /// shifted down by the 4 bytes of the tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame is already VLAN tagged. Returns an
+    /// error if the buffer does not have enough free space.
+    #[inline]
+    pub fn push_vlan(&mut self, vid: u16, pcp: u8) -> Result<()> {
+        ensure!(
+            !self.is_dot1q() && !self.is_qinq(),
+            anyhow!("frame is already VLAN tagged.")
+        );
+
+        let tci = (u16::from(pcp & 0x07) << 13) | (vid & 0x0fff);
+        let [tpid_hi, tpid_lo] = VLAN_802_1Q.to_be_bytes();
+        let [tci_hi, tci_lo] = tci.to_be_bytes();
+
+        // the chunk after the source MAC starts with the ether type, which
+        // ends up after the tag once the buffer is extended.
+        let offset = self.offset() + MacAddr::size_of() * 2;
+        self.mbuf_mut().extend(offset, VLAN_TAG_SIZE)?;
+        let _ = self
+            .mbuf_mut()
+            .write_data_slice(offset, &[tpid_hi, tpid_lo, tci_hi, tci_lo])?;
+
+        Ok(())
+    }
+
+    /// Removes the outermost VLAN tag after the source MAC address.
+    ///
+    /// Reverses [`push_vlan`]. A Dot1q frame becomes untagged, and the
+    /// ether type in the tag becomes the ether type of the frame. A QinQ
+    /// frame has its S-TAG removed and becomes a Dot1q frame tagged with
+    /// the C-TAG. The payload is shifted up by the 4 bytes of the tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame is not VLAN tagged.
+    ///
+    /// [`push_vlan`]: Ethernet::push_vlan
+    #[inline]
+    pub fn pop_vlan(&mut self) -> Result<()> {
+        ensure!(
+            self.is_dot1q() || self.is_qinq(),
+            anyhow!("frame is not VLAN tagged.")
+        );
+
+        let offset = self.offset() + MacAddr::size_of() * 2;
+        self.mbuf_mut().shrink(offset, VLAN_TAG_SIZE)
     }
 
     /// Swaps the source MAC address with the destination MAC address.
@@ -211,6 +303,57 @@ impl Ethernet {
         self.set_src(dst);
         self.set_dst(src);
     }
+
+    /// Returns the frame bytes, from the Ethernet header to the end of
+    /// the buffer.
+    #[inline]
+    fn frame(&self) -> &[u8] {
+        // the frame is in the buffer, should never run out.
+        let data = self
+            .mbuf()
+            .read_data_slice::<u8>(self.offset(), self.len())
+            .unwrap();
+        unsafe { &*data.as_ptr() }
+    }
+
+    /// Computes the frame check sequence of the frame.
+    ///
+    /// The CRC-32 is computed over the entire frame, which should not
+    /// already end with an FCS. Most NICs strip and generate the FCS in
+    /// hardware, so this is only useful for raw or offline frames.
+    #[inline]
+    pub fn compute_fcs(&self) -> u32 {
+        checksum::crc32(self.frame())
+    }
+
+    /// Returns whether the last 4 bytes of the frame are a valid frame
+    /// check sequence for the rest of the frame.
+    #[inline]
+    pub fn validate_fcs(&self) -> bool {
+        let frame = self.frame();
+        if frame.len() < EthernetHeader::size_of() + 4 {
+            return false;
+        }
+
+        let (data, fcs) = frame.split_at(frame.len() - 4);
+        checksum::crc32(data) == u32::from_le_bytes([fcs[0], fcs[1], fcs[2], fcs[3]])
+    }
+
+    /// Appends the frame check sequence to the end of the frame.
+    ///
+    /// The FCS is transmitted least significant byte first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    pub fn append_fcs(&mut self) -> Result<()> {
+        let fcs = self.compute_fcs().to_le_bytes();
+        let offset = self.mbuf().data_len();
+        self.mbuf_mut().extend(offset, fcs.len())?;
+        self.mbuf_mut().write_data_slice(offset, &fcs)?;
+        Ok(())
+    }
 }
 
 impl fmt::Debug for Ethernet {
@@ -251,13 +394,7 @@ impl Packet for Ethernet {
     /// The length of the Ethernet header depends on the VLAN tags.
     #[inline]
     fn header_len(&self) -> usize {
-        if self.is_dot1q() {
-            EthernetHeader::size_of() + VlanTag::size_of()
-        } else if self.is_qinq() {
-            EthernetHeader::size_of() + VlanTag::size_of() * 2
-        } else {
-            EthernetHeader::size_of()
-        }
+        self.header().header_len()
     }
 
     #[inline]
@@ -330,136 +467,6 @@ impl Packet for Ethernet {
     }
 }
 
-/// The protocol identifier of the Ethernet frame payload.
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
-#[repr(C, packed)]
-pub struct EtherType(pub u16);
-
-impl EtherType {
-    /// Creates an Ethernet payload protocol identifier.
-    pub fn new(value: u16) -> Self {
-        EtherType(value)
-    }
-}
-
-/// Supported Ethernet payload protocol types.
-#[allow(non_snake_case)]
-#[allow(non_upper_case_globals)]
-pub mod EtherTypes {
-    use super::EtherType;
-
-    /// Address resolution protocol.
-    pub const Arp: EtherType = EtherType(0x0806);
-    /// Internet Protocol version 4.
-    pub const Ipv4: EtherType = EtherType(0x0800);
-    /// Internet Protocol version 6.
-    pub const Ipv6: EtherType = EtherType(0x86DD);
-}
-
-impl fmt::Display for EtherType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match *self {
-                EtherTypes::Arp => "ARP".to_string(),
-                EtherTypes::Ipv4 => "IPv4".to_string(),
-                EtherTypes::Ipv6 => "IPv6".to_string(),
-                _ => {
-                    let t = self.0;
-                    format!("0x{:04x}", t)
-                }
-            }
-        )
-    }
-}
-
-/// VLAN tag.
-#[derive(Clone, Copy, Debug, Default, SizeOf)]
-#[repr(C, packed)]
-struct VlanTag {
-    tpid: u16be,
-    tci: u16be,
-}
-
-#[allow(clippy::trivially_copy_pass_by_ref)]
-impl VlanTag {
-    /// Returns the tag protocol identifier, either 802.1q (Dot1q) or 802.1ad (QinQ).
-    #[allow(dead_code)]
-    #[inline]
-    fn tag_id(&self) -> u16 {
-        self.tpid.into()
-    }
-
-    /// Returns the priority code point.
-    #[allow(dead_code)]
-    #[inline]
-    fn priority(&self) -> u8 {
-        let tci: u16 = self.tci.into();
-        (tci >> 13) as u8
-    }
-
-    /// Returns whether the frame is eligible to be dropped in the presence
-    /// of congestion.
-    #[allow(dead_code)]
-    #[inline]
-    fn drop_eligible(&self) -> bool {
-        self.tci & u16be::from(0x1000) > u16be::MIN
-    }
-
-    /// Returns the VLAN identifier.
-    #[allow(dead_code)]
-    #[inline]
-    fn identifier(&self) -> u16 {
-        (self.tci & u16be::from(0x0fff)).into()
-    }
-}
-
-/// Dot1q chunk for a VLAN header.
-#[derive(Clone, Copy, Debug, Default)]
-#[repr(C, packed)]
-struct Dot1q {
-    tag: VlanTag,
-    ether_type: u16be,
-}
-
-/// QinQ chunk for a VLAN header.
-#[derive(Clone, Copy, Debug, Default)]
-#[repr(C, packed)]
-struct Qinq {
-    stag: VlanTag,
-    ctag: VlanTag,
-    ether_type: u16be,
-}
-
-/// The Ethernet header chunk follows the source MAC.
-#[allow(missing_debug_implementations)]
-#[derive(Clone, Copy)]
-#[repr(C, packed)]
-union Chunk {
-    ether_type: u16be,
-    dot1q: Dot1q,
-    qinq: Qinq,
-}
-
-impl Default for Chunk {
-    fn default() -> Chunk {
-        Chunk {
-            ether_type: u16be::default(),
-        }
-    }
-}
-
-/// Ethernet header.
-#[allow(missing_debug_implementations)]
-#[derive(Clone, Copy, Default)]
-#[repr(C, packed)]
-struct EthernetHeader {
-    dst: MacAddr,
-    src: MacAddr,
-    chunk: Chunk,
-}
-
 impl SizeOf for EthernetHeader {
     /// Size of the Ethernet header.
     ///
@@ -469,30 +476,27 @@ impl SizeOf for EthernetHeader {
     /// which is the minimum size without any tags. `Ethernet::header_len()`
     /// will report the correct instance size based on the presence or absence
     /// of VLAN tags.
-    #[inline]
-    fn size_of() -> usize {
-        ETH_HEADER_SIZE
-    }
+    const SIZE: usize = ETH_HEADER_SIZE;
+}
+
+impl SizeOf for Vlan {
+    const SIZE: usize = VLAN_TAG_SIZE;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, VLAN_DOT1Q_PACKET, VLAN_QINQ_PACKET};
+    use crate::packets::ip::v4::Ipv4;
+    use crate::testils::byte_arrays::{
+        IPV4_UDP_PACKET, STP_CONFIG_PACKET, VLAN_DOT1Q_PACKET, VLAN_QINQ_PACKET,
+    };
+    use capsule_headers::VLAN_802_1AD;
 
     #[test]
     fn size_of_ethernet_header() {
         assert_eq!(14, EthernetHeader::size_of());
     }
 
-    #[test]
-    fn ether_type_to_string() {
-        assert_eq!("ARP", EtherTypes::Arp.to_string());
-        assert_eq!("IPv4", EtherTypes::Ipv4.to_string());
-        assert_eq!("IPv6", EtherTypes::Ipv6.to_string());
-        assert_eq!("0x0000", EtherType::new(0).to_string());
-    }
-
     #[capsule::test]
     fn parse_ethernet_packet() {
         let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
@@ -501,6 +505,27 @@ mod tests {
         assert_eq!("00:00:00:00:00:01", ethernet.dst().to_string());
         assert_eq!("00:00:00:00:00:02", ethernet.src().to_string());
         assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        assert!(ethernet.is_ethernet2());
+        assert!(!ethernet.is_802_3());
+    }
+
+    #[capsule::test]
+    fn parse_802_3_frame() {
+        let packet = Mbuf::from_bytes(&STP_CONFIG_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert!(ethernet.is_802_3());
+        assert!(!ethernet.is_ethernet2());
+        assert_eq!(EtherType::new(38), ethernet.ether_type());
+    }
+
+    #[capsule::test]
+    fn next_protocol_of_ethernet_packet() {
+        let packet = Mbuf::from_bytes(&VLAN_DOT1Q_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert_eq!(ethernet.ether_type(), ethernet.next_protocol());
+        assert_eq!(EtherTypes::Arp, ethernet.next_protocol());
     }
 
     #[capsule::test]
@@ -513,6 +538,13 @@ mod tests {
         assert!(ethernet.is_dot1q());
         assert_eq!(EtherTypes::Arp, ethernet.ether_type());
         assert_eq!(18, ethernet.header_len());
+
+        let vlan = ethernet.vlan().unwrap();
+        assert_eq!(VLAN_802_1Q, vlan.tpid());
+        assert_eq!(0, vlan.pcp());
+        assert!(!vlan.dei());
+        assert_eq!(123, vlan.vid());
+        assert!(ethernet.inner_vlan().is_none());
     }
 
     #[capsule::test]
@@ -525,6 +557,66 @@ mod tests {
         assert!(ethernet.is_qinq());
         assert_eq!(EtherTypes::Arp, ethernet.ether_type());
         assert_eq!(22, ethernet.header_len());
+
+        let stag = ethernet.vlan().unwrap();
+        assert_eq!(VLAN_802_1AD, stag.tpid());
+        assert_eq!(30, stag.vid());
+
+        let ctag = ethernet.inner_vlan().unwrap();
+        assert_eq!(VLAN_802_1Q, ctag.tpid());
+        assert_eq!(1, ctag.pcp());
+        assert_eq!(101, ctag.vid());
+    }
+
+    #[capsule::test]
+    fn remark_vlan_priority() {
+        let packet = Mbuf::from_bytes(&VLAN_DOT1Q_PACKET).unwrap();
+        let mut ethernet = packet.parse::<Ethernet>().unwrap();
+
+        let vlan = ethernet.vlan_mut().unwrap();
+        vlan.set_dei(true);
+        assert_eq!(0, vlan.pcp());
+
+        vlan.set_pcp(5);
+        assert_eq!(5, vlan.pcp());
+        assert!(vlan.dei());
+        assert_eq!(123, vlan.vid());
+
+        vlan.set_dei(false);
+        assert_eq!(5, vlan.pcp());
+        assert!(!vlan.dei());
+        assert_eq!(123, vlan.vid());
+
+        vlan.set_vid(0xfff);
+        assert_eq!(5, vlan.pcp());
+        assert_eq!(0xfff, vlan.vid());
+
+        // the tag is updated in place.
+        assert_eq!(5, ethernet.vlan().unwrap().pcp());
+    }
+
+    #[capsule::test]
+    fn compute_and_validate_fcs() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let mut ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert_eq!(0x9416_0d5b, ethernet.compute_fcs());
+        assert!(!ethernet.validate_fcs());
+
+        ethernet.append_fcs().unwrap();
+        assert_eq!(IPV4_UDP_PACKET.len() + 4, ethernet.len());
+        assert!(ethernet.validate_fcs());
+
+        // the FCS is appended least significant byte first.
+        let mbuf = ethernet.mbuf();
+        let fcs = mbuf
+            .read_data_slice::<u8>(IPV4_UDP_PACKET.len(), 4)
+            .unwrap();
+        assert_eq!(&[0x5b, 0x0d, 0x16, 0x94], unsafe { fcs.as_ref() });
+
+        // corrupts one byte of the frame.
+        ethernet.set_ether_type(EtherTypes::Ipv6);
+        assert!(!ethernet.validate_fcs());
     }
 
     #[capsule::test]
@@ -537,6 +629,47 @@ mod tests {
         assert_eq!("00:00:00:00:00:01", ethernet.src().to_string());
     }
 
+    #[capsule::test]
+    fn push_and_pop_vlan_tag() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let mut ethernet = packet.parse::<Ethernet>().unwrap();
+
+        ethernet.push_vlan(100, 5).unwrap();
+        assert!(ethernet.is_dot1q());
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        assert_eq!(18, ethernet.header_len());
+        assert_eq!(IPV4_UDP_PACKET.len() + 4, ethernet.len());
+
+        let vlan = ethernet.vlan().unwrap();
+        assert_eq!(VLAN_802_1Q, vlan.tpid());
+        assert_eq!(5, vlan.pcp());
+        assert_eq!(100, vlan.vid());
+
+        assert!(ethernet.push_vlan(200, 0).is_err());
+
+        // the payload is intact after the tag.
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut ethernet = ipv4.deparse();
+
+        ethernet.pop_vlan().unwrap();
+        assert!(!ethernet.is_dot1q());
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        assert_eq!(&IPV4_UDP_PACKET[..], &ethernet.to_vec()[..]);
+        assert!(ethernet.pop_vlan().is_err());
+    }
+
+    #[capsule::test]
+    fn pop_vlan_tag_of_qinq_packet() {
+        let packet = Mbuf::from_bytes(&VLAN_QINQ_PACKET).unwrap();
+        let mut ethernet = packet.parse::<Ethernet>().unwrap();
+        let ctag = ethernet.inner_vlan().unwrap().vid();
+
+        ethernet.pop_vlan().unwrap();
+        assert!(ethernet.is_dot1q());
+        assert_eq!(ctag, ethernet.vlan().unwrap().vid());
+        assert_eq!(EtherTypes::Arp, ethernet.ether_type());
+    }
+
     #[capsule::test]
     fn push_ethernet_packet() {
         let packet = Mbuf::new().unwrap();
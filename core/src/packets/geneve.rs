@@ -0,0 +1,603 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Generic Network Virtualization Encapsulation.
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::IpPacket;
+use crate::packets::tunnel::{self, Encap, NextProtocol};
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{EtherType, EtherTypes, Ethernet, Internal, Packet, Udp};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The IANA assigned UDP port of Geneve.
+pub const GENEVE_PORT: u16 = 6081;
+
+// Masks.
+const VERSION: u8 = 0b1100_0000;
+const OPT_LEN: u8 = 0b0011_1111;
+const FLAGS_O: u8 = 0b1000_0000;
+const FLAGS_C: u8 = 0b0100_0000;
+const OPTION_LEN: u8 = 0b0001_1111;
+const VNI_MAX: u32 = 0x00ff_ffff;
+
+/// Generic Network Virtualization Encapsulation packet based on
+/// [IETF RFC 8926].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |Ver|  Opt Len  |O|C|    Rsvd.  |          Protocol Type        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |        Virtual Network Identifier (VNI)       |    Reserved   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// ~                    Variable-Length Options                    ~
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Version*: (2 bits)
+///      The current version number is 0.
+///
+/// - *Opt Len*: (6 bits)
+///      The length of the option fields, expressed in 4-byte multiples,
+///      not including the 8-byte fixed tunnel header.
+///
+/// - *O*: (1 bit)
+///      Control packet. This packet contains a control message.
+///
+/// - *C*: (1 bit)
+///      Critical options present. One or more options has the critical
+///      bit set.
+///
+/// - *Protocol Type*: (16 bits)
+///      The type of protocol data unit appearing after the Geneve header,
+///      using the Ethernet types. Ethernet frames are indicated by
+///      [`EtherTypes::TransparentEthernetBridging`].
+///
+/// - *Virtual Network Identifier (VNI)*: (24 bits)
+///      An identifier for a unique element of a virtual network.
+///
+/// - *Variable-Length Options*: (variable)
+///      Zero or more option TLVs, each with a class, a type and a length
+///      in 4-byte multiples.
+///
+/// [IETF RFC 8926]: https://tools.ietf.org/html/rfc8926#section-3.4
+pub struct Geneve<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<GeneveHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Geneve<E> {
+    #[inline]
+    fn header(&self) -> &GeneveHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut GeneveHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the version.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        (self.header().ver_opt_len & VERSION) >> 6
+    }
+
+    /// Returns the length of the options in 4-byte multiples.
+    #[inline]
+    pub fn opt_len(&self) -> u8 {
+        self.header().ver_opt_len & OPT_LEN
+    }
+
+    /// Returns a flag indicating whether the packet contains a control
+    /// message.
+    #[inline]
+    pub fn control(&self) -> bool {
+        self.header().flags & FLAGS_O != 0
+    }
+
+    /// Sets the control message flag.
+    #[inline]
+    pub fn set_control(&mut self, control: bool) {
+        if control {
+            self.header_mut().flags |= FLAGS_O;
+        } else {
+            self.header_mut().flags &= !FLAGS_O;
+        }
+    }
+
+    /// Returns a flag indicating whether critical options are present.
+    #[inline]
+    pub fn critical(&self) -> bool {
+        self.header().flags & FLAGS_C != 0
+    }
+
+    /// Sets the critical options present flag.
+    #[inline]
+    pub fn set_critical(&mut self, critical: bool) {
+        if critical {
+            self.header_mut().flags |= FLAGS_C;
+        } else {
+            self.header_mut().flags &= !FLAGS_C;
+        }
+    }
+
+    /// Returns the protocol type of the payload.
+    #[inline]
+    pub fn protocol_type(&self) -> EtherType {
+        EtherType::new(self.header().protocol_type.into())
+    }
+
+    /// Sets the protocol type of the payload.
+    #[inline]
+    pub fn set_protocol_type(&mut self, protocol_type: EtherType) {
+        self.header_mut().protocol_type = protocol_type.0.into();
+    }
+
+    /// Returns the protocol of the payload, or `None` if the protocol
+    /// type is not a supported inner protocol.
+    #[inline]
+    pub fn next_protocol(&self) -> Option<NextProtocol> {
+        NextProtocol::from_ether_type(self.protocol_type())
+    }
+
+    /// Returns the virtual network identifier.
+    #[inline]
+    pub fn vni(&self) -> u32 {
+        u32::from(self.header().vni_reserved) >> 8
+    }
+
+    /// Sets the virtual network identifier.
+    ///
+    /// Only the lower 24 bits of the `vni` are used.
+    #[inline]
+    pub fn set_vni(&mut self, vni: u32) {
+        self.header_mut().vni_reserved = ((vni & VNI_MAX) << 8).into();
+    }
+
+    /// Returns an iterator that iterates through the options.
+    #[inline]
+    pub fn options(&self) -> GeneveOptionsIterator<'_> {
+        GeneveOptionsIterator {
+            mbuf: self.mbuf(),
+            offset: self.offset + GeneveHeader::size_of(),
+            end: self.payload_offset(),
+        }
+    }
+
+    /// Removes the outer headers and returns an Ethernet frame that can be
+    /// parsed as the [`next_protocol`].
+    ///
+    /// If the payload is an Ethernet frame, the inner frame is returned.
+    /// Otherwise, the outer Ethernet frame is returned with the payload
+    /// following the Ethernet header and the Ethernet type set to the
+    /// protocol type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the protocol type is not a supported inner
+    /// protocol, or if the payload is not an Ethernet frame when the
+    /// protocol type is [`EtherTypes::TransparentEthernetBridging`].
+    ///
+    /// [`next_protocol`]: Geneve::next_protocol
+    #[inline]
+    pub fn decap(self) -> Result<Ethernet> {
+        let next = self
+            .next_protocol()
+            .ok_or_else(|| anyhow!("unknown Geneve payload type."))?;
+        tunnel::decap_next(self, next)
+    }
+}
+
+impl<E: IpPacket<Envelope = Ethernet>> Geneve<E> {
+    /// Encapsulates the Ethernet frame in outer Ethernet, IP, UDP and
+    /// Geneve headers.
+    ///
+    /// The UDP destination port is set to [`GENEVE_PORT`]. The outer
+    /// addresses and the UDP source port should be set afterwards through
+    /// the envelopes, followed by [`reconcile_all`] to fix the lengths and
+    /// the checksums.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    #[inline]
+    pub fn encap(inner: Ethernet, vni: u32) -> Result<Self> {
+        let mut geneve = inner
+            .reset()
+            .push::<Ethernet>()?
+            .push::<E>()?
+            .push::<Udp<E>>()?
+            .push::<Geneve<E>>()?;
+        geneve.set_vni(vni);
+        Ok(geneve)
+    }
+}
+
+impl<E: IpPacket<Envelope = Ethernet>> Encap for Geneve<E> {
+    type Inner = Ethernet;
+
+    /// The virtual network identifier.
+    type Params = u32;
+
+    #[inline]
+    fn encapsulate(inner: Self::Inner, vni: Self::Params) -> Result<Self> {
+        Geneve::encap(inner, vni)
+    }
+
+    #[inline]
+    fn decapsulate(self) -> Result<Self::Inner> {
+        self.decap()
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Geneve<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("geneve")
+            .field("version", &self.version())
+            .field("opt_len", &self.opt_len())
+            .field("control", &self.control())
+            .field("critical", &self.critical())
+            .field("protocol_type", &format!("{}", self.protocol_type()))
+            .field("vni", &self.vni())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Geneve<E> {
+    /// The preceding type for Geneve must be `Udp`.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the Geneve header, including the options.
+    #[inline]
+    fn header_len(&self) -> usize {
+        GeneveHeader::size_of() + self.opt_len() as usize * 4
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Geneve {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP payload as a Geneve packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the UDP destination port is not [`GENEVE_PORT`].
+    /// Returns an error if the payload does not have sufficient data for
+    /// the Geneve header and the options.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.dst_port() == GENEVE_PORT,
+            anyhow!("not a Geneve packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Geneve {
+            envelope,
+            header,
+            offset,
+        };
+
+        // makes sure the options are in the buffer.
+        let _ = packet
+            .mbuf()
+            .read_data_slice::<u8>(offset, packet.header_len())?;
+
+        Ok(packet)
+    }
+
+    /// Prepends a Geneve header without options to the beginning of the
+    /// UDP's payload.
+    ///
+    /// The UDP destination port is set to [`GENEVE_PORT`] and the protocol
+    /// type is set to [`EtherTypes::TransparentEthernetBridging`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, GeneveHeader::size_of())?;
+        let header = mbuf.write_data(offset, &GeneveHeader::default())?;
+
+        envelope.set_dst_port(GENEVE_PORT);
+
+        Ok(Geneve {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// A type alias for a Geneve packet over IPv4.
+pub type Geneve4 = Geneve<Ipv4>;
+
+/// A type alias for a Geneve packet over IPv6.
+pub type Geneve6 = Geneve<Ipv6>;
+
+/// A Geneve option.
+pub struct GeneveOption<'a> {
+    class: u16,
+    option_type: u8,
+    data: &'a [u8],
+}
+
+impl<'a> GeneveOption<'a> {
+    /// Returns the option class.
+    #[inline]
+    pub fn class(&self) -> u16 {
+        self.class
+    }
+
+    /// Returns the option type. The high order bit is the critical bit.
+    #[inline]
+    pub fn option_type(&self) -> u8 {
+        self.option_type
+    }
+
+    /// Returns a flag indicating whether the option is critical.
+    #[inline]
+    pub fn is_critical(&self) -> bool {
+        self.option_type & 0x80 != 0
+    }
+
+    /// Returns the length of the option data in 4-byte multiples.
+    #[inline]
+    pub fn length(&self) -> u8 {
+        (self.data.len() / 4) as u8
+    }
+
+    /// Returns the option data.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl fmt::Debug for GeneveOption<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeneveOption")
+            .field("class", &format!("0x{:04x}", self.class()))
+            .field("option_type", &format!("0x{:02x}", self.option_type()))
+            .field("length", &self.length())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the options of a Geneve packet.
+pub struct GeneveOptionsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> GeneveOptionsIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<GeneveOption<'a>>> {
+        if self.end <= self.offset {
+            return Ok(None);
+        }
+
+        let header = unsafe { *self.mbuf.read_data::<OptionHeader>(self.offset)?.as_ref() };
+        let data_len = (header.length & OPTION_LEN) as usize * 4;
+        let data_offset = self.offset + OptionHeader::size_of();
+        ensure!(
+            data_offset + data_len <= self.end,
+            anyhow!("invalid Geneve option length {}.", data_len)
+        );
+
+        let data: &'a [u8] = if data_len > 0 {
+            let data = self.mbuf.read_data_slice::<u8>(data_offset, data_len)?;
+            unsafe { &*data.as_ptr() }
+        } else {
+            &[]
+        };
+
+        self.offset = data_offset + data_len;
+
+        Ok(Some(GeneveOption {
+            class: header.class.into(),
+            option_type: header.option_type,
+            data,
+        }))
+    }
+}
+
+impl fmt::Debug for GeneveOptionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeneveOptionsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// Geneve option class, type and length.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct OptionHeader {
+    class: u16be,
+    option_type: u8,
+    length: u8,
+}
+
+/// Geneve fixed header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct GeneveHeader {
+    ver_opt_len: u8,
+    flags: u8,
+    protocol_type: u16be,
+    vni_reserved: u32be,
+}
+
+impl Default for GeneveHeader {
+    fn default() -> GeneveHeader {
+        GeneveHeader {
+            ver_opt_len: 0,
+            flags: 0,
+            protocol_type: EtherTypes::TransparentEthernetBridging.0.into(),
+            vni_reserved: u32be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::MacAddr;
+    use crate::testils::byte_arrays::{GENEVE_IPV4_PACKET, GENEVE_PACKET, IPV4_UDP_PACKET};
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn size_of_geneve_header() {
+        assert_eq!(8, GeneveHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_geneve_packet() {
+        let packet = Mbuf::from_bytes(&GENEVE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let geneve = udp.parse::<Geneve4>().unwrap();
+
+        assert_eq!(0, geneve.version());
+        assert_eq!(2, geneve.opt_len());
+        assert!(!geneve.control());
+        assert!(!geneve.critical());
+        assert_eq!(
+            EtherTypes::TransparentEthernetBridging,
+            geneve.protocol_type()
+        );
+        assert_eq!(100, geneve.vni());
+        assert_eq!(16, geneve.header_len());
+
+        let mut iter = geneve.options();
+        let option = iter.next().unwrap().unwrap();
+        assert_eq!(0x0102, option.class());
+        assert_eq!(0x80, option.option_type());
+        assert!(option.is_critical());
+        assert_eq!(1, option.length());
+        assert_eq!(&[0xde, 0xad, 0xbe, 0xef], option.data());
+        assert!(iter.next().unwrap().is_none());
+
+        let inner = geneve.decap().unwrap();
+        assert_eq!(MacAddr::new(0, 0, 0, 0, 0, 2), inner.src());
+        assert_eq!(MacAddr::new(0, 0, 0, 0, 0, 1), inner.dst());
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), inner.to_vec());
+    }
+
+    #[capsule::test]
+    fn parse_geneve_ethernet_payload() {
+        let packet = Mbuf::from_bytes(&GENEVE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let geneve = udp.parse::<Geneve4>().unwrap();
+
+        assert_eq!(Some(NextProtocol::Ethernet), geneve.next_protocol());
+
+        let inner = geneve.decap().unwrap();
+        assert_eq!(EtherTypes::Ipv4, inner.ether_type());
+        let ipv4 = inner.parse::<Ipv4>().unwrap();
+        assert_eq!(Ipv4Addr::new(139, 133, 217, 110), ipv4.src());
+    }
+
+    #[capsule::test]
+    fn parse_geneve_ipv4_payload() {
+        let packet = Mbuf::from_bytes(&GENEVE_IPV4_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let geneve = udp.parse::<Geneve4>().unwrap();
+
+        assert_eq!(EtherTypes::Ipv4, geneve.protocol_type());
+        assert_eq!(Some(NextProtocol::Ipv4), geneve.next_protocol());
+
+        // the outer Ethernet header is kept in front of the inner packet.
+        let ethernet = geneve.decap().unwrap();
+        assert_eq!(MacAddr::new(0, 0, 0, 0, 0, 0x0a), ethernet.src());
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert_eq!(Ipv4Addr::new(139, 133, 217, 110), ipv4.src());
+        assert_eq!(Ipv4Addr::new(139, 133, 233, 2), ipv4.dst());
+        assert_eq!(IPV4_UDP_PACKET[14..].to_vec(), ipv4.to_vec());
+    }
+
+    #[capsule::test]
+    fn parse_non_geneve_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Geneve4>().is_err());
+    }
+}
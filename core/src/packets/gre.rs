@@ -0,0 +1,575 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Generic Routing Encapsulation.
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::{IpPacket, ProtocolNumbers};
+use crate::packets::tunnel::Encap;
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{checksum, EtherType, Ethernet, Internal, Packet};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+// Masks.
+const FLAGS_C: u16be = u16be(u16::to_be(0b1000_0000_0000_0000));
+const FLAGS_K: u16be = u16be(u16::to_be(0b0010_0000_0000_0000));
+const FLAGS_S: u16be = u16be(u16::to_be(0b0001_0000_0000_0000));
+const VERSION: u16be = u16be(u16::to_be(0b0000_0000_0000_0111));
+
+/// Generic Routing Encapsulation packet based on [IETF RFC 2784] and
+/// [IETF RFC 2890].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |C| |K|S| Reserved0       | Ver |         Protocol Type         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |      Checksum (optional)      |       Reserved1 (Optional)    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         Key (optional)                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 Sequence Number (Optional)                    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Checksum Present*: (1 bit)
+///      If set to 1, then the Checksum and the Reserved1 fields are present
+///      and the Checksum field contains valid information.
+///
+/// - *Key Present*: (1 bit)
+///      If set to 1, then it indicates that the Key field is present.
+///
+/// - *Sequence Number Present*: (1 bit)
+///      If set to 1, then it indicates that the Sequence Number field is
+///      present.
+///
+/// - *Version Number*: (3 bits)
+///      The Version Number field must contain the value zero.
+///
+/// - *Protocol Type*: (16 bits)
+///      The Protocol Type field contains the protocol type of the payload
+///      packet. These are the same as the Ethernet types.
+///
+/// - *Checksum*: (16 bits)
+///      The Checksum field contains the IP (one's complement) checksum sum
+///      of all the 16 bit words in the GRE header and the payload packet.
+///
+/// - *Key*: (32 bits)
+///      The Key field contains a four octet number which is inserted by
+///      the encapsulator. It may be used by the receiver to authenticate
+///      the source of the packet.
+///
+/// - *Sequence Number*: (32 bits)
+///      The Sequence Number field contains an unsigned 32 bit integer
+///      which is inserted by the encapsulator. It may be used by the
+///      receiver to establish the order in which packets have been
+///      transmitted.
+///
+/// [IETF RFC 2784]: https://tools.ietf.org/html/rfc2784#section-2
+/// [IETF RFC 2890]: https://tools.ietf.org/html/rfc2890#section-2
+pub struct Gre<E: IpPacket> {
+    envelope: E,
+    header: NonNull<GreHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Gre<E> {
+    #[inline]
+    fn header(&self) -> &GreHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut GreHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the flags and version word.
+    #[inline]
+    pub fn flags_version(&self) -> u16 {
+        self.header().flags_version.into()
+    }
+
+    /// Returns a flag indicating whether the checksum field is present.
+    #[inline]
+    pub fn checksum_present(&self) -> bool {
+        self.header().flags_version & FLAGS_C != u16be::MIN
+    }
+
+    /// Returns a flag indicating whether the key field is present.
+    #[inline]
+    pub fn key_present(&self) -> bool {
+        self.header().flags_version & FLAGS_K != u16be::MIN
+    }
+
+    /// Returns a flag indicating whether the sequence number field is
+    /// present.
+    #[inline]
+    pub fn sequence_present(&self) -> bool {
+        self.header().flags_version & FLAGS_S != u16be::MIN
+    }
+
+    /// Returns the version number.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        let version: u16 = (self.header().flags_version & VERSION).into();
+        version as u8
+    }
+
+    /// Returns the protocol type of the payload packet.
+    #[inline]
+    pub fn protocol_type(&self) -> EtherType {
+        EtherType::new(self.header().protocol_type.into())
+    }
+
+    /// Sets the protocol type of the payload packet.
+    #[inline]
+    pub fn set_protocol_type(&mut self, protocol_type: EtherType) {
+        self.header_mut().protocol_type = protocol_type.0.into();
+    }
+
+    /// Returns the offset of the optional field that follows the fields
+    /// whose flags are set in `preceding`.
+    #[inline]
+    fn optional_offset(&self, preceding: &[u16be]) -> usize {
+        let flags = self.header().flags_version;
+        self.offset
+            + GreHeader::size_of()
+            + preceding
+                .iter()
+                .filter(|&&flag| flags & flag != u16be::MIN)
+                .count()
+                * 4
+    }
+
+    #[inline]
+    fn read_optional(&self, offset: usize) -> u32 {
+        // the length of the optional fields is checked when parsed.
+        let value = self.mbuf().read_data::<u32be>(offset).unwrap();
+        unsafe { (*value.as_ptr()).into() }
+    }
+
+    #[inline]
+    fn write_optional(&mut self, offset: usize, value: u32) {
+        let value: u32be = value.into();
+        // the length of the optional fields is checked when parsed.
+        let _ = self.mbuf_mut().write_data(offset, &value).unwrap();
+    }
+
+    /// Inserts, updates or removes the optional field of `flag`, which
+    /// follows the fields whose flags are set in `preceding`.
+    #[inline]
+    fn set_optional(&mut self, flag: u16be, preceding: &[u16be], value: Option<u32>) -> Result<()> {
+        let offset = self.optional_offset(preceding);
+        let present = self.header().flags_version & flag != u16be::MIN;
+
+        match (present, value) {
+            (true, Some(value)) => self.write_optional(offset, value),
+            (false, Some(value)) => {
+                self.mbuf_mut().extend(offset, u32be::size_of())?;
+                self.header_mut().flags_version |= flag;
+                self.write_optional(offset, value);
+            }
+            (true, None) => {
+                self.mbuf_mut().shrink(offset, u32be::size_of())?;
+                self.header_mut().flags_version &= !flag;
+            }
+            (false, None) => (),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the checksum if present.
+    #[inline]
+    pub fn checksum(&self) -> Option<u16> {
+        if self.checksum_present() {
+            Some((self.read_optional(self.optional_offset(&[])) >> 16) as u16)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the key if present.
+    #[inline]
+    pub fn key(&self) -> Option<u32> {
+        if self.key_present() {
+            Some(self.read_optional(self.optional_offset(&[FLAGS_C])))
+        } else {
+            None
+        }
+    }
+
+    /// Sets the key, or removes it with `None`.
+    ///
+    /// The header grows or shrinks by 4 octets when the key is added or
+    /// removed, and the key present flag is set accordingly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    pub fn set_key(&mut self, key: Option<u32>) -> Result<()> {
+        self.set_optional(FLAGS_K, &[FLAGS_C], key)
+    }
+
+    /// Returns the sequence number if present.
+    #[inline]
+    pub fn sequence(&self) -> Option<u32> {
+        if self.sequence_present() {
+            Some(self.read_optional(self.optional_offset(&[FLAGS_C, FLAGS_K])))
+        } else {
+            None
+        }
+    }
+
+    /// Sets the sequence number, or removes it with `None`.
+    ///
+    /// The header grows or shrinks by 4 octets when the sequence number is
+    /// added or removed, and the sequence number present flag is set
+    /// accordingly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    pub fn set_sequence(&mut self, sequence: Option<u32>) -> Result<()> {
+        self.set_optional(FLAGS_S, &[FLAGS_C, FLAGS_K], sequence)
+    }
+
+    /// Computes the checksum over the GRE header and the payload packet
+    /// if the checksum field is present.
+    #[inline]
+    pub fn compute_checksum(&mut self) {
+        if self.checksum_present() {
+            let offset = self.optional_offset(&[]);
+            self.write_optional(offset, 0);
+
+            if let Ok(data) = self.mbuf().read_data_slice(self.offset, self.len()) {
+                let data = unsafe { data.as_ref() };
+                let checksum = checksum::compute(0, data);
+                self.write_optional(offset, u32::from(checksum) << 16);
+            } else {
+                // we are reading till the end of buffer, should never run out
+                unreachable!()
+            }
+        }
+    }
+}
+
+impl<E: IpPacket<Envelope = Ethernet>> Gre<E> {
+    /// Encapsulates the payload of the Ethernet frame in outer IP and GRE
+    /// headers, inserted after the Ethernet header.
+    ///
+    /// The protocol type is set to the Ethernet type of the payload. The
+    /// outer addresses should be set afterwards through the envelope,
+    /// followed by [`reconcile_all`] to fix the lengths and the checksums.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    #[inline]
+    pub fn encap(inner: Ethernet) -> Result<Self> {
+        let protocol_type = inner.ether_type();
+        let mut gre = inner.push::<E>()?.push::<Gre<E>>()?;
+        gre.set_protocol_type(protocol_type);
+        Ok(gre)
+    }
+
+    /// Removes the outer IP and GRE headers, and returns the Ethernet
+    /// frame with the Ethernet type set to the GRE protocol type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have sufficient data to
+    /// remove.
+    #[inline]
+    pub fn decap(self) -> Result<Ethernet> {
+        let protocol_type = self.protocol_type();
+        let mut ethernet = self.remove()?.remove()?;
+        ethernet.set_ether_type(protocol_type);
+        Ok(ethernet)
+    }
+}
+
+impl<E: IpPacket<Envelope = Ethernet>> Encap for Gre<E> {
+    type Inner = Ethernet;
+
+    /// GRE encapsulation takes no parameters.
+    type Params = ();
+
+    #[inline]
+    fn encapsulate(inner: Self::Inner, _params: Self::Params) -> Result<Self> {
+        Gre::encap(inner)
+    }
+
+    #[inline]
+    fn decapsulate(self) -> Result<Self::Inner> {
+        self.decap()
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Gre<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("gre")
+            .field("checksum_present", &self.checksum_present())
+            .field("key_present", &self.key_present())
+            .field("sequence_present", &self.sequence_present())
+            .field("version", &self.version())
+            .field("protocol_type", &format!("{}", self.protocol_type()))
+            .field("checksum", &self.checksum())
+            .field("key", &self.key())
+            .field("sequence", &self.sequence())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Gre<E> {
+    /// The preceding type for GRE must be either an IPv4 or IPv6 packet.
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the GRE header, including the optional fields
+    /// that are present.
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.optional_offset(&[FLAGS_C, FLAGS_K, FLAGS_S]) - self.offset
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Gre {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as a GRE packet.
+    ///
+    /// [`next_protocol`] must be set to [`ProtocolNumbers::Gre`]. Otherwise
+    /// parsing will fail.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload does not have sufficient data for
+    /// the GRE header and the optional fields that are present.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Gre,
+            anyhow!("not a GRE packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Gre {
+            envelope,
+            header,
+            offset,
+        };
+
+        // makes sure the optional fields are in the buffer.
+        let _ = packet
+            .mbuf()
+            .read_data_slice::<u8>(offset, packet.header_len())?;
+
+        Ok(packet)
+    }
+
+    /// Prepends a GRE header without any optional fields to the beginning
+    /// of the envelope's payload.
+    ///
+    /// The envelope's [`next_protocol`] is set to [`ProtocolNumbers::Gre`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, GreHeader::size_of())?;
+        let header = mbuf.write_data(offset, &GreHeader::default())?;
+
+        envelope.set_next_protocol(ProtocolNumbers::Gre);
+
+        Ok(Gre {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`checksum`] is computed over the GRE header and the payload if
+    /// the checksum field is present.
+    ///
+    /// [`checksum`]: Gre::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        self.compute_checksum();
+    }
+}
+
+/// A type alias for an IPv4 GRE packet.
+pub type Gre4 = Gre<Ipv4>;
+
+/// A type alias for an IPv6 GRE packet.
+pub type Gre6 = Gre<Ipv6>;
+
+/// GRE header without the optional fields.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct GreHeader {
+    flags_version: u16be,
+    protocol_type: u16be,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::EtherTypes;
+    use crate::testils::byte_arrays::{GRE_PACKET, IPV4_UDP_PACKET};
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_gre_header() {
+        assert_eq!(4, GreHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_gre_packet() {
+        let packet = Mbuf::from_bytes(&GRE_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let gre = ipv4.parse::<Gre4>().unwrap();
+
+        assert!(!gre.checksum_present());
+        assert!(gre.key_present());
+        assert!(!gre.sequence_present());
+        assert_eq!(0, gre.version());
+        assert_eq!(EtherTypes::Ipv4, gre.protocol_type());
+        assert_eq!(None, gre.checksum());
+        assert_eq!(Some(1234), gre.key());
+        assert_eq!(None, gre.sequence());
+        assert_eq!(8, gre.header_len());
+
+        assert_eq!(IPV4_UDP_PACKET.len() - 14, gre.payload_len());
+    }
+
+    #[capsule::test]
+    fn parse_non_gre_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Gre4>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_gre_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut gre = ipv4.push::<Gre4>().unwrap();
+        gre.set_protocol_type(EtherTypes::Ipv6);
+
+        assert_eq!(4, gre.len());
+        assert_eq!(EtherTypes::Ipv6, gre.protocol_type());
+        assert_eq!(None, gre.key());
+        assert_eq!(ProtocolNumbers::Gre, gre.envelope().next_protocol());
+    }
+
+    #[capsule::test]
+    fn toggle_gre_key_and_sequence() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut gre = ipv4.push::<Gre4>().unwrap();
+
+        gre.set_sequence(Some(9)).unwrap();
+        assert!(gre.sequence_present());
+        assert_eq!(8, gre.header_len());
+
+        // the key is inserted before the sequence number.
+        gre.set_key(Some(7)).unwrap();
+        assert!(gre.key_present());
+        assert_eq!(12, gre.header_len());
+        assert_eq!(Some(7), gre.key());
+        assert_eq!(Some(9), gre.sequence());
+
+        gre.set_key(Some(8)).unwrap();
+        assert_eq!(12, gre.header_len());
+        assert_eq!(Some(8), gre.key());
+
+        gre.set_key(None).unwrap();
+        assert!(!gre.key_present());
+        assert_eq!(8, gre.header_len());
+        assert_eq!(None, gre.key());
+        assert_eq!(Some(9), gre.sequence());
+
+        gre.set_sequence(None).unwrap();
+        assert!(!gre.sequence_present());
+        assert_eq!(4, gre.header_len());
+        assert_eq!(4, gre.len());
+        assert_eq!(0, gre.flags_version());
+
+        // clearing an absent field is a no-op.
+        gre.set_key(None).unwrap();
+        assert_eq!(4, gre.header_len());
+    }
+}
@@ -0,0 +1,621 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! GPRS Tunnelling Protocol User Plane.
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::IpPacket;
+use crate::packets::tunnel::Encap;
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{EtherType, EtherTypes, Ethernet, Internal, Packet, Udp};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The IANA assigned UDP port of GTP-U.
+pub const GTPU_PORT: u16 = 2152;
+
+// Masks.
+const VERSION: u8 = 0b1110_0000;
+const FLAGS_PT: u8 = 0b0001_0000;
+const FLAGS_E: u8 = 0b0000_0100;
+const FLAGS_S: u8 = 0b0000_0010;
+const FLAGS_PN: u8 = 0b0000_0001;
+
+/// GPRS Tunnelling Protocol User Plane packet based on [3GPP TS 29.281].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |Ver  |P|R|E|S|N| Message Type  |            Length             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 Tunnel Endpoint Identifier                    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |        Sequence Number        |    N-PDU No.  |  Next Ext Type|
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Version*: (3 bits)
+///      The version of GTP, set to 1 for GTPv1.
+///
+/// - *Protocol Type*: (1 bit)
+///      Set to 1 for GTP, and 0 for GTP'.
+///
+/// - *Extension Header Flag*: (1 bit)
+///      Indicates the presence of the Next Extension Header Type field.
+///
+/// - *Sequence Number Flag*: (1 bit)
+///      Indicates the presence of a meaningful Sequence Number field.
+///
+/// - *N-PDU Number Flag*: (1 bit)
+///      Indicates the presence of a meaningful N-PDU Number field.
+///
+/// - *Message Type*: (8 bits)
+///      The type of GTP-U message. User data is carried in G-PDU messages.
+///
+/// - *Length*: (16 bits)
+///      The length in octets of the payload, i.e. the rest of the packet
+///      following the mandatory part of the header. The optional fields
+///      are considered to be part of the payload.
+///
+/// - *Tunnel Endpoint Identifier*: (32 bits)
+///      Unambiguously identifies a tunnel endpoint in the receiving
+///      GTP-U protocol entity.
+///
+/// - *Sequence Number, N-PDU Number, Next Extension Header Type*: (32 bits)
+///      Present if any one of the E, S or PN flags is set. Each field is
+///      only meaningful when its own flag is set.
+///
+/// The extension headers, if any, follow the optional fields. The
+/// payload of a G-PDU is the user IP packet. Use [`decap`] to replace the
+/// outer IP, UDP and GTP-U headers with the user packet.
+///
+/// [3GPP TS 29.281]: https://www.3gpp.org/ftp/Specs/archive/29_series/29.281/
+/// [`decap`]: Gtpu::decap
+pub struct Gtpu<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<GtpuHeader>,
+    offset: usize,
+    header_len: usize,
+}
+
+impl<E: IpPacket> Gtpu<E> {
+    #[inline]
+    fn header(&self) -> &GtpuHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut GtpuHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the version and flags byte.
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.header().flags
+    }
+
+    /// Returns the version.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        (self.header().flags & VERSION) >> 5
+    }
+
+    /// Returns the protocol type, `true` for GTP and `false` for GTP'.
+    #[inline]
+    pub fn protocol_type(&self) -> bool {
+        self.header().flags & FLAGS_PT != 0
+    }
+
+    /// Returns a flag indicating whether extension headers are present.
+    #[inline]
+    pub fn extension_present(&self) -> bool {
+        self.header().flags & FLAGS_E != 0
+    }
+
+    /// Returns a flag indicating whether the sequence number is present.
+    #[inline]
+    pub fn sequence_present(&self) -> bool {
+        self.header().flags & FLAGS_S != 0
+    }
+
+    /// Returns a flag indicating whether the N-PDU number is present.
+    #[inline]
+    pub fn n_pdu_present(&self) -> bool {
+        self.header().flags & FLAGS_PN != 0
+    }
+
+    /// Returns the message type.
+    #[inline]
+    pub fn message_type(&self) -> MessageType {
+        MessageType::new(self.header().message_type)
+    }
+
+    /// Sets the message type.
+    #[inline]
+    pub fn set_message_type(&mut self, message_type: MessageType) {
+        self.header_mut().message_type = message_type.0;
+    }
+
+    /// Returns the length of the payload following the mandatory header.
+    #[inline]
+    pub fn length(&self) -> u16 {
+        self.header().length.into()
+    }
+
+    #[inline]
+    fn set_length(&mut self, length: u16) {
+        self.header_mut().length = length.into();
+    }
+
+    /// Returns the tunnel endpoint identifier.
+    #[inline]
+    pub fn teid(&self) -> u32 {
+        self.header().teid.into()
+    }
+
+    /// Sets the tunnel endpoint identifier.
+    #[inline]
+    pub fn set_teid(&mut self, teid: u32) {
+        self.header_mut().teid = teid.into();
+    }
+
+    #[inline]
+    fn optional(&self) -> Option<&GtpuOptional> {
+        if self.flags() & (FLAGS_E | FLAGS_S | FLAGS_PN) != 0 {
+            // the optional fields are checked when parsed.
+            let optional = self
+                .mbuf()
+                .read_data::<GtpuOptional>(self.offset + GtpuHeader::size_of())
+                .unwrap();
+            Some(unsafe { &*optional.as_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the sequence number if present.
+    #[inline]
+    pub fn sequence(&self) -> Option<u16> {
+        if self.sequence_present() {
+            self.optional().map(|opt| opt.sequence.into())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the N-PDU number if present.
+    #[inline]
+    pub fn n_pdu(&self) -> Option<u8> {
+        if self.n_pdu_present() {
+            self.optional().map(|opt| opt.n_pdu)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the type of the first extension header if present.
+    #[inline]
+    pub fn next_extension_type(&self) -> Option<u8> {
+        if self.extension_present() {
+            self.optional().map(|opt| opt.next_extension_type)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the Ethernet type of the user packet guessed from the
+    /// first nibble, or `None` if the payload is neither IPv4 nor IPv6.
+    #[inline]
+    pub fn payload_type(&self) -> Option<EtherType> {
+        let first = self.mbuf().read_data::<u8>(self.payload_offset()).ok()?;
+        match unsafe { *first.as_ref() } >> 4 {
+            4 => Some(EtherTypes::Ipv4),
+            6 => Some(EtherTypes::Ipv6),
+            _ => None,
+        }
+    }
+
+    /// Removes the outer IP, UDP and GTP-U headers, and returns the outer
+    /// Ethernet frame with the user IP packet as its payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is not a G-PDU, or if the payload is
+    /// neither an IPv4 nor an IPv6 packet.
+    #[inline]
+    pub fn decap(self) -> Result<Ethernet> {
+        ensure!(
+            self.message_type() == MessageTypes::GPdu,
+            anyhow!("not a G-PDU message.")
+        );
+        let ether_type = self
+            .payload_type()
+            .ok_or_else(|| anyhow!("unknown GTP-U payload type."))?;
+
+        let outer_len = self.payload_offset();
+        let mut ethernet = self.reset().parse::<Ethernet>()?;
+        let offset = ethernet.payload_offset();
+        ethernet.mbuf_mut().shrink(offset, outer_len - offset)?;
+        ethernet.set_ether_type(ether_type);
+        Ok(ethernet)
+    }
+}
+
+impl<E: IpPacket<Envelope = Ethernet>> Gtpu<E> {
+    /// Encapsulates the payload of the Ethernet frame in outer IP, UDP and
+    /// GTP-U headers, inserted after the Ethernet header.
+    ///
+    /// The UDP source and destination ports are set to [`GTPU_PORT`]. The
+    /// outer addresses should be set afterwards through the envelopes,
+    /// followed by [`reconcile_all`] to fix the lengths and the checksums.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    #[inline]
+    pub fn encap(inner: Ethernet, teid: u32) -> Result<Self> {
+        let mut udp = inner.push::<E>()?.push::<Udp<E>>()?;
+        udp.set_src_port(GTPU_PORT);
+        let mut gtpu = udp.push::<Gtpu<E>>()?;
+        gtpu.set_teid(teid);
+        Ok(gtpu)
+    }
+}
+
+impl<E: IpPacket<Envelope = Ethernet>> Encap for Gtpu<E> {
+    type Inner = Ethernet;
+
+    /// The tunnel endpoint identifier.
+    type Params = u32;
+
+    #[inline]
+    fn encapsulate(inner: Self::Inner, teid: Self::Params) -> Result<Self> {
+        Gtpu::encap(inner, teid)
+    }
+
+    #[inline]
+    fn decapsulate(self) -> Result<Self::Inner> {
+        self.decap()
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Gtpu<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("gtpu")
+            .field("version", &self.version())
+            .field("protocol_type", &self.protocol_type())
+            .field("message_type", &format!("{}", self.message_type()))
+            .field("length", &self.length())
+            .field("teid", &format!("0x{:08x}", self.teid()))
+            .field("sequence", &self.sequence())
+            .field("n_pdu", &self.n_pdu())
+            .field("next_extension_type", &self.next_extension_type())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Gtpu<E> {
+    /// The preceding type for GTP-U must be `Udp`.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the GTP-U header, including the optional
+    /// fields and the extension headers.
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.header_len
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Gtpu {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+            header_len: self.header_len,
+        }
+    }
+
+    /// Parses the UDP payload as a GTP-U packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the UDP destination port is not [`GTPU_PORT`].
+    /// Returns an error if the version is not 1. Returns an error if the
+    /// payload does not have sufficient data for the header, the optional
+    /// fields and the extension headers.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.dst_port() == GTPU_PORT,
+            anyhow!("not a GTP-U packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data::<GtpuHeader>(offset)?;
+
+        let flags = unsafe { header.as_ref() }.flags;
+        ensure!(flags & VERSION == 1 << 5, anyhow!("not a GTPv1 packet."));
+
+        let mut header_len = GtpuHeader::size_of();
+        if flags & (FLAGS_E | FLAGS_S | FLAGS_PN) != 0 {
+            let optional = mbuf.read_data::<GtpuOptional>(offset + header_len)?;
+            header_len += GtpuOptional::size_of();
+
+            if flags & FLAGS_E != 0 {
+                let next = unsafe { optional.as_ref() }.next_extension_type;
+                header_len += extensions_len(mbuf, offset + header_len, next)?;
+            }
+        }
+
+        Ok(Gtpu {
+            envelope,
+            header,
+            offset,
+            header_len,
+        })
+    }
+
+    /// Prepends a GTP-U header without optional fields to the beginning
+    /// of the UDP's payload.
+    ///
+    /// The UDP destination port is set to [`GTPU_PORT`] and the message
+    /// type is set to [`MessageTypes::GPdu`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, GtpuHeader::size_of())?;
+        let header = mbuf.write_data(offset, &GtpuHeader::default())?;
+
+        envelope.set_dst_port(GTPU_PORT);
+
+        Ok(Gtpu {
+            envelope,
+            header,
+            offset,
+            header_len: GtpuHeader::size_of(),
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`length`] is set to the length of the packet following the
+    /// mandatory header.
+    ///
+    /// [`length`]: Gtpu::length
+    #[inline]
+    fn reconcile(&mut self) {
+        let len = self.len() - GtpuHeader::size_of();
+        self.set_length(len as u16);
+    }
+}
+
+/// Returns the total length of the extension headers at offset.
+///
+/// Each extension header begins with its length in 4-byte multiples, and
+/// ends with the type of the next extension header. Type 0 indicates no
+/// more extension headers follow.
+fn extensions_len(mbuf: &Mbuf, offset: usize, mut next: u8) -> Result<usize> {
+    let mut len = 0;
+
+    while next != 0 {
+        let ext_len = unsafe { *mbuf.read_data::<u8>(offset + len)?.as_ref() } as usize * 4;
+        ensure!(
+            ext_len > 0,
+            anyhow!("invalid GTP-U extension header length.")
+        );
+        len += ext_len;
+        next = unsafe { *mbuf.read_data::<u8>(offset + len - 1)?.as_ref() };
+    }
+
+    Ok(len)
+}
+
+/// A type alias for a GTP-U packet over IPv4.
+pub type Gtpu4 = Gtpu<Ipv4>;
+
+/// A type alias for a GTP-U packet over IPv6.
+pub type Gtpu6 = Gtpu<Ipv6>;
+
+/// GTP-U message type.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct MessageType(pub u8);
+
+impl MessageType {
+    /// Creates a new message type.
+    pub fn new(value: u8) -> Self {
+        MessageType(value)
+    }
+}
+
+/// Supported GTP-U message types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod MessageTypes {
+    use super::MessageType;
+
+    /// Echo request.
+    pub const EchoRequest: MessageType = MessageType(1);
+    /// Echo response.
+    pub const EchoResponse: MessageType = MessageType(2);
+    /// Error indication.
+    pub const ErrorIndication: MessageType = MessageType(26);
+    /// Supported extension headers notification.
+    pub const SupportedExtensionHeadersNotification: MessageType = MessageType(31);
+    /// End marker.
+    pub const EndMarker: MessageType = MessageType(254);
+    /// Encapsulated user data.
+    pub const GPdu: MessageType = MessageType(255);
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                MessageTypes::EchoRequest => "Echo Request".to_string(),
+                MessageTypes::EchoResponse => "Echo Response".to_string(),
+                MessageTypes::ErrorIndication => "Error Indication".to_string(),
+                MessageTypes::SupportedExtensionHeadersNotification => {
+                    "Supported Extension Headers Notification".to_string()
+                }
+                MessageTypes::EndMarker => "End Marker".to_string(),
+                MessageTypes::GPdu => "G-PDU".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("{}", t)
+                }
+            }
+        )
+    }
+}
+
+/// GTP-U mandatory header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct GtpuHeader {
+    flags: u8,
+    message_type: u8,
+    length: u16be,
+    teid: u32be,
+}
+
+impl Default for GtpuHeader {
+    fn default() -> GtpuHeader {
+        GtpuHeader {
+            flags: (1 << 5) | FLAGS_PT,
+            message_type: MessageTypes::GPdu.0,
+            length: u16be::default(),
+            teid: u32be::default(),
+        }
+    }
+}
+
+/// GTP-U optional fields.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct GtpuOptional {
+    sequence: u16be,
+    n_pdu: u8,
+    next_extension_type: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testils::byte_arrays::{GTPU_PACKET, IPV4_UDP_PACKET};
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn size_of_gtpu_header() {
+        assert_eq!(8, GtpuHeader::size_of());
+        assert_eq!(4, GtpuOptional::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_gtpu_packet() {
+        let packet = Mbuf::from_bytes(&GTPU_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let gtpu = udp.parse::<Gtpu4>().unwrap();
+
+        assert_eq!(1, gtpu.version());
+        assert!(gtpu.protocol_type());
+        assert_eq!(MessageTypes::GPdu, gtpu.message_type());
+        assert_eq!(42, gtpu.length());
+        assert_eq!(0x1234_5678, gtpu.teid());
+        assert_eq!(Some(1), gtpu.sequence());
+        assert_eq!(None, gtpu.n_pdu());
+        assert_eq!(None, gtpu.next_extension_type());
+        assert_eq!(12, gtpu.header_len());
+        assert_eq!(Some(EtherTypes::Ipv4), gtpu.payload_type());
+
+        let ethernet = gtpu.decap().unwrap();
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert_eq!(Ipv4Addr::new(139, 133, 217, 110), ipv4.src());
+        assert_eq!(Ipv4Addr::new(139, 133, 233, 2), ipv4.dst());
+    }
+
+    #[capsule::test]
+    fn parse_non_gtpu_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Gtpu4>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_and_reconcile_gtpu_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let mut gtpu = udp.push::<Gtpu4>().unwrap();
+        gtpu.set_teid(7);
+        gtpu.reconcile();
+
+        assert_eq!(1, gtpu.version());
+        assert_eq!(MessageTypes::GPdu, gtpu.message_type());
+        assert_eq!(0, gtpu.length());
+        assert_eq!(GTPU_PORT, gtpu.envelope().dst_port());
+        assert_eq!(None, gtpu.sequence());
+    }
+}
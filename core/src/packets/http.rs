@@ -0,0 +1,274 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! HTTP/1 request head parsing.
+//!
+//! HTTP runs over TCP, so the request head does not align with the TCP
+//! segments. The functions in this module operate on the reassembled TCP
+//! payload. When the payload ends before the end of the request head,
+//! `HttpError::Incomplete` is returned so the caller can retry once more
+//! data has arrived.
+//!
+//! No memory is allocated. The method, the target and the header field
+//! values borrow from the payload.
+
+use crate::ensure;
+use anyhow::Result;
+use std::str;
+use thiserror::Error;
+
+/// Error indicating an HTTP parsing failure.
+#[derive(Debug, Error)]
+pub enum HttpError {
+    /// The data ends before the end of the request head.
+    #[error("Need more bytes to parse the HTTP request head.")]
+    Incomplete,
+
+    /// The data does not begin with an HTTP/1 request line.
+    #[error("Data is not an HTTP/1 request.")]
+    NotHttp,
+}
+
+/// HTTP/1 request head based on [IETF RFC 7230].
+///
+/// ```
+/// GET /index.html HTTP/1.1
+/// Host: www.example.com
+/// User-Agent: curl/7.64.1
+///
+/// ```
+///
+/// - *Request Line*:
+///      The method, the request target and the HTTP version, separated by
+///      a single space.
+///
+/// - *Header Fields*:
+///      `name: value` lines, terminated by an empty line.
+///
+/// The message body that follows the request head is not parsed.
+///
+/// [IETF RFC 7230]: https://tools.ietf.org/html/rfc7230#section-3
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RequestHead<'a> {
+    method: &'a str,
+    target: &'a str,
+    version: &'a str,
+    headers: &'a [u8],
+    len: usize,
+}
+
+impl<'a> RequestHead<'a> {
+    /// Parses the request head at the beginning of the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HttpError::NotHttp` if the data does not begin with an
+    /// HTTP/1 request line. Returns `HttpError::Incomplete` if the data
+    /// ends before the empty line that terminates the header fields.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let (line, rest) = match next_line(data) {
+            Some(split) => split,
+            None => {
+                ensure!(is_request_line_prefix(data), HttpError::NotHttp);
+                return Err(HttpError::Incomplete.into());
+            }
+        };
+
+        let line = str::from_utf8(line).map_err(|_| HttpError::NotHttp)?;
+        let mut parts = line.splitn(3, ' ');
+        let method = parts.next().unwrap_or("");
+        let target = parts.next().unwrap_or("");
+        let version = parts.next().unwrap_or("");
+        ensure!(is_method(method), HttpError::NotHttp);
+        ensure!(!target.is_empty(), HttpError::NotHttp);
+        ensure!(is_version(version), HttpError::NotHttp);
+
+        // finds the empty line that terminates the header fields.
+        let mut remaining = rest;
+        loop {
+            match next_line(remaining) {
+                Some((line, next)) => {
+                    remaining = next;
+                    if line.is_empty() {
+                        break;
+                    }
+                }
+                None => return Err(HttpError::Incomplete.into()),
+            }
+        }
+
+        let len = data.len() - remaining.len();
+        let headers = &rest[..rest.len() - remaining.len()];
+
+        Ok(RequestHead {
+            method,
+            target,
+            version,
+            headers,
+            len,
+        })
+    }
+
+    /// Returns the method, for example `GET`.
+    pub fn method(&self) -> &'a str {
+        self.method
+    }
+
+    /// Returns the request target, for example `/index.html`.
+    pub fn target(&self) -> &'a str {
+        self.target
+    }
+
+    /// Returns the HTTP version, either `HTTP/1.0` or `HTTP/1.1`.
+    pub fn version(&self) -> &'a str {
+        self.version
+    }
+
+    /// Returns the length of the request head in octets, including the
+    /// empty line. The message body, if any, begins at this offset.
+    pub fn head_len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the value of the first header field with the name.
+    ///
+    /// The name is case-insensitive. Returns `None` if the header field
+    /// is not present or its value is not valid UTF-8.
+    pub fn header(&self, name: &str) -> Option<&'a str> {
+        let mut data = self.headers;
+        while let Some((line, rest)) = next_line(data) {
+            data = rest;
+
+            if let Ok(line) = str::from_utf8(line) {
+                if let Some(pos) = line.find(':') {
+                    if line[..pos].eq_ignore_ascii_case(name) {
+                        return Some(line[pos + 1..].trim());
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the value of the `Host` header field.
+    pub fn host(&self) -> Option<&'a str> {
+        self.header("Host")
+    }
+
+    /// Returns the value of the `User-Agent` header field.
+    pub fn user_agent(&self) -> Option<&'a str> {
+        self.header("User-Agent")
+    }
+}
+
+/// Splits the first line terminated by LF from the rest of the data.
+///
+/// The trailing CR of a CRLF is removed. Returns `None` if the data has no
+/// line terminator.
+fn next_line(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let pos = data.iter().position(|&b| b == b'\n')?;
+    let line = match &data[..pos] {
+        [line @ .., b'\r'] => line,
+        line => line,
+    };
+    Some((line, &data[pos + 1..]))
+}
+
+/// Returns whether the method is an uppercase token, for example `GET`.
+fn is_method(method: &str) -> bool {
+    !method.is_empty() && method.bytes().all(|b| b.is_ascii_uppercase())
+}
+
+/// Returns whether the version is HTTP/1.0 or HTTP/1.1.
+fn is_version(version: &str) -> bool {
+    version == "HTTP/1.0" || version == "HTTP/1.1"
+}
+
+/// Returns whether the data could be the beginning of a request line.
+fn is_request_line_prefix(data: &[u8]) -> bool {
+    let method_len = data.iter().position(|&b| b == b' ').unwrap_or(data.len());
+    data[..method_len].iter().all(|b| b.is_ascii_uppercase())
+        && data[method_len..]
+            .iter()
+            .all(|&b| b.is_ascii_graphic() || b == b' ' || b == b'\r')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GET_REQUEST: &[u8] = b"GET /index.html HTTP/1.1\r\n\
+        Host: www.example.com\r\n\
+        User-Agent: curl/7.64.1\r\n\
+        Accept: */*\r\n\
+        \r\n";
+
+    #[test]
+    fn parse_get_request() {
+        let head = RequestHead::parse(GET_REQUEST).unwrap();
+
+        assert_eq!("GET", head.method());
+        assert_eq!("/index.html", head.target());
+        assert_eq!("HTTP/1.1", head.version());
+        assert_eq!(GET_REQUEST.len(), head.head_len());
+        assert_eq!(Some("www.example.com"), head.host());
+        assert_eq!(Some("curl/7.64.1"), head.user_agent());
+        assert_eq!(Some("*/*"), head.header("accept"));
+        assert_eq!(None, head.header("Cookie"));
+    }
+
+    #[test]
+    fn parse_request_with_body() {
+        let mut data = b"POST /form HTTP/1.0\r\nHost: example.com\r\n\r\n".to_vec();
+        let len = data.len();
+        data.extend_from_slice(b"name=value");
+        let head = RequestHead::parse(&data).unwrap();
+
+        assert_eq!("POST", head.method());
+        assert_eq!(len, head.head_len());
+        assert_eq!(b"name=value", &data[head.head_len()..]);
+    }
+
+    #[test]
+    fn parse_incomplete_request() {
+        for end in &[0, 3, 10, 30, GET_REQUEST.len() - 1] {
+            let err = RequestHead::parse(&GET_REQUEST[..*end]).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<HttpError>(),
+                Some(HttpError::Incomplete)
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_non_http_data() {
+        for data in &[
+            &b"SSH-2.0-OpenSSH_8.2\r\n"[..],
+            &b"GET / HTTP/2.0\r\n\r\n"[..],
+            &b"get / HTTP/1.1\r\n\r\n"[..],
+            &[0x16, 0x03, 0x01, 0x00][..],
+        ] {
+            let err = RequestHead::parse(data).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<HttpError>(),
+                Some(HttpError::NotHttp)
+            ));
+        }
+    }
+}
@@ -18,11 +18,13 @@
 
 //! Internet Control Message Protocol for IPv4.
 
+mod destination_unreachable;
 mod echo_reply;
 mod echo_request;
 mod redirect;
 mod time_exceeded;
 
+pub use self::destination_unreachable::*;
 pub use self::echo_reply::*;
 pub use self::echo_request::*;
 pub use self::redirect::*;
@@ -32,8 +34,8 @@ pub use capsule_macros::Icmpv4Packet;
 use crate::packets::ip::v4::Ipv4;
 use crate::packets::ip::ProtocolNumbers;
 use crate::packets::types::u16be;
-use crate::packets::{checksum, Internal, Packet};
-use crate::{ensure, SizeOf};
+use crate::packets::{checksum, Ethernet, Internal, Packet};
+use crate::{ensure, Mbuf, SizeOf};
 use anyhow::{anyhow, Result};
 use std::fmt;
 use std::ptr::NonNull;
@@ -143,6 +145,72 @@ impl Icmpv4 {
 
         T::try_parse(self, Internal(()))
     }
+
+    /// Creates a time exceeded message in a new buffer, in response to the
+    /// original IPv4 packet being discarded because its time to live
+    /// reached zero.
+    ///
+    /// The message embeds the IPv4 header and the first 64 bits of the
+    /// payload of the original packet. The source and destination of the
+    /// Ethernet and IPv4 headers are swapped from the original packet. If
+    /// the original packet is not addressed to this host, the source
+    /// should be set to the address of the sending interface instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the allocation of the buffer fails.
+    pub fn time_exceeded(original: &Ipv4) -> Result<TimeExceeded> {
+        // code 0, time to live exceeded in transit.
+        Icmpv4::error_message(original, 0)
+    }
+
+    /// Creates a destination unreachable message with the code in a new
+    /// buffer, in response to the original IPv4 packet being discarded.
+    ///
+    /// The message embeds the IPv4 header and the first 64 bits of the
+    /// payload of the original packet. The addressing is the same as
+    /// [`time_exceeded`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the allocation of the buffer fails.
+    ///
+    /// [`time_exceeded`]: Icmpv4::time_exceeded
+    pub fn destination_unreachable(original: &Ipv4, code: u8) -> Result<DestinationUnreachable> {
+        Icmpv4::error_message(original, code)
+    }
+
+    /// Creates an ICMPv4 error message in response to the original IPv4
+    /// packet.
+    fn error_message<T>(original: &Ipv4, code: u8) -> Result<T>
+    where
+        T: Icmpv4Message + Packet<Envelope = Ipv4>,
+    {
+        // the original IPv4 header and 64 bits of the original data.
+        let len = (original.header_len() + 8).min(original.len());
+        let data = original
+            .mbuf()
+            .read_data_slice::<u8>(original.offset(), len)?;
+        let data = unsafe { data.as_ref() };
+
+        let mut ethernet = Mbuf::new()?.push::<Ethernet>()?;
+        ethernet.set_src(original.envelope().dst());
+        ethernet.set_dst(original.envelope().src());
+
+        let mut ipv4 = ethernet.push::<Ipv4>()?;
+        ipv4.set_src(original.dst());
+        ipv4.set_dst(original.src());
+
+        let offset = ipv4.payload_offset();
+        ipv4.mbuf_mut().extend(offset, len)?;
+        let _ = ipv4.mbuf_mut().write_data_slice(offset, data)?;
+
+        let mut message = ipv4.push::<T>()?;
+        message.icmp_mut().set_code(code);
+        message.reconcile_all();
+
+        Ok(message)
+    }
 }
 
 impl fmt::Debug for Icmpv4 {
@@ -290,6 +358,11 @@ pub mod Icmpv4Types {
     ///
     /// [Redirect]: crate::packets::icmp::v4::Redirect
     pub const Redirect: Icmpv4Type = Icmpv4Type(5);
+
+    /// Message type for [Destination Unreachable].
+    ///
+    /// [Destination Unreachable]: crate::packets::icmp::v4::DestinationUnreachable
+    pub const DestinationUnreachable: Icmpv4Type = Icmpv4Type(3);
 }
 
 impl fmt::Display for Icmpv4Type {
@@ -302,6 +375,7 @@ impl fmt::Display for Icmpv4Type {
                 Icmpv4Types::EchoReply => "Echo Reply".to_string(),
                 Icmpv4Types::TimeExceeded => "Time Exceeded".to_string(),
                 Icmpv4Types::Redirect => "Redirect".to_string(),
+                Icmpv4Types::DestinationUnreachable => "Destination Unreachable".to_string(),
                 _ => format!("{}", self.0),
             }
         )
@@ -446,10 +520,7 @@ pub trait Icmpv4Packet {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::packets::ip::v4::Ipv4;
-    use crate::packets::Ethernet;
     use crate::testils::byte_arrays::{ICMPV4_PACKET, IPV4_UDP_PACKET};
-    use crate::Mbuf;
 
     #[test]
     fn size_of_icmpv4_header() {
@@ -521,4 +592,40 @@ mod tests {
 
         assert!(ipv4.push::<Icmpv4>().is_err());
     }
+
+    #[capsule::test]
+    fn time_exceeded_for_ipv4_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let original = ethernet.parse::<Ipv4>().unwrap();
+
+        let exceeded = Icmpv4::time_exceeded(&original).unwrap();
+
+        assert_eq!(Icmpv4Types::TimeExceeded, exceeded.msg_type());
+        assert_eq!(0, exceeded.code());
+        assert_eq!(original.src(), exceeded.envelope().dst());
+        assert_eq!(original.dst(), exceeded.envelope().src());
+        assert_eq!(ProtocolNumbers::Icmpv4, exceeded.envelope().protocol());
+
+        // the IPv4 header and 8 bytes of the UDP datagram.
+        assert_eq!(&IPV4_UDP_PACKET[14..42], exceeded.data());
+
+        // the checksum of a valid message sums to zero.
+        assert_eq!(0, checksum::compute(0, exceeded.as_bytes()));
+    }
+
+    #[capsule::test]
+    fn destination_unreachable_for_ipv4_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let original = ethernet.parse::<Ipv4>().unwrap();
+
+        // code 1, host unreachable.
+        let unreachable = Icmpv4::destination_unreachable(&original, 1).unwrap();
+
+        assert_eq!(Icmpv4Types::DestinationUnreachable, unreachable.msg_type());
+        assert_eq!(1, unreachable.code());
+        assert_eq!(&IPV4_UDP_PACKET[14..42], unreachable.data());
+        assert_eq!(0, checksum::compute(0, unreachable.as_bytes()));
+    }
 }
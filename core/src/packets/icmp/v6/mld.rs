@@ -0,0 +1,597 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Multicast Listener Discovery for IPv6.
+
+use crate::packets::icmp::v6::{Icmpv6, Icmpv6Message, Icmpv6Packet, Icmpv6Type, Icmpv6Types};
+use crate::packets::igmp::GroupRecordType;
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::types::u16be;
+use crate::packets::{Internal, Packet};
+use crate::{Mbuf, SizeOf};
+use anyhow::Result;
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::ptr::NonNull;
+
+/// Generates a MLDv1 message.
+///
+/// The query, report and done messages share the same format and only
+/// differ in the message type that identifies them.
+macro_rules! mld_message {
+    (
+        $(#[$meta:meta])*
+        $name:ident, $msg_type:expr
+    ) => {
+        $(#[$meta])*
+        #[derive(Icmpv6Packet)]
+        pub struct $name<E: Ipv6Packet> {
+            icmp: Icmpv6<E>,
+            body: NonNull<MldBody>,
+        }
+
+        impl<E: Ipv6Packet> $name<E> {
+            #[inline]
+            fn body(&self) -> &MldBody {
+                unsafe { self.body.as_ref() }
+            }
+
+            #[inline]
+            fn body_mut(&mut self) -> &mut MldBody {
+                unsafe { self.body.as_mut() }
+            }
+
+            /// Returns the maximum response delay in milliseconds.
+            #[inline]
+            pub fn max_response_delay(&self) -> u16 {
+                self.body().max_response_delay.into()
+            }
+
+            /// Sets the maximum response delay in milliseconds.
+            #[inline]
+            pub fn set_max_response_delay(&mut self, max_response_delay: u16) {
+                self.body_mut().max_response_delay = max_response_delay.into();
+            }
+
+            /// Returns the multicast address.
+            #[inline]
+            pub fn multicast_addr(&self) -> Ipv6Addr {
+                self.body().multicast_addr
+            }
+
+            /// Sets the multicast address.
+            #[inline]
+            pub fn set_multicast_addr(&mut self, multicast_addr: Ipv6Addr) {
+                self.body_mut().multicast_addr = multicast_addr;
+            }
+        }
+
+        impl<E: Ipv6Packet> fmt::Debug for $name<E> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("type", &format!("{}", self.msg_type()))
+                    .field("code", &self.code())
+                    .field("checksum", &format!("0x{:04x}", self.checksum()))
+                    .field("max_response_delay", &self.max_response_delay())
+                    .field("multicast_addr", &self.multicast_addr())
+                    .field("$offset", &self.offset())
+                    .field("$len", &self.len())
+                    .field("$header_len", &self.header_len())
+                    .finish()
+            }
+        }
+
+        impl<E: Ipv6Packet> Icmpv6Message for $name<E> {
+            type Envelope = E;
+
+            #[inline]
+            fn msg_type() -> Icmpv6Type {
+                $msg_type
+            }
+
+            #[inline]
+            fn icmp(&self) -> &Icmpv6<Self::Envelope> {
+                &self.icmp
+            }
+
+            #[inline]
+            fn icmp_mut(&mut self) -> &mut Icmpv6<Self::Envelope> {
+                &mut self.icmp
+            }
+
+            #[inline]
+            fn into_icmp(self) -> Icmpv6<Self::Envelope> {
+                self.icmp
+            }
+
+            #[inline]
+            unsafe fn clone(&self, internal: Internal) -> Self {
+                $name {
+                    icmp: self.icmp.clone(internal),
+                    body: self.body,
+                }
+            }
+
+            /// Parses the ICMPv6 packet's payload as the message.
+            ///
+            /// # Errors
+            ///
+            /// Returns an error if the payload does not have sufficient data
+            /// for the message body.
+            #[inline]
+            fn try_parse(icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Result<Self> {
+                let mbuf = icmp.mbuf();
+                let offset = icmp.payload_offset();
+                let body = mbuf.read_data(offset)?;
+
+                Ok($name { icmp, body })
+            }
+
+            /// Prepends a new message to the beginning of the ICMPv6's
+            /// payload.
+            ///
+            /// # Errors
+            ///
+            /// Returns an error if the buffer does not have enough free
+            /// space.
+            #[inline]
+            fn try_push(mut icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Result<Self> {
+                let offset = icmp.payload_offset();
+                let mbuf = icmp.mbuf_mut();
+
+                mbuf.extend(offset, MldBody::size_of())?;
+                let body = mbuf.write_data(offset, &MldBody::default())?;
+
+                Ok($name { icmp, body })
+            }
+        }
+    };
+}
+
+mld_message! {
+    /// Multicast Listener Query Message defined in [IETF RFC 2710].
+    ///
+    /// ```
+    ///  0                   1                   2                   3
+    ///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+    /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    /// |     Type      |     Code      |          Checksum             |
+    /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    /// |     Maximum Response Delay    |          Reserved             |
+    /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    /// |                                                               |
+    /// +                                                               +
+    /// |                                                               |
+    /// +                       Multicast Address                       +
+    /// |                                                               |
+    /// +                                                               +
+    /// |                                                               |
+    /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    /// ```
+    ///
+    /// - *Maximum Response Delay*: The maximum allowed delay before sending
+    ///                             a responding report, in milliseconds.
+    ///
+    /// - *Multicast Address*:      Zero in a general query, or the address
+    ///                             being queried in a multicast address
+    ///                             specific query.
+    ///
+    /// An MLDv2 query has the same prefix, so the fields above can be read
+    /// from either version.
+    ///
+    /// [IETF RFC 2710]: https://tools.ietf.org/html/rfc2710#section-3
+    MulticastListenerQuery,
+    Icmpv6Types::MulticastListenerQuery
+}
+
+mld_message! {
+    /// Multicast Listener Report Message defined in [IETF RFC 2710].
+    ///
+    /// The message has the same format as the [`MulticastListenerQuery`].
+    ///
+    /// - *Maximum Response Delay*: Set to zero by the sender and ignored by
+    ///                             the receivers.
+    ///
+    /// - *Multicast Address*:      The address to which the sender is
+    ///                             listening.
+    ///
+    /// [IETF RFC 2710]: https://tools.ietf.org/html/rfc2710#section-3
+    /// [`MulticastListenerQuery`]: MulticastListenerQuery
+    MulticastListenerReport,
+    Icmpv6Types::MulticastListenerReport
+}
+
+mld_message! {
+    /// Multicast Listener Done Message defined in [IETF RFC 2710].
+    ///
+    /// The message has the same format as the [`MulticastListenerQuery`].
+    ///
+    /// - *Maximum Response Delay*: Set to zero by the sender and ignored by
+    ///                             the receivers.
+    ///
+    /// - *Multicast Address*:      The address to which the sender is no
+    ///                             longer listening.
+    ///
+    /// [IETF RFC 2710]: https://tools.ietf.org/html/rfc2710#section-3
+    /// [`MulticastListenerQuery`]: MulticastListenerQuery
+    MulticastListenerDone,
+    Icmpv6Types::MulticastListenerDone
+}
+
+/// MLDv1 message body.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct MldBody {
+    max_response_delay: u16be,
+    reserved: u16be,
+    multicast_addr: Ipv6Addr,
+}
+
+impl Default for MldBody {
+    fn default() -> Self {
+        MldBody {
+            max_response_delay: u16be::default(),
+            reserved: u16be::default(),
+            multicast_addr: Ipv6Addr::UNSPECIFIED,
+        }
+    }
+}
+
+/// Version 2 Multicast Listener Report Message defined in [IETF RFC 3810].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Type = 143   |    Reserved   |           Checksum            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           Reserved            |Nr of Mcast Address Records (M)|
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// .                  Multicast Address Record [1]                 .
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                               .                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// .                  Multicast Address Record [M]                 .
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Nr of Mcast Address Records*:  The number of multicast address
+///                                   records present in the report.
+///
+/// - *Multicast Address Records*:    Each record is a block of fields
+///                                   containing information on the
+///                                   sender listening to a single
+///                                   multicast address. Use [`records`]
+///                                   to iterate through the records.
+///
+/// The record types are the same as the IGMPv3 [`GroupRecordTypes`].
+///
+/// [IETF RFC 3810]: https://tools.ietf.org/html/rfc3810#section-5.2
+/// [`records`]: V2MulticastListenerReport::records
+/// [`GroupRecordTypes`]: crate::packets::igmp::GroupRecordTypes
+#[derive(Icmpv6Packet)]
+pub struct V2MulticastListenerReport<E: Ipv6Packet> {
+    icmp: Icmpv6<E>,
+    body: NonNull<V2ReportBody>,
+}
+
+impl<E: Ipv6Packet> V2MulticastListenerReport<E> {
+    #[inline]
+    fn body(&self) -> &V2ReportBody {
+        unsafe { self.body.as_ref() }
+    }
+
+    /// Returns the number of multicast address records.
+    #[inline]
+    pub fn num_records(&self) -> u16 {
+        self.body().num_records.into()
+    }
+
+    /// Returns an iterator that iterates through the multicast address
+    /// records.
+    #[inline]
+    pub fn records(&self) -> MulticastAddressRecordsIterator<'_> {
+        MulticastAddressRecordsIterator {
+            mbuf: self.mbuf(),
+            offset: self.payload_offset() + V2ReportBody::size_of(),
+            remaining: self.num_records(),
+        }
+    }
+}
+
+impl<E: Ipv6Packet> fmt::Debug for V2MulticastListenerReport<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("V2MulticastListenerReport")
+            .field("type", &format!("{}", self.msg_type()))
+            .field("code", &self.code())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("num_records", &self.num_records())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: Ipv6Packet> Icmpv6Message for V2MulticastListenerReport<E> {
+    type Envelope = E;
+
+    #[inline]
+    fn msg_type() -> Icmpv6Type {
+        Icmpv6Types::V2MulticastListenerReport
+    }
+
+    #[inline]
+    fn icmp(&self) -> &Icmpv6<Self::Envelope> {
+        &self.icmp
+    }
+
+    #[inline]
+    fn icmp_mut(&mut self) -> &mut Icmpv6<Self::Envelope> {
+        &mut self.icmp
+    }
+
+    #[inline]
+    fn into_icmp(self) -> Icmpv6<Self::Envelope> {
+        self.icmp
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        V2MulticastListenerReport {
+            icmp: self.icmp.clone(internal),
+            body: self.body,
+        }
+    }
+
+    /// Parses the ICMPv6 packet's payload as version 2 multicast listener
+    /// report.
+    ///
+    /// The records are not parsed until they are iterated through.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload does not have sufficient data for
+    /// the report body.
+    #[inline]
+    fn try_parse(icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Result<Self> {
+        let mbuf = icmp.mbuf();
+        let offset = icmp.payload_offset();
+        let body = mbuf.read_data(offset)?;
+
+        Ok(V2MulticastListenerReport { icmp, body })
+    }
+
+    /// Prepends a new version 2 multicast listener report without any
+    /// record to the beginning of the ICMPv6's payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut icmp: Icmpv6<Self::Envelope>, _internal: Internal) -> Result<Self> {
+        let offset = icmp.payload_offset();
+        let mbuf = icmp.mbuf_mut();
+
+        mbuf.extend(offset, V2ReportBody::size_of())?;
+        let body = mbuf.write_data(offset, &V2ReportBody::default())?;
+
+        Ok(V2MulticastListenerReport { icmp, body })
+    }
+}
+
+/// MLDv2 report body.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct V2ReportBody {
+    reserved: u16be,
+    num_records: u16be,
+}
+
+/// An MLDv2 multicast address record.
+pub struct MulticastAddressRecord<'a> {
+    record_type: GroupRecordType,
+    multicast_addr: Ipv6Addr,
+    sources: &'a [Ipv6Addr],
+    aux_data: &'a [u8],
+}
+
+impl<'a> MulticastAddressRecord<'a> {
+    /// Returns the record type.
+    #[inline]
+    pub fn record_type(&self) -> GroupRecordType {
+        self.record_type
+    }
+
+    /// Returns the multicast address to which the record pertains.
+    #[inline]
+    pub fn multicast_addr(&self) -> Ipv6Addr {
+        self.multicast_addr
+    }
+
+    /// Returns the source addresses.
+    #[inline]
+    pub fn sources(&self) -> &'a [Ipv6Addr] {
+        self.sources
+    }
+
+    /// Returns the auxiliary data.
+    #[inline]
+    pub fn aux_data(&self) -> &'a [u8] {
+        self.aux_data
+    }
+}
+
+impl fmt::Debug for MulticastAddressRecord<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MulticastAddressRecord")
+            .field("record_type", &format!("{}", self.record_type()))
+            .field("multicast_addr", &self.multicast_addr())
+            .field("sources", &self.sources())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the multicast address records of an
+/// MLDv2 report.
+pub struct MulticastAddressRecordsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    remaining: u16,
+}
+
+impl<'a> MulticastAddressRecordsIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<MulticastAddressRecord<'a>>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        let header = unsafe {
+            *self
+                .mbuf
+                .read_data::<AddressRecordHeader>(self.offset)?
+                .as_ref()
+        };
+        let mut offset = self.offset + AddressRecordHeader::size_of();
+
+        let num_sources = u16::from(header.num_sources) as usize;
+        let sources: &'a [Ipv6Addr] = if num_sources > 0 {
+            let sources = self.mbuf.read_data_slice::<Ipv6Addr>(offset, num_sources)?;
+            unsafe { &*sources.as_ptr() }
+        } else {
+            &[]
+        };
+        offset += num_sources * Ipv6Addr::size_of();
+
+        // the auxiliary data length is in units of 32-bit words.
+        let aux_data_len = header.aux_data_len as usize * 4;
+        let aux_data: &'a [u8] = if aux_data_len > 0 {
+            let aux_data = self.mbuf.read_data_slice::<u8>(offset, aux_data_len)?;
+            unsafe { &*aux_data.as_ptr() }
+        } else {
+            &[]
+        };
+        offset += aux_data_len;
+
+        self.offset = offset;
+        self.remaining -= 1;
+
+        Ok(Some(MulticastAddressRecord {
+            record_type: GroupRecordType(header.record_type),
+            multicast_addr: header.multicast_addr,
+            sources,
+            aux_data,
+        }))
+    }
+}
+
+impl fmt::Debug for MulticastAddressRecordsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MulticastAddressRecordsIterator")
+            .field("offset", &self.offset)
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
+
+/// MLDv2 multicast address record type, lengths and multicast address.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct AddressRecordHeader {
+    record_type: u8,
+    aux_data_len: u8,
+    num_sources: u16be,
+    multicast_addr: Ipv6Addr,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::igmp::GroupRecordTypes;
+    use crate::packets::ip::v6::{HopByHop, Ipv6};
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{IPV6_TCP_PACKET, MLDV2_REPORT_PACKET};
+
+    #[test]
+    fn size_of_mld_body() {
+        assert_eq!(20, MldBody::size_of());
+        assert_eq!(4, V2ReportBody::size_of());
+        assert_eq!(20, AddressRecordHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_mldv2_report_packet() {
+        let packet = Mbuf::from_bytes(&MLDV2_REPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let hbh = ipv6.parse::<HopByHop<Ipv6>>().unwrap();
+        let icmpv6 = hbh.parse::<Icmpv6<HopByHop<Ipv6>>>().unwrap();
+
+        assert_eq!(Icmpv6Types::V2MulticastListenerReport, icmpv6.msg_type());
+        assert_eq!(0x4537, icmpv6.checksum());
+
+        let report = icmpv6
+            .downcast::<V2MulticastListenerReport<HopByHop<Ipv6>>>()
+            .unwrap();
+        assert_eq!(1, report.num_records());
+
+        let mut records = report.records();
+        let record = records.next().unwrap().unwrap();
+        assert_eq!(GroupRecordTypes::ModeIsInclude, record.record_type());
+        assert_eq!(
+            "ff05::1:3".parse::<Ipv6Addr>().unwrap(),
+            record.multicast_addr()
+        );
+        assert_eq!(
+            &["2001:db8::1".parse::<Ipv6Addr>().unwrap()],
+            record.sources()
+        );
+        assert!(record.aux_data().is_empty());
+        assert!(records.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn push_and_set_multicast_listener_report() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        let mut report = ipv6.push::<MulticastListenerReport<Ipv6>>().unwrap();
+
+        assert_eq!(4, report.header_len());
+        assert_eq!(Icmpv6Types::MulticastListenerReport, report.msg_type());
+        assert_eq!(0, report.max_response_delay());
+        assert_eq!(Ipv6Addr::UNSPECIFIED, report.multicast_addr());
+
+        let addr = "ff02::1:ff00:1".parse::<Ipv6Addr>().unwrap();
+        report.set_multicast_addr(addr);
+        assert_eq!(addr, report.multicast_addr());
+
+        report.reconcile_all();
+        assert!(report.checksum() != 0);
+    }
+}
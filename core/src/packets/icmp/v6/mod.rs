@@ -21,6 +21,7 @@
 mod destination_unreachable;
 mod echo_reply;
 mod echo_request;
+pub mod mld;
 pub mod ndp;
 mod time_exceeded;
 mod too_big;
@@ -32,11 +33,11 @@ pub use self::time_exceeded::*;
 pub use self::too_big::*;
 pub use capsule_macros::Icmpv6Packet;
 
-use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::ip::v6::{Ipv6, Ipv6Packet, IPV6_MIN_MTU};
 use crate::packets::ip::ProtocolNumbers;
 use crate::packets::types::u16be;
-use crate::packets::{checksum, Internal, Packet};
-use crate::{ensure, SizeOf};
+use crate::packets::{checksum, Ethernet, Internal, Packet};
+use crate::{ensure, Mbuf, SizeOf};
 use anyhow::{anyhow, Result};
 use std::fmt;
 use std::ptr::NonNull;
@@ -154,6 +155,103 @@ impl<E: Ipv6Packet> Icmpv6<E> {
     }
 }
 
+impl Icmpv6<Ipv6> {
+    /// Creates a packet too big message with the MTU of the next-hop link
+    /// in a new buffer, in response to the original IPv6 packet being
+    /// discarded because it is larger than the MTU.
+    ///
+    /// The message embeds as much of the original packet as possible
+    /// without the message exceeding the [minimum IPv6 MTU]. The source and
+    /// destination of the Ethernet and IPv6 headers are swapped from the
+    /// original packet. If the original packet is not addressed to this
+    /// host, the source should be set to the address of the sending
+    /// interface instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the allocation of the buffer fails.
+    ///
+    /// [minimum IPv6 MTU]: IPV6_MIN_MTU
+    pub fn packet_too_big(original: &Ipv6, mtu: u32) -> Result<PacketTooBig<Ipv6>> {
+        let mut message = Icmpv6::error_message::<PacketTooBig<Ipv6>>(original, 0)?;
+        message.set_mtu(mtu);
+        message.reconcile_all();
+        Ok(message)
+    }
+
+    /// Creates a time exceeded message in a new buffer, in response to the
+    /// original IPv6 packet being discarded because its hop limit reached
+    /// zero.
+    ///
+    /// The message embeds the original packet and is addressed the same as
+    /// [`packet_too_big`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the allocation of the buffer fails.
+    ///
+    /// [`packet_too_big`]: Icmpv6::packet_too_big
+    pub fn time_exceeded(original: &Ipv6) -> Result<TimeExceeded<Ipv6>> {
+        // code 0, hop limit exceeded in transit.
+        let mut message = Icmpv6::error_message::<TimeExceeded<Ipv6>>(original, 0)?;
+        message.reconcile_all();
+        Ok(message)
+    }
+
+    /// Creates a destination unreachable message with the code in a new
+    /// buffer, in response to the original IPv6 packet being discarded.
+    ///
+    /// The message embeds the original packet and is addressed the same as
+    /// [`packet_too_big`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the allocation of the buffer fails.
+    ///
+    /// [`packet_too_big`]: Icmpv6::packet_too_big
+    pub fn destination_unreachable(
+        original: &Ipv6,
+        code: u8,
+    ) -> Result<DestinationUnreachable<Ipv6>> {
+        let mut message = Icmpv6::error_message::<DestinationUnreachable<Ipv6>>(original, code)?;
+        message.reconcile_all();
+        Ok(message)
+    }
+
+    /// Creates an ICMPv6 error message in response to the original IPv6
+    /// packet, without reconciling the message.
+    fn error_message<T>(original: &Ipv6, code: u8) -> Result<T>
+    where
+        T: Icmpv6Message<Envelope = Ipv6> + Packet<Envelope = Ipv6>,
+    {
+        // leaves room for the 40 octets of IPv6 header, and the 8 octets of
+        // error message header and body.
+        let max_len = IPV6_MIN_MTU - 48;
+        let len = original.len().min(max_len);
+        let data = original
+            .mbuf()
+            .read_data_slice::<u8>(original.offset(), len)?;
+        let data = unsafe { data.as_ref() };
+
+        let mut ethernet = Mbuf::new()?.push::<Ethernet>()?;
+        ethernet.set_src(original.envelope().dst());
+        ethernet.set_dst(original.envelope().src());
+
+        let mut ipv6 = ethernet.push::<Ipv6>()?;
+        ipv6.set_src(original.dst());
+        ipv6.set_dst(original.src());
+
+        let offset = ipv6.payload_offset();
+        ipv6.mbuf_mut().extend(offset, len)?;
+        let _ = ipv6.mbuf_mut().write_data_slice(offset, data)?;
+
+        let mut message = ipv6.push::<T>()?;
+        message.icmp_mut().set_code(code);
+
+        Ok(message)
+    }
+}
+
 impl<E: Ipv6Packet> fmt::Debug for Icmpv6<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("icmpv6")
@@ -310,6 +408,21 @@ pub mod Icmpv6Types {
     /// [Echo Reply]: crate::packets::icmp::v6::EchoReply
     pub const EchoReply: Icmpv6Type = Icmpv6Type(129);
 
+    /// Message type for [Multicast Listener Query].
+    ///
+    /// [Multicast Listener Query]: crate::packets::icmp::v6::mld::MulticastListenerQuery
+    pub const MulticastListenerQuery: Icmpv6Type = Icmpv6Type(130);
+
+    /// Message type for [Multicast Listener Report].
+    ///
+    /// [Multicast Listener Report]: crate::packets::icmp::v6::mld::MulticastListenerReport
+    pub const MulticastListenerReport: Icmpv6Type = Icmpv6Type(131);
+
+    /// Message type for [Multicast Listener Done].
+    ///
+    /// [Multicast Listener Done]: crate::packets::icmp::v6::mld::MulticastListenerDone
+    pub const MulticastListenerDone: Icmpv6Type = Icmpv6Type(132);
+
     /// Message type for [Router Solicitation].
     ///
     /// [Router Solicitation]: crate::packets::icmp::v6::ndp::RouterSolicitation
@@ -334,6 +447,11 @@ pub mod Icmpv6Types {
     ///
     /// [Redirect]: crate::packets::icmp::v6::ndp::Redirect
     pub const Redirect: Icmpv6Type = Icmpv6Type(137);
+
+    /// Message type for [Version 2 Multicast Listener Report].
+    ///
+    /// [Version 2 Multicast Listener Report]: crate::packets::icmp::v6::mld::V2MulticastListenerReport
+    pub const V2MulticastListenerReport: Icmpv6Type = Icmpv6Type(143);
 }
 
 impl fmt::Display for Icmpv6Type {
@@ -346,11 +464,17 @@ impl fmt::Display for Icmpv6Type {
                 Icmpv6Types::TimeExceeded => "Time Exceeded".to_string(),
                 Icmpv6Types::EchoRequest => "Echo Request".to_string(),
                 Icmpv6Types::EchoReply => "Echo Reply".to_string(),
+                Icmpv6Types::MulticastListenerQuery => "Multicast Listener Query".to_string(),
+                Icmpv6Types::MulticastListenerReport => "Multicast Listener Report".to_string(),
+                Icmpv6Types::MulticastListenerDone => "Multicast Listener Done".to_string(),
                 Icmpv6Types::RouterSolicitation => "Router Solicitation".to_string(),
                 Icmpv6Types::RouterAdvertisement => "Router Advertisement".to_string(),
                 Icmpv6Types::NeighborSolicitation => "Neighbor Solicitation".to_string(),
                 Icmpv6Types::NeighborAdvertisement => "Neighbor Advertisement".to_string(),
                 Icmpv6Types::Redirect => "Redirect".to_string(),
+                Icmpv6Types::V2MulticastListenerReport => {
+                    "Version 2 Multicast Listener Report".to_string()
+                }
                 _ => format!("{}", self.0),
             }
         )
@@ -499,10 +623,8 @@ pub trait Icmpv6Packet {
 mod tests {
     use super::*;
     use crate::packets::icmp::v6::ndp::RouterAdvertisement;
-    use crate::packets::ip::v6::Ipv6;
-    use crate::packets::Ethernet;
+    use crate::packets::ip::IpPacket;
     use crate::testils::byte_arrays::{ICMPV6_PACKET, IPV6_TCP_PACKET, ROUTER_ADVERT_PACKET};
-    use crate::Mbuf;
 
     #[test]
     fn size_of_icmpv6_header() {
@@ -578,4 +700,45 @@ mod tests {
 
         assert!(ipv6.push::<Icmpv6<Ipv6>>().is_err());
     }
+
+    #[capsule::test]
+    fn packet_too_big_for_ipv6_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let original = ethernet.parse::<Ipv6>().unwrap();
+
+        let too_big = Icmpv6::packet_too_big(&original, 1400).unwrap();
+
+        assert_eq!(Icmpv6Types::PacketTooBig, too_big.msg_type());
+        assert_eq!(0, too_big.code());
+        assert_eq!(1400, too_big.mtu());
+        assert_eq!(original.src(), too_big.envelope().dst());
+        assert_eq!(original.dst(), too_big.envelope().src());
+        assert_eq!(ProtocolNumbers::Icmpv6, too_big.envelope().next_header());
+
+        // the original packet fits in whole.
+        assert_eq!(&IPV6_TCP_PACKET[14..], too_big.data());
+
+        // the checksum of a valid message sums to zero.
+        let data = too_big.as_bytes();
+        let pseudo_header_sum = too_big
+            .envelope()
+            .pseudo_header(data.len() as u16, ProtocolNumbers::Icmpv6)
+            .sum();
+        assert_eq!(0, checksum::compute(pseudo_header_sum, data));
+    }
+
+    #[capsule::test]
+    fn time_exceeded_embeds_up_to_ipv6_min_mtu() {
+        // starts with a packet larger than the minimum MTU.
+        let packet = Mbuf::from_bytes(&[42; 1500]).unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let original = ethernet.push::<Ipv6>().unwrap();
+
+        let exceeded = Icmpv6::time_exceeded(&original).unwrap();
+
+        assert_eq!(Icmpv6Types::TimeExceeded, exceeded.msg_type());
+        assert_eq!(IPV6_MIN_MTU, exceeded.envelope().len());
+        assert_eq!(IPV6_MIN_MTU - 48, exceeded.data().len());
+    }
 }
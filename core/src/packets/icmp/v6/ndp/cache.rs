@@ -0,0 +1,417 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::{
+    LinkLayerAddress, NdpOptionType, NdpOptionTypes, NdpPacket, NeighborAdvertisement,
+    NeighborSolicitation,
+};
+use crate::net::MacAddr;
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::Packet;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr};
+use std::time::{Duration, Instant};
+
+/// The default time a neighbor is considered reachable after a
+/// reachability confirmation, defined in [IETF RFC 4861].
+///
+/// [IETF RFC 4861]: https://tools.ietf.org/html/rfc4861#section-10
+pub const REACHABLE_TIME: Duration = Duration::from_secs(30);
+
+/// The time an incomplete entry waits for a neighbor advertisement,
+/// `MAX_MULTICAST_SOLICIT` solicitations `RETRANS_TIMER` apart, defined
+/// in [IETF RFC 4861].
+///
+/// [IETF RFC 4861]: https://tools.ietf.org/html/rfc4861#section-10
+pub const INCOMPLETE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The reachability state of a neighbor cache entry, as defined in
+/// [IETF RFC 4861].
+///
+/// [IETF RFC 4861]: https://tools.ietf.org/html/rfc4861#section-7.3.2
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NeighborState {
+    /// Address resolution is in progress and the link-layer address of
+    /// the neighbor is not yet known.
+    Incomplete,
+    /// The neighbor is known to have been reachable recently.
+    Reachable,
+    /// The neighbor is no longer known to be reachable. The link-layer
+    /// address can still be used until reachability is verified again.
+    Stale,
+}
+
+/// An entry in the neighbor cache.
+#[derive(Clone, Copy, Debug)]
+pub struct NeighborEntry {
+    link_addr: Option<MacAddr>,
+    state: NeighborState,
+    updated: Instant,
+}
+
+impl NeighborEntry {
+    fn new(link_addr: Option<MacAddr>, state: NeighborState) -> Self {
+        NeighborEntry {
+            link_addr,
+            state,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Returns the link-layer address of the neighbor, `None` while the
+    /// entry is incomplete.
+    pub fn link_addr(&self) -> Option<MacAddr> {
+        self.link_addr
+    }
+
+    /// Returns the reachability state of the neighbor.
+    pub fn state(&self) -> NeighborState {
+        self.state
+    }
+
+    fn update(&mut self, link_addr: Option<MacAddr>, state: NeighborState) {
+        if link_addr.is_some() {
+            self.link_addr = link_addr;
+        }
+        self.state = state;
+        self.updated = Instant::now();
+    }
+}
+
+/// A cache of IPv6 neighbors, the IPv6 counterpart of the ARP cache.
+///
+/// The cache is updated from parsed neighbor advertisements and
+/// solicitations, following the rules of [IETF RFC 4861]. A reachable
+/// entry becomes stale after the reachable time elapses, and an
+/// incomplete entry is removed after [`INCOMPLETE_TIMEOUT`]. The timers
+/// are applied by [`expire`].
+///
+/// The cache is not synchronized. It's meant to be owned by a single
+/// pipeline on one core. To share it across cores, wrap it in a lock.
+///
+/// [IETF RFC 4861]: https://tools.ietf.org/html/rfc4861#section-7.2
+/// [`expire`]: NeighborCache::expire
+#[derive(Debug)]
+pub struct NeighborCache {
+    entries: HashMap<Ipv6Addr, NeighborEntry>,
+    reachable_time: Duration,
+}
+
+impl NeighborCache {
+    /// Creates a new empty cache with a reachable time of
+    /// [`REACHABLE_TIME`].
+    pub fn new() -> Self {
+        NeighborCache::with_reachable_time(REACHABLE_TIME)
+    }
+
+    /// Creates a new empty cache with a custom reachable time.
+    pub fn with_reachable_time(reachable_time: Duration) -> Self {
+        NeighborCache {
+            entries: HashMap::new(),
+            reachable_time,
+        }
+    }
+
+    /// Returns the number of entries in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entry of `ip`.
+    pub fn get(&self, ip: Ipv6Addr) -> Option<&NeighborEntry> {
+        self.entries.get(&ip)
+    }
+
+    /// Returns the link-layer address of `ip` if it's resolved.
+    ///
+    /// The address of a stale entry is still returned.
+    pub fn lookup(&self, ip: Ipv6Addr) -> Option<MacAddr> {
+        self.entries
+            .get(&ip)
+            .map(NeighborEntry::link_addr)
+            .unwrap_or(None)
+    }
+
+    /// Inserts an incomplete entry for `ip` when address resolution
+    /// starts, unless an entry already exists.
+    pub fn insert_incomplete(&mut self, ip: Ipv6Addr) {
+        self.entries
+            .entry(ip)
+            .or_insert_with(|| NeighborEntry::new(None, NeighborState::Incomplete));
+    }
+
+    /// Removes the entry of `ip`.
+    pub fn remove(&mut self, ip: Ipv6Addr) -> Option<NeighborEntry> {
+        self.entries.remove(&ip)
+    }
+
+    /// Applies the timers of the state machine.
+    ///
+    /// Reachable entries not confirmed within the reachable time become
+    /// stale, and incomplete entries not resolved within
+    /// [`INCOMPLETE_TIMEOUT`] are removed. Returns the number of entries
+    /// removed.
+    pub fn expire(&mut self) -> usize {
+        let len = self.entries.len();
+        let reachable_time = self.reachable_time;
+
+        self.entries.retain(|_, entry| match entry.state {
+            NeighborState::Incomplete => entry.updated.elapsed() <= INCOMPLETE_TIMEOUT,
+            NeighborState::Reachable => {
+                if entry.updated.elapsed() > reachable_time {
+                    entry.state = NeighborState::Stale;
+                }
+                true
+            }
+            NeighborState::Stale => true,
+        });
+
+        len - self.entries.len()
+    }
+
+    /// Updates the cache from a neighbor advertisement.
+    ///
+    /// A solicited advertisement confirms the target is reachable. If the
+    /// target link-layer address differs from the cached one, it replaces
+    /// the cached address only when the override flag is set. Otherwise a
+    /// reachable entry becomes stale. Unlike a host, which discards
+    /// advertisements without an existing entry, the cache learns every
+    /// advertised target.
+    ///
+    /// Returns whether the cache is modified.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options are malformed.
+    pub fn learn_advertisement<E: Ipv6Packet>(
+        &mut self,
+        advert: &NeighborAdvertisement<E>,
+    ) -> Result<bool> {
+        let link_addr = link_layer_addr(advert, NdpOptionTypes::TargetLinkLayerAddress)?;
+        let confirmed = if advert.solicited() {
+            NeighborState::Reachable
+        } else {
+            NeighborState::Stale
+        };
+
+        match self.entries.get_mut(&advert.target()) {
+            None => {
+                if link_addr.is_none() {
+                    return Ok(false);
+                }
+                self.entries
+                    .insert(advert.target(), NeighborEntry::new(link_addr, confirmed));
+            }
+            Some(entry) if entry.state == NeighborState::Incomplete => {
+                if link_addr.is_none() {
+                    return Ok(false);
+                }
+                entry.update(link_addr, confirmed);
+            }
+            Some(entry) => {
+                let changed = link_addr.is_some() && link_addr != entry.link_addr;
+                if changed && !advert.r#override() {
+                    if entry.state != NeighborState::Reachable {
+                        return Ok(false);
+                    }
+                    entry.state = NeighborState::Stale;
+                } else if advert.solicited() {
+                    entry.update(link_addr, NeighborState::Reachable);
+                } else if changed {
+                    entry.update(link_addr, NeighborState::Stale);
+                } else {
+                    return Ok(false);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Updates the cache from a neighbor solicitation, learning the source
+    /// link-layer address of the sender.
+    ///
+    /// A new or changed address is cached as stale. Solicitations from the
+    /// unspecified address, sent during duplicate address detection, or
+    /// without the source link-layer address option are ignored.
+    ///
+    /// Returns whether the cache is modified.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the options are malformed.
+    pub fn learn_solicitation<E: Ipv6Packet>(
+        &mut self,
+        solicit: &NeighborSolicitation<E>,
+    ) -> Result<bool> {
+        let src = match solicit.envelope().src() {
+            IpAddr::V6(src) if !src.is_unspecified() => src,
+            _ => return Ok(false),
+        };
+
+        let link_addr = link_layer_addr(solicit, NdpOptionTypes::SourceLinkLayerAddress)?;
+        if link_addr.is_none() {
+            return Ok(false);
+        }
+
+        match self.entries.get_mut(&src) {
+            Some(entry) if entry.link_addr == link_addr => Ok(false),
+            Some(entry) => {
+                entry.update(link_addr, NeighborState::Stale);
+                Ok(true)
+            }
+            None => {
+                self.entries
+                    .insert(src, NeighborEntry::new(link_addr, NeighborState::Stale));
+                Ok(true)
+            }
+        }
+    }
+}
+
+impl Default for NeighborCache {
+    fn default() -> Self {
+        NeighborCache::new()
+    }
+}
+
+/// Returns the address of the first link-layer address option of the
+/// option type.
+fn link_layer_addr<T: NdpPacket>(
+    packet: &T,
+    option_type: NdpOptionType,
+) -> Result<Option<MacAddr>> {
+    let mut iter = packet.options_iter();
+    while let Some(mut option) = iter.next()? {
+        if option.option_type() == option_type {
+            let option = option.downcast::<LinkLayerAddress<'_>>()?;
+            return Ok(Some(option.addr()));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::Ethernet;
+    use crate::Mbuf;
+
+    const TARGET: Ipv6Addr = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+
+    fn advertisement(solicited: bool, addr: MacAddr) -> NeighborAdvertisement<Ipv6> {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let mut advert = ipv6.push::<NeighborAdvertisement<Ipv6>>().unwrap();
+        advert.set_target(TARGET);
+        if solicited {
+            advert.set_solicited();
+        }
+
+        let mut options = advert.options_mut();
+        let mut target = options.append::<LinkLayerAddress<'_>>().unwrap();
+        target.set_option_type_target();
+        target.set_addr(addr);
+
+        advert
+    }
+
+    #[capsule::test]
+    fn learn_from_neighbor_advertisement() {
+        let mut cache = NeighborCache::new();
+        let addr = MacAddr::new(0, 0, 0, 0, 0, 1);
+
+        let advert = advertisement(true, addr);
+        assert!(cache.learn_advertisement(&advert).unwrap());
+
+        let entry = cache.get(TARGET).unwrap();
+        assert_eq!(NeighborState::Reachable, entry.state());
+        assert_eq!(Some(addr), cache.lookup(TARGET));
+
+        // a different address without the override flag only marks the
+        // entry stale.
+        let advert = advertisement(true, MacAddr::new(0, 0, 0, 0, 0, 2));
+        assert!(cache.learn_advertisement(&advert).unwrap());
+        assert_eq!(NeighborState::Stale, cache.get(TARGET).unwrap().state());
+        assert_eq!(Some(addr), cache.lookup(TARGET));
+    }
+
+    #[capsule::test]
+    fn resolve_incomplete_entry() {
+        let mut cache = NeighborCache::new();
+        cache.insert_incomplete(TARGET);
+        assert_eq!(
+            NeighborState::Incomplete,
+            cache.get(TARGET).unwrap().state()
+        );
+        assert_eq!(None, cache.lookup(TARGET));
+
+        let addr = MacAddr::new(0, 0, 0, 0, 0, 1);
+        let advert = advertisement(false, addr);
+        assert!(cache.learn_advertisement(&advert).unwrap());
+        assert_eq!(NeighborState::Stale, cache.get(TARGET).unwrap().state());
+        assert_eq!(Some(addr), cache.lookup(TARGET));
+    }
+
+    #[capsule::test]
+    fn reachable_entry_becomes_stale() {
+        let mut cache = NeighborCache::with_reachable_time(Duration::from_millis(10));
+        let advert = advertisement(true, MacAddr::new(0, 0, 0, 0, 0, 1));
+        assert!(cache.learn_advertisement(&advert).unwrap());
+
+        assert_eq!(0, cache.expire());
+        assert_eq!(NeighborState::Reachable, cache.get(TARGET).unwrap().state());
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(0, cache.expire());
+        assert_eq!(NeighborState::Stale, cache.get(TARGET).unwrap().state());
+        assert!(cache.lookup(TARGET).is_some());
+    }
+
+    #[capsule::test]
+    fn learn_from_neighbor_solicitation() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let mut ipv6 = ethernet.push::<Ipv6>().unwrap();
+        ipv6.set_src(TARGET);
+        let mut solicit = ipv6.push::<NeighborSolicitation<Ipv6>>().unwrap();
+
+        let mut cache = NeighborCache::new();
+
+        // no source link-layer address option.
+        assert!(!cache.learn_solicitation(&solicit).unwrap());
+
+        let addr = MacAddr::new(0, 0, 0, 0, 0, 1);
+        let mut options = solicit.options_mut();
+        let mut source = options.append::<LinkLayerAddress<'_>>().unwrap();
+        source.set_addr(addr);
+
+        assert!(cache.learn_solicitation(&solicit).unwrap());
+        assert_eq!(NeighborState::Stale, cache.get(TARGET).unwrap().state());
+        assert_eq!(Some(addr), cache.lookup(TARGET));
+    }
+}
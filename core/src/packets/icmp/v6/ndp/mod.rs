@@ -29,6 +29,7 @@
 //!
 //! [IETF RFC 4861]: https://tools.ietf.org/html/rfc4861
 
+mod cache;
 mod neighbor_advert;
 mod neighbor_solicit;
 mod options;
@@ -36,6 +37,7 @@ mod redirect;
 mod router_advert;
 mod router_solicit;
 
+pub use self::cache::*;
 pub use self::neighbor_advert::*;
 pub use self::neighbor_solicit::*;
 pub use self::options::*;
@@ -0,0 +1,1038 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Internet Group Management Protocol.
+
+use crate::packets::icmp::v6::mld::{
+    MulticastListenerDone, MulticastListenerReport, V2MulticastListenerReport,
+};
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::ip::ProtocolNumbers;
+use crate::packets::types::u16be;
+use crate::packets::{checksum, Internal, Packet};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::net::{IpAddr, Ipv4Addr};
+use std::ptr::NonNull;
+use std::time::{Duration, Instant};
+
+/// The default time a port stays subscribed to a group without hearing a
+/// report, the group membership interval defined in [IETF RFC 2236].
+///
+/// [IETF RFC 2236]: https://tools.ietf.org/html/rfc2236#section-8.4
+pub const GROUP_MEMBERSHIP_INTERVAL: Duration = Duration::from_secs(260);
+
+/// The default time a port stays subscribed to a group after a leave,
+/// the last member query time defined in [IETF RFC 2236].
+///
+/// [IETF RFC 2236]: https://tools.ietf.org/html/rfc2236#section-8.8
+pub const LAST_MEMBER_QUERY_TIME: Duration = Duration::from_secs(2);
+
+/// Internet Group Management Protocol packet based on [IETF RFC 2236] and
+/// [IETF RFC 3376].
+///
+/// IGMPv1 and IGMPv2 messages share the same format.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |      Type     | Max Resp Time |           Checksum            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         Group Address                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// IGMPv3 membership reports replace the group address with the number
+/// of group records that follow.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Type = 0x22  |    Reserved   |           Checksum            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           Reserved            |  Number of Group Records (M)  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// .                        Group Record [1]                       .
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                               .                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// .                        Group Record [M]                       .
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Type*: (8 bits)
+///      The type of the IGMP message.
+///
+/// - *Max Resp Time*: (8 bits)
+///      Only meaningful in membership query messages, and specifies the
+///      maximum allowed time before sending a responding report in units
+///      of 1/10 second.
+///
+/// - *Checksum*: (16 bits)
+///      The 16-bit one's complement of the one's complement sum of the
+///      whole IGMP message.
+///
+/// - *Group Address*: (32 bits)
+///      The multicast group address being queried, reported or left.
+///
+/// - *Group Records*: (variable)
+///      Each group record is a block of fields containing information
+///      pertaining to the sender's membership in a single multicast group.
+///      Use [`group_records`] to iterate through the records.
+///
+/// [IETF RFC 2236]: https://tools.ietf.org/html/rfc2236#section-2
+/// [IETF RFC 3376]: https://tools.ietf.org/html/rfc3376#section-4.2
+/// [`group_records`]: Igmp::group_records
+pub struct Igmp {
+    envelope: Ipv4,
+    header: NonNull<IgmpHeader>,
+    offset: usize,
+}
+
+impl Igmp {
+    #[inline]
+    fn header(&self) -> &IgmpHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut IgmpHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the message type.
+    #[inline]
+    pub fn msg_type(&self) -> IgmpType {
+        IgmpType::new(self.header().msg_type)
+    }
+
+    /// Sets the message type.
+    #[inline]
+    pub fn set_msg_type(&mut self, msg_type: IgmpType) {
+        self.header_mut().msg_type = msg_type.0
+    }
+
+    /// Returns the IGMP version of the message.
+    ///
+    /// The version of a membership query is determined by its length and
+    /// its max response time, see [IETF RFC 3376].
+    ///
+    /// [IETF RFC 3376]: https://tools.ietf.org/html/rfc3376#section-7.1
+    #[inline]
+    pub fn version(&self) -> u8 {
+        match self.msg_type() {
+            IgmpTypes::V1MembershipReport => 1,
+            IgmpTypes::V2MembershipReport | IgmpTypes::LeaveGroup => 2,
+            IgmpTypes::V3MembershipReport => 3,
+            _ if self.len() >= 12 => 3,
+            _ if self.max_resp_time() == 0 => 1,
+            _ => 2,
+        }
+    }
+
+    /// Returns the max response time in units of 1/10 second.
+    #[inline]
+    pub fn max_resp_time(&self) -> u8 {
+        self.header().max_resp_time
+    }
+
+    /// Sets the max response time.
+    #[inline]
+    pub fn set_max_resp_time(&mut self, max_resp_time: u8) {
+        self.header_mut().max_resp_time = max_resp_time
+    }
+
+    /// Returns the checksum.
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        self.header().checksum.into()
+    }
+
+    /// Returns the group address.
+    ///
+    /// For an IGMPv3 membership report, the field holds the number of group
+    /// records instead.
+    #[inline]
+    pub fn group_addr(&self) -> Ipv4Addr {
+        self.header().group_addr
+    }
+
+    /// Sets the group address.
+    #[inline]
+    pub fn set_group_addr(&mut self, group_addr: Ipv4Addr) {
+        self.header_mut().group_addr = group_addr
+    }
+
+    /// Returns the number of group records of an IGMPv3 membership report.
+    /// Returns 0 for the other message types.
+    #[inline]
+    pub fn num_group_records(&self) -> u16 {
+        if self.msg_type() == IgmpTypes::V3MembershipReport {
+            // the number of records is the last 2 bytes of the group field.
+            let octets = self.group_addr().octets();
+            u16::from_be_bytes([octets[2], octets[3]])
+        } else {
+            0
+        }
+    }
+
+    /// Returns an iterator that iterates through the group records of an
+    /// IGMPv3 membership report.
+    #[inline]
+    pub fn group_records(&self) -> GroupRecordsIterator<'_> {
+        GroupRecordsIterator {
+            mbuf: self.mbuf(),
+            offset: self.payload_offset(),
+            remaining: self.num_group_records(),
+        }
+    }
+
+    /// Computes the checksum over the IGMP message.
+    #[inline]
+    pub fn compute_checksum(&mut self) {
+        self.header_mut().checksum = u16be::default();
+
+        if let Ok(data) = self.mbuf().read_data_slice(self.offset(), self.len()) {
+            let data = unsafe { data.as_ref() };
+            let checksum = checksum::compute(0, data);
+            self.header_mut().checksum = checksum.into();
+        } else {
+            // we are reading till the end of buffer, should never run out
+            unreachable!()
+        }
+    }
+}
+
+impl fmt::Debug for Igmp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("igmp")
+            .field("type", &format!("{}", self.msg_type()))
+            .field("version", &self.version())
+            .field("max_resp_time", &self.max_resp_time())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("group_addr", &self.group_addr())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Igmp {
+    /// The preceding type for an IGMP packet must be IPv4.
+    type Envelope = Ipv4;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the fixed header. The group records of an
+    /// IGMPv3 membership report and the additional fields of an IGMPv3
+    /// membership query are the payload.
+    #[inline]
+    fn header_len(&self) -> usize {
+        IgmpHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Igmp {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the IPv4 payload as an IGMP packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the [`protocol`] is not [`ProtocolNumbers::Igmp`].
+    /// Returns an error if the payload does not have sufficient data for
+    /// the IGMP header.
+    ///
+    /// [`protocol`]: Ipv4::protocol
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.protocol() == ProtocolNumbers::Igmp,
+            anyhow!("not an IGMP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        Ok(Igmp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an IGMP header to the beginning of the IPv4's payload.
+    ///
+    /// [`protocol`] is set to [`ProtocolNumbers::Igmp`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`protocol`]: Ipv4::protocol
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, IgmpHeader::size_of())?;
+        let header = mbuf.write_data(offset, &IgmpHeader::default())?;
+
+        envelope.set_protocol(ProtocolNumbers::Igmp);
+
+        Ok(Igmp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`checksum`] is computed based on the whole IGMP message.
+    ///
+    /// [`checksum`]: Igmp::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        self.compute_checksum();
+    }
+}
+
+/// Type of IGMP message.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct IgmpType(pub u8);
+
+impl IgmpType {
+    /// Creates a new IGMP message type.
+    pub fn new(value: u8) -> Self {
+        IgmpType(value)
+    }
+}
+
+/// Supported IGMP message types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod IgmpTypes {
+    use super::IgmpType;
+
+    /// Membership query of all IGMP versions.
+    pub const MembershipQuery: IgmpType = IgmpType(0x11);
+
+    /// IGMPv1 membership report.
+    pub const V1MembershipReport: IgmpType = IgmpType(0x12);
+
+    /// IGMPv2 membership report.
+    pub const V2MembershipReport: IgmpType = IgmpType(0x16);
+
+    /// IGMPv2 leave group.
+    pub const LeaveGroup: IgmpType = IgmpType(0x17);
+
+    /// IGMPv3 membership report.
+    pub const V3MembershipReport: IgmpType = IgmpType(0x22);
+}
+
+impl fmt::Display for IgmpType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                IgmpTypes::MembershipQuery => "Membership Query".to_string(),
+                IgmpTypes::V1MembershipReport => "Version 1 Membership Report".to_string(),
+                IgmpTypes::V2MembershipReport => "Version 2 Membership Report".to_string(),
+                IgmpTypes::LeaveGroup => "Leave Group".to_string(),
+                IgmpTypes::V3MembershipReport => "Version 3 Membership Report".to_string(),
+                _ => format!("0x{:02x}", self.0),
+            }
+        )
+    }
+}
+
+/// Type of IGMPv3 group record.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct GroupRecordType(pub u8);
+
+impl GroupRecordType {
+    /// Creates a new group record type.
+    pub fn new(value: u8) -> Self {
+        GroupRecordType(value)
+    }
+}
+
+/// Supported IGMPv3 group record types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod GroupRecordTypes {
+    use super::GroupRecordType;
+
+    /// The interface has a filter mode of include for the group.
+    pub const ModeIsInclude: GroupRecordType = GroupRecordType(1);
+
+    /// The interface has a filter mode of exclude for the group.
+    pub const ModeIsExclude: GroupRecordType = GroupRecordType(2);
+
+    /// The interface has changed to the include filter mode.
+    pub const ChangeToIncludeMode: GroupRecordType = GroupRecordType(3);
+
+    /// The interface has changed to the exclude filter mode.
+    pub const ChangeToExcludeMode: GroupRecordType = GroupRecordType(4);
+
+    /// The interface wishes to hear from additional sources.
+    pub const AllowNewSources: GroupRecordType = GroupRecordType(5);
+
+    /// The interface no longer wishes to hear from some sources.
+    pub const BlockOldSources: GroupRecordType = GroupRecordType(6);
+}
+
+impl fmt::Display for GroupRecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                GroupRecordTypes::ModeIsInclude => "MODE_IS_INCLUDE".to_string(),
+                GroupRecordTypes::ModeIsExclude => "MODE_IS_EXCLUDE".to_string(),
+                GroupRecordTypes::ChangeToIncludeMode => "CHANGE_TO_INCLUDE_MODE".to_string(),
+                GroupRecordTypes::ChangeToExcludeMode => "CHANGE_TO_EXCLUDE_MODE".to_string(),
+                GroupRecordTypes::AllowNewSources => "ALLOW_NEW_SOURCES".to_string(),
+                GroupRecordTypes::BlockOldSources => "BLOCK_OLD_SOURCES".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("{}", t)
+                }
+            }
+        )
+    }
+}
+
+/// An IGMPv3 group record.
+pub struct GroupRecord<'a> {
+    record_type: GroupRecordType,
+    multicast_addr: Ipv4Addr,
+    sources: &'a [Ipv4Addr],
+    aux_data: &'a [u8],
+}
+
+impl<'a> GroupRecord<'a> {
+    /// Returns the record type.
+    #[inline]
+    pub fn record_type(&self) -> GroupRecordType {
+        self.record_type
+    }
+
+    /// Returns the multicast address to which the record pertains.
+    #[inline]
+    pub fn multicast_addr(&self) -> Ipv4Addr {
+        self.multicast_addr
+    }
+
+    /// Returns the source addresses.
+    #[inline]
+    pub fn sources(&self) -> &'a [Ipv4Addr] {
+        self.sources
+    }
+
+    /// Returns the auxiliary data.
+    #[inline]
+    pub fn aux_data(&self) -> &'a [u8] {
+        self.aux_data
+    }
+}
+
+impl fmt::Debug for GroupRecord<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GroupRecord")
+            .field("record_type", &format!("{}", self.record_type()))
+            .field("multicast_addr", &self.multicast_addr())
+            .field("sources", &self.sources())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the group records of an IGMPv3
+/// membership report.
+pub struct GroupRecordsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    remaining: u16,
+}
+
+impl<'a> GroupRecordsIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<GroupRecord<'a>>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        let header = unsafe {
+            *self
+                .mbuf
+                .read_data::<GroupRecordHeader>(self.offset)?
+                .as_ref()
+        };
+        let mut offset = self.offset + GroupRecordHeader::size_of();
+
+        let num_sources = u16::from(header.num_sources) as usize;
+        let sources: &'a [Ipv4Addr] = if num_sources > 0 {
+            let sources = self.mbuf.read_data_slice::<Ipv4Addr>(offset, num_sources)?;
+            unsafe { &*sources.as_ptr() }
+        } else {
+            &[]
+        };
+        offset += num_sources * Ipv4Addr::size_of();
+
+        // the auxiliary data length is in units of 32-bit words.
+        let aux_data_len = header.aux_data_len as usize * 4;
+        let aux_data: &'a [u8] = if aux_data_len > 0 {
+            let aux_data = self.mbuf.read_data_slice::<u8>(offset, aux_data_len)?;
+            unsafe { &*aux_data.as_ptr() }
+        } else {
+            &[]
+        };
+        offset += aux_data_len;
+
+        self.offset = offset;
+        self.remaining -= 1;
+
+        Ok(Some(GroupRecord {
+            record_type: GroupRecordType(header.record_type),
+            multicast_addr: header.multicast_addr,
+            sources,
+            aux_data,
+        }))
+    }
+}
+
+impl fmt::Debug for GroupRecordsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GroupRecordsIterator")
+            .field("offset", &self.offset)
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
+
+/// A table of the multicast group subscriptions of the ports of a
+/// snooping switch, learned from IGMP and MLD messages.
+///
+/// Memberships are tracked per port, not per host. A host suppresses its
+/// own report when it hears a report of another member of the group, so
+/// one report keeps the port subscribed for all the members behind it.
+/// For the same reason, a leave does not unsubscribe the port right away.
+/// The membership is cut short to the last member query time instead,
+/// within which a report of any remaining member, usually answering the
+/// group-specific query of the querier, keeps the port subscribed.
+///
+/// Source filters are not tracked. A port is subscribed to a group as long
+/// as it wants the traffic of any source. Expired memberships are ignored
+/// by [`members`] and [`is_subscribed`], and are removed with [`expire`].
+///
+/// The table is not synchronized. It's meant to be owned by a single
+/// pipeline on one core. To share it across cores, wrap it in a lock.
+///
+/// [`members`]: MulticastGroupTable::members
+/// [`is_subscribed`]: MulticastGroupTable::is_subscribed
+/// [`expire`]: MulticastGroupTable::expire
+#[derive(Debug)]
+pub struct MulticastGroupTable<P> {
+    groups: HashMap<IpAddr, HashMap<P, Instant>>,
+    membership_interval: Duration,
+    last_member_query_time: Duration,
+}
+
+impl<P: Copy + Eq + Hash> MulticastGroupTable<P> {
+    /// Creates a new empty table with the default timers of
+    /// [`GROUP_MEMBERSHIP_INTERVAL`] and [`LAST_MEMBER_QUERY_TIME`].
+    pub fn new() -> Self {
+        MulticastGroupTable::with_timers(GROUP_MEMBERSHIP_INTERVAL, LAST_MEMBER_QUERY_TIME)
+    }
+
+    /// Creates a new empty table with custom timers.
+    pub fn with_timers(membership_interval: Duration, last_member_query_time: Duration) -> Self {
+        MulticastGroupTable {
+            groups: HashMap::new(),
+            membership_interval,
+            last_member_query_time,
+        }
+    }
+
+    /// Returns the number of groups in the table, including the groups
+    /// with only expired memberships not removed yet.
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Returns whether the table is empty.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Returns the ports subscribed to `group`, in no particular order.
+    pub fn members(&self, group: IpAddr) -> Vec<P> {
+        let now = Instant::now();
+        self.groups
+            .get(&group)
+            .map(|ports| {
+                ports
+                    .iter()
+                    .filter(|&(_, &expires)| now < expires)
+                    .map(|(&port, _)| port)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns whether `port` is subscribed to `group`.
+    pub fn is_subscribed(&self, port: P, group: IpAddr) -> bool {
+        self.groups
+            .get(&group)
+            .and_then(|ports| ports.get(&port))
+            .map_or(false, |&expires| Instant::now() < expires)
+    }
+
+    /// Subscribes `port` to `group`, or refreshes the subscription for
+    /// another membership interval.
+    pub fn join(&mut self, port: P, group: IpAddr) {
+        let expires = Instant::now() + self.membership_interval;
+        self.groups.entry(group).or_default().insert(port, expires);
+    }
+
+    /// Starts the leave of `port` from `group`.
+    ///
+    /// The subscription expires after the last member query time, unless
+    /// it's refreshed by another report. Returns whether the port was
+    /// subscribed.
+    pub fn leave(&mut self, port: P, group: IpAddr) -> bool {
+        let expires = Instant::now() + self.last_member_query_time;
+        match self
+            .groups
+            .get_mut(&group)
+            .and_then(|ports| ports.get_mut(&port))
+        {
+            Some(current) => {
+                if expires < *current {
+                    *current = expires;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the expired memberships, and the groups left without any
+    /// port.
+    ///
+    /// Returns the number of memberships removed.
+    pub fn expire(&mut self) -> usize {
+        let now = Instant::now();
+        let mut removed = 0;
+
+        self.groups.retain(|_, ports| {
+            let len = ports.len();
+            ports.retain(|_, &mut expires| now < expires);
+            removed += len - ports.len();
+            !ports.is_empty()
+        });
+
+        removed
+    }
+
+    /// Updates the table from an IGMP message received on `port`.
+    ///
+    /// IGMPv1 and IGMPv2 membership reports join the group, and IGMPv2
+    /// leaves start the leave. The group records of an IGMPv3 report are
+    /// applied in order. A record in exclude mode, or one that includes
+    /// at least one source, joins the group. A change to include mode
+    /// without any source is a leave. Records that only block sources do
+    /// not change the membership.
+    ///
+    /// Queries and groups that are not multicast addresses are ignored.
+    /// Returns whether the table is updated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an IGMPv3 group record is malformed.
+    pub fn learn_igmp(&mut self, port: P, igmp: &Igmp) -> Result<bool> {
+        match igmp.msg_type() {
+            IgmpTypes::V1MembershipReport | IgmpTypes::V2MembershipReport => {
+                let group = igmp.group_addr();
+                if group.is_multicast() {
+                    self.join(port, group.into());
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            IgmpTypes::LeaveGroup => {
+                let group = igmp.group_addr();
+                Ok(group.is_multicast() && self.leave(port, group.into()))
+            }
+            IgmpTypes::V3MembershipReport => {
+                let mut updated = false;
+                let mut records = igmp.group_records();
+
+                while let Some(record) = records.next()? {
+                    let group = record.multicast_addr();
+                    if group.is_multicast() {
+                        updated |= self.learn_record(
+                            port,
+                            group.into(),
+                            record.record_type(),
+                            !record.sources().is_empty(),
+                        );
+                    }
+                }
+
+                Ok(updated)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Updates the table from an MLDv1 report received on `port`.
+    ///
+    /// Returns whether the table is updated.
+    pub fn learn_mld_report<E: Ipv6Packet>(
+        &mut self,
+        port: P,
+        report: &MulticastListenerReport<E>,
+    ) -> bool {
+        let group = report.multicast_addr();
+        if group.is_multicast() {
+            self.join(port, group.into());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Updates the table from an MLDv1 done received on `port`.
+    ///
+    /// Returns whether the table is updated.
+    pub fn learn_mld_done<E: Ipv6Packet>(
+        &mut self,
+        port: P,
+        done: &MulticastListenerDone<E>,
+    ) -> bool {
+        let group = done.multicast_addr();
+        group.is_multicast() && self.leave(port, group.into())
+    }
+
+    /// Updates the table from an MLDv2 report received on `port`.
+    ///
+    /// The multicast address records are applied the same way as the
+    /// group records of an IGMPv3 report. Returns whether the table is
+    /// updated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a multicast address record is malformed.
+    pub fn learn_mldv2_report<E: Ipv6Packet>(
+        &mut self,
+        port: P,
+        report: &V2MulticastListenerReport<E>,
+    ) -> Result<bool> {
+        let mut updated = false;
+        let mut records = report.records();
+
+        while let Some(record) = records.next()? {
+            let group = record.multicast_addr();
+            if group.is_multicast() {
+                updated |= self.learn_record(
+                    port,
+                    group.into(),
+                    record.record_type(),
+                    !record.sources().is_empty(),
+                );
+            }
+        }
+
+        Ok(updated)
+    }
+
+    /// Applies an IGMPv3 group record or an MLDv2 multicast address record.
+    fn learn_record(
+        &mut self,
+        port: P,
+        group: IpAddr,
+        record_type: GroupRecordType,
+        has_sources: bool,
+    ) -> bool {
+        match record_type {
+            GroupRecordTypes::ModeIsExclude | GroupRecordTypes::ChangeToExcludeMode => {
+                self.join(port, group);
+                true
+            }
+            GroupRecordTypes::ModeIsInclude
+            | GroupRecordTypes::ChangeToIncludeMode
+            | GroupRecordTypes::AllowNewSources
+                if has_sources =>
+            {
+                self.join(port, group);
+                true
+            }
+            GroupRecordTypes::ChangeToIncludeMode => self.leave(port, group),
+            _ => false,
+        }
+    }
+}
+
+impl<P: Copy + Eq + Hash> Default for MulticastGroupTable<P> {
+    fn default() -> Self {
+        MulticastGroupTable::new()
+    }
+}
+
+/// IGMPv3 group record type, lengths and multicast address.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct GroupRecordHeader {
+    record_type: u8,
+    aux_data_len: u8,
+    num_sources: u16be,
+    multicast_addr: Ipv4Addr,
+}
+
+/// IGMP header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct IgmpHeader {
+    msg_type: u8,
+    max_resp_time: u8,
+    checksum: u16be,
+    group_addr: Ipv4Addr,
+}
+
+impl Default for IgmpHeader {
+    fn default() -> IgmpHeader {
+        IgmpHeader {
+            msg_type: 0,
+            max_resp_time: 0,
+            checksum: u16be::default(),
+            group_addr: Ipv4Addr::UNSPECIFIED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::icmp::v6::Icmpv6;
+    use crate::packets::ip::v6::{HopByHop, Ipv6};
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{
+        IGMPV2_REPORT_PACKET, IGMPV3_REPORT_PACKET, IPV4_UDP_PACKET, MLDV2_REPORT_PACKET,
+    };
+
+    #[test]
+    fn size_of_igmp_header() {
+        assert_eq!(8, IgmpHeader::size_of());
+        assert_eq!(8, GroupRecordHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_igmpv2_report_packet() {
+        let packet = Mbuf::from_bytes(&IGMPV2_REPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let igmp = ipv4.parse::<Igmp>().unwrap();
+
+        assert_eq!(IgmpTypes::V2MembershipReport, igmp.msg_type());
+        assert_eq!(2, igmp.version());
+        assert_eq!(0, igmp.max_resp_time());
+        assert_eq!(0xf8fa, igmp.checksum());
+        assert_eq!(Ipv4Addr::new(239, 1, 2, 3), igmp.group_addr());
+        assert!(igmp.group_records().next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn parse_igmpv3_report_packet() {
+        let packet = Mbuf::from_bytes(&IGMPV3_REPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let igmp = ipv4.parse::<Igmp>().unwrap();
+
+        assert_eq!(IgmpTypes::V3MembershipReport, igmp.msg_type());
+        assert_eq!(3, igmp.version());
+        assert_eq!(1, igmp.num_group_records());
+
+        let mut records = igmp.group_records();
+        let record = records.next().unwrap().unwrap();
+        assert_eq!(GroupRecordTypes::ModeIsInclude, record.record_type());
+        assert_eq!(Ipv4Addr::new(239, 1, 2, 3), record.multicast_addr());
+        assert_eq!(
+            &[Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)],
+            record.sources()
+        );
+        assert!(record.aux_data().is_empty());
+        assert!(records.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn parse_non_igmp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Igmp>().is_err());
+    }
+
+    #[capsule::test]
+    fn compute_checksum() {
+        let packet = Mbuf::from_bytes(&IGMPV3_REPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut igmp = ipv4.parse::<Igmp>().unwrap();
+
+        let expected = igmp.checksum();
+        // no payload change but force a checksum recompute anyway
+        igmp.reconcile_all();
+        assert_eq!(expected, igmp.checksum());
+    }
+
+    fn igmp_from(packet: &[u8]) -> Igmp {
+        let packet = Mbuf::from_bytes(packet).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        ipv4.parse::<Igmp>().unwrap()
+    }
+
+    #[capsule::test]
+    fn join_and_leave_multicast_group() {
+        let group = IpAddr::V4(Ipv4Addr::new(239, 1, 2, 3));
+        let mut table =
+            MulticastGroupTable::with_timers(GROUP_MEMBERSHIP_INTERVAL, Duration::from_millis(10));
+
+        let mut igmp = igmp_from(&IGMPV2_REPORT_PACKET);
+        assert!(table.learn_igmp(1u16, &igmp).unwrap());
+        assert!(table.learn_igmp(2u16, &igmp).unwrap());
+        assert!(table.is_subscribed(1, group));
+
+        let mut members = table.members(group);
+        members.sort_unstable();
+        assert_eq!(vec![1, 2], members);
+
+        // other members behind the port may still want the group.
+        igmp.set_msg_type(IgmpTypes::LeaveGroup);
+        assert!(table.learn_igmp(1, &igmp).unwrap());
+        assert!(table.is_subscribed(1, group));
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!table.is_subscribed(1, group));
+        assert!(table.is_subscribed(2, group));
+        assert_eq!(vec![2], table.members(group));
+
+        assert_eq!(1, table.expire());
+        assert_eq!(1, table.len());
+        assert!(!table.learn_igmp(1, &igmp).unwrap());
+    }
+
+    #[capsule::test]
+    fn report_refreshes_leaving_member() {
+        let group = IpAddr::V4(Ipv4Addr::new(239, 1, 2, 3));
+        let mut table =
+            MulticastGroupTable::with_timers(GROUP_MEMBERSHIP_INTERVAL, Duration::from_millis(10));
+
+        let mut igmp = igmp_from(&IGMPV2_REPORT_PACKET);
+        table.learn_igmp(1u16, &igmp).unwrap();
+        igmp.set_msg_type(IgmpTypes::LeaveGroup);
+        table.learn_igmp(1, &igmp).unwrap();
+
+        // a remaining member answers the group-specific query.
+        igmp.set_msg_type(IgmpTypes::V2MembershipReport);
+        table.learn_igmp(1, &igmp).unwrap();
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(table.is_subscribed(1, group));
+    }
+
+    #[capsule::test]
+    fn join_multicast_group_with_igmpv3_report() {
+        let group = IpAddr::V4(Ipv4Addr::new(239, 1, 2, 3));
+        let mut table = MulticastGroupTable::new();
+
+        let igmp = igmp_from(&IGMPV3_REPORT_PACKET);
+        assert!(table.learn_igmp(7u16, &igmp).unwrap());
+        assert!(table.is_subscribed(7, group));
+        assert!(!table.is_subscribed(8, group));
+        assert!(table
+            .members(IpAddr::V4(Ipv4Addr::new(239, 1, 2, 4)))
+            .is_empty());
+    }
+
+    #[capsule::test]
+    fn join_multicast_group_with_mldv2_report() {
+        let group = IpAddr::V6("ff05::1:3".parse().unwrap());
+        let mut table = MulticastGroupTable::new();
+
+        let packet = Mbuf::from_bytes(&MLDV2_REPORT_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let hbh = ipv6.parse::<HopByHop<Ipv6>>().unwrap();
+        let icmpv6 = hbh.parse::<Icmpv6<HopByHop<Ipv6>>>().unwrap();
+        let report = icmpv6
+            .downcast::<V2MulticastListenerReport<HopByHop<Ipv6>>>()
+            .unwrap();
+
+        assert!(table.learn_mldv2_report(3u16, &report).unwrap());
+        assert!(table.is_subscribed(3, group));
+        assert_eq!(vec![3], table.members(group));
+    }
+}
@@ -0,0 +1,311 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::checksum::PseudoHeader;
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::{IpPacket, ProtocolNumber};
+use crate::packets::{EtherTypes, Ethernet, Internal, Packet};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::IpAddr;
+
+/// An IP packet of either version.
+///
+/// `AnyIp` is parsed from an Ethernet frame of either [`EtherTypes::Ipv4`]
+/// or [`EtherTypes::Ipv6`], and implements [`IpPacket`] by delegating to
+/// the packet of the parsed version. The addresses are returned as
+/// [`IpAddr`]. Transport packets enveloped in `AnyIp`, for example
+/// `Udp<AnyIp>`, are handled the same regardless of the IP version.
+///
+/// # Example
+///
+/// ```
+/// let ip = ethernet.parse::<AnyIp>()?;
+/// let udp = ip.parse::<Udp<AnyIp>>()?;
+/// println!("{} > {}", udp.envelope().src(), udp.envelope().dst());
+/// ```
+///
+/// [`EtherTypes::Ipv4`]: EtherTypes::Ipv4
+/// [`EtherTypes::Ipv6`]: EtherTypes::Ipv6
+pub enum AnyIp {
+    /// An IPv4 packet.
+    V4(Ipv4),
+    /// An IPv6 packet.
+    V6(Ipv6),
+}
+
+impl From<Ipv4> for AnyIp {
+    fn from(ipv4: Ipv4) -> Self {
+        AnyIp::V4(ipv4)
+    }
+}
+
+impl From<Ipv6> for AnyIp {
+    fn from(ipv6: Ipv6) -> Self {
+        AnyIp::V6(ipv6)
+    }
+}
+
+impl fmt::Debug for AnyIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnyIp::V4(ipv4) => fmt::Debug::fmt(ipv4, f),
+            AnyIp::V6(ipv6) => fmt::Debug::fmt(ipv6, f),
+        }
+    }
+}
+
+impl Packet for AnyIp {
+    /// The preceding type for an IP packet must be Ethernet.
+    type Envelope = Ethernet;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        match self {
+            AnyIp::V4(ipv4) => ipv4.envelope(),
+            AnyIp::V6(ipv6) => ipv6.envelope(),
+        }
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        match self {
+            AnyIp::V4(ipv4) => ipv4.envelope_mut(),
+            AnyIp::V6(ipv6) => ipv6.envelope_mut(),
+        }
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        match self {
+            AnyIp::V4(ipv4) => ipv4.offset(),
+            AnyIp::V6(ipv6) => ipv6.offset(),
+        }
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        match self {
+            AnyIp::V4(ipv4) => ipv4.header_len(),
+            AnyIp::V6(ipv6) => ipv6.header_len(),
+        }
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        match self {
+            AnyIp::V4(ipv4) => AnyIp::V4(ipv4.clone(internal)),
+            AnyIp::V6(ipv6) => AnyIp::V6(ipv6.clone(internal)),
+        }
+    }
+
+    /// Parses the Ethernet's payload as an IP packet of the version
+    /// indicated by [`ether_type`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`ether_type`] is neither [`EtherTypes::Ipv4`]
+    /// nor [`EtherTypes::Ipv6`]. Returns an error if the payload fails to
+    /// parse as the IP packet.
+    ///
+    /// [`ether_type`]: Ethernet::ether_type
+    /// [`EtherTypes::Ipv4`]: EtherTypes::Ipv4
+    /// [`EtherTypes::Ipv6`]: EtherTypes::Ipv6
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        match envelope.ether_type() {
+            EtherTypes::Ipv4 => envelope.parse::<Ipv4>().map(AnyIp::V4),
+            EtherTypes::Ipv6 => envelope.parse::<Ipv6>().map(AnyIp::V6),
+            ether_type => Err(anyhow!("{} is not an IP packet.", ether_type)),
+        }
+    }
+
+    /// Cannot push an IP packet without knowing its version. This will
+    /// always error. Instead, push either [`Ipv4`] or [`Ipv6`], and convert
+    /// it into `AnyIp`.
+    ///
+    /// [`Ipv4`]: Ipv4
+    /// [`Ipv6`]: Ipv6
+    #[inline]
+    fn try_push(_envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        Err(anyhow!(
+            "cannot push an IP packet without knowing its version."
+        ))
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        match self {
+            AnyIp::V4(ipv4) => ipv4.deparse(),
+            AnyIp::V6(ipv6) => ipv6.deparse(),
+        }
+    }
+
+    #[inline]
+    fn remove(self) -> Result<Self::Envelope> {
+        match self {
+            AnyIp::V4(ipv4) => ipv4.remove(),
+            AnyIp::V6(ipv6) => ipv6.remove(),
+        }
+    }
+
+    #[inline]
+    fn reconcile(&mut self) {
+        match self {
+            AnyIp::V4(ipv4) => ipv4.reconcile(),
+            AnyIp::V6(ipv6) => ipv6.reconcile(),
+        }
+    }
+}
+
+impl IpPacket for AnyIp {
+    #[inline]
+    fn next_protocol(&self) -> ProtocolNumber {
+        match self {
+            AnyIp::V4(ipv4) => IpPacket::next_protocol(ipv4),
+            AnyIp::V6(ipv6) => IpPacket::next_protocol(ipv6),
+        }
+    }
+
+    #[inline]
+    fn set_next_protocol(&mut self, proto: ProtocolNumber) {
+        match self {
+            AnyIp::V4(ipv4) => IpPacket::set_next_protocol(ipv4, proto),
+            AnyIp::V6(ipv6) => IpPacket::set_next_protocol(ipv6, proto),
+        }
+    }
+
+    #[inline]
+    fn src(&self) -> IpAddr {
+        match self {
+            AnyIp::V4(ipv4) => IpPacket::src(ipv4),
+            AnyIp::V6(ipv6) => IpPacket::src(ipv6),
+        }
+    }
+
+    #[inline]
+    fn set_src(&mut self, src: IpAddr) -> Result<()> {
+        match self {
+            AnyIp::V4(ipv4) => IpPacket::set_src(ipv4, src),
+            AnyIp::V6(ipv6) => IpPacket::set_src(ipv6, src),
+        }
+    }
+
+    #[inline]
+    fn dst(&self) -> IpAddr {
+        match self {
+            AnyIp::V4(ipv4) => IpPacket::dst(ipv4),
+            AnyIp::V6(ipv6) => IpPacket::dst(ipv6),
+        }
+    }
+
+    #[inline]
+    fn set_dst(&mut self, dst: IpAddr) -> Result<()> {
+        match self {
+            AnyIp::V4(ipv4) => IpPacket::set_dst(ipv4, dst),
+            AnyIp::V6(ipv6) => IpPacket::set_dst(ipv6, dst),
+        }
+    }
+
+    #[inline]
+    fn pseudo_header(&self, packet_len: u16, protocol: ProtocolNumber) -> PseudoHeader {
+        match self {
+            AnyIp::V4(ipv4) => ipv4.pseudo_header(packet_len, protocol),
+            AnyIp::V6(ipv6) => ipv6.pseudo_header(packet_len, protocol),
+        }
+    }
+
+    #[inline]
+    fn truncate(&mut self, mtu: usize) -> Result<()> {
+        match self {
+            AnyIp::V4(ipv4) => IpPacket::truncate(ipv4, mtu),
+            AnyIp::V6(ipv6) => IpPacket::truncate(ipv6, mtu),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::ProtocolNumbers;
+    use crate::packets::{Tcp, Udp};
+    use crate::testils::byte_arrays::{ARP4_PACKET, IPV4_UDP_PACKET, IPV6_TCP_PACKET};
+    use crate::Mbuf;
+
+    /// Extracts the addresses and ports through the same code path
+    /// regardless of the IP version.
+    fn endpoints(mbuf: Mbuf) -> Result<(IpAddr, u16, IpAddr, u16)> {
+        let ip = mbuf.parse::<Ethernet>()?.parse::<AnyIp>()?;
+        let (src_port, dst_port) = match ip.next_protocol() {
+            ProtocolNumbers::Tcp => {
+                let tcp = ip.peek::<Tcp<AnyIp>>()?;
+                (tcp.src_port(), tcp.dst_port())
+            }
+            ProtocolNumbers::Udp => {
+                let udp = ip.peek::<Udp<AnyIp>>()?;
+                (udp.src_port(), udp.dst_port())
+            }
+            protocol => return Err(anyhow!("{} is not TCP or UDP.", protocol)),
+        };
+        Ok((ip.src(), src_port, ip.dst(), dst_port))
+    }
+
+    #[capsule::test]
+    fn parse_ipv4_and_ipv6_as_any_ip() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let (src, src_port, dst, dst_port) = endpoints(packet).unwrap();
+        assert_eq!("139.133.217.110", src.to_string());
+        assert_eq!(39376, src_port);
+        assert_eq!("139.133.233.2", dst.to_string());
+        assert_eq!(1087, dst_port);
+
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let (src, src_port, dst, dst_port) = endpoints(packet).unwrap();
+        assert_eq!("2001:db8:85a3::1", src.to_string());
+        assert_eq!(36869, src_port);
+        assert_eq!("2001:db8:85a3::8a2e:370:7334", dst.to_string());
+        assert_eq!(23, dst_port);
+    }
+
+    #[capsule::test]
+    fn payload_offset_of_any_ip() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ip = packet
+            .parse::<Ethernet>()
+            .unwrap()
+            .parse::<AnyIp>()
+            .unwrap();
+        assert_eq!(34, ip.payload_offset());
+
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ip = packet
+            .parse::<Ethernet>()
+            .unwrap()
+            .parse::<AnyIp>()
+            .unwrap();
+        assert_eq!(54, ip.payload_offset());
+    }
+
+    #[capsule::test]
+    fn parse_non_ip_as_any_ip() {
+        let packet = Mbuf::from_bytes(&ARP4_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert!(ethernet.parse::<AnyIp>().is_err());
+    }
+}
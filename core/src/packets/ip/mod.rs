@@ -18,12 +18,18 @@
 
 //! Internet Protocol v4 and v6.
 
+mod any;
 pub mod v4;
 pub mod v6;
 
+pub use self::any::*;
+
+use self::v4::Ipv4;
+use self::v6::Ipv6;
 use crate::packets::checksum::PseudoHeader;
-use crate::packets::Packet;
-use anyhow::Result;
+use crate::packets::{EtherTypes, Ethernet, Packet, Tcp, Udp};
+use crate::Mbuf;
+use anyhow::{anyhow, Result};
 use std::fmt;
 use std::net::{IpAddr, Ipv4Addr};
 
@@ -32,6 +38,21 @@ use std::net::{IpAddr, Ipv4Addr};
 /// [IANA]: https://www.iana.org/assignments/ip-parameters/ip-parameters.xml#ip-parameters-2
 pub const DEFAULT_IP_TTL: u8 = 64;
 
+/// ECN codepoint of a packet that is not ECN-capable, defined in
+/// [IETF RFC 3168].
+///
+/// [IETF RFC 3168]: https://tools.ietf.org/html/rfc3168#section-5
+pub const ECN_NOT_ECT: u8 = 0b00;
+
+/// ECN codepoint ECT(1) of an ECN-capable packet.
+pub const ECN_ECT1: u8 = 0b01;
+
+/// ECN codepoint ECT(0) of an ECN-capable packet.
+pub const ECN_ECT0: u8 = 0b10;
+
+/// ECN codepoint of a packet that experienced congestion.
+pub const ECN_CE: u8 = 0b11;
+
 /// [IANA] assigned Internet protocol number.
 ///
 /// See [`ProtocolNumbers`] for which are current supported.
@@ -61,17 +82,44 @@ pub mod ProtocolNumbers {
     /// User Datagram Protocol.
     pub const Udp: ProtocolNumber = ProtocolNumber(0x11);
 
+    /// Hop-by-Hop Options Header for IPv6.
+    pub const Ipv6HopByHop: ProtocolNumber = ProtocolNumber(0x00);
+
     /// Routing Header for IPv6.
     pub const Ipv6Route: ProtocolNumber = ProtocolNumber(0x2B);
 
     /// Fragment Header for IPv6.
     pub const Ipv6Frag: ProtocolNumber = ProtocolNumber(0x2C);
 
+    /// Destination Options Header for IPv6.
+    pub const Ipv6Opts: ProtocolNumber = ProtocolNumber(0x3C);
+
     /// Internet Control Message Protocol for IPv6.
     pub const Icmpv6: ProtocolNumber = ProtocolNumber(0x3A);
 
     /// Internet Control Message Protocol for IPv4.
     pub const Icmpv4: ProtocolNumber = ProtocolNumber(0x01);
+
+    /// Internet Group Management Protocol.
+    pub const Igmp: ProtocolNumber = ProtocolNumber(0x02);
+
+    /// Generic Routing Encapsulation.
+    pub const Gre: ProtocolNumber = ProtocolNumber(0x2F);
+
+    /// Encapsulating Security Payload.
+    pub const Esp: ProtocolNumber = ProtocolNumber(0x32);
+
+    /// Authentication Header.
+    pub const Ah: ProtocolNumber = ProtocolNumber(0x33);
+
+    /// Stream Control Transmission Protocol.
+    pub const Sctp: ProtocolNumber = ProtocolNumber(0x84);
+
+    /// Open Shortest Path First.
+    pub const Ospf: ProtocolNumber = ProtocolNumber(0x59);
+
+    /// Virtual Router Redundancy Protocol.
+    pub const Vrrp: ProtocolNumber = ProtocolNumber(0x70);
 }
 
 impl fmt::Display for ProtocolNumber {
@@ -84,8 +132,16 @@ impl fmt::Display for ProtocolNumber {
                 ProtocolNumbers::Udp => "UDP".to_string(),
                 ProtocolNumbers::Ipv6Route => "IPv6 Route".to_string(),
                 ProtocolNumbers::Ipv6Frag => "IPv6 Frag".to_string(),
+                ProtocolNumbers::Ipv6Opts => "IPv6 Opts".to_string(),
                 ProtocolNumbers::Icmpv6 => "ICMPv6".to_string(),
                 ProtocolNumbers::Icmpv4 => "ICMPv4".to_string(),
+                ProtocolNumbers::Igmp => "IGMP".to_string(),
+                ProtocolNumbers::Gre => "GRE".to_string(),
+                ProtocolNumbers::Esp => "ESP".to_string(),
+                ProtocolNumbers::Ah => "AH".to_string(),
+                ProtocolNumbers::Sctp => "SCTP".to_string(),
+                ProtocolNumbers::Ospf => "OSPF".to_string(),
+                ProtocolNumbers::Vrrp => "VRRP".to_string(),
                 _ => format!("0x{:02x}", self.0),
             }
         )
@@ -250,6 +306,49 @@ impl Flow {
             protocol: self.protocol,
         }
     }
+
+    /// Returns the canonical form of the flow, where the lesser of the two
+    /// endpoints is the source.
+    ///
+    /// Both directions of a connection have the same canonical form, so it
+    /// can be used as the key to track bidirectional flows.
+    #[inline]
+    pub fn canonical(&self) -> Self {
+        if (self.src_ip, self.src_port) <= (self.dst_ip, self.dst_port) {
+            *self
+        } else {
+            self.reverse()
+        }
+    }
+}
+
+/// Alias of [`Flow`], the 5-tuple that identifies a network connection.
+///
+/// [`Flow`]: crate::packets::ip::Flow
+pub type FiveTuple = Flow;
+
+/// Extracts the 5-tuple from an Ethernet frame carrying a TCP or UDP
+/// packet, over either IPv4 or IPv6.
+///
+/// # Errors
+///
+/// Returns an error if the frame is not an IP packet, or the IP packet is
+/// not a TCP or UDP packet.
+pub fn extract_flow(mbuf: &Mbuf) -> Result<Flow> {
+    let ethernet = mbuf.peek::<Ethernet>()?;
+    match ethernet.ether_type() {
+        EtherTypes::Ipv4 => transport_flow(&*ethernet.peek::<Ipv4>()?),
+        EtherTypes::Ipv6 => transport_flow(&*ethernet.peek::<Ipv6>()?),
+        ether_type => Err(anyhow!("{} is not an IP packet.", ether_type)),
+    }
+}
+
+fn transport_flow<E: IpPacket>(ip: &E) -> Result<Flow> {
+    match ip.next_protocol() {
+        ProtocolNumbers::Tcp => Ok(ip.peek::<Tcp<E>>()?.flow()),
+        ProtocolNumbers::Udp => Ok(ip.peek::<Udp<E>>()?.flow()),
+        protocol => Err(anyhow!("{} is not a TCP or UDP packet.", protocol)),
+    }
 }
 
 impl fmt::Debug for Flow {
@@ -267,6 +366,9 @@ impl fmt::Debug for Flow {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testils::byte_arrays::{ARP4_PACKET, IPV4_UDP_PACKET, IPV6_TCP_PACKET};
+    use std::collections::HashMap;
+    use std::net::Ipv6Addr;
 
     #[test]
     fn protocol_number_to_string() {
@@ -276,4 +378,49 @@ mod tests {
         assert_eq!("ICMPv6", ProtocolNumbers::Icmpv6.to_string());
         assert_eq!("0x00", ProtocolNumber::new(0).to_string());
     }
+
+    #[capsule::test]
+    fn extract_flow_from_ipv4_udp() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let flow = extract_flow(&packet).unwrap();
+
+        assert_eq!(IpAddr::V4(Ipv4Addr::new(139, 133, 217, 110)), flow.src_ip());
+        assert_eq!(IpAddr::V4(Ipv4Addr::new(139, 133, 233, 2)), flow.dst_ip());
+        assert_eq!(39376, flow.src_port());
+        assert_eq!(1087, flow.dst_port());
+        assert_eq!(ProtocolNumbers::Udp, flow.protocol());
+    }
+
+    #[capsule::test]
+    fn extract_flow_from_ipv6_tcp() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let flow = extract_flow(&packet).unwrap();
+
+        let src: Ipv6Addr = "2001:db8:85a3::1".parse().unwrap();
+        assert_eq!(IpAddr::V6(src), flow.src_ip());
+        assert_eq!(36869, flow.src_port());
+        assert_eq!(23, flow.dst_port());
+        assert_eq!(ProtocolNumbers::Tcp, flow.protocol());
+    }
+
+    #[capsule::test]
+    fn extract_flow_from_non_ip() {
+        let packet = Mbuf::from_bytes(&ARP4_PACKET).unwrap();
+        assert!(extract_flow(&packet).is_err());
+    }
+
+    #[capsule::test]
+    fn canonical_flow_as_key() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let flow = extract_flow(&packet).unwrap();
+        let reverse = flow.reverse();
+
+        assert_ne!(flow, reverse);
+        assert_eq!(flow.canonical(), reverse.canonical());
+
+        let mut flows = HashMap::new();
+        flows.insert(flow.canonical(), 1);
+        *flows.get_mut(&reverse.canonical()).unwrap() += 1;
+        assert_eq!(Some(&2), flows.get(&flow.canonical()));
+    }
 }
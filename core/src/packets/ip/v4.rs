@@ -18,11 +18,12 @@
 
 //! Internet Protocol v4.
 
+use crate::ffi;
 use crate::packets::checksum::{self, PseudoHeader};
-use crate::packets::ip::{IpPacket, ProtocolNumber, DEFAULT_IP_TTL};
+use crate::packets::ip::{IpPacket, ProtocolNumber, DEFAULT_IP_TTL, ECN_CE, ECN_NOT_ECT};
 use crate::packets::types::u16be;
-use crate::packets::{EtherTypes, Ethernet, Internal, Packet};
-use crate::{ensure, SizeOf};
+use crate::packets::{EtherTypes, Ethernet, Internal, Packet, PacketError, ValidateLength};
+use crate::{bitfield, ensure, Mbuf, SizeOf};
 use anyhow::{anyhow, Result};
 use std::fmt;
 use std::net::{IpAddr, Ipv4Addr};
@@ -38,8 +39,6 @@ use std::ptr::NonNull;
 pub const IPV4_MIN_MTU: usize = 68;
 
 // Masks.
-const DSCP: u8 = 0b1111_1100;
-const ECN: u8 = !DSCP;
 const FLAGS_DF: u16be = u16be(u16::to_be(0b0100_0000_0000_0000));
 const FLAGS_MF: u16be = u16be(u16::to_be(0b0010_0000_0000_0000));
 
@@ -159,47 +158,68 @@ impl Ipv4 {
         unsafe { self.header.as_mut() }
     }
 
-    /// Returns the protocol version. Should always be `4`.
-    #[inline]
-    pub fn version(&self) -> u8 {
-        (self.header().version_ihl & 0xf0) >> 4
+    bitfield! {
+        version_ihl: u8 {
+            /// Returns the protocol version. Should always be `4`.
+            #[inline]
+            pub get version -> u8 = 4..8;
+
+            /// Returns the length of the internet header measured in number of
+            /// 32-bit words. This indicates where the data begins.
+            #[inline]
+            pub get ihl -> u8 = 0..4;
+
+            /// Sets the length of the internet header measured in number of
+            /// 32-bit words. Only the lower 4 bits of the value are used, and
+            /// the version is preserved.
+            ///
+            /// The IHL must be adjusted when options are added to or removed
+            /// from the header, because the header length is derived from it.
+            #[inline]
+            pub set set_ihl(u8) = 0..4;
+        }
     }
 
-    /// Returns the length of the internet header measured in number of
-    /// 32-bit words. This indicates where the data begins.
-    #[inline]
-    pub fn ihl(&self) -> u8 {
-        self.header().version_ihl & 0x0f
-    }
+    bitfield! {
+        dscp_ecn: u8 {
+            /// Returns the differentiated services codepoint.
+            #[inline]
+            pub get dscp -> u8 = 2..8;
 
-    #[allow(dead_code)]
-    #[inline]
-    fn set_ihl(&mut self, ihl: u8) {
-        self.header_mut().version_ihl = (self.header().version_ihl & 0x0f) | (ihl & 0x0f);
-    }
-
-    /// Returns the differentiated services codepoint.
-    #[inline]
-    pub fn dscp(&self) -> u8 {
-        self.header().dscp_ecn >> 2
-    }
+            /// Sets the differentiated services codepoint.
+            #[inline]
+            pub set set_dscp(u8) = 2..8;
 
-    /// Sets the differentiated services codepoint.
-    #[inline]
-    pub fn set_dscp(&mut self, dscp: u8) {
-        self.header_mut().dscp_ecn = (self.header().dscp_ecn & ECN) | (dscp << 2);
-    }
+            /// Returns the explicit congestion notification codepoint.
+            #[inline]
+            pub get ecn -> u8 = 0..2;
 
-    /// Returns the explicit congestion notification codepoint.
-    #[inline]
-    pub fn ecn(&self) -> u8 {
-        self.header().dscp_ecn & ECN
+            /// Sets the explicit congestion notification codepoint.
+            #[inline]
+            pub set set_ecn(u8) = 0..2;
+        }
     }
 
-    /// Sets the explicit congestion notification codepoint.
+    /// Marks the packet as having experienced congestion.
+    ///
+    /// If the packet is ECN-capable, the ECN codepoint is set to CE and the
+    /// header checksum is updated incrementally. Returns `false` and leaves
+    /// the packet unchanged if the codepoint is Not-ECT. A packet already
+    /// marked CE is also left unchanged, but `true` is returned.
     #[inline]
-    pub fn set_ecn(&mut self, ecn: u8) {
-        self.header_mut().dscp_ecn = (self.header().dscp_ecn & DSCP) | (ecn & ECN);
+    pub fn mark_congestion(&mut self) -> bool {
+        match self.ecn() {
+            ECN_NOT_ECT => false,
+            ECN_CE => true,
+            _ => {
+                let old = u16::from_be_bytes([self.header().version_ihl, self.header().dscp_ecn]);
+                self.set_ecn(ECN_CE);
+                let new = u16::from_be_bytes([self.header().version_ihl, self.header().dscp_ecn]);
+                let checksum = checksum::compute_inc(self.checksum(), &[old], &[new]);
+                self.set_checksum(checksum);
+                true
+            }
+        }
     }
 
     /// Returns the length of the packet, measured in octets, including
@@ -266,20 +286,18 @@ impl Ipv4 {
         self.header_mut().flags_to_frag_offset &= !FLAGS_MF
     }
 
-    /// Returns an offset indicating where in the datagram this fragment
-    /// belongs. It is measured in units of 8 octets or 64 bits. The first
-    /// fragment has offset zero.
-    #[inline]
-    pub fn fragment_offset(&self) -> u16 {
-        (self.header().flags_to_frag_offset & u16be::from(0x1fff)).into()
-    }
-
-    /// Sets the fragment offset.
-    #[inline]
-    pub fn set_fragment_offset(&mut self, offset: u16) {
-        self.header_mut().flags_to_frag_offset = (self.header().flags_to_frag_offset
-            & u16be::from(0xe000))
-            | u16be::from(offset & 0x1fff)
+    bitfield! {
+        flags_to_frag_offset: u16 {
+            /// Returns an offset indicating where in the datagram this fragment
+            /// belongs. It is measured in units of 8 octets or 64 bits. The first
+            /// fragment has offset zero.
+            #[inline]
+            pub get fragment_offset -> u16 = 0..13;
+
+            /// Sets the fragment offset.
+            #[inline]
+            pub set set_fragment_offset(u16) = 0..13;
+        }
     }
 
     /// Returns the packet's time to live.
@@ -318,20 +336,158 @@ impl Ipv4 {
         self.header_mut().checksum = checksum.into();
     }
 
+    /// Returns whether the header checksum is correct.
     #[inline]
-    fn compute_checksum(&mut self) {
-        self.set_checksum(0);
+    pub fn validate_checksum(&self) -> bool {
+        match self.mbuf().read_data_slice(self.offset, self.header_len()) {
+            Ok(data) => {
+                let data = unsafe { data.as_ref() };
+                checksum::compute(0, data) == 0
+            }
+            Err(_) => false,
+        }
+    }
 
+    /// Returns the header checksum, without writing it to the packet.
+    #[inline]
+    pub fn calculate_checksum(&self) -> u16 {
         if let Ok(data) = self.mbuf().read_data_slice(self.offset, self.header_len()) {
             let data = unsafe { data.as_ref() };
-            let checksum = checksum::compute(0, data);
-            self.set_checksum(checksum);
+            // the checksum field is at offset 10 of the header.
+            checksum::compute_cleared(0, data, 10)
         } else {
             // we are reading the entire header, should never run out
             unreachable!()
         }
     }
 
+    #[inline]
+    fn compute_checksum(&mut self) {
+        let checksum = self.calculate_checksum();
+        self.set_checksum(checksum);
+    }
+
+    /// Requests the network device to compute the header checksum on
+    /// transmit.
+    ///
+    /// Sets the `PKT_TX_IPV4` and `PKT_TX_IP_CKSUM` offload flags and the
+    /// layer-2 and layer-3 header lengths on the buffer, and clears the
+    /// checksum field as DPDK requires. The port must be configured with
+    /// the `DEV_TX_OFFLOAD_IPV4_CKSUM` capability. Because [`reconcile`]
+    /// recomputes the checksum in software, it should not be called after
+    /// requesting the offload.
+    ///
+    /// [`reconcile`]: Packet::reconcile
+    #[inline]
+    pub fn offload_checksum(&mut self) {
+        self.set_checksum(0);
+
+        let l2_len = self.envelope().header_len();
+        let l3_len = self.header_len();
+        self.mbuf_mut()
+            .set_tx_offload(ffi::PKT_TX_IPV4 | ffi::PKT_TX_IP_CKSUM, l2_len, l3_len);
+    }
+
+    /// Fragments the packet into on-wire frames no larger than `mtu`.
+    ///
+    /// The payload is split at 8-octet boundaries, and each fragment is a
+    /// copy of the Ethernet header and the IPv4 header, followed by its
+    /// slice of the payload. Only the first fragment carries the header of
+    /// the next level protocol and all the options; the later fragments
+    /// only keep the options with the copied flag set, padded to a 4-octet
+    /// boundary. The don't fragment flag is cleared, the
+    /// more fragments flag is set on all but the last fragment, and the
+    /// fragment offset, the total length and the checksum of each fragment
+    /// are recomputed. A fragment of an already fragmented packet is split
+    /// again, preserving its offset and its more fragments flag.
+    ///
+    /// The payload is copied from all the segments of a chained buffer, so
+    /// a datagram that exceeds the size of a single buffer can be
+    /// fragmented. Returns the packet unchanged if it already fits within
+    /// `mtu`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the desired MTU is less than [`IPV4_MIN_MTU`].
+    /// Returns an error if the options are malformed.
+    /// Returns an error if the allocation of a fragment fails.
+    ///
+    /// [`IPV4_MIN_MTU`]: IPV4_MIN_MTU
+    pub fn fragment(self, mtu: usize) -> Result<Vec<Mbuf>> {
+        ensure!(
+            mtu >= IPV4_MIN_MTU,
+            anyhow!("MTU {} must be greater than {}.", mtu, IPV4_MIN_MTU)
+        );
+
+        let datagram = self.to_vec();
+        let total_length = (self.total_length() as usize).min(datagram.len());
+        if total_length <= mtu {
+            return Ok(vec![self.reset()]);
+        }
+
+        // the Ethernet header copied into every fragment.
+        let envelope = self.envelope();
+        let mut frame = self
+            .mbuf()
+            .read_data_slice::<u8>(envelope.offset(), envelope.header_len())
+            .map(|data| unsafe { data.as_ref() }.to_vec())?;
+        let l2_len = frame.len();
+
+        // the first fragment has the original IPv4 header. the later ones
+        // have the fixed header followed by the copied options only.
+        let first_header = &datagram[..self.header_len()];
+        let mut later_header = datagram[..Ipv4Header::size_of()].to_vec();
+        let mut options = self.options();
+        while let Some(option) = options.next()? {
+            if option.option_type().copied() {
+                later_header.push(option.option_type().0);
+                later_header.push(option.length() as u8);
+                later_header.extend_from_slice(option.data());
+            }
+        }
+        // pads with end of options list.
+        later_header.resize((later_header.len() + 3) & !3, 0);
+        later_header[0] = (later_header[0] & 0xf0) | (later_header.len() / 4) as u8;
+
+        let payload = &datagram[self.header_len()..total_length];
+        let base_offset = self.fragment_offset() as usize * 8;
+        let more_fragments = self.more_fragments();
+
+        let mut fragments = vec![];
+        let mut offset = 0;
+        while offset < payload.len() {
+            let header = if offset == 0 {
+                first_header
+            } else {
+                &later_header[..]
+            };
+            let chunk_len = (mtu - header.len()) & !7;
+            let end = (offset + chunk_len).min(payload.len());
+
+            frame.truncate(l2_len);
+            frame.extend_from_slice(header);
+            frame.extend_from_slice(&payload[offset..end]);
+
+            let mbuf = Mbuf::from_bytes(&frame)?;
+            let ethernet = mbuf.parse::<Ethernet>()?;
+            let mut ipv4 = ethernet.parse::<Ipv4>()?;
+
+            ipv4.unset_dont_fragment();
+            if more_fragments || end < payload.len() {
+                ipv4.set_more_fragments();
+            } else {
+                ipv4.unset_more_fragments();
+            }
+            ipv4.set_fragment_offset(((base_offset + offset) / 8) as u16);
+            ipv4.reconcile();
+
+            fragments.push(ipv4.reset());
+            offset = end;
+        }
+
+        Ok(fragments)
+    }
+
     /// Returns the source address.
     #[inline]
     pub fn src(&self) -> Ipv4Addr {
@@ -355,6 +511,19 @@ impl Ipv4 {
     pub fn set_dst(&mut self, dst: Ipv4Addr) {
         self.header_mut().dst = dst;
     }
+
+    /// Returns an iterator that iterates through the options between the
+    /// fixed header and the end of the header indicated by [`ihl`].
+    ///
+    /// [`ihl`]: Ipv4::ihl
+    #[inline]
+    pub fn options(&self) -> Ipv4OptionsIterator<'_> {
+        Ipv4OptionsIterator {
+            mbuf: self.mbuf(),
+            offset: self.offset + Ipv4Header::size_of(),
+            end: self.payload_offset(),
+        }
+    }
 }
 
 impl fmt::Debug for Ipv4 {
@@ -399,9 +568,13 @@ impl Packet for Ipv4 {
         self.offset
     }
 
+    /// Returns the length of the IPv4 header, including the options,
+    /// derived from [`ihl`].
+    ///
+    /// [`ihl`]: Ipv4::ihl
     #[inline]
     fn header_len(&self) -> usize {
-        Ipv4Header::size_of()
+        self.ihl() as usize * 4
     }
 
     #[inline]
@@ -418,11 +591,15 @@ impl Packet for Ipv4 {
     /// # Errors
     ///
     /// Returns an error if [`ether_type`] is not set to [`EtherTypes::Ipv4`].
-    /// Returns an error if the payload does not have sufficient data for the
-    /// IPv4 header.
+    /// Returns `PacketError::BadHeaderLength` if [`ihl`] is less than 5.
+    /// Returns `PacketError::LengthTooShort` if [`total_length`] is less
+    /// than the header length. Returns an error if the payload does not
+    /// have sufficient data for the IPv4 header, including the options.
     ///
     /// [`ether_type`]: Ethernet::ether_type
     /// [`EtherTypes::Ipv4`]: EtherTypes::Ipv4
+    /// [`ihl`]: Ipv4::ihl
+    /// [`total_length`]: Ipv4::total_length
     #[inline]
     fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
         ensure!(
@@ -434,11 +611,38 @@ impl Packet for Ipv4 {
         let offset = envelope.payload_offset();
         let header = mbuf.read_data(offset)?;
 
-        Ok(Ipv4 {
+        let packet = Ipv4 {
             envelope,
             header,
             offset,
-        })
+        };
+
+        ensure!(
+            packet.header_len() >= Ipv4Header::size_of(),
+            PacketError::BadHeaderLength {
+                len: packet.header_len(),
+                min: Ipv4Header::size_of(),
+            }
+        );
+
+        // a zero total length is accepted. it's the length of a newly
+        // pushed header not reconciled yet, or of a packet left for
+        // segmentation offload.
+        let total_len = packet.total_length() as usize;
+        ensure!(
+            total_len == 0 || total_len >= packet.header_len(),
+            PacketError::LengthTooShort {
+                len: total_len,
+                header_len: packet.header_len(),
+            }
+        );
+
+        // makes sure the options are in the buffer.
+        let _ = packet
+            .mbuf()
+            .read_data_slice::<u8>(offset, packet.header_len())?;
+
+        Ok(packet)
     }
 
     /// Prepends an IPv4 packet to the beginning of the Ethernet's payload.
@@ -572,6 +776,244 @@ impl IpPacket for Ipv4 {
     }
 }
 
+impl ValidateLength for Ipv4 {
+    /// Returns whether the total length covers the header and does not
+    /// exceed the data in all the segments of the buffer.
+    #[inline]
+    fn validate_length(&self) -> bool {
+        let total_length = self.total_length() as usize;
+        total_length >= self.header_len()
+            && total_length <= self.mbuf().pkt_len().saturating_sub(self.offset)
+    }
+}
+
+/// Validates the header checksums of a burst of IPv4 packets.
+///
+/// The results are the same as calling [`validate_checksum`] on each packet
+/// and are returned in the same order. While the current packet is being
+/// validated, the next packet is prefetched into the cache.
+///
+/// [`validate_checksum`]: Ipv4::validate_checksum
+pub fn validate_checksums(packets: &[Ipv4]) -> Vec<bool> {
+    let mut results = Vec::with_capacity(packets.len());
+
+    for (i, ipv4) in packets.iter().enumerate() {
+        if let Some(next) = packets.get(i + 1) {
+            next.prefetch();
+        }
+        results.push(ipv4.validate_checksum());
+    }
+
+    results
+}
+
+/// IPv4 option type.
+///
+/// The option type is made of the copied flag, the 2-bit option class and
+/// the 5-bit option number.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct Ipv4OptionType(pub u8);
+
+impl Ipv4OptionType {
+    /// Creates a new option type.
+    pub fn new(value: u8) -> Self {
+        Ipv4OptionType(value)
+    }
+
+    /// Returns whether the option is copied into all fragments.
+    #[inline]
+    pub fn copied(self) -> bool {
+        self.0 & 0b1000_0000 != 0
+    }
+
+    /// Returns the option class.
+    #[inline]
+    pub fn class(self) -> Ipv4OptionClass {
+        match (self.0 & 0b0110_0000) >> 5 {
+            0 => Ipv4OptionClass::Control,
+            2 => Ipv4OptionClass::DebuggingAndMeasurement,
+            class => Ipv4OptionClass::Reserved(class),
+        }
+    }
+
+    /// Returns the option number.
+    #[inline]
+    pub fn number(self) -> u8 {
+        self.0 & 0b0001_1111
+    }
+}
+
+/// Supported IPv4 option types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod Ipv4OptionTypes {
+    use super::Ipv4OptionType;
+
+    /// End of options list.
+    pub const EndOfList: Ipv4OptionType = Ipv4OptionType(0);
+    /// No operation.
+    pub const NoOperation: Ipv4OptionType = Ipv4OptionType(1);
+    /// Record route.
+    pub const RecordRoute: Ipv4OptionType = Ipv4OptionType(7);
+    /// Internet timestamp.
+    pub const Timestamp: Ipv4OptionType = Ipv4OptionType(68);
+    /// Loose source and record route.
+    pub const LooseSourceRoute: Ipv4OptionType = Ipv4OptionType(131);
+    /// Strict source and record route.
+    pub const StrictSourceRoute: Ipv4OptionType = Ipv4OptionType(137);
+    /// Router alert.
+    pub const RouterAlert: Ipv4OptionType = Ipv4OptionType(148);
+}
+
+impl fmt::Display for Ipv4OptionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Ipv4OptionTypes::EndOfList => "End of Options List".to_string(),
+                Ipv4OptionTypes::NoOperation => "No Operation".to_string(),
+                Ipv4OptionTypes::RecordRoute => "Record Route".to_string(),
+                Ipv4OptionTypes::Timestamp => "Timestamp".to_string(),
+                Ipv4OptionTypes::LooseSourceRoute => "Loose Source Route".to_string(),
+                Ipv4OptionTypes::StrictSourceRoute => "Strict Source Route".to_string(),
+                Ipv4OptionTypes::RouterAlert => "Router Alert".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("{}", t)
+                }
+            }
+        )
+    }
+}
+
+/// IPv4 option class.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Ipv4OptionClass {
+    /// Control options, class 0.
+    Control,
+    /// Debugging and measurement options, class 2.
+    DebuggingAndMeasurement,
+    /// Reserved for future use, class 1 or 3.
+    Reserved(u8),
+}
+
+/// An IPv4 option.
+pub struct Ipv4Option<'a> {
+    option_type: Ipv4OptionType,
+    data: &'a [u8],
+}
+
+impl<'a> Ipv4Option<'a> {
+    /// Returns the option type.
+    #[inline]
+    pub fn option_type(&self) -> Ipv4OptionType {
+        self.option_type
+    }
+
+    /// Returns the length of the option in octets, including the type and
+    /// the length fields. The no-operation option is 1 octet long.
+    #[inline]
+    pub fn length(&self) -> usize {
+        if self.option_type == Ipv4OptionTypes::NoOperation {
+            1
+        } else {
+            self.data.len() + 2
+        }
+    }
+
+    /// Returns the option data, excluding the type and the length.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl fmt::Debug for Ipv4Option<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ipv4Option")
+            .field("option_type", &format!("{}", self.option_type()))
+            .field("length", &self.length())
+            .field("data", &self.data())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the IPv4 options.
+///
+/// The iteration stops at the end of options list option, which is not
+/// returned.
+pub struct Ipv4OptionsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> Ipv4OptionsIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<Ipv4Option<'a>>> {
+        if self.end <= self.offset {
+            return Ok(None);
+        }
+
+        let option_type = unsafe { *self.mbuf.read_data::<u8>(self.offset)?.as_ref() };
+        let option_type = Ipv4OptionType::new(option_type);
+
+        match option_type {
+            Ipv4OptionTypes::EndOfList => {
+                self.offset = self.end;
+                Ok(None)
+            }
+            Ipv4OptionTypes::NoOperation => {
+                self.offset += 1;
+                Ok(Some(Ipv4Option {
+                    option_type,
+                    data: &[],
+                }))
+            }
+            _ => {
+                // marks the iteration finished so a malformed option is
+                // only reported once.
+                let offset = self.offset;
+                self.offset = self.end;
+
+                ensure!(
+                    offset + 2 <= self.end,
+                    anyhow!("truncated IPv4 {} option.", option_type)
+                );
+                let len = unsafe { *self.mbuf.read_data::<u8>(offset + 1)?.as_ref() } as usize;
+                ensure!(
+                    len >= 2 && offset + len <= self.end,
+                    anyhow!("invalid IPv4 {} option length {}.", option_type, len)
+                );
+
+                let data: &'a [u8] = if len > 2 {
+                    let data = self.mbuf.read_data_slice::<u8>(offset + 2, len - 2)?;
+                    unsafe { &*data.as_ptr() }
+                } else {
+                    &[]
+                };
+
+                self.offset = offset + len;
+                Ok(Some(Ipv4Option { option_type, data }))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Ipv4OptionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ipv4OptionsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
 /// IPv4 header.
 ///
 /// The header only include the fixed portion of the IPv4 header.
@@ -611,15 +1053,56 @@ impl Default for Ipv4Header {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::packets::ip::ProtocolNumbers;
-    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, IPV6_TCP_PACKET};
-    use crate::Mbuf;
+    use crate::packets::ip::{ProtocolNumbers, ECN_ECT0};
+    use crate::packets::Udp;
+    use crate::testils::byte_arrays::{IPV4_OPTIONS_PACKET, IPV4_UDP_PACKET, IPV6_TCP_PACKET};
 
     #[test]
     fn size_of_ipv4_header() {
         assert_eq!(20, Ipv4Header::size_of());
     }
 
+    #[capsule::test]
+    fn validate_checksums_of_burst() {
+        let mut bad_ttl = IPV4_UDP_PACKET;
+        bad_ttl[22] -= 1;
+
+        let packets = [IPV4_UDP_PACKET, bad_ttl, IPV4_UDP_PACKET]
+            .iter()
+            .map(|bytes| {
+                let packet = Mbuf::from_bytes(bytes).unwrap();
+                packet.parse::<Ethernet>().unwrap().parse::<Ipv4>().unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert!(!packets[1].validate_checksum());
+        assert_eq!(vec![true, false, true], validate_checksums(&packets));
+    }
+
+    #[capsule::test]
+    fn validate_ipv4_length() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert!(ipv4.validate_length());
+
+        // total length overstates the buffer by one byte.
+        let mut bytes = IPV4_UDP_PACKET;
+        bytes[17] += 1;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert!(!ethernet.peek::<Ipv4>().unwrap().validate_length());
+        assert!(ethernet.parse_strict::<Ipv4>().is_err());
+
+        // the payload continues in a second segment.
+        let mut packet = Mbuf::from_bytes(&IPV4_UDP_PACKET[..45]).unwrap();
+        packet
+            .chain(Mbuf::from_bytes(&IPV4_UDP_PACKET[45..]).unwrap())
+            .unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert!(ethernet.parse_strict::<Ipv4>().is_ok());
+    }
+
     #[capsule::test]
     fn parse_ipv4_packet() {
         let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
@@ -642,6 +1125,151 @@ mod tests {
         assert_eq!("139.133.233.2", ipv4.dst().to_string());
     }
 
+    #[capsule::test]
+    fn mark_congestion_of_ect_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        ipv4.set_ecn(ECN_ECT0);
+        ipv4.compute_checksum();
+
+        assert!(ipv4.mark_congestion());
+        assert_eq!(ECN_CE, ipv4.ecn());
+        assert_eq!(0, ipv4.dscp());
+        assert!(ipv4.validate_checksum());
+    }
+
+    #[capsule::test]
+    fn mark_congestion_of_not_ect_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(!ipv4.mark_congestion());
+        assert_eq!(ECN_NOT_ECT, ipv4.ecn());
+        assert_eq!(0xf700, ipv4.checksum());
+        assert!(ipv4.validate_checksum());
+    }
+
+    #[capsule::test]
+    fn iterate_ipv4_options() {
+        let packet = Mbuf::from_bytes(&IPV4_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let mut iter = ipv4.options();
+
+        let nop = iter.next().unwrap().unwrap();
+        assert_eq!(Ipv4OptionTypes::NoOperation, nop.option_type());
+        assert_eq!(1, nop.length());
+        assert!(nop.data().is_empty());
+
+        let alert = iter.next().unwrap().unwrap();
+        assert_eq!(Ipv4OptionTypes::RouterAlert, alert.option_type());
+        assert!(alert.option_type().copied());
+        assert_eq!(Ipv4OptionClass::Control, alert.option_type().class());
+        assert_eq!(20, alert.option_type().number());
+        assert_eq!(4, alert.length());
+        assert_eq!(&[0, 0], alert.data());
+
+        // stops at the end of options list.
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn parse_ipv4_packet_with_options() {
+        let packet = Mbuf::from_bytes(&IPV4_OPTIONS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert_eq!(7, ipv4.ihl());
+        assert_eq!(ipv4.ihl() as usize * 4, ipv4.header_len());
+        assert_eq!(28, ipv4.header_len());
+        assert_eq!(18, ipv4.payload_len());
+        assert!(ipv4.validate_checksum());
+
+        let udp = ipv4.peek::<Udp<Ipv4>>().unwrap();
+        assert_eq!(39376, udp.src_port());
+        assert_eq!(1087, udp.dst_port());
+
+        // only the lower 4 bits are used and the version is preserved.
+        ipv4.set_ihl(0x15);
+        assert_eq!(4, ipv4.version());
+        assert_eq!(5, ipv4.ihl());
+        assert_eq!(20, ipv4.header_len());
+    }
+
+    #[capsule::test]
+    fn parse_ipv4_packet_with_invalid_ihl() {
+        let mut bytes = IPV4_UDP_PACKET;
+        // version = 4, ihl = 4
+        bytes[14] = 0x44;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let err = ethernet.parse::<Ipv4>().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PacketError>(),
+            Some(PacketError::BadHeaderLength { len: 16, min: 20 })
+        ));
+
+        // version = 4, ihl = 15, past the end of the buffer
+        bytes[14] = 0x4f;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert!(ethernet.parse::<Ipv4>().is_err());
+    }
+
+    #[capsule::test]
+    fn parse_ipv4_packet_with_short_total_length() {
+        let mut bytes = IPV4_UDP_PACKET;
+        // total length = 16, less than the header
+        bytes[16] = 0x00;
+        bytes[17] = 0x10;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let err = ethernet.parse::<Ipv4>().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PacketError>(),
+            Some(PacketError::LengthTooShort {
+                len: 16,
+                header_len: 20,
+            })
+        ));
+    }
+
+    #[capsule::test]
+    fn iterate_malformed_ipv4_options() {
+        let mut bytes = IPV4_OPTIONS_PACKET;
+        // router alert length = 9, past the end of the header
+        bytes[36] = 0x09;
+
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let mut iter = ipv4.options();
+        assert!(iter.next().unwrap().is_some());
+        assert!(iter.next().is_err());
+        assert!(iter.next().unwrap().is_none());
+
+        // no options without the IHL extending past the fixed header.
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert!(ipv4.options().next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn next_protocol_of_ipv4_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.peek::<Ipv4>().unwrap();
+
+        assert_eq!(ProtocolNumber::new(17), ipv4.next_protocol());
+        assert_eq!(ProtocolNumbers::Udp, ipv4.next_protocol());
+    }
+
     #[capsule::test]
     fn parse_non_ipv4_packet() {
         let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
@@ -685,6 +1313,45 @@ mod tests {
         assert_eq!(3, ipv4.ecn());
     }
 
+    #[capsule::test]
+    fn ipv4_addrs_round_trip() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        // addresses are `std::net` types, both directly and as `IpAddr`.
+        let src = Ipv4Addr::new(10, 0, 0, 1);
+        ipv4.set_src(src);
+        assert_eq!(src, ipv4.src());
+        assert_eq!(IpAddr::V4(src), IpPacket::src(&ipv4));
+
+        let dst: IpAddr = "10.0.0.2".parse().unwrap();
+        IpPacket::set_dst(&mut ipv4, dst).unwrap();
+        assert_eq!(dst, IpAddr::V4(ipv4.dst()));
+        assert_eq!("10.0.0.2", ipv4.dst().to_string());
+
+        // mismatched address family is rejected.
+        assert!(IpPacket::set_src(&mut ipv4, "::1".parse().unwrap()).is_err());
+        assert_eq!(src, ipv4.src());
+    }
+
+    #[capsule::test]
+    fn set_dst_and_recompute_checksum() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let dst = Ipv4Addr::new(192, 168, 1, 1);
+        ipv4.set_dst(dst);
+        assert_eq!(dst, ipv4.dst());
+        assert_eq!([192, 168, 1, 1], ipv4.dst().octets());
+        assert!(!ipv4.validate_checksum());
+
+        ipv4.reconcile();
+        assert_eq!(0xa9df, ipv4.checksum());
+        assert!(ipv4.validate_checksum());
+    }
+
     #[capsule::test]
     fn push_ipv4_packet() {
         let packet = Mbuf::new().unwrap();
@@ -715,6 +1382,17 @@ mod tests {
         assert_eq!(1000, ipv4.len());
     }
 
+    #[capsule::test]
+    fn calculate_checksum_without_writing() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        ipv4.set_checksum(0x1234);
+        assert_eq!(0xf700, ipv4.calculate_checksum());
+        assert_eq!(0x1234, ipv4.checksum());
+    }
+
     #[capsule::test]
     fn compute_checksum() {
         let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
@@ -726,4 +1404,154 @@ mod tests {
         ipv4.reconcile_all();
         assert_eq!(expected, ipv4.checksum());
     }
+
+    #[capsule::test]
+    fn offload_checksum() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        ipv4.offload_checksum();
+
+        assert_eq!(0, ipv4.checksum());
+        let mbuf = ipv4.mbuf();
+        assert_eq!(ffi::PKT_TX_IPV4, mbuf.ol_flags() & ffi::PKT_TX_IPV4);
+        assert_eq!(ffi::PKT_TX_IP_CKSUM, mbuf.ol_flags() & ffi::PKT_TX_IP_CKSUM);
+        assert_eq!(14, mbuf.l2_len());
+        assert_eq!(20, mbuf.l3_len());
+    }
+
+    #[capsule::test]
+    fn fragment_and_reassemble() {
+        let payload = (0..2992).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+        #[rustfmt::skip]
+        let mut bytes = vec![
+            // Ethernet header
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+            0x08, 0x00,
+            // IPv4 header, total length = 3020, DF set
+            0x45, 0x00, 0x0b, 0xcc,
+            0x12, 0x34, 0x40, 0x00,
+            0x40, 0x11, 0x00, 0x00,
+            0x0a, 0x00, 0x00, 0x01,
+            0x0a, 0x00, 0x00, 0x02,
+            // UDP header, length = 3000
+            0x04, 0xd2, 0x16, 0x2e,
+            0x0b, 0xb8, 0x00, 0x00,
+        ];
+        bytes.extend_from_slice(&payload);
+
+        // the datagram does not fit in a single buffer.
+        let mut packet = Mbuf::from_bytes(&bytes[..1000]).unwrap();
+        packet
+            .chain(Mbuf::from_bytes(&bytes[1000..]).unwrap())
+            .unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let fragments = ipv4.fragment(1500).unwrap();
+        assert_eq!(3, fragments.len());
+
+        let mut reassembled = vec![0; 3000];
+        let mut last_offset = None;
+        for (i, fragment) in fragments.into_iter().enumerate() {
+            let ethernet = fragment.parse::<Ethernet>().unwrap();
+            let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+            assert!(ipv4.len() <= 1500);
+            assert_eq!(ipv4.len(), ipv4.total_length() as usize);
+            assert_eq!(0x1234, ipv4.identification());
+            assert!(!ipv4.dont_fragment());
+            assert_eq!(i < 2, ipv4.more_fragments());
+
+            let header = ipv4
+                .mbuf()
+                .read_data_slice::<u8>(ipv4.offset(), ipv4.header_len())
+                .unwrap();
+            assert_eq!(0, checksum::compute(0, unsafe { header.as_ref() }));
+
+            let data = ipv4.to_vec();
+            let chunk = &data[ipv4.header_len()..];
+            let offset = ipv4.fragment_offset() as usize * 8;
+            reassembled[offset..offset + chunk.len()].copy_from_slice(chunk);
+            last_offset = Some(offset + chunk.len());
+
+            // only the first fragment has the UDP header.
+            if i == 0 {
+                let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+                assert_eq!(1234, udp.src_port());
+                assert_eq!(3000, udp.length());
+            }
+        }
+
+        assert_eq!(Some(3000), last_offset);
+        assert_eq!(&bytes[34..], &reassembled[..]);
+    }
+
+    #[capsule::test]
+    fn fragment_keeps_copied_options_only() {
+        let payload = (0..192).map(|i| i as u8).collect::<Vec<_>>();
+
+        #[rustfmt::skip]
+        let mut bytes = vec![
+            // Ethernet header
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+            0x08, 0x00,
+            // IPv4 header, ihl = 8, total length = 232
+            0x48, 0x00, 0x00, 0xe8,
+            0x12, 0x34, 0x00, 0x00,
+            0x40, 0x11, 0x00, 0x00,
+            0x0a, 0x00, 0x00, 0x01,
+            0x0a, 0x00, 0x00, 0x02,
+            // record route, not copied
+            0x07, 0x07, 0x04, 0x00, 0x00, 0x00, 0x00,
+            // router alert, copied
+            0x94, 0x04, 0x00, 0x00,
+            // end of options list
+            0x00,
+            // UDP header, length = 200
+            0x04, 0xd2, 0x16, 0x2e,
+            0x00, 0xc8, 0x00, 0x00,
+        ];
+        bytes.extend_from_slice(&payload);
+
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let fragments = ipv4.fragment(100).unwrap();
+        assert_eq!(3, fragments.len());
+
+        let mut reassembled = vec![0; 200];
+        for (i, fragment) in fragments.into_iter().enumerate() {
+            let ethernet = fragment.parse::<Ethernet>().unwrap();
+            let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+            assert!(ipv4.len() <= 100);
+            assert_eq!(ipv4.len(), ipv4.total_length() as usize);
+            assert!(ipv4.validate_checksum());
+
+            let mut options = ipv4.options();
+            if i == 0 {
+                assert_eq!(8, ipv4.ihl());
+                let route = options.next().unwrap().unwrap();
+                assert_eq!(Ipv4OptionTypes::RecordRoute, route.option_type());
+            } else {
+                assert_eq!(6, ipv4.ihl());
+            }
+            let alert = options.next().unwrap().unwrap();
+            assert_eq!(Ipv4OptionTypes::RouterAlert, alert.option_type());
+            assert!(options.next().unwrap().is_none());
+
+            let data = ipv4.to_vec();
+            let chunk = &data[ipv4.header_len()..];
+            let offset = ipv4.fragment_offset() as usize * 8;
+            reassembled[offset..offset + chunk.len()].copy_from_slice(chunk);
+        }
+
+        assert_eq!(&bytes[46..], &reassembled[..]);
+    }
 }
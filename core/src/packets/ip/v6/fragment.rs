@@ -20,7 +20,7 @@ use crate::packets::checksum::PseudoHeader;
 use crate::packets::ip::v6::Ipv6Packet;
 use crate::packets::ip::{IpPacket, ProtocolNumber, ProtocolNumbers};
 use crate::packets::types::{u16be, u32be};
-use crate::packets::{Internal, Packet};
+use crate::packets::{Internal, Packet, PacketError};
 use crate::{ensure, SizeOf};
 use anyhow::{anyhow, Result};
 use std::fmt;
@@ -30,6 +30,7 @@ use std::ptr::NonNull;
 /// Masks
 const FRAG_OS: u16be = u16be(u16::to_be(!0b111));
 const FLAG_MORE: u16be = u16be(u16::to_be(0b1));
+const RES: u16be = u16be(u16::to_be(0b110));
 
 /// IPv6 Fragment Extension packet based on [IETF RFC 8200].
 ///
@@ -49,7 +50,7 @@ const FLAG_MORE: u16be = u16be(u16::to_be(0b1));
 ///                       field [IANA-PN].
 ///
 /// - *Reserved*:         8-bit reserved field.  Initialized to zero for
-///                       transmission; ignored on reception.
+///                       transmission.
 ///
 /// - *Fragment Offset*:  13-bit unsigned integer.  The offset, in
 ///                       8-octet units, of the data following this
@@ -57,7 +58,7 @@ const FLAG_MORE: u16be = u16be(u16::to_be(0b1));
 ///                       Fragmentable Part of the original packet.
 ///
 /// - *Res*:              2-bit reserved field.  Initialized to zero for
-///                       transmission; ignored on reception.
+///                       transmission.
 ///
 /// - *M flag*:           1 = more fragments; 0 = last fragment.
 ///
@@ -65,6 +66,11 @@ const FLAG_MORE: u16be = u16be(u16::to_be(0b1));
 ///
 /// # Remarks
 ///
+/// Although RFC 8200 says the reserved fields are ignored on reception,
+/// a fragment with any reserved bit set is rejected when parsed. Those
+/// bits are never set by a conforming sender, so it's most likely a
+/// malformed or crafted packet.
+///
 /// Because the payload following the fragment header is incomplete data,
 /// `push` and `remove` should be used with care. The result is likely not
 /// a valid packet without additional fixes.
@@ -179,7 +185,8 @@ impl<E: Ipv6Packet> Packet for Fragment<E> {
     ///
     /// Returns an error if [`next_header`] is not set to [`ProtocolNumbers::Ipv6Frag`].
     /// Returns an error if the payload does not have sufficient data for the
-    /// fragment extension header.
+    /// fragment extension header. Returns `PacketError::ReservedBitsSet` if
+    /// any of the reserved bits is set.
     ///
     /// [`next_header`]: Ipv6Packet::next_header
     /// [`ProtocolNumbers::Ipv6Frag`]: ProtocolNumbers::Ipv6Frag
@@ -194,11 +201,18 @@ impl<E: Ipv6Packet> Packet for Fragment<E> {
         let offset = envelope.payload_offset();
         let header = mbuf.read_data(offset)?;
 
-        Ok(Fragment {
+        let packet = Fragment {
             envelope,
             header,
             offset,
-        })
+        };
+
+        ensure!(
+            packet.header().reserved == 0 && packet.header().frag_res_m & RES == u16be::MIN,
+            PacketError::ReservedBitsSet("IPv6 fragment")
+        );
+
+        Ok(packet)
     }
 
     /// Prepends an IPv6 fragment packet to the beginning of the envelope's
@@ -352,6 +366,29 @@ mod tests {
         assert_eq!(0xf88e_b466, frag.identification());
     }
 
+    #[capsule::test]
+    fn parse_fragment_packet_with_reserved_bits() {
+        let mut bytes = IPV6_FRAGMENT_PACKET;
+        // reserved = 1
+        bytes[55] = 0x01;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let err = ipv6.parse::<Fragment<Ipv6>>().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PacketError>(),
+            Some(PacketError::ReservedBitsSet(_))
+        ));
+
+        // res = 0b10, next to the more fragments flag
+        bytes[55] = 0x00;
+        bytes[57] = 0xfc;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        assert!(ipv6.parse::<Fragment<Ipv6>>().is_err());
+    }
+
     #[capsule::test]
     fn parse_non_fragment_packet() {
         let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
@@ -19,16 +19,20 @@
 //! Internet Protocol v6 and extension headers.
 
 mod fragment;
+mod options;
 mod srh;
 
 pub use self::fragment::*;
+pub use self::options::*;
 pub use self::srh::*;
 
 use crate::packets::checksum::PseudoHeader;
-use crate::packets::ip::{IpPacket, ProtocolNumber, DEFAULT_IP_TTL};
+use crate::packets::ip::{
+    IpPacket, ProtocolNumber, ProtocolNumbers, DEFAULT_IP_TTL, ECN_CE, ECN_NOT_ECT,
+};
 use crate::packets::types::{u16be, u32be};
-use crate::packets::{EtherTypes, Ethernet, Internal, Packet};
-use crate::{ensure, SizeOf};
+use crate::packets::{EtherTypes, Ethernet, Internal, Packet, ValidateLength};
+use crate::{bitfield, ensure, SizeOf};
 use anyhow::{anyhow, Result};
 use std::fmt;
 use std::net::{IpAddr, Ipv6Addr};
@@ -39,11 +43,6 @@ use std::ptr::NonNull;
 /// [IETF RFC 2460]: https://tools.ietf.org/html/rfc2460#section-5
 pub const IPV6_MIN_MTU: usize = 1280;
 
-// Masks
-const DSCP: u32be = u32be(u32::to_be(0x0fc0_0000));
-const ECN: u32be = u32be(u32::to_be(0x0030_0000));
-const FLOW: u32be = u32be(u32::to_be(0xfffff));
-
 /// Internet Protocol v6 based on [IETF RFC 8200].
 ///
 /// ```
@@ -112,52 +111,54 @@ impl Ipv6 {
         unsafe { self.header.as_mut() }
     }
 
-    /// Returns the protocol version. Should always be `6`.
-    #[inline]
-    pub fn version(&self) -> u8 {
-        let v: u32 = (self.header().version_to_flow_label & u32be::from(0xf000_0000)).into();
-        (v >> 28) as u8
-    }
+    bitfield! {
+        version_to_flow_label: u32 {
+            /// Returns the protocol version. Should always be `6`.
+            #[inline]
+            pub get version -> u8 = 28..32;
 
-    /// Returns the differentiated services codepoint.
-    #[inline]
-    pub fn dscp(&self) -> u8 {
-        let v: u32 = (self.header().version_to_flow_label & DSCP).into();
-        (v >> 22) as u8
-    }
+            /// Returns the differentiated services codepoint.
+            #[inline]
+            pub get dscp -> u8 = 22..28;
 
-    /// Sets the differentiated services codepoint.
-    #[inline]
-    pub fn set_dscp(&mut self, dscp: u8) {
-        self.header_mut().version_to_flow_label = (self.header().version_to_flow_label & !DSCP)
-            | (u32be::from(u32::from(dscp) << 22) & DSCP)
-    }
+            /// Sets the differentiated services codepoint.
+            #[inline]
+            pub set set_dscp(u8) = 22..28;
 
-    /// Returns the explicit congestion notification codepoint.
-    #[inline]
-    pub fn ecn(&self) -> u8 {
-        let v: u32 = (self.header().version_to_flow_label & ECN).into();
-        (v >> 20) as u8
-    }
+            /// Returns the explicit congestion notification codepoint.
+            #[inline]
+            pub get ecn -> u8 = 20..22;
 
-    /// Sets the explicit congestion notification codepoint.
-    #[inline]
-    pub fn set_ecn(&mut self, ecn: u8) {
-        self.header_mut().version_to_flow_label =
-            (self.header().version_to_flow_label & !ECN) | (u32be::from(u32::from(ecn) << 20) & ECN)
-    }
+            /// Sets the explicit congestion notification codepoint.
+            #[inline]
+            pub set set_ecn(u8) = 20..22;
 
-    /// Returns the flow label.
-    #[inline]
-    pub fn flow_label(&self) -> u32 {
-        (self.header().version_to_flow_label & FLOW).into()
+            /// Returns the flow label.
+            #[inline]
+            pub get flow_label -> u32 = 0..20;
+
+            /// Sets the flow label.
+            #[inline]
+            pub set set_flow_label(u32) = 0..20;
+        }
     }
 
-    /// Sets the flow label.
-    #[inline]
-    pub fn set_flow_label(&mut self, flow_label: u32) {
-        self.header_mut().version_to_flow_label =
-            (self.header().version_to_flow_label & !FLOW) | (u32be::from(flow_label) & FLOW)
+    /// Marks the packet as having experienced congestion.
+    ///
+    /// If the packet is ECN-capable, the ECN bits of the traffic class are
+    /// set to CE. Returns `false` and leaves the packet unchanged if the
+    /// codepoint is Not-ECT. A packet already marked CE is also left
+    /// unchanged, but `true` is returned.
+    #[inline]
+    pub fn mark_congestion(&mut self) -> bool {
+        match self.ecn() {
+            ECN_NOT_ECT => false,
+            ECN_CE => true,
+            _ => {
+                self.set_ecn(ECN_CE);
+                true
+            }
+        }
     }
 
     /// Returns the length of the payload measured in octets.
@@ -172,6 +173,80 @@ impl Ipv6 {
         self.header_mut().payload_length = payload_length.into();
     }
 
+    /// Returns the length of the payload measured in octets if the packet
+    /// is a jumbogram.
+    ///
+    /// A jumbogram sets the payload length to zero and carries the actual
+    /// length in the Jumbo Payload option of the Hop-by-Hop Options header,
+    /// based on [IETF RFC 2675].
+    ///
+    /// [IETF RFC 2675]: https://tools.ietf.org/html/rfc2675
+    pub fn jumbo_payload_length(&self) -> Option<u32> {
+        if self.payload_length() != 0 || self.next_header() != ProtocolNumbers::Ipv6HopByHop {
+            return None;
+        }
+
+        self.peek::<HopByHop<Ipv6>>()
+            .ok()
+            .and_then(|hbh| hbh.jumbo_payload_length().ok().flatten())
+    }
+
+    /// Returns the offset in the buffer where the payload ends.
+    ///
+    /// The end is computed from the payload length, or from the jumbo
+    /// payload length if the packet is a jumbogram. Any data in the buffer
+    /// beyond the end, such as Ethernet padding, is not part of the packet.
+    #[inline]
+    pub fn payload_end(&self) -> usize {
+        let len = match self.jumbo_payload_length() {
+            Some(len) => len as usize,
+            None => self.payload_length() as usize,
+        };
+        self.payload_offset() + len
+    }
+
+    /// Returns the extension headers of the packet in order, as pairs of
+    /// the next header value that identifies the header and the length of
+    /// the header in octets.
+    ///
+    /// The walk starts with the next header of the IPv6 header and stops
+    /// at the first header that is not an extension header, usually the
+    /// upper-layer protocol. It also stops if an extension header does not
+    /// fit in the buffer.
+    ///
+    /// The Fragment header has a fixed length of 8 octets, and the length
+    /// of the Authentication header is measured in 4-octet units, as
+    /// defined in [IETF RFC 4302].
+    ///
+    /// [IETF RFC 4302]: https://tools.ietf.org/html/rfc4302#section-2.2
+    pub fn extension_headers(&self) -> Vec<(u8, usize)> {
+        let mut headers = Vec::new();
+        let mut next_header = self.next_header();
+        let mut offset = self.payload_offset();
+
+        while let Ok(data) = self.mbuf().read_data_slice::<u8>(offset, 2) {
+            let data = unsafe { data.as_ref() };
+            let len = match next_header {
+                ProtocolNumbers::Ipv6HopByHop
+                | ProtocolNumbers::Ipv6Route
+                | ProtocolNumbers::Ipv6Opts => (data[1] as usize + 1) * 8,
+                ProtocolNumbers::Ipv6Frag => 8,
+                ProtocolNumbers::Ah => (data[1] as usize + 2) * 4,
+                _ => break,
+            };
+
+            if offset + len > self.mbuf().data_len() {
+                break;
+            }
+
+            headers.push((next_header.0, len));
+            next_header = ProtocolNumber::new(data[0]);
+            offset += len;
+        }
+
+        headers
+    }
+
     /// Returns the packet's hop limit.
     #[inline]
     pub fn hop_limit(&self) -> u8 {
@@ -324,13 +399,16 @@ impl Packet for Ipv6 {
     /// the packet.
     ///
     /// * [`payload_length`] is set to the length of the payload which includes
-    /// any extension headers present.
+    /// any extension headers present. It is left at zero if the packet is a
+    /// jumbogram.
     ///
     /// [`payload_length`]: Ipv6::payload_length
     #[inline]
     fn reconcile(&mut self) {
-        let len = self.payload_len() as u16;
-        self.set_payload_length(len);
+        if self.jumbo_payload_length().is_none() {
+            let len = self.payload_len() as u16;
+            self.set_payload_length(len);
+        }
     }
 }
 
@@ -429,6 +507,15 @@ impl Ipv6Packet for Ipv6 {
     }
 }
 
+impl ValidateLength for Ipv6 {
+    /// Returns whether the payload length, or the jumbo payload length if
+    /// the packet is a jumbogram, does not exceed the data in the buffer.
+    #[inline]
+    fn validate_length(&self) -> bool {
+        self.payload_end() <= self.mbuf().data_len()
+    }
+}
+
 /// A trait implemented by IPv6 and extension packets.
 pub trait Ipv6Packet: IpPacket {
     /// Returns the next header type.
@@ -466,8 +553,9 @@ impl Default for Ipv6Header {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::packets::ip::ProtocolNumbers;
-    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, IPV6_TCP_PACKET};
+    use crate::packets::ip::{ProtocolNumbers, ECN_ECT1};
+    use crate::packets::Udp;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, IPV6_JUMBOGRAM_PACKET, IPV6_TCP_PACKET};
     use crate::Mbuf;
 
     #[test]
@@ -492,6 +580,16 @@ mod tests {
         assert_eq!("2001:db8:85a3::8a2e:370:7334", ipv6.dst().to_string());
     }
 
+    #[capsule::test]
+    fn next_protocol_of_ipv6_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.peek::<Ipv6>().unwrap();
+
+        assert_eq!(ProtocolNumber::new(6), ipv6.next_protocol());
+        assert_eq!(ProtocolNumbers::Tcp, ipv6.next_protocol());
+    }
+
     #[capsule::test]
     fn parse_non_ipv6_packet() {
         let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
@@ -518,6 +616,44 @@ mod tests {
         assert_eq!(0, ipv6.flow_label());
     }
 
+    #[capsule::test]
+    fn mark_congestion_of_ipv6_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert!(!ipv6.mark_congestion());
+        assert_eq!(ECN_NOT_ECT, ipv6.ecn());
+
+        ipv6.set_ecn(ECN_ECT1);
+        assert!(ipv6.mark_congestion());
+        assert_eq!(ECN_CE, ipv6.ecn());
+        assert_eq!(0, ipv6.dscp());
+        assert_eq!(0, ipv6.flow_label());
+    }
+
+    #[capsule::test]
+    fn ipv6_addrs_round_trip() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        // addresses are `std::net` types, both directly and as `IpAddr`.
+        let src = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        ipv6.set_src(src);
+        assert_eq!(src, ipv6.src());
+        assert_eq!(IpAddr::V6(src), IpPacket::src(&ipv6));
+
+        let dst: IpAddr = "2001:db8::2".parse().unwrap();
+        IpPacket::set_dst(&mut ipv6, dst).unwrap();
+        assert_eq!(dst, IpAddr::V6(ipv6.dst()));
+        assert_eq!("2001:db8::2", ipv6.dst().to_string());
+
+        // mismatched address family is rejected.
+        assert!(IpPacket::set_src(&mut ipv6, "10.0.0.1".parse().unwrap()).is_err());
+        assert_eq!(src, ipv6.src());
+    }
+
     #[capsule::test]
     fn push_ipv6_packet() {
         let packet = Mbuf::new().unwrap();
@@ -547,4 +683,67 @@ mod tests {
         let _ = ipv6.truncate(1500);
         assert_eq!(1500, ipv6.len());
     }
+
+    #[capsule::test]
+    fn list_ipv6_extension_headers() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv6 = ethernet.push::<Ipv6>().unwrap();
+        let hbh = ipv6.push::<HopByHop<Ipv6>>().unwrap();
+        let srh = hbh.push::<SegmentRouting<HopByHop<Ipv6>>>().unwrap();
+        let udp = srh.push::<Udp<SegmentRouting<HopByHop<Ipv6>>>>().unwrap();
+
+        let packet = udp.reset();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert_eq!(
+            vec![
+                (ProtocolNumbers::Ipv6HopByHop.0, 8),
+                (ProtocolNumbers::Ipv6Route.0, 24),
+            ],
+            ipv6.extension_headers()
+        );
+    }
+
+    #[capsule::test]
+    fn no_ipv6_extension_headers() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert!(ipv6.extension_headers().is_empty());
+    }
+
+    #[capsule::test]
+    fn payload_end_of_ipv6_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert_eq!(None, ipv6.jumbo_payload_length());
+        assert_eq!(14 + 40 + 24, ipv6.payload_end());
+        assert!(ipv6.validate_length());
+    }
+
+    #[capsule::test]
+    fn payload_end_of_ipv6_jumbogram() {
+        // appends trailing bytes beyond the jumbo payload.
+        let mut bytes = IPV6_JUMBOGRAM_PACKET.to_vec();
+        bytes.extend_from_slice(&[0; 6]);
+
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert_eq!(0, ipv6.payload_length());
+        assert_eq!(Some(28), ipv6.jumbo_payload_length());
+        assert_eq!(34, ipv6.payload_len());
+        assert_eq!(14 + 40 + 28, ipv6.payload_end());
+        assert!(ipv6.validate_length());
+
+        // the payload length stays at zero for a jumbogram.
+        ipv6.reconcile();
+        assert_eq!(0, ipv6.payload_length());
+    }
 }
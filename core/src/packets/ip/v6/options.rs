@@ -0,0 +1,658 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::packets::checksum::PseudoHeader;
+use crate::packets::ip::v6::Ipv6Packet;
+use crate::packets::ip::{IpPacket, ProtocolNumber, ProtocolNumbers};
+use crate::packets::{Internal, Packet};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::IpAddr;
+use std::ptr::NonNull;
+
+/// Generates an IPv6 extension header packet type that carries options.
+///
+/// The Hop-by-Hop Options and the Destination Options headers share the
+/// same format and only differ in the protocol number that identifies
+/// them.
+macro_rules! options_header {
+    (
+        $(#[$meta:meta])*
+        $name:ident, $proto:expr, $label:literal
+    ) => {
+        $(#[$meta])*
+        pub struct $name<E: Ipv6Packet> {
+            envelope: E,
+            header: NonNull<OptionsHeader>,
+            offset: usize,
+        }
+
+        impl<E: Ipv6Packet> $name<E> {
+            #[inline]
+            fn header(&self) -> &OptionsHeader {
+                unsafe { self.header.as_ref() }
+            }
+
+            #[inline]
+            fn header_mut(&mut self) -> &mut OptionsHeader {
+                unsafe { self.header.as_mut() }
+            }
+
+            /// Returns the length of the header in 8-octet units, not
+            /// including the first 8 octets.
+            #[inline]
+            pub fn hdr_ext_len(&self) -> u8 {
+                self.header().hdr_ext_len
+            }
+
+            /// Returns an iterator that iterates through the options.
+            #[inline]
+            pub fn options(&self) -> Ipv6OptionsIterator<'_> {
+                Ipv6OptionsIterator {
+                    mbuf: self.mbuf(),
+                    offset: self.offset + OptionsHeader::size_of(),
+                    end: self.payload_offset(),
+                }
+            }
+
+            /// Returns the data of the first option with the option type.
+            ///
+            /// # Errors
+            ///
+            /// Returns an error if an option is malformed.
+            pub fn option(&self, option_type: Ipv6OptionType) -> Result<Option<&[u8]>> {
+                let mut iter = self.options();
+                while let Some(option) = iter.next()? {
+                    if option.option_type() == option_type {
+                        return Ok(Some(option.data()));
+                    }
+                }
+                Ok(None)
+            }
+        }
+
+        impl<E: Ipv6Packet> fmt::Debug for $name<E> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct($label)
+                    .field("next_header", &format!("{}", self.next_header()))
+                    .field("hdr_ext_len", &self.hdr_ext_len())
+                    .finish()
+            }
+        }
+
+        impl<E: Ipv6Packet> Packet for $name<E> {
+            /// The preceding type can be either an IPv6 packet or any
+            /// possible IPv6 extension packets.
+            type Envelope = E;
+
+            #[inline]
+            fn envelope(&self) -> &Self::Envelope {
+                &self.envelope
+            }
+
+            #[inline]
+            fn envelope_mut(&mut self) -> &mut Self::Envelope {
+                &mut self.envelope
+            }
+
+            #[inline]
+            fn offset(&self) -> usize {
+                self.offset
+            }
+
+            /// Returns the length of the header, including the options.
+            #[inline]
+            fn header_len(&self) -> usize {
+                (self.hdr_ext_len() as usize + 1) * 8
+            }
+
+            #[inline]
+            unsafe fn clone(&self, internal: Internal) -> Self {
+                $name {
+                    envelope: self.envelope.clone(internal),
+                    header: self.header,
+                    offset: self.offset,
+                }
+            }
+
+            /// Parses the envelope's payload as an options extension
+            /// header.
+            ///
+            /// # Errors
+            ///
+            /// Returns an error if the envelope's next header is not the
+            /// protocol number of the extension header. Returns an error if
+            /// the payload does not have sufficient data for the header,
+            /// including the options.
+            #[inline]
+            fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+                ensure!(
+                    envelope.next_header() == $proto,
+                    anyhow!(concat!("not an IPv6 ", $label, " packet."))
+                );
+
+                let mbuf = envelope.mbuf();
+                let offset = envelope.payload_offset();
+                let header = mbuf.read_data(offset)?;
+
+                let packet = $name {
+                    envelope,
+                    header,
+                    offset,
+                };
+
+                // makes sure the options are in the buffer.
+                let _ = packet
+                    .mbuf()
+                    .read_data_slice::<u8>(offset, packet.header_len())?;
+
+                Ok(packet)
+            }
+
+            /// Prepends an options extension header to the beginning of
+            /// the envelope's payload.
+            ///
+            /// The header is 8 octets long, with a PadN option filling the
+            /// space for options. The next header is set to the value of
+            /// the `next_header` field of the envelope, and the envelope's
+            /// next header is set to the protocol number of the extension
+            /// header.
+            ///
+            /// # Errors
+            ///
+            /// Returns an error if the buffer does not have enough free
+            /// space.
+            #[inline]
+            fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+                let offset = envelope.payload_offset();
+                let mbuf = envelope.mbuf_mut();
+
+                mbuf.extend(offset, 8)?;
+                let header = mbuf.write_data(offset, &OptionsHeader::default())?;
+                // PadN with 4 octets of padding.
+                let _ = mbuf.write_data_slice(offset + OptionsHeader::size_of(), &[1u8, 4])?;
+
+                let mut packet = $name {
+                    envelope,
+                    header,
+                    offset,
+                };
+
+                packet.set_next_header(packet.envelope().next_header());
+                packet.envelope_mut().set_next_header($proto);
+
+                Ok(packet)
+            }
+
+            /// Removes the options extension header from the message
+            /// buffer.
+            ///
+            /// The envelope's [`next_header`] field is set to the value of
+            /// the `next_header` field of the extension header.
+            ///
+            /// # Errors
+            ///
+            /// Returns an error if the buffer does not have sufficient data
+            /// to remove.
+            ///
+            /// [`next_header`]: Ipv6Packet::next_header
+            #[inline]
+            fn remove(mut self) -> Result<Self::Envelope> {
+                let offset = self.offset();
+                let len = self.header_len();
+                let next_header = self.next_header();
+                self.mbuf_mut().shrink(offset, len)?;
+                self.envelope_mut().set_next_header(next_header);
+                Ok(self.envelope)
+            }
+
+            #[inline]
+            fn deparse(self) -> Self::Envelope {
+                self.envelope
+            }
+        }
+
+        impl<E: Ipv6Packet> IpPacket for $name<E> {
+            #[inline]
+            fn next_protocol(&self) -> ProtocolNumber {
+                self.next_header()
+            }
+
+            #[inline]
+            fn set_next_protocol(&mut self, proto: ProtocolNumber) {
+                self.set_next_header(proto);
+            }
+
+            #[inline]
+            fn src(&self) -> IpAddr {
+                self.envelope().src()
+            }
+
+            #[inline]
+            fn set_src(&mut self, src: IpAddr) -> Result<()> {
+                self.envelope_mut().set_src(src)
+            }
+
+            #[inline]
+            fn dst(&self) -> IpAddr {
+                self.envelope().dst()
+            }
+
+            #[inline]
+            fn set_dst(&mut self, dst: IpAddr) -> Result<()> {
+                self.envelope_mut().set_dst(dst)
+            }
+
+            #[inline]
+            fn pseudo_header(&self, packet_len: u16, protocol: ProtocolNumber) -> PseudoHeader {
+                self.envelope().pseudo_header(packet_len, protocol)
+            }
+
+            #[inline]
+            fn truncate(&mut self, mtu: usize) -> Result<()> {
+                self.envelope_mut().truncate(mtu)
+            }
+        }
+
+        impl<E: Ipv6Packet> Ipv6Packet for $name<E> {
+            #[inline]
+            fn next_header(&self) -> ProtocolNumber {
+                ProtocolNumber::new(self.header().next_header)
+            }
+
+            #[inline]
+            fn set_next_header(&mut self, next_header: ProtocolNumber) {
+                self.header_mut().next_header = next_header.0;
+            }
+        }
+    };
+}
+
+options_header! {
+    /// IPv6 Hop-by-Hop Options extension packet based on [IETF RFC 8200].
+    ///
+    /// ```
+    ///  0                   1                   2                   3
+    ///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+    /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    /// |  Next Header  |  Hdr Ext Len  |                               |
+    /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               +
+    /// |                                                               |
+    /// .                                                               .
+    /// .                            Options                            .
+    /// .                                                               .
+    /// |                                                               |
+    /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    /// ```
+    ///
+    /// - *Next Header*:      8-bit selector. Identifies the type of header
+    ///                       immediately following the Hop-by-Hop Options
+    ///                       header.
+    ///
+    /// - *Hdr Ext Len*:      8-bit unsigned integer. Length of the
+    ///                       Hop-by-Hop Options header in 8-octet units,
+    ///                       not including the first 8 octets.
+    ///
+    /// - *Options*:          Variable-length field, of length such that the
+    ///                       complete Hop-by-Hop Options header is an
+    ///                       integer multiple of 8 octets long.
+    ///
+    /// The options are examined by every node along the delivery path. The
+    /// header must immediately follow the IPv6 header.
+    ///
+    /// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.3
+    HopByHop, ProtocolNumbers::Ipv6HopByHop, "hop-by-hop"
+}
+
+options_header! {
+    /// IPv6 Destination Options extension packet based on [IETF RFC 8200].
+    ///
+    /// ```
+    ///  0                   1                   2                   3
+    ///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+    /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    /// |  Next Header  |  Hdr Ext Len  |                               |
+    /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               +
+    /// |                                                               |
+    /// .                                                               .
+    /// .                            Options                            .
+    /// .                                                               .
+    /// |                                                               |
+    /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    /// ```
+    ///
+    /// - *Next Header*:      8-bit selector. Identifies the type of header
+    ///                       immediately following the Destination Options
+    ///                       header.
+    ///
+    /// - *Hdr Ext Len*:      8-bit unsigned integer. Length of the
+    ///                       Destination Options header in 8-octet units,
+    ///                       not including the first 8 octets.
+    ///
+    /// - *Options*:          Variable-length field, of length such that the
+    ///                       complete Destination Options header is an
+    ///                       integer multiple of 8 octets long.
+    ///
+    /// The options are only examined by the destination node, or by each
+    /// node listed in a routing header that follows.
+    ///
+    /// [IETF RFC 8200]: https://tools.ietf.org/html/rfc8200#section-4.6
+    DestOpts, ProtocolNumbers::Ipv6Opts, "destination options"
+}
+
+impl<E: Ipv6Packet> HopByHop<E> {
+    /// Returns the length of the jumbogram payload in octets, or `None` if
+    /// there is no Jumbo Payload option.
+    ///
+    /// The length excludes the IPv6 header, but includes the Hop-by-Hop
+    /// Options header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an option is malformed.
+    pub fn jumbo_payload_length(&self) -> Result<Option<u32>> {
+        match self.option(Ipv6OptionTypes::JumboPayload)? {
+            Some(&[a, b, c, d]) => Ok(Some(u32::from_be_bytes([a, b, c, d]))),
+            Some(_) => Err(anyhow!("invalid IPv6 Jumbo Payload option length.")),
+            None => Ok(None),
+        }
+    }
+}
+
+/// IPv6 option type.
+///
+/// The highest-order two bits specify the action taken by a node that does
+/// not recognize the option, and the third-highest-order bit specifies
+/// whether the option data can change en route.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct Ipv6OptionType(pub u8);
+
+impl Ipv6OptionType {
+    /// Creates a new option type.
+    pub fn new(value: u8) -> Self {
+        Ipv6OptionType(value)
+    }
+
+    /// Returns the action to take if the option is not recognized.
+    #[inline]
+    pub fn action(self) -> Ipv6OptionAction {
+        match self.0 >> 6 {
+            0 => Ipv6OptionAction::Skip,
+            1 => Ipv6OptionAction::Discard,
+            2 => Ipv6OptionAction::DiscardAndReport,
+            _ => Ipv6OptionAction::DiscardAndReportUnicast,
+        }
+    }
+
+    /// Returns whether the option data may change en route.
+    #[inline]
+    pub fn may_change(self) -> bool {
+        self.0 & 0b0010_0000 != 0
+    }
+}
+
+/// Supported IPv6 option types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod Ipv6OptionTypes {
+    use super::Ipv6OptionType;
+
+    /// Pad1, a single octet of padding.
+    pub const Pad1: Ipv6OptionType = Ipv6OptionType(0x00);
+    /// PadN, two or more octets of padding.
+    pub const PadN: Ipv6OptionType = Ipv6OptionType(0x01);
+    /// Tunnel encapsulation limit.
+    pub const TunnelEncapsulationLimit: Ipv6OptionType = Ipv6OptionType(0x04);
+    /// Router alert.
+    pub const RouterAlert: Ipv6OptionType = Ipv6OptionType(0x05);
+    /// Jumbo payload.
+    pub const JumboPayload: Ipv6OptionType = Ipv6OptionType(0xC2);
+}
+
+impl fmt::Display for Ipv6OptionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Ipv6OptionTypes::Pad1 => "Pad1".to_string(),
+                Ipv6OptionTypes::PadN => "PadN".to_string(),
+                Ipv6OptionTypes::TunnelEncapsulationLimit => {
+                    "Tunnel Encapsulation Limit".to_string()
+                }
+                Ipv6OptionTypes::RouterAlert => "Router Alert".to_string(),
+                Ipv6OptionTypes::JumboPayload => "Jumbo Payload".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("0x{:02x}", t)
+                }
+            }
+        )
+    }
+}
+
+/// The action a node takes when it does not recognize an option.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Ipv6OptionAction {
+    /// Skips over the option and continues processing the header.
+    Skip,
+    /// Discards the packet.
+    Discard,
+    /// Discards the packet and sends an ICMP Parameter Problem message to
+    /// the source.
+    DiscardAndReport,
+    /// Discards the packet and sends an ICMP Parameter Problem message to
+    /// the source, only if the destination is not a multicast address.
+    DiscardAndReportUnicast,
+}
+
+/// An IPv6 option.
+pub struct Ipv6Option<'a> {
+    option_type: Ipv6OptionType,
+    data: &'a [u8],
+}
+
+impl<'a> Ipv6Option<'a> {
+    /// Returns the option type.
+    #[inline]
+    pub fn option_type(&self) -> Ipv6OptionType {
+        self.option_type
+    }
+
+    /// Returns the length of the option data in octets.
+    #[inline]
+    pub fn length(&self) -> u8 {
+        self.data.len() as u8
+    }
+
+    /// Returns the option data, excluding the type and the length.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl fmt::Debug for Ipv6Option<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ipv6Option")
+            .field("option_type", &format!("{}", self.option_type()))
+            .field("length", &self.length())
+            .field("data", &self.data())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the options of an IPv6 Hop-by-Hop
+/// or Destination Options header.
+///
+/// The Pad1 and PadN options are skipped and not returned.
+pub struct Ipv6OptionsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> Ipv6OptionsIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<Ipv6Option<'a>>> {
+        while self.offset < self.end {
+            let option_type = unsafe { *self.mbuf.read_data::<u8>(self.offset)?.as_ref() };
+            let option_type = Ipv6OptionType::new(option_type);
+
+            if option_type == Ipv6OptionTypes::Pad1 {
+                self.offset += 1;
+                continue;
+            }
+
+            // marks the iteration finished so a malformed option is only
+            // reported once.
+            let offset = self.offset;
+            self.offset = self.end;
+
+            ensure!(
+                offset + 2 <= self.end,
+                anyhow!("truncated IPv6 {} option.", option_type)
+            );
+            let len = unsafe { *self.mbuf.read_data::<u8>(offset + 1)?.as_ref() } as usize;
+            ensure!(
+                offset + 2 + len <= self.end,
+                anyhow!("invalid IPv6 {} option length {}.", option_type, len)
+            );
+
+            self.offset = offset + 2 + len;
+
+            if option_type == Ipv6OptionTypes::PadN {
+                continue;
+            }
+
+            let data: &'a [u8] = if len > 0 {
+                let data = self.mbuf.read_data_slice::<u8>(offset + 2, len)?;
+                unsafe { &*data.as_ptr() }
+            } else {
+                &[]
+            };
+
+            return Ok(Some(Ipv6Option { option_type, data }));
+        }
+
+        Ok(None)
+    }
+}
+
+impl fmt::Debug for Ipv6OptionsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ipv6OptionsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// IPv6 Hop-by-Hop and Destination Options extension header, without the
+/// options.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct OptionsHeader {
+    next_header: u8,
+    hdr_ext_len: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::{Ethernet, Udp};
+    use crate::testils::byte_arrays::{IPV6_JUMBOGRAM_PACKET, IPV6_TCP_PACKET};
+
+    #[test]
+    fn size_of_options_header() {
+        assert_eq!(2, OptionsHeader::size_of());
+    }
+
+    #[test]
+    fn ipv6_option_type_action() {
+        assert_eq!(
+            Ipv6OptionAction::Skip,
+            Ipv6OptionTypes::RouterAlert.action()
+        );
+        assert_eq!(
+            Ipv6OptionAction::DiscardAndReportUnicast,
+            Ipv6OptionTypes::JumboPayload.action()
+        );
+        assert!(!Ipv6OptionTypes::JumboPayload.may_change());
+        assert!(Ipv6OptionType::new(0x3e).may_change());
+    }
+
+    #[capsule::test]
+    fn parse_hop_by_hop_jumbo_payload() {
+        let packet = Mbuf::from_bytes(&IPV6_JUMBOGRAM_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let hbh = ipv6.parse::<HopByHop<Ipv6>>().unwrap();
+
+        assert_eq!(ProtocolNumbers::Udp, hbh.next_header());
+        assert_eq!(0, hbh.hdr_ext_len());
+        assert_eq!(8, hbh.header_len());
+
+        let mut iter = hbh.options();
+        let option = iter.next().unwrap().unwrap();
+        assert_eq!(Ipv6OptionTypes::JumboPayload, option.option_type());
+        assert_eq!(4, option.length());
+        assert!(iter.next().unwrap().is_none());
+
+        assert_eq!(Some(28), hbh.jumbo_payload_length().unwrap());
+
+        let udp = hbh.parse::<Udp<HopByHop<Ipv6>>>().unwrap();
+        assert_eq!(5678, udp.dst_port());
+    }
+
+    #[capsule::test]
+    fn parse_non_hop_by_hop_packet() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+
+        assert!(ipv6.peek::<HopByHop<Ipv6>>().is_err());
+        assert!(ipv6.parse::<DestOpts<Ipv6>>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_and_remove_destination_options() {
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let payload_len = ipv6.payload_len();
+
+        let opts = ipv6.push::<DestOpts<Ipv6>>().unwrap();
+        assert_eq!(ProtocolNumbers::Ipv6Opts, opts.envelope().next_header());
+        assert_eq!(ProtocolNumbers::Tcp, opts.next_header());
+        assert_eq!(8, opts.header_len());
+        assert_eq!(payload_len, opts.payload_len());
+        // the header is filled with padding.
+        assert!(opts.options().next().unwrap().is_none());
+
+        let ipv6 = opts.remove().unwrap();
+        assert_eq!(ProtocolNumbers::Tcp, ipv6.next_header());
+        assert_eq!(payload_len, ipv6.payload_len());
+    }
+}
@@ -0,0 +1,265 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Serializing parsed packets to JSON.
+//!
+//! Each decoded layer becomes a nested object keyed by the protocol name,
+//! with the header fields named and the addresses and ports in their
+//! human-readable form.
+//!
+//! ```json
+//! {
+//!   "ethernet": { "src": "00:00:00:00:00:02", ... },
+//!   "ipv4": { "src": "139.133.217.110", ... },
+//!   "udp": { "src_port": 39376, ... }
+//! }
+//! ```
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::{IpPacket, ProtocolNumbers};
+use crate::packets::{EtherTypes, Ethernet, Packet, Tcp, Udp};
+use crate::Mbuf;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Default, Serialize)]
+struct PacketRecord {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ethernet: Option<EthernetRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipv4: Option<Ipv4Record>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipv6: Option<Ipv6Record>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tcp: Option<TcpRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    udp: Option<UdpRecord>,
+}
+
+#[derive(Debug, Serialize)]
+struct EthernetRecord {
+    src: String,
+    dst: String,
+    ether_type: String,
+    dot1q: bool,
+    qinq: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Ipv4Record {
+    src: String,
+    dst: String,
+    dscp: u8,
+    ecn: u8,
+    total_length: u16,
+    identification: u16,
+    dont_fragment: bool,
+    more_fragments: bool,
+    fragment_offset: u16,
+    ttl: u8,
+    protocol: String,
+    checksum: u16,
+}
+
+#[derive(Debug, Serialize)]
+struct Ipv6Record {
+    src: String,
+    dst: String,
+    dscp: u8,
+    ecn: u8,
+    flow_label: u32,
+    payload_length: u16,
+    next_header: String,
+    hop_limit: u8,
+}
+
+#[derive(Debug, Serialize)]
+struct TcpRecord {
+    src_port: u16,
+    dst_port: u16,
+    seq_no: u32,
+    ack_no: u32,
+    data_offset: u8,
+    flags: Vec<&'static str>,
+    window: u16,
+    checksum: u16,
+    urgent_pointer: u16,
+}
+
+#[derive(Debug, Serialize)]
+struct UdpRecord {
+    src_port: u16,
+    dst_port: u16,
+    length: u16,
+    checksum: u16,
+}
+
+/// Serializes the packet to a JSON value.
+///
+/// The packet is parsed starting with Ethernet. The known layers are
+/// Ethernet, IPv4, IPv6, TCP and UDP. Layers after the first unknown or
+/// malformed layer are not included.
+///
+/// # Errors
+///
+/// Returns an error if the packet is not an Ethernet frame.
+pub fn to_value(mbuf: &Mbuf) -> Result<Value> {
+    let mut record = PacketRecord::default();
+
+    let ethernet = mbuf.peek::<Ethernet>()?;
+    record.ethernet = Some(EthernetRecord {
+        src: ethernet.src().to_string(),
+        dst: ethernet.dst().to_string(),
+        ether_type: ethernet.ether_type().to_string(),
+        dot1q: ethernet.is_dot1q(),
+        qinq: ethernet.is_qinq(),
+    });
+
+    match ethernet.ether_type() {
+        EtherTypes::Ipv4 => {
+            if let Ok(ipv4) = ethernet.peek::<Ipv4>() {
+                record.ipv4 = Some(Ipv4Record {
+                    src: ipv4.src().to_string(),
+                    dst: ipv4.dst().to_string(),
+                    dscp: ipv4.dscp(),
+                    ecn: ipv4.ecn(),
+                    total_length: ipv4.total_length(),
+                    identification: ipv4.identification(),
+                    dont_fragment: ipv4.dont_fragment(),
+                    more_fragments: ipv4.more_fragments(),
+                    fragment_offset: ipv4.fragment_offset(),
+                    ttl: ipv4.ttl(),
+                    protocol: ipv4.protocol().to_string(),
+                    checksum: ipv4.checksum(),
+                });
+                transport(&*ipv4, &mut record);
+            }
+        }
+        EtherTypes::Ipv6 => {
+            if let Ok(ipv6) = ethernet.peek::<Ipv6>() {
+                record.ipv6 = Some(Ipv6Record {
+                    src: ipv6.src().to_string(),
+                    dst: ipv6.dst().to_string(),
+                    dscp: ipv6.dscp(),
+                    ecn: ipv6.ecn(),
+                    flow_label: ipv6.flow_label(),
+                    payload_length: ipv6.payload_length(),
+                    next_header: ipv6.next_protocol().to_string(),
+                    hop_limit: ipv6.hop_limit(),
+                });
+                transport(&*ipv6, &mut record);
+            }
+        }
+        _ => (),
+    }
+
+    Ok(serde_json::to_value(record)?)
+}
+
+/// Serializes the packet to a string of JSON.
+///
+/// # Errors
+///
+/// Returns an error if the packet is not an Ethernet frame.
+pub fn to_string(mbuf: &Mbuf) -> Result<String> {
+    Ok(serde_json::to_string(&to_value(mbuf)?)?)
+}
+
+fn transport<E: IpPacket>(ip: &E, record: &mut PacketRecord) {
+    match ip.next_protocol() {
+        ProtocolNumbers::Tcp => {
+            if let Ok(tcp) = ip.peek::<Tcp<E>>() {
+                let flags = [
+                    (tcp.ns(), "ns"),
+                    (tcp.cwr(), "cwr"),
+                    (tcp.ece(), "ece"),
+                    (tcp.urg(), "urg"),
+                    (tcp.ack(), "ack"),
+                    (tcp.psh(), "psh"),
+                    (tcp.rst(), "rst"),
+                    (tcp.syn(), "syn"),
+                    (tcp.fin(), "fin"),
+                ];
+
+                record.tcp = Some(TcpRecord {
+                    src_port: tcp.src_port(),
+                    dst_port: tcp.dst_port(),
+                    seq_no: tcp.seq_no(),
+                    ack_no: tcp.ack_no(),
+                    data_offset: tcp.data_offset(),
+                    flags: flags
+                        .iter()
+                        .filter(|(set, _)| *set)
+                        .map(|(_, flag)| *flag)
+                        .collect(),
+                    window: tcp.window(),
+                    checksum: tcp.checksum(),
+                    urgent_pointer: tcp.urgent_pointer(),
+                });
+            }
+        }
+        ProtocolNumbers::Udp => {
+            if let Ok(udp) = ip.peek::<Udp<E>>() {
+                record.udp = Some(UdpRecord {
+                    src_port: udp.src_port(),
+                    dst_port: udp.dst_port(),
+                    length: udp.length(),
+                    checksum: udp.checksum(),
+                });
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testils::byte_arrays::{ARP4_PACKET, IPV4_UDP_PACKET};
+
+    #[capsule::test]
+    fn serialize_udp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let json = to_value(&packet).unwrap();
+
+        assert_eq!("00:00:00:00:00:02", json["ethernet"]["src"]);
+        assert_eq!("IPv4", json["ethernet"]["ether_type"]);
+        assert_eq!("139.133.217.110", json["ipv4"]["src"]);
+        assert_eq!("139.133.233.2", json["ipv4"]["dst"]);
+        assert_eq!("UDP", json["ipv4"]["protocol"]);
+        assert_eq!(39376, json["udp"]["src_port"]);
+        assert_eq!(1087, json["udp"]["dst_port"]);
+        assert_eq!(18, json["udp"]["length"]);
+        assert!(json.get("ipv6").is_none());
+        assert!(json.get("tcp").is_none());
+
+        let string = to_string(&packet).unwrap();
+        assert!(string.starts_with(r#"{"ethernet":{"src":"00:00:00:00:00:02""#));
+    }
+
+    #[capsule::test]
+    fn serialize_non_ip_packet() {
+        let packet = Mbuf::from_bytes(&ARP4_PACKET).unwrap();
+        let json = to_value(&packet).unwrap();
+
+        assert_eq!("ARP", json["ethernet"]["ether_type"]);
+        assert!(json.get("ipv4").is_none());
+    }
+}
@@ -0,0 +1,333 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Walking and summarizing the header chain of a packet without knowing
+//! its types.
+
+use crate::packets::arp::Arp4;
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::{IpPacket, ProtocolNumbers};
+use crate::packets::{EtherTypes, Ethernet, Packet, Tcp, Udp};
+use crate::Mbuf;
+use anyhow::Result;
+use std::fmt;
+use std::vec;
+
+/// A description of a parsed layer of a packet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Layer {
+    name: &'static str,
+    offset: usize,
+    len: usize,
+    summary: String,
+}
+
+impl Layer {
+    pub(crate) fn new<T: Packet>(name: &'static str, packet: &T, summary: String) -> Self {
+        Layer {
+            name,
+            offset: packet.offset(),
+            len: packet.header_len(),
+            summary,
+        }
+    }
+
+    /// Moves the layer's offset further into the buffer by `delta` bytes.
+    pub(crate) fn shift(mut self, delta: usize) -> Self {
+        self.offset += delta;
+        self
+    }
+
+    /// Returns the name of the protocol.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the buffer offset where the layer's header starts.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the layer's header.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the layer's header is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.summary)
+    }
+}
+
+/// An iterator over the layers of a packet.
+///
+/// The packet is parsed starting with Ethernet, dispatching on the ether
+/// type and the IP next protocol to determine the next layer. The walk
+/// stops at the first layer that is either unknown or fails to parse,
+/// leaving the rest of the packet as the opaque payload of the last layer.
+///
+/// The known layers are Ethernet, ARP, IPv4, IPv6, TCP and UDP.
+///
+/// # Example
+///
+/// ```
+/// for layer in LayerIterator::new(&mbuf) {
+///     println!("{}: {}", layer.offset(), layer);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LayerIterator {
+    layers: vec::IntoIter<Layer>,
+}
+
+impl LayerIterator {
+    /// Creates a new iterator over the layers of the packet.
+    pub fn new(mbuf: &Mbuf) -> Self {
+        let mut layers = Vec::new();
+        let _ = walk_ethernet(mbuf, &mut layers);
+        LayerIterator {
+            layers: layers.into_iter(),
+        }
+    }
+}
+
+impl Iterator for LayerIterator {
+    type Item = Layer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.layers.next()
+    }
+}
+
+/// A tcpdump-style one-line summary of a packet.
+///
+/// The transport packets are summarized with the addresses of the IP
+/// envelope combined with the ports, for example
+/// `139.133.217.110.39376 > 139.133.233.2.1087: UDP length 10`.
+pub trait Summary {
+    /// Returns the one-line summary of the packet.
+    fn summarize(&self) -> String;
+}
+
+impl<E: IpPacket> Summary for Tcp<E> {
+    fn summarize(&self) -> String {
+        let mut flags = String::new();
+        for &(set, flag) in &[
+            (self.fin(), 'F'),
+            (self.syn(), 'S'),
+            (self.rst(), 'R'),
+            (self.psh(), 'P'),
+            (self.urg(), 'U'),
+            (self.ece(), 'E'),
+            (self.cwr(), 'W'),
+            (self.ack(), '.'),
+        ] {
+            if set {
+                flags.push(flag);
+            }
+        }
+
+        let ack = if self.ack() {
+            format!(", ack {}", self.ack_no())
+        } else {
+            String::new()
+        };
+
+        format!(
+            "{}.{} > {}.{}: Flags [{}], seq {}{}, win {}, length {}",
+            self.envelope().src(),
+            self.src_port(),
+            self.envelope().dst(),
+            self.dst_port(),
+            flags,
+            self.seq_no(),
+            ack,
+            self.window(),
+            self.payload_len()
+        )
+    }
+}
+
+impl<E: IpPacket> Summary for Udp<E> {
+    fn summarize(&self) -> String {
+        format!(
+            "{}.{} > {}.{}: UDP length {}",
+            self.envelope().src(),
+            self.src_port(),
+            self.envelope().dst(),
+            self.dst_port(),
+            self.payload_len()
+        )
+    }
+}
+
+fn walk_ethernet(mbuf: &Mbuf, layers: &mut Vec<Layer>) -> Result<()> {
+    let ethernet = mbuf.peek::<Ethernet>()?;
+    let summary = format!(
+        "{} > {}, ethertype {}",
+        ethernet.src(),
+        ethernet.dst(),
+        ethernet.ether_type()
+    );
+    layers.push(Layer::new("Ethernet", &*ethernet, summary));
+
+    match ethernet.ether_type() {
+        EtherTypes::Arp => {
+            let arp = ethernet.peek::<Arp4>()?;
+            let summary = format!(
+                "{}, {} > {}",
+                arp.operation_code(),
+                arp.sender_protocol_addr(),
+                arp.target_protocol_addr()
+            );
+            layers.push(Layer::new("ARP", &*arp, summary));
+            Ok(())
+        }
+        EtherTypes::Ipv4 => {
+            let ipv4 = ethernet.peek::<Ipv4>()?;
+            let summary = format!(
+                "{} > {}, proto {}, ttl {}",
+                ipv4.src(),
+                ipv4.dst(),
+                ipv4.protocol(),
+                ipv4.ttl()
+            );
+            layers.push(Layer::new("IPv4", &*ipv4, summary));
+            walk_transport(&*ipv4, layers)
+        }
+        EtherTypes::Ipv6 => {
+            let ipv6 = ethernet.peek::<Ipv6>()?;
+            let summary = format!(
+                "{} > {}, next header {}, hop limit {}",
+                ipv6.src(),
+                ipv6.dst(),
+                ipv6.next_protocol(),
+                ipv6.hop_limit()
+            );
+            layers.push(Layer::new("IPv6", &*ipv6, summary));
+            walk_transport(&*ipv6, layers)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn walk_transport<E: IpPacket>(ip: &E, layers: &mut Vec<Layer>) -> Result<()> {
+    match ip.next_protocol() {
+        ProtocolNumbers::Tcp => {
+            let tcp = ip.peek::<Tcp<E>>()?;
+            let summary = format!(
+                "{} > {}, seq {}, win {}",
+                tcp.src_port(),
+                tcp.dst_port(),
+                tcp.seq_no(),
+                tcp.window()
+            );
+            layers.push(Layer::new("TCP", &*tcp, summary));
+        }
+        ProtocolNumbers::Udp => {
+            let udp = ip.peek::<Udp<E>>()?;
+            let summary = format!(
+                "{} > {}, length {}",
+                udp.src_port(),
+                udp.dst_port(),
+                udp.length()
+            );
+            layers.push(Layer::new("UDP", &*udp, summary));
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testils::byte_arrays::{
+        ARP4_PACKET, IPV4_TCP_PACKET, IPV4_UDP_PACKET, IPV6_TCP_PACKET,
+    };
+
+    #[capsule::test]
+    fn iterate_udp_packet_layers() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let layers = LayerIterator::new(&packet).collect::<Vec<_>>();
+
+        let names = layers.iter().map(Layer::name).collect::<Vec<_>>();
+        assert_eq!(vec!["Ethernet", "IPv4", "UDP"], names);
+
+        assert_eq!(0, layers[0].offset());
+        assert_eq!(14, layers[0].len());
+        assert_eq!(14, layers[1].offset());
+        assert_eq!(20, layers[1].len());
+        assert_eq!(34, layers[2].offset());
+        assert_eq!(8, layers[2].len());
+        assert_eq!("UDP 39376 > 1087, length 18", layers[2].to_string());
+    }
+
+    #[capsule::test]
+    fn stop_at_unknown_layer() {
+        let packet = Mbuf::from_bytes(&ARP4_PACKET).unwrap();
+        let names = LayerIterator::new(&packet)
+            .map(|layer| layer.name())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["Ethernet", "ARP"], names);
+    }
+
+    #[capsule::test]
+    fn summarize_udp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert_eq!(
+            "139.133.217.110.39376 > 139.133.233.2.1087: UDP length 10",
+            udp.summarize()
+        );
+    }
+
+    #[capsule::test]
+    fn summarize_tcp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let tcp = ipv4.parse::<Tcp<Ipv4>>().unwrap();
+
+        assert_eq!(
+            "139.133.217.110.36869 > 139.133.233.2.23: Flags [S], seq 1913975060, win 8760, length 0",
+            tcp.summarize()
+        );
+
+        let packet = Mbuf::from_bytes(&IPV6_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let tcp = ipv6.parse::<Tcp<Ipv6>>().unwrap();
+
+        assert_eq!(
+            "2001:db8:85a3::1.36869 > 2001:db8:85a3::8a2e:370:7334.23: Flags [S], seq 1913975060, win 8760, length 0",
+            tcp.summarize()
+        );
+    }
+}
@@ -0,0 +1,567 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! IEEE 802.2 Logical Link Control.
+
+use crate::packets::types::u16be;
+use crate::packets::{EtherType, Ethernet, Internal, Packet};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The maximum value of the Ethernet type field that is a length.
+///
+/// An 802.3 frame has the payload length in place of the Ethernet type.
+pub const MAX_802_3_LENGTH: u16 = 1500;
+
+/// The LLC service access point of the subnetwork access protocol.
+pub const SNAP_SAP: u8 = 0xaa;
+
+// Unnumbered format control field.
+const CONTROL_U_FORMAT: u8 = 0b0000_0011;
+
+/// Logical Link Control header based on [IEEE 802.2].
+///
+/// The header follows the length field of an IEEE 802.3 frame.
+///
+/// ```
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     DSAP      |     SSAP      |    Control    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *DSAP*: (8 bits)
+///      The destination service access point.
+///
+/// - *SSAP*: (8 bits)
+///      The source service access point.
+///
+/// - *Control*: (8 or 16 bits)
+///      8 bits for the unnumbered format, and 16 bits for the information
+///      and the supervisory formats.
+///
+/// [IEEE 802.2]: https://standards.ieee.org/standard/802_2-1998.html
+pub struct Llc {
+    envelope: Ethernet,
+    header: NonNull<LlcHeader>,
+    offset: usize,
+}
+
+impl Llc {
+    #[inline]
+    fn header(&self) -> &LlcHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut LlcHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the destination service access point.
+    #[inline]
+    pub fn dsap(&self) -> u8 {
+        self.header().dsap
+    }
+
+    /// Sets the destination service access point.
+    #[inline]
+    pub fn set_dsap(&mut self, dsap: u8) {
+        self.header_mut().dsap = dsap;
+    }
+
+    /// Returns the source service access point.
+    #[inline]
+    pub fn ssap(&self) -> u8 {
+        self.header().ssap
+    }
+
+    /// Sets the source service access point.
+    #[inline]
+    pub fn set_ssap(&mut self, ssap: u8) {
+        self.header_mut().ssap = ssap;
+    }
+
+    /// Returns the first octet of the control field.
+    #[inline]
+    pub fn control(&self) -> u8 {
+        self.header().control
+    }
+
+    /// Returns whether the control field is of the unnumbered format, and
+    /// 8 bits long.
+    #[inline]
+    pub fn is_unnumbered(&self) -> bool {
+        self.control() & CONTROL_U_FORMAT == CONTROL_U_FORMAT
+    }
+}
+
+impl fmt::Debug for Llc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("llc")
+            .field("dsap", &format!("0x{:02x}", self.dsap()))
+            .field("ssap", &format!("0x{:02x}", self.ssap()))
+            .field("control", &format!("0x{:02x}", self.control()))
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Llc {
+    /// The preceding type for LLC must be `Ethernet`.
+    type Envelope = Ethernet;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the LLC header, 4 if the control field is 16
+    /// bits long, otherwise 3.
+    #[inline]
+    fn header_len(&self) -> usize {
+        if self.is_unnumbered() {
+            LlcHeader::size_of()
+        } else {
+            LlcHeader::size_of() + 1
+        }
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Llc {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the payload of an 802.3 frame as an LLC header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Ethernet type field is not a length, that
+    /// is larger than [`MAX_802_3_LENGTH`]. Returns an error if the payload
+    /// does not have sufficient data for the LLC header.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(envelope.is_802_3(), anyhow!("not an 802.3 frame."));
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Llc {
+            envelope,
+            header,
+            offset,
+        };
+
+        // the 16-bit control field.
+        if !packet.is_unnumbered() {
+            let _ = packet
+                .mbuf()
+                .read_data::<u8>(offset + LlcHeader::size_of())?;
+        }
+
+        Ok(packet)
+    }
+
+    /// Prepends an LLC header with an unnumbered information control field
+    /// to the beginning of the Ethernet's payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, LlcHeader::size_of())?;
+        let header = mbuf.write_data(offset, &LlcHeader::default())?;
+
+        Ok(Llc {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * The Ethernet type field of the envelope is set to the length of
+    /// the LLC header and its payload.
+    #[inline]
+    fn reconcile(&mut self) {
+        let len = self.len() as u16;
+        self.envelope_mut().set_ether_type(EtherType::new(len));
+    }
+}
+
+/// LLC header with a Subnetwork Access Protocol extension based on
+/// [IETF RFC 1042].
+///
+/// The SNAP header recovers the protocol identifier that an 802.3 frame
+/// lacks.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   DSAP=0xAA   |   SSAP=0xAA   |  Control=UI   |    OUI ...    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          ... OUI              |          Protocol ID          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *OUI*: (24 bits)
+///      The organizationally unique identifier of the organization that
+///      assigns the protocol identifier. 0 indicates the protocol
+///      identifier is an Ethernet type.
+///
+/// - *Protocol ID*: (16 bits)
+///      The protocol of the payload.
+///
+/// [IETF RFC 1042]: https://tools.ietf.org/html/rfc1042
+pub struct LlcSnap {
+    envelope: Ethernet,
+    header: NonNull<SnapHeader>,
+    offset: usize,
+}
+
+impl LlcSnap {
+    #[inline]
+    fn header(&self) -> &SnapHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut SnapHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the organizationally unique identifier.
+    #[inline]
+    pub fn oui(&self) -> [u8; 3] {
+        self.header().oui
+    }
+
+    /// Sets the organizationally unique identifier.
+    #[inline]
+    pub fn set_oui(&mut self, oui: [u8; 3]) {
+        self.header_mut().oui = oui;
+    }
+
+    /// Returns the protocol identifier.
+    #[inline]
+    pub fn protocol_id(&self) -> u16 {
+        self.header().protocol_id.into()
+    }
+
+    /// Sets the protocol identifier.
+    #[inline]
+    pub fn set_protocol_id(&mut self, protocol_id: u16) {
+        self.header_mut().protocol_id = protocol_id.into();
+    }
+
+    /// Returns the Ethernet type of the payload if the OUI is 0.
+    ///
+    /// Returns `None` for organization specific protocol identifiers.
+    #[inline]
+    pub fn ether_type(&self) -> Option<EtherType> {
+        if self.oui() == [0; 3] {
+            Some(EtherType::new(self.protocol_id()))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Debug for LlcSnap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let oui = self.oui();
+        f.debug_struct("llc_snap")
+            .field(
+                "oui",
+                &format!("{:02x}:{:02x}:{:02x}", oui[0], oui[1], oui[2]),
+            )
+            .field("protocol_id", &format!("0x{:04x}", self.protocol_id()))
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for LlcSnap {
+    /// The preceding type for LLC/SNAP must be `Ethernet`.
+    type Envelope = Ethernet;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        SnapHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        LlcSnap {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the payload of an 802.3 frame as an LLC/SNAP header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Ethernet type field is not a length. Returns
+    /// an error if the DSAP or the SSAP is not [`SNAP_SAP`], or the control
+    /// field is not unnumbered information. Returns an error if the payload
+    /// does not have sufficient data for the LLC/SNAP header.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(envelope.is_802_3(), anyhow!("not an 802.3 frame."));
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data::<SnapHeader>(offset)?;
+
+        let header_ref = unsafe { header.as_ref() };
+        ensure!(
+            header_ref.llc.dsap == SNAP_SAP
+                && header_ref.llc.ssap == SNAP_SAP
+                && header_ref.llc.control == CONTROL_U_FORMAT,
+            anyhow!("not an LLC/SNAP frame.")
+        );
+
+        Ok(LlcSnap {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an LLC/SNAP header to the beginning of the Ethernet's
+    /// payload.
+    ///
+    /// The DSAP and the SSAP are set to [`SNAP_SAP`], and the OUI and the
+    /// protocol identifier are zeroed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, SnapHeader::size_of())?;
+        let header = mbuf.write_data(offset, &SnapHeader::default())?;
+
+        Ok(LlcSnap {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * The Ethernet type field of the envelope is set to the length of
+    /// the LLC/SNAP header and its payload.
+    #[inline]
+    fn reconcile(&mut self) {
+        let len = self.len() as u16;
+        self.envelope_mut().set_ether_type(EtherType::new(len));
+    }
+}
+
+/// LLC header with an 8-bit control field.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct LlcHeader {
+    dsap: u8,
+    ssap: u8,
+    control: u8,
+}
+
+impl Default for LlcHeader {
+    fn default() -> LlcHeader {
+        LlcHeader {
+            dsap: 0,
+            ssap: 0,
+            // unnumbered information.
+            control: CONTROL_U_FORMAT,
+        }
+    }
+}
+
+/// LLC header followed by the SNAP header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct SnapHeader {
+    llc: LlcHeader,
+    oui: [u8; 3],
+    protocol_id: u16be,
+}
+
+impl Default for SnapHeader {
+    fn default() -> SnapHeader {
+        SnapHeader {
+            llc: LlcHeader {
+                dsap: SNAP_SAP,
+                ssap: SNAP_SAP,
+                control: CONTROL_U_FORMAT,
+            },
+            oui: [0; 3],
+            protocol_id: u16be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::EtherTypes;
+    use crate::testils::byte_arrays::IPV4_UDP_PACKET;
+    use crate::Mbuf;
+
+    /// An 802.3 frame with an LLC/SNAP header and a 4-byte payload.
+    #[rustfmt::skip]
+    const SNAP_FRAME: [u8; 26] = [
+    // 802.3 header
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+        // length = 12
+        0x00, 0x0c,
+    // LLC header
+        // dsap = 0xaa, ssap = 0xaa, control = UI
+        0xaa, 0xaa, 0x03,
+    // SNAP header
+        // oui = 00:00:00, protocol id = IPv4
+        0x00, 0x00, 0x00, 0x08, 0x00,
+    // payload
+        0x01, 0x02, 0x03, 0x04,
+    ];
+
+    #[test]
+    fn size_of_llc_header() {
+        assert_eq!(3, LlcHeader::size_of());
+        assert_eq!(8, SnapHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_snap_frame() {
+        let packet = Mbuf::from_bytes(&SNAP_FRAME).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert!(ethernet.is_802_3());
+
+        let snap = ethernet.peek::<LlcSnap>().unwrap();
+        assert_eq!([0; 3], snap.oui());
+        assert_eq!(0x0800, snap.protocol_id());
+        assert_eq!(Some(EtherTypes::Ipv4), snap.ether_type());
+        assert_eq!(4, snap.payload_len());
+
+        let llc = ethernet.parse::<Llc>().unwrap();
+        assert_eq!(SNAP_SAP, llc.dsap());
+        assert_eq!(SNAP_SAP, llc.ssap());
+        assert!(llc.is_unnumbered());
+    }
+
+    #[capsule::test]
+    fn parse_non_802_3_frame() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert!(ethernet.peek::<Llc>().is_err());
+        assert!(ethernet.parse::<LlcSnap>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_and_reconcile_llc_header() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let mut llc = ethernet.push::<Llc>().unwrap();
+        llc.set_dsap(0x42);
+        llc.set_ssap(0x42);
+        llc.reconcile();
+
+        assert!(llc.is_unnumbered());
+        assert_eq!(3, llc.header_len());
+        assert_eq!(EtherType::new(3), llc.envelope().ether_type());
+    }
+
+    #[capsule::test]
+    fn push_and_reconcile_snap_header() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let mut snap = ethernet.push::<LlcSnap>().unwrap();
+        snap.set_oui([0x00, 0x00, 0x0c]);
+        snap.set_protocol_id(0x2000);
+        snap.reconcile();
+
+        assert_eq!(None, snap.ether_type());
+        assert_eq!(EtherType::new(8), snap.envelope().ether_type());
+
+        // parses back the pushed header
+        let ethernet = snap.deparse();
+        assert!(ethernet.peek::<LlcSnap>().is_ok());
+    }
+}
@@ -0,0 +1,502 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Link Layer Discovery Protocol.
+
+use crate::packets::types::u16be;
+use crate::packets::{EtherTypes, Ethernet, Internal, Packet};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::str;
+
+// Masks.
+const TLV_TYPE: u16 = 0b1111_1110_0000_0000;
+const TLV_LENGTH: u16 = 0b0000_0001_1111_1111;
+
+/// Link Layer Discovery Protocol data unit based on [IEEE 802.1AB].
+///
+/// The LLDPDU is a sequence of type-length-value structures, or TLVs,
+/// terminated by the End of LLDPDU TLV.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   Type (7)  |  Length (9)     |        Value (0-511 octets)   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Type*: (7 bits)
+///      The type of the TLV.
+///
+/// - *Length*: (9 bits)
+///      The length of the value in octets.
+///
+/// - *Value*: (variable)
+///      The information of the TLV.
+///
+/// The Chassis ID, the Port ID and the Time To Live TLVs are mandatory,
+/// and must be the first three TLVs in that order.
+///
+/// LLDP does not have a fixed header. The header length is 0 and the
+/// whole LLDPDU is the payload.
+///
+/// [IEEE 802.1AB]: https://standards.ieee.org/standard/802_1AB-2016.html
+pub struct Lldp {
+    envelope: Ethernet,
+    offset: usize,
+}
+
+impl Lldp {
+    /// Returns an iterator over the TLVs.
+    #[inline]
+    pub fn tlvs(&self) -> LldpTlvsIterator<'_> {
+        LldpTlvsIterator {
+            mbuf: self.mbuf(),
+            offset: self.payload_offset(),
+        }
+    }
+
+    /// Returns the value of the first TLV with the TLV type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a TLV is malformed.
+    pub fn tlv(&self, tlv_type: LldpTlvType) -> Result<Option<&[u8]>> {
+        let mut iter = self.tlvs();
+        while let Some(tlv) = iter.next()? {
+            if tlv.tlv_type() == tlv_type {
+                return Ok(Some(tlv.value()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the chassis ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a TLV is malformed.
+    pub fn chassis_id(&self) -> Result<Option<LldpId<'_>>> {
+        self.id_tlv(LldpTlvTypes::ChassisId)
+    }
+
+    /// Returns the port ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a TLV is malformed.
+    pub fn port_id(&self) -> Result<Option<LldpId<'_>>> {
+        self.id_tlv(LldpTlvTypes::PortId)
+    }
+
+    /// Returns the time to live in seconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a TLV is malformed.
+    pub fn ttl(&self) -> Result<Option<u16>> {
+        match self.tlv(LldpTlvTypes::Ttl)? {
+            Some(&[a, b]) => Ok(Some(u16::from_be_bytes([a, b]))),
+            Some(_) => Err(anyhow!("invalid LLDP TTL length.")),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the port description.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a TLV is malformed.
+    pub fn port_description(&self) -> Result<Option<&str>> {
+        self.string_tlv(LldpTlvTypes::PortDescription)
+    }
+
+    /// Returns the system name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a TLV is malformed.
+    pub fn system_name(&self) -> Result<Option<&str>> {
+        self.string_tlv(LldpTlvTypes::SystemName)
+    }
+
+    /// Returns the system description.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a TLV is malformed.
+    pub fn system_description(&self) -> Result<Option<&str>> {
+        self.string_tlv(LldpTlvTypes::SystemDescription)
+    }
+
+    fn id_tlv(&self, tlv_type: LldpTlvType) -> Result<Option<LldpId<'_>>> {
+        match self.tlv(tlv_type)? {
+            Some(value) if value.len() >= 2 => Ok(Some(LldpId {
+                subtype: value[0],
+                id: &value[1..],
+            })),
+            Some(_) => Err(anyhow!("invalid LLDP {} length.", tlv_type)),
+            None => Ok(None),
+        }
+    }
+
+    fn string_tlv(&self, tlv_type: LldpTlvType) -> Result<Option<&str>> {
+        match self.tlv(tlv_type)? {
+            Some(value) => str::from_utf8(value)
+                .map(Some)
+                .map_err(|_| anyhow!("invalid LLDP {} string.", tlv_type)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl fmt::Debug for Lldp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("lldp")
+            .field("chassis_id", &self.chassis_id().unwrap_or(None))
+            .field("port_id", &self.port_id().unwrap_or(None))
+            .field("ttl", &self.ttl().unwrap_or(None))
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Lldp {
+    /// The preceding type for LLDP must be `Ethernet`.
+    type Envelope = Ethernet;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns 0. LLDP does not have a fixed header, the TLVs are the
+    /// payload.
+    #[inline]
+    fn header_len(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Lldp {
+            envelope: self.envelope.clone(internal),
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the Ethernet payload as an LLDPDU.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the [`ether_type`] is not set to
+    /// [`EtherTypes::Lldp`]. Returns an error if the payload does not have
+    /// sufficient data for a TLV header.
+    ///
+    /// [`ether_type`]: Ethernet::ether_type
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.ether_type() == EtherTypes::Lldp,
+            anyhow!("not an LLDP packet.")
+        );
+
+        let offset = envelope.payload_offset();
+        let _ = envelope.mbuf().read_data::<u16be>(offset)?;
+
+        Ok(Lldp { envelope, offset })
+    }
+
+    /// Prepends an LLDPDU with only the End of LLDPDU TLV to the
+    /// beginning of the Ethernet's payload.
+    ///
+    /// [`ether_type`] is set to [`EtherTypes::Lldp`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`ether_type`]: Ethernet::ether_type
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, u16be::size_of())?;
+        let _ = mbuf.write_data(offset, &u16be::default())?;
+
+        envelope.set_ether_type(EtherTypes::Lldp);
+
+        Ok(Lldp { envelope, offset })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// LLDP TLV type.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct LldpTlvType(pub u8);
+
+impl LldpTlvType {
+    /// Creates a new TLV type.
+    pub fn new(value: u8) -> Self {
+        LldpTlvType(value)
+    }
+}
+
+/// Supported LLDP TLV types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod LldpTlvTypes {
+    use super::LldpTlvType;
+
+    /// End of LLDPDU.
+    pub const End: LldpTlvType = LldpTlvType(0);
+    /// Chassis ID.
+    pub const ChassisId: LldpTlvType = LldpTlvType(1);
+    /// Port ID.
+    pub const PortId: LldpTlvType = LldpTlvType(2);
+    /// Time to live.
+    pub const Ttl: LldpTlvType = LldpTlvType(3);
+    /// Port description.
+    pub const PortDescription: LldpTlvType = LldpTlvType(4);
+    /// System name.
+    pub const SystemName: LldpTlvType = LldpTlvType(5);
+    /// System description.
+    pub const SystemDescription: LldpTlvType = LldpTlvType(6);
+    /// System capabilities.
+    pub const SystemCapabilities: LldpTlvType = LldpTlvType(7);
+    /// Management address.
+    pub const ManagementAddress: LldpTlvType = LldpTlvType(8);
+    /// Organizationally specific.
+    pub const OrganizationallySpecific: LldpTlvType = LldpTlvType(127);
+}
+
+impl fmt::Display for LldpTlvType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                LldpTlvTypes::End => "End of LLDPDU".to_string(),
+                LldpTlvTypes::ChassisId => "Chassis ID".to_string(),
+                LldpTlvTypes::PortId => "Port ID".to_string(),
+                LldpTlvTypes::Ttl => "Time To Live".to_string(),
+                LldpTlvTypes::PortDescription => "Port Description".to_string(),
+                LldpTlvTypes::SystemName => "System Name".to_string(),
+                LldpTlvTypes::SystemDescription => "System Description".to_string(),
+                LldpTlvTypes::SystemCapabilities => "System Capabilities".to_string(),
+                LldpTlvTypes::ManagementAddress => "Management Address".to_string(),
+                LldpTlvTypes::OrganizationallySpecific => {
+                    "Organizationally Specific".to_string()
+                }
+                _ => {
+                    let t = self.0;
+                    format!("{}", t)
+                }
+            }
+        )
+    }
+}
+
+/// A chassis ID or a port ID, with the subtype that identifies the
+/// format of the ID.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LldpId<'a> {
+    subtype: u8,
+    id: &'a [u8],
+}
+
+impl<'a> LldpId<'a> {
+    /// Returns the subtype.
+    #[inline]
+    pub fn subtype(&self) -> u8 {
+        self.subtype
+    }
+
+    /// Returns the ID.
+    #[inline]
+    pub fn id(&self) -> &'a [u8] {
+        self.id
+    }
+}
+
+/// An LLDP TLV.
+pub struct LldpTlv<'a> {
+    tlv_type: LldpTlvType,
+    value: &'a [u8],
+}
+
+impl<'a> LldpTlv<'a> {
+    /// Returns the TLV type.
+    #[inline]
+    pub fn tlv_type(&self) -> LldpTlvType {
+        self.tlv_type
+    }
+
+    /// Returns the value, excluding the type and length.
+    #[inline]
+    pub fn value(&self) -> &'a [u8] {
+        self.value
+    }
+}
+
+impl fmt::Debug for LldpTlv<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LldpTlv")
+            .field("tlv_type", &format!("{}", self.tlv_type()))
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the LLDP TLVs.
+///
+/// The iteration stops at the End of LLDPDU TLV, which is not returned.
+pub struct LldpTlvsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+}
+
+impl<'a> LldpTlvsIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<LldpTlv<'a>>> {
+        if self.mbuf.data_len() <= self.offset {
+            return Ok(None);
+        }
+
+        let header = unsafe { *self.mbuf.read_data::<u16be>(self.offset)?.as_ref() };
+        let header = u16::from(header);
+        let tlv_type = LldpTlvType::new(((header & TLV_TYPE) >> 9) as u8);
+        let len = (header & TLV_LENGTH) as usize;
+
+        if tlv_type == LldpTlvTypes::End {
+            self.offset = self.mbuf.data_len();
+            return Ok(None);
+        }
+
+        let value: &'a [u8] = if len > 0 {
+            let data = self.mbuf.read_data_slice::<u8>(self.offset + 2, len)?;
+            unsafe { &*data.as_ptr() }
+        } else {
+            &[]
+        };
+
+        self.offset += 2 + len;
+        Ok(Some(LldpTlv { tlv_type, value }))
+    }
+}
+
+impl fmt::Debug for LldpTlvsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LldpTlvsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, LLDP_PACKET};
+
+    #[capsule::test]
+    fn parse_lldp_packet() {
+        let packet = Mbuf::from_bytes(&LLDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let lldp = ethernet.parse::<Lldp>().unwrap();
+
+        let chassis_id = lldp.chassis_id().unwrap().unwrap();
+        assert_eq!(4, chassis_id.subtype());
+        assert_eq!(&[0, 0, 0, 0, 0, 2], chassis_id.id());
+
+        let port_id = lldp.port_id().unwrap().unwrap();
+        assert_eq!(5, port_id.subtype());
+        assert_eq!(b"eth0", port_id.id());
+
+        assert_eq!(Some(120), lldp.ttl().unwrap());
+        assert_eq!(Some("uplink"), lldp.port_description().unwrap());
+        assert_eq!(Some("switch1"), lldp.system_name().unwrap());
+        assert_eq!(None, lldp.system_description().unwrap());
+    }
+
+    #[capsule::test]
+    fn iterate_lldp_tlvs() {
+        let packet = Mbuf::from_bytes(&LLDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let lldp = ethernet.parse::<Lldp>().unwrap();
+
+        let mut types = vec![];
+        let mut iter = lldp.tlvs();
+        while let Some(tlv) = iter.next().unwrap() {
+            types.push(tlv.tlv_type());
+        }
+
+        assert_eq!(
+            vec![
+                LldpTlvTypes::ChassisId,
+                LldpTlvTypes::PortId,
+                LldpTlvTypes::Ttl,
+                LldpTlvTypes::PortDescription,
+                LldpTlvTypes::SystemName,
+            ],
+            types
+        );
+    }
+
+    #[capsule::test]
+    fn parse_non_lldp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert!(ethernet.parse::<Lldp>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_lldp_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let lldp = ethernet.push::<Lldp>().unwrap();
+
+        assert_eq!(EtherTypes::Lldp, lldp.envelope().ether_type());
+        assert_eq!(2, lldp.len());
+        assert!(lldp.tlvs().next().unwrap().is_none());
+    }
+}
@@ -18,24 +18,127 @@
 
 //! Packet types for reading and writing various network protocols.
 
+pub mod ah;
 pub mod arp;
+pub mod build;
+pub mod cdp;
 pub mod checksum;
+pub mod dhcp;
+pub mod dhcpv6;
+pub mod esp;
 mod ethernet;
+pub mod geneve;
+pub mod gre;
+pub mod gtpu;
+pub mod http;
 pub mod icmp;
+pub mod igmp;
 pub mod ip;
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub mod json;
+pub mod layer;
+pub mod llc;
+pub mod lldp;
+pub mod mpls;
+pub mod nsh;
+pub mod ntp;
+pub mod ospf;
+pub mod quic;
+pub mod registry;
+pub mod rtp;
+pub mod sctp;
+pub mod sip;
+pub mod stp;
+pub mod stun;
 mod tcp;
+pub mod tls;
+pub mod tunnel;
 pub mod types;
 mod udp;
+pub mod vrrp;
+pub mod vxlan;
 
 pub use self::ethernet::*;
 pub use self::tcp::*;
 pub use self::udp::*;
 
-use crate::Mbuf;
+use crate::dpdk::BufferError;
+use crate::pcap::LinkType;
+use crate::{ensure, DataChunks, Mbuf};
 use anyhow::{Context, Result};
 use std::fmt;
 use std::marker::PhantomData;
 use std::ops::Deref;
+use thiserror::Error;
+
+/// Error indicating a packet cannot be created or parsed.
+#[derive(Debug, Error)]
+pub enum PacketError {
+    /// The packet has no data.
+    #[error("Packet is empty.")]
+    Empty,
+
+    /// The packet data is larger than what the message buffer can hold.
+    #[error("Packet length {len} exceeds the maximum buffer size {max}.")]
+    TooLarge {
+        /// The length of the packet data.
+        len: usize,
+        /// The maximum length the message buffer can hold.
+        max: usize,
+    },
+
+    /// The requested buffer capacity cannot hold the packet data.
+    #[error("Buffer capacity {capacity} is smaller than the packet length {len}.")]
+    CapacityTooSmall {
+        /// The length of the packet data.
+        len: usize,
+        /// The requested capacity of the message buffer.
+        capacity: usize,
+    },
+
+    /// The message buffer cannot be allocated.
+    #[error("Cannot allocate a message buffer for the packet.")]
+    AllocFailed,
+
+    /// The buffer ends before the end of the packet header.
+    #[error("Packet header is truncated.")]
+    Truncated,
+
+    /// The length field of the packet is inconsistent with the buffer.
+    #[error("Packet length field is inconsistent with the buffer length.")]
+    BadLength,
+
+    /// The header length field is smaller than the fixed part of the header.
+    #[error("Header length {len} is less than the minimum header length {min}.")]
+    BadHeaderLength {
+        /// The header length read from the packet.
+        len: usize,
+        /// The length of the fixed part of the header.
+        min: usize,
+    },
+
+    /// The length field of the packet is smaller than its own header.
+    #[error("Length field {len} is less than the header length {header_len}.")]
+    LengthTooShort {
+        /// The length read from the packet.
+        len: usize,
+        /// The length of the header.
+        header_len: usize,
+    },
+
+    /// Reserved bits that must be zero are set.
+    #[error("Reserved bits are set in the {0} header.")]
+    ReservedBitsSet(&'static str),
+
+    /// The IP version of a packet without link-layer framing is unknown.
+    #[error("Unknown IP version {0}.")]
+    UnknownIpVersion(u8),
+
+    /// The link-layer header type is not supported.
+    #[error("Unsupported link type {0}.")]
+    UnsupportedLinkType(LinkType),
+}
 
 /// An argument to restrict users from calling functions on the [`Packet`]
 /// trait.
@@ -115,6 +218,22 @@ pub trait Packet {
         self.offset() + self.header_len()
     }
 
+    /// Prefetches the cache line where the packet header begins.
+    ///
+    /// Issuing the prefetch ahead of reading the header hides the memory
+    /// latency. It is a no-op on targets without a prefetch instruction.
+    #[inline]
+    fn prefetch(&self) {
+        self.mbuf().prefetch_data(self.offset());
+    }
+
+    /// Returns the length of the data stored in the message buffer,
+    /// regardless of where the packet begins.
+    #[inline]
+    fn packet_len(&self) -> usize {
+        self.mbuf().data_len()
+    }
+
     /// Returns the length of the packet with the payload.
     #[inline]
     fn len(&self) -> usize {
@@ -127,6 +246,105 @@ pub trait Packet {
         self.len() - self.header_len()
     }
 
+    /// Copies the packet, starting at [`offset`], into a vector of bytes.
+    ///
+    /// [`offset`]: Packet::offset
+    #[inline]
+    fn to_vec(&self) -> Vec<u8> {
+        self.mbuf().copy_data(self.offset())
+    }
+
+    /// Returns the packet, starting at [`offset`], as a slice of bytes.
+    ///
+    /// The slice covers the header and the payload of the packet. Unlike
+    /// [`to_vec`], the data is not copied. For a segmented mbuf, the slice
+    /// only covers the data in the first segment, consistent with [`len`].
+    ///
+    /// [`offset`]: Packet::offset
+    /// [`to_vec`]: Packet::to_vec
+    /// [`len`]: Packet::len
+    #[inline]
+    fn as_bytes(&self) -> &[u8] {
+        match self.mbuf().read_data_slice::<u8>(self.offset(), self.len()) {
+            Ok(data) => unsafe { &*data.as_ptr() },
+            Err(_) => &[],
+        }
+    }
+
+    /// Returns the header of the packet, from [`offset`] to
+    /// [`payload_offset`], as a mutable slice of bytes.
+    ///
+    /// The slice covers the header as it's laid out on the wire, so a
+    /// whole header can be zeroed or overwritten in one shot. Because it
+    /// mutably borrows the packet, no other reference into the header can
+    /// be held at the same time. The fields derived from the header, such
+    /// as the checksums, are not updated until the packet is reconciled.
+    ///
+    /// [`offset`]: Packet::offset
+    /// [`payload_offset`]: Packet::payload_offset
+    #[inline]
+    fn header_bytes_mut(&mut self) -> &mut [u8] {
+        let offset = self.offset();
+        let len = self.header_len();
+        match self.mbuf_mut().read_data_slice::<u8>(offset, len) {
+            Ok(mut data) => unsafe { data.as_mut() },
+            Err(_) => &mut [],
+        }
+    }
+
+    /// Returns an iterator over the packet payload, as contiguous slices
+    /// of bytes.
+    ///
+    /// One slice is returned per mbuf segment covering the payload. Unlike
+    /// [`to_vec`], the payload of a segmented mbuf is not copied.
+    ///
+    /// [`to_vec`]: Packet::to_vec
+    #[inline]
+    fn payload_chunks(&self) -> DataChunks<'_> {
+        self.mbuf().data_chunks(self.payload_offset())
+    }
+
+    /// Formats the packet, starting at [`offset`], as a hex dump.
+    ///
+    /// The layout is similar to `tcpdump -X`. Each line has the offset,
+    /// followed by 16 bytes in hex grouped in twos, and the ASCII
+    /// representation of the bytes where non-printable bytes are shown as
+    /// `.`.
+    ///
+    /// ```
+    /// 0x0000:  0000 0000 0001 0000 0000 0002 0800 4500  ..............E.
+    /// ```
+    ///
+    /// [`offset`]: Packet::offset
+    fn hexdump(&self) -> String {
+        let mut dump = String::new();
+
+        for (i, line) in self.to_vec().chunks(16).enumerate() {
+            let hex = line
+                .chunks(2)
+                .map(|word| {
+                    word.iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii = line
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+            dump.push_str(&format!("0x{:04x}:  {:<39}  {}\n", i * 16, hex, ascii));
+        }
+
+        dump
+    }
+
     /// Returns a copy of the packet.
     ///
     /// # Remarks
@@ -173,13 +391,61 @@ pub trait Packet {
     /// The ownership of the packet is moved after invocation. To retain
     /// ownership, use [`peek`] instead.
     ///
+    /// # Errors
+    ///
+    /// Returns `PacketError::Truncated` if the buffer ends before the end
+    /// of the header of `T`.
+    ///
     /// [`peek`]: Packet::peek
     #[inline]
     fn parse<T: Packet<Envelope = Self>>(self) -> Result<T>
     where
         Self: Sized,
     {
-        T::try_parse(self, Internal(()))
+        let packet = T::try_parse(self, Internal(())).map_err(|err| {
+            match err.downcast_ref::<BufferError>() {
+                Some(BufferError::BadOffset(..)) | Some(BufferError::OutOfBuffer(..)) => {
+                    PacketError::Truncated.into()
+                }
+                _ => err,
+            }
+        })?;
+
+        // variable length headers are only partially read on parse.
+        ensure!(
+            packet.offset() + packet.header_len() <= packet.mbuf().data_len(),
+            PacketError::Truncated
+        );
+
+        Ok(packet)
+    }
+
+    /// Parses the packet's payload as a packet of type `T`, skipping the
+    /// truncation check that [`parse`] performs after the header is read.
+    ///
+    /// Intended for hot loops in trusted pipelines, where the packet was
+    /// already validated by an earlier stage and the layout is known to be
+    /// well-formed. The check is still done in debug builds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the payload does not parse as `T`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must guarantee that the whole header of `T` is in the
+    /// first segment of the buffer. A variable length header that runs
+    /// past the end of the data is read out of bounds by its accessors.
+    ///
+    /// [`parse`]: Packet::parse
+    #[inline]
+    unsafe fn parse_unchecked<T: Packet<Envelope = Self>>(self) -> T
+    where
+        Self: Sized,
+    {
+        let packet = T::try_parse(self, Internal(())).expect("the payload should parse.");
+        debug_assert!(packet.offset() + packet.header_len() <= packet.mbuf().data_len());
+        packet
     }
 
     /// Peeks into the packet's payload as a packet of type `T`.
@@ -195,6 +461,51 @@ pub trait Packet {
         clone.parse::<T>().map(Immutable::new)
     }
 
+    /// Peeks into the packet's payload as a packet of type `T`, returning
+    /// `None` if the payload does not parse.
+    ///
+    /// Like [`peek`], the caller retains full ownership of the packet, so
+    /// multiple packet types can be attempted in turn.
+    ///
+    /// An empty payload is rejected up front without attempting the parse.
+    /// Otherwise the payload goes through [`peek`], and a payload of a
+    /// different type still allocates the parse error before it's
+    /// discarded. There is no generic way to probe the type of the payload
+    /// ahead of the parse, so in a hot loop, prefer dispatching on the
+    /// envelope's protocol field over trying packet types in turn.
+    ///
+    /// [`peek`]: Packet::peek
+    #[inline]
+    fn try_peek<T: Packet<Envelope = Self>>(&self) -> Option<Immutable<'_, T>>
+    where
+        Self: Sized,
+    {
+        if self.payload_len() == 0 {
+            return None;
+        }
+
+        self.peek::<T>().ok()
+    }
+
+    /// Parses the packet's payload as a packet of type `T`, and rejects the
+    /// packet if its length field is inconsistent with the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PacketError::BadLength` if [`validate_length`] fails.
+    ///
+    /// [`validate_length`]: ValidateLength::validate_length
+    #[inline]
+    fn parse_strict<T>(self) -> Result<T>
+    where
+        T: Packet<Envelope = Self> + ValidateLength,
+        Self: Sized,
+    {
+        let packet = self.parse::<T>()?;
+        ensure!(packet.validate_length(), PacketError::BadLength);
+        Ok(packet)
+    }
+
     /// Prepends a new packet to the beginning of the envelope's payload.
     ///
     /// When the packet is inserted into an envelope with an existing payload,
@@ -300,6 +611,48 @@ pub trait Packet {
     }
 }
 
+/// A packet with a length field that can be validated against the buffer.
+///
+/// Malformed or truncated packets can claim a length larger than the data
+/// in the buffer. Reading the payload of such packets by the length field
+/// would go out of bounds.
+pub trait ValidateLength: Packet {
+    /// Returns whether the length field is consistent with the data in the
+    /// buffer.
+    fn validate_length(&self) -> bool;
+}
+
+/// Parses a burst of message buffers with the `parse` function.
+///
+/// DPDK receives packets in bursts. While the current buffer is being
+/// parsed, the packet data of the next buffer is prefetched into the cache
+/// to hide the memory latency. The results are returned in the same order
+/// as the buffers.
+///
+/// # Example
+///
+/// ```
+/// let packets = parse_batch(mbufs, |mbuf| {
+///     mbuf.parse::<Ethernet>()?.parse::<Ipv4>()?.parse::<Udp4>()
+/// });
+/// ```
+pub fn parse_batch<T, F>(mbufs: Vec<Mbuf>, mut parse: F) -> Vec<Result<T>>
+where
+    F: FnMut(Mbuf) -> Result<T>,
+{
+    let mut results = Vec::with_capacity(mbufs.len());
+    let mut mbufs = mbufs.into_iter().peekable();
+
+    while let Some(mbuf) = mbufs.next() {
+        if let Some(next) = mbufs.peek() {
+            next.prefetch();
+        }
+        results.push(parse(mbuf));
+    }
+
+    results
+}
+
 /// Immutable smart pointer to a struct.
 ///
 /// A smart pointer that prevents the struct from being modified. The main
@@ -356,6 +709,44 @@ mod tests {
         assert_eq!(len, reset.data_len());
     }
 
+    #[capsule::test]
+    fn parse_unchecked_matches_parse() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let udp = packet
+            .parse::<Ethernet>()
+            .unwrap()
+            .parse::<Ipv4>()
+            .unwrap()
+            .parse::<Udp4>()
+            .unwrap();
+
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let unchecked = unsafe {
+            packet
+                .parse_unchecked::<Ethernet>()
+                .parse_unchecked::<Ipv4>()
+                .parse_unchecked::<Udp4>()
+        };
+
+        assert_eq!(udp.offset(), unchecked.offset());
+        assert_eq!(udp.header_len(), unchecked.header_len());
+        assert_eq!(udp.src_port(), unchecked.src_port());
+        assert_eq!(udp.dst_port(), unchecked.dst_port());
+        assert_eq!(udp.envelope().src(), unchecked.envelope().src());
+        assert_eq!(udp.to_vec(), unchecked.to_vec());
+    }
+
+    #[capsule::test]
+    fn parse_unchecked_panics_on_malformed_packet() {
+        // too short for an IPv4 header.
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET[..20]).unwrap();
+        let ethernet = unsafe { packet.parse_unchecked::<Ethernet>() };
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
+            ethernet.parse_unchecked::<Ipv4>()
+        }));
+        assert!(result.is_err());
+    }
+
     #[capsule::test]
     fn peek_packet() {
         let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
@@ -368,6 +759,57 @@ mod tests {
         assert_eq!(39376, udp.src_port());
     }
 
+    #[capsule::test]
+    fn packet_as_bytes() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert_eq!(&IPV4_UDP_PACKET[..], ethernet.as_bytes());
+
+        let v4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = v4.parse::<Udp4>().unwrap();
+        assert_eq!(&IPV4_UDP_PACKET[34..], udp.as_bytes());
+        assert_eq!(udp.src_port().to_be_bytes(), udp.as_bytes()[..2]);
+    }
+
+    #[capsule::test]
+    fn zero_header_bytes() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let mut ethernet = packet.parse::<Ethernet>().unwrap();
+
+        let header = ethernet.header_bytes_mut();
+        assert_eq!(14, header.len());
+        header.fill(0);
+
+        assert_eq!(MacAddr::UNSPECIFIED, ethernet.src());
+        assert_eq!(MacAddr::UNSPECIFIED, ethernet.dst());
+        assert_eq!(EtherType::new(0), ethernet.ether_type());
+
+        // the payload is untouched.
+        assert_eq!(&IPV4_UDP_PACKET[14..], &ethernet.as_bytes()[14..]);
+    }
+
+    #[capsule::test]
+    fn try_peek_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.peek::<Ethernet>().unwrap();
+        let v4 = ethernet.try_peek::<Ipv4>().unwrap();
+        assert!(v4.try_peek::<Udp4>().is_some());
+        assert!(v4.try_peek::<Tcp4>().is_none());
+
+        let packet = Mbuf::from_bytes(&[0xa5; 20]).unwrap();
+        let ethernet = packet.try_peek::<Ethernet>().unwrap();
+        assert!(ethernet.try_peek::<Ipv4>().is_none());
+
+        let packet = Mbuf::from_bytes(&[0xa5; 10]).unwrap();
+        assert!(packet.try_peek::<Ethernet>().is_none());
+
+        // no payload to peek into.
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET[..14]).unwrap();
+        let ethernet = packet.peek::<Ethernet>().unwrap();
+        assert_eq!(0, ethernet.payload_len());
+        assert!(ethernet.try_peek::<Ipv4>().is_none());
+    }
+
     #[capsule::test]
     fn peek_back_via_envelope() {
         let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
@@ -381,6 +823,22 @@ mod tests {
         assert_eq!(v4_4.ttl(), 25);
     }
 
+    #[capsule::test]
+    fn iterate_payload_chunks_across_segments() {
+        let mut packet = Mbuf::from_bytes(&IPV4_UDP_PACKET[..45]).unwrap();
+        packet
+            .chain(Mbuf::from_bytes(&IPV4_UDP_PACKET[45..]).unwrap())
+            .unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let v4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = v4.parse::<Udp4>().unwrap();
+
+        let chunks = udp.payload_chunks().collect::<Vec<_>>();
+        assert_eq!(2, chunks.len());
+        assert_eq!(3, chunks[0].len());
+        assert_eq!(&IPV4_UDP_PACKET[42..], &chunks.concat()[..]);
+    }
+
     #[capsule::test]
     fn remove_header_and_payload() {
         let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
@@ -397,6 +855,95 @@ mod tests {
         assert_eq!(0, v4.payload_len());
     }
 
+    #[capsule::test]
+    fn packet_len_of_all_layers() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        assert_eq!(52, packet.len());
+        assert_eq!(52, packet.packet_len());
+
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp4>().unwrap();
+        assert_eq!(52, udp.packet_len());
+        assert_eq!(18, udp.len());
+    }
+
+    #[capsule::test]
+    fn packet_to_vec() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), packet.to_vec());
+
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp4>().unwrap();
+        assert_eq!(IPV4_UDP_PACKET[34..].to_vec(), udp.to_vec());
+    }
+
+    #[capsule::test]
+    fn hexdump_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let dump = packet.hexdump();
+        let mut lines = dump.lines();
+
+        assert_eq!(
+            Some("0x0000:  0000 0000 0001 0000 0000 0002 0800 4500  ..............E."),
+            lines.next()
+        );
+        // 52 bytes are dumped in 4 lines
+        assert_eq!(3, lines.count());
+    }
+
+    #[capsule::test]
+    fn prefetch_packet_header() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        packet.prefetch();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        ipv4.prefetch();
+        let udp = ipv4.parse::<Udp4>().unwrap();
+        udp.prefetch();
+
+        assert_eq!(39376, udp.src_port());
+    }
+
+    #[capsule::test]
+    fn parse_truncated_packet() {
+        // one byte shorter than a full UDP header.
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET[..41]).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        let err = ipv4.parse::<Udp4>().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PacketError>(),
+            Some(PacketError::Truncated)
+        ));
+
+        // no room for the header at all.
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET[..34]).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert!(ipv4.parse::<Udp4>().is_err());
+    }
+
+    #[capsule::test]
+    fn parse_batch_of_packets() {
+        let mbufs = (0..32)
+            .map(|_| Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap())
+            .collect::<Vec<_>>();
+
+        let packets = parse_batch(mbufs, |mbuf| {
+            mbuf.parse::<Ethernet>()?.parse::<Ipv4>()?.parse::<Udp4>()
+        });
+
+        assert_eq!(32, packets.len());
+        for udp in packets {
+            let udp = udp.unwrap();
+            assert_eq!(39376, udp.src_port());
+            assert_eq!(1087, udp.dst_port());
+        }
+    }
+
     /// Demonstrates that `Packet::peek` behaves as an immutable borrow on
     /// the envelope. Compilation will fail because it tries to have a
     /// mutable borrow on `Ethernet` while there's already an immutable
@@ -0,0 +1,506 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Multiprotocol Label Switching.
+
+use crate::packets::types::u32be;
+use crate::packets::{EtherType, EtherTypes, Ethernet, Internal, Packet};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+// Masks.
+const LABEL: u32 = 0xffff_f000;
+const TC: u32 = 0x0000_0e00;
+const BOS: u32 = 0x0000_0100;
+const TTL: u32 = 0x0000_00ff;
+
+/// The largest value of a 20-bit label.
+pub const LABEL_MAX: u32 = 0x000f_ffff;
+
+/// Multiprotocol Label Switching label stack based on [IETF RFC 3032].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                Label                  | TC  |S|       TTL     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Label*: (20 bits)
+///      Label value.
+///
+/// - *Traffic Class*: (3 bits)
+///      Used for QoS priority and explicit congestion notification.
+///
+/// - *Bottom of Stack*: (1 bit)
+///      This bit is set to one for the last entry in the label stack, and
+///      zero for all other label stack entries.
+///
+/// - *Time to Live*: (8 bits)
+///      Time to live.
+///
+/// The packet covers the whole label stack, from the top entry to the
+/// entry with the bottom of stack bit set. The accessors read and write
+/// the top entry; use [`labels`] to iterate through all the entries. The
+/// payload after the stack carries no type, and is typically an IPv4 or
+/// IPv6 packet guessed from the first nibble, see [`payload_type`].
+///
+/// [IETF RFC 3032]: https://tools.ietf.org/html/rfc3032#section-2.1
+/// [`labels`]: Mpls::labels
+/// [`payload_type`]: Mpls::payload_type
+pub struct Mpls {
+    envelope: Ethernet,
+    header: NonNull<MplsHeader>,
+    offset: usize,
+    depth: usize,
+}
+
+impl Mpls {
+    #[inline]
+    fn header(&self) -> &MplsHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut MplsHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the top label stack entry.
+    #[inline]
+    pub fn top(&self) -> MplsLabel {
+        MplsLabel::from(self.header().entry)
+    }
+
+    /// Returns the label of the top entry.
+    #[inline]
+    pub fn label(&self) -> u32 {
+        self.top().label()
+    }
+
+    /// Sets the label of the top entry.
+    ///
+    /// Only the lower 20 bits of the `label` are used.
+    #[inline]
+    pub fn set_label(&mut self, label: u32) {
+        let entry = u32::from(self.header().entry);
+        self.header_mut().entry = ((entry & !LABEL) | ((label & LABEL_MAX) << 12)).into();
+    }
+
+    /// Returns the traffic class of the top entry.
+    #[inline]
+    pub fn traffic_class(&self) -> u8 {
+        self.top().traffic_class()
+    }
+
+    /// Sets the traffic class of the top entry.
+    ///
+    /// Only the lower 3 bits of the `tc` are used.
+    #[inline]
+    pub fn set_traffic_class(&mut self, tc: u8) {
+        let entry = u32::from(self.header().entry);
+        self.header_mut().entry = ((entry & !TC) | ((u32::from(tc) << 9) & TC)).into();
+    }
+
+    /// Returns whether the top entry is the bottom of the stack.
+    #[inline]
+    pub fn bottom_of_stack(&self) -> bool {
+        self.top().bottom_of_stack()
+    }
+
+    /// Returns the time to live of the top entry.
+    #[inline]
+    pub fn ttl(&self) -> u8 {
+        self.top().ttl()
+    }
+
+    /// Sets the time to live of the top entry.
+    #[inline]
+    pub fn set_ttl(&mut self, ttl: u8) {
+        let entry = u32::from(self.header().entry);
+        self.header_mut().entry = ((entry & !TTL) | u32::from(ttl)).into();
+    }
+
+    /// Returns the number of entries in the label stack.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns an iterator that iterates through the label stack entries,
+    /// from the top of the stack to the bottom.
+    #[inline]
+    pub fn labels(&self) -> MplsLabelsIterator<'_> {
+        MplsLabelsIterator {
+            mbuf: self.mbuf(),
+            offset: self.offset,
+            end: self.payload_offset(),
+        }
+    }
+
+    /// Returns the Ethernet type of the payload guessed from the first
+    /// nibble, or `None` if the payload is neither IPv4 nor IPv6.
+    #[inline]
+    pub fn payload_type(&self) -> Option<EtherType> {
+        let first = self.mbuf().read_data::<u8>(self.payload_offset()).ok()?;
+        match unsafe { *first.as_ref() } >> 4 {
+            4 => Some(EtherTypes::Ipv4),
+            6 => Some(EtherTypes::Ipv6),
+            _ => None,
+        }
+    }
+
+    /// Removes the label stack and returns the Ethernet frame, with the
+    /// Ethernet type set to the guessed [`payload_type`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload type cannot be guessed.
+    ///
+    /// [`payload_type`]: Mpls::payload_type
+    #[inline]
+    pub fn decap(self) -> Result<Ethernet> {
+        let ether_type = self
+            .payload_type()
+            .ok_or_else(|| anyhow!("unknown MPLS payload type."))?;
+        let mut ethernet = self.remove()?;
+        ethernet.set_ether_type(ether_type);
+        Ok(ethernet)
+    }
+}
+
+impl fmt::Debug for Mpls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("mpls")
+            .field("label", &self.label())
+            .field("traffic_class", &self.traffic_class())
+            .field("bottom_of_stack", &self.bottom_of_stack())
+            .field("ttl", &self.ttl())
+            .field("depth", &self.depth())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Mpls {
+    /// The preceding type for MPLS must be `Ethernet`.
+    type Envelope = Ethernet;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the whole label stack.
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.depth * MplsHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Mpls {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+            depth: self.depth,
+        }
+    }
+
+    /// Parses the Ethernet payload as a MPLS label stack.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`ether_type`] is not [`EtherTypes::Mpls`].
+    /// Returns an error if the payload ends before the entry with the
+    /// bottom of stack bit set.
+    ///
+    /// [`ether_type`]: Ethernet::ether_type
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.ether_type() == EtherTypes::Mpls,
+            anyhow!("not a MPLS packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+        let depth = stack_depth(mbuf, offset)?;
+
+        Ok(Mpls {
+            envelope,
+            header,
+            offset,
+            depth,
+        })
+    }
+
+    /// Prepends a label stack entry to the beginning of the Ethernet's
+    /// payload.
+    ///
+    /// If the payload is already a MPLS label stack, the entry is pushed
+    /// onto the top of the stack. Otherwise the entry is the bottom of the
+    /// stack, and [`ether_type`] is set to [`EtherTypes::Mpls`]. The TTL of
+    /// the entry is set to 64.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`ether_type`]: Ethernet::ether_type
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let stacked = envelope.ether_type() == EtherTypes::Mpls;
+        let mbuf = envelope.mbuf_mut();
+
+        let depth = if stacked {
+            stack_depth(mbuf, offset)? + 1
+        } else {
+            1
+        };
+
+        let entry = if stacked { 64 } else { BOS | 64 };
+        mbuf.extend(offset, MplsHeader::size_of())?;
+        let header = mbuf.write_data(
+            offset,
+            &MplsHeader {
+                entry: entry.into(),
+            },
+        )?;
+
+        envelope.set_ether_type(EtherTypes::Mpls);
+
+        Ok(Mpls {
+            envelope,
+            header,
+            offset,
+            depth,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// Returns the number of entries in the label stack at offset.
+fn stack_depth(mbuf: &Mbuf, offset: usize) -> Result<usize> {
+    let mut depth = 0;
+
+    loop {
+        let header = mbuf.read_data::<MplsHeader>(offset + depth * MplsHeader::size_of())?;
+        depth += 1;
+        if u32::from(unsafe { header.as_ref() }.entry) & BOS != 0 {
+            return Ok(depth);
+        }
+    }
+}
+
+/// A MPLS label stack entry.
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
+pub struct MplsLabel(u32);
+
+impl MplsLabel {
+    /// Creates a new label stack entry.
+    ///
+    /// Only the lower 20 bits of the `label` and the lower 3 bits of the
+    /// `tc` are used.
+    pub fn new(label: u32, tc: u8, bottom_of_stack: bool, ttl: u8) -> Self {
+        let bos = if bottom_of_stack { BOS } else { 0 };
+        MplsLabel(((label & LABEL_MAX) << 12) | ((u32::from(tc) << 9) & TC) | bos | u32::from(ttl))
+    }
+
+    /// Returns the label.
+    #[inline]
+    pub fn label(&self) -> u32 {
+        self.0 >> 12
+    }
+
+    /// Returns the traffic class.
+    #[inline]
+    pub fn traffic_class(&self) -> u8 {
+        ((self.0 & TC) >> 9) as u8
+    }
+
+    /// Returns whether the entry is the bottom of the stack.
+    #[inline]
+    pub fn bottom_of_stack(&self) -> bool {
+        self.0 & BOS != 0
+    }
+
+    /// Returns the time to live.
+    #[inline]
+    pub fn ttl(&self) -> u8 {
+        (self.0 & TTL) as u8
+    }
+}
+
+impl From<u32be> for MplsLabel {
+    fn from(entry: u32be) -> Self {
+        MplsLabel(entry.into())
+    }
+}
+
+impl fmt::Debug for MplsLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MplsLabel")
+            .field("label", &self.label())
+            .field("traffic_class", &self.traffic_class())
+            .field("bottom_of_stack", &self.bottom_of_stack())
+            .field("ttl", &self.ttl())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the entries of a MPLS label stack.
+pub struct MplsLabelsIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl MplsLabelsIterator<'_> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<MplsLabel>> {
+        if self.end <= self.offset {
+            return Ok(None);
+        }
+
+        let header = self.mbuf.read_data::<MplsHeader>(self.offset)?;
+        self.offset += MplsHeader::size_of();
+
+        Ok(Some(unsafe { header.as_ref() }.entry.into()))
+    }
+}
+
+impl fmt::Debug for MplsLabelsIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MplsLabelsIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// MPLS label stack entry.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct MplsHeader {
+    entry: u32be,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, MPLS_PACKET};
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn size_of_mpls_header() {
+        assert_eq!(4, MplsHeader::size_of());
+    }
+
+    #[test]
+    fn mpls_label_fields() {
+        let label = MplsLabel::new(LABEL_MAX, 7, true, 255);
+        assert_eq!(LABEL_MAX, label.label());
+        assert_eq!(7, label.traffic_class());
+        assert!(label.bottom_of_stack());
+        assert_eq!(255, label.ttl());
+    }
+
+    #[capsule::test]
+    fn parse_mpls_packet() {
+        let packet = Mbuf::from_bytes(&MPLS_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mpls = ethernet.parse::<Mpls>().unwrap();
+
+        assert_eq!(100, mpls.label());
+        assert_eq!(0, mpls.traffic_class());
+        assert!(!mpls.bottom_of_stack());
+        assert_eq!(64, mpls.ttl());
+        assert_eq!(2, mpls.depth());
+        assert_eq!(8, mpls.header_len());
+
+        let mut labels = mpls.labels();
+        let label = labels.next().unwrap().unwrap();
+        assert_eq!(MplsLabel::new(100, 0, false, 64), label);
+        let label = labels.next().unwrap().unwrap();
+        assert_eq!(200, label.label());
+        assert_eq!(5, label.traffic_class());
+        assert!(label.bottom_of_stack());
+        assert_eq!(63, label.ttl());
+        assert!(labels.next().unwrap().is_none());
+
+        assert_eq!(Some(EtherTypes::Ipv4), mpls.payload_type());
+        let ethernet = mpls.decap().unwrap();
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert_eq!(Ipv4Addr::new(139, 133, 217, 110), ipv4.src());
+    }
+
+    #[capsule::test]
+    fn parse_non_mpls_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        assert!(ethernet.parse::<Mpls>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_mpls_label_stack() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+
+        let mut mpls = ethernet.push::<Mpls>().unwrap();
+        mpls.set_label(200);
+        assert!(mpls.bottom_of_stack());
+        assert_eq!(1, mpls.depth());
+
+        let mut mpls = mpls.deparse().push::<Mpls>().unwrap();
+        mpls.set_label(100);
+        assert!(!mpls.bottom_of_stack());
+        assert_eq!(2, mpls.depth());
+        assert_eq!(EtherTypes::Mpls, mpls.envelope().ether_type());
+
+        let ethernet = mpls.decap().unwrap();
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), ethernet.to_vec());
+    }
+}
@@ -0,0 +1,627 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Network Service Header.
+
+use crate::packets::tunnel::{self, NextProtocol};
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{EtherTypes, Ethernet, Internal, Packet};
+use crate::{bitfield, ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The metadata type with fixed length context headers.
+pub const MD_TYPE_1: u8 = 0x1;
+
+/// The metadata type with variable length context headers.
+pub const MD_TYPE_2: u8 = 0x2;
+
+// Masks.
+const FLAGS_O: u16be = u16be(u16::to_be(0b0010_0000_0000_0000));
+const MD_TYPE: u8 = 0b0000_1111;
+const SPI_MAX: u32 = 0x00ff_ffff;
+
+// Next protocols.
+const NEXT_IPV4: u8 = 0x01;
+const NEXT_IPV6: u8 = 0x02;
+const NEXT_ETHERNET: u8 = 0x03;
+const NEXT_NSH: u8 = 0x04;
+const NEXT_MPLS: u8 = 0x05;
+
+/// The length of the fixed context headers of MD type 1.
+const MD_TYPE_1_CONTEXT_LEN: usize = 16;
+
+/// Network Service Header based on [IETF RFC 8300].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |Ver|O|U|    TTL    |   Length  |U|U|U|U|MD Type| Next Protocol |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          Service Path Identifier (SPI)        | Service Index |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// ~              Context Header(s)                                ~
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Version*: (2 bits)
+///      The version of NSH, currently 0.
+///
+/// - *O*: (1 bit)
+///      Set to 1 to indicate that the packet is an OAM packet.
+///
+/// - *TTL*: (6 bits)
+///      Decremented by the service function forwarders, the packet is
+///      discarded when it reaches 0.
+///
+/// - *Length*: (6 bits)
+///      The total length of the NSH in 4-byte words, including the base
+///      header, the service path header and the context headers.
+///
+/// - *MD Type*: (4 bits)
+///      The format of the context headers. MD type 1 has 16 bytes of fixed
+///      length context headers, MD type 2 has zero or more variable length
+///      context headers.
+///
+/// - *Next Protocol*: (8 bits)
+///      The protocol of the payload. 1 is IPv4, 2 is IPv6, 3 is Ethernet,
+///      4 is NSH and 5 is MPLS.
+///
+/// - *Service Path Identifier (SPI)*: (24 bits)
+///      Identifies the service function path.
+///
+/// - *Service Index (SI)*: (8 bits)
+///      The location within the service function path, decremented by each
+///      service function.
+///
+/// NSH is carried in Ethernet frames with the Ethernet type set to
+/// [`EtherTypes::Nsh`]. Use [`decap`] to remove the NSH and get an Ethernet
+/// frame that can be parsed as the [`next_protocol`].
+///
+/// [IETF RFC 8300]: https://tools.ietf.org/html/rfc8300#section-2
+/// [`decap`]: Nsh::decap
+/// [`next_protocol`]: Nsh::next_protocol
+pub struct Nsh {
+    envelope: Ethernet,
+    header: NonNull<NshHeader>,
+    offset: usize,
+}
+
+impl Nsh {
+    #[inline]
+    fn header(&self) -> &NshHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut NshHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    bitfield! {
+        ver_to_length: u16 {
+            /// Returns the version. Should always be `0`.
+            #[inline]
+            pub get version -> u8 = 14..16;
+
+            /// Returns the time to live.
+            #[inline]
+            pub get ttl -> u8 = 6..12;
+
+            /// Sets the time to live. Only the lower 6 bits are used.
+            #[inline]
+            pub set set_ttl(u8) = 6..12;
+
+            /// Returns the total length of the header in 4-byte words.
+            #[inline]
+            pub get length -> u8 = 0..6;
+
+            #[inline]
+            set set_length(u8) = 0..6;
+        }
+    }
+
+    /// Returns a flag indicating whether the packet is an OAM packet.
+    #[inline]
+    pub fn oam(&self) -> bool {
+        self.header().ver_to_length & FLAGS_O != u16be::MIN
+    }
+
+    /// Sets the OAM flag.
+    #[inline]
+    pub fn set_oam(&mut self, oam: bool) {
+        if oam {
+            self.header_mut().ver_to_length |= FLAGS_O;
+        } else {
+            self.header_mut().ver_to_length &= !FLAGS_O;
+        }
+    }
+
+    /// Returns the metadata type.
+    #[inline]
+    pub fn md_type(&self) -> u8 {
+        self.header().md_type & MD_TYPE
+    }
+
+    /// Returns the protocol of the payload, or `None` if the next protocol
+    /// is not supported.
+    #[inline]
+    pub fn next_protocol(&self) -> Option<NextProtocol> {
+        match self.header().next_protocol {
+            NEXT_IPV4 => Some(NextProtocol::Ipv4),
+            NEXT_IPV6 => Some(NextProtocol::Ipv6),
+            NEXT_ETHERNET => Some(NextProtocol::Ethernet),
+            NEXT_NSH => Some(NextProtocol::Nsh),
+            NEXT_MPLS => Some(NextProtocol::Mpls),
+            _ => None,
+        }
+    }
+
+    /// Sets the protocol of the payload.
+    #[inline]
+    pub fn set_next_protocol(&mut self, next: NextProtocol) {
+        self.header_mut().next_protocol = match next {
+            NextProtocol::Ipv4 => NEXT_IPV4,
+            NextProtocol::Ipv6 => NEXT_IPV6,
+            NextProtocol::Ethernet => NEXT_ETHERNET,
+            NextProtocol::Nsh => NEXT_NSH,
+            NextProtocol::Mpls => NEXT_MPLS,
+        };
+    }
+
+    /// Returns the service path identifier.
+    #[inline]
+    pub fn service_path_id(&self) -> u32 {
+        u32::from(self.header().spi_si) >> 8
+    }
+
+    /// Sets the service path identifier.
+    ///
+    /// Only the lower 24 bits of the `spi` are used.
+    #[inline]
+    pub fn set_service_path_id(&mut self, spi: u32) {
+        let si = self.service_index() as u32;
+        self.header_mut().spi_si = (((spi & SPI_MAX) << 8) | si).into();
+    }
+
+    /// Returns the service index.
+    #[inline]
+    pub fn service_index(&self) -> u8 {
+        u32::from(self.header().spi_si) as u8
+    }
+
+    /// Sets the service index.
+    #[inline]
+    pub fn set_service_index(&mut self, si: u8) {
+        let spi_si = u32::from(self.header().spi_si) & !0xff;
+        self.header_mut().spi_si = (spi_si | si as u32).into();
+    }
+
+    /// Decrements the service index, as a service function does after
+    /// processing the packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the service index is already 0. The packet
+    /// should be discarded.
+    #[inline]
+    pub fn decrement_si(&mut self) -> Result<()> {
+        let si = self.service_index();
+        ensure!(si > 0, anyhow!("NSH service index is already 0."));
+        self.set_service_index(si - 1);
+        Ok(())
+    }
+
+    /// Returns the fixed length context headers, or `None` if the metadata
+    /// type is not [`MD_TYPE_1`].
+    #[inline]
+    pub fn context_headers(&self) -> Option<[u32; 4]> {
+        if self.md_type() != MD_TYPE_1 || self.header_len() < 24 {
+            return None;
+        }
+
+        // the context headers are checked when parsed.
+        let offset = self.offset + NshHeader::size_of();
+        let data = self
+            .mbuf()
+            .read_data_slice::<u32be>(offset, MD_TYPE_1_CONTEXT_LEN / 4)
+            .ok()?;
+        let data = unsafe { data.as_ref() };
+        Some([
+            data[0].into(),
+            data[1].into(),
+            data[2].into(),
+            data[3].into(),
+        ])
+    }
+
+    /// Returns an iterator that iterates through the variable length
+    /// context headers. The iterator is empty if the metadata type is not
+    /// [`MD_TYPE_2`].
+    #[inline]
+    pub fn metadata(&self) -> NshMetadataIterator<'_> {
+        let offset = self.offset + NshHeader::size_of();
+        let end = if self.md_type() == MD_TYPE_2 {
+            self.payload_offset()
+        } else {
+            offset
+        };
+
+        NshMetadataIterator {
+            mbuf: self.mbuf(),
+            offset,
+            end,
+        }
+    }
+
+    /// Removes the NSH and returns an Ethernet frame that can be parsed as
+    /// the [`next_protocol`].
+    ///
+    /// If the payload is an Ethernet frame, the inner frame is returned.
+    /// Otherwise, the outer Ethernet frame is returned with the Ethernet
+    /// type set to the next protocol.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the next protocol is not supported, or if the
+    /// payload is not an Ethernet frame when the next protocol is Ethernet.
+    ///
+    /// [`next_protocol`]: Nsh::next_protocol
+    #[inline]
+    pub fn decap(self) -> Result<Ethernet> {
+        let next = self
+            .next_protocol()
+            .ok_or_else(|| anyhow!("unknown NSH payload type."))?;
+        tunnel::decap_next(self, next)
+    }
+}
+
+impl fmt::Debug for Nsh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("nsh")
+            .field("version", &self.version())
+            .field("oam", &self.oam())
+            .field("ttl", &self.ttl())
+            .field("length", &self.length())
+            .field("md_type", &self.md_type())
+            .field("next_protocol", &self.next_protocol())
+            .field("service_path_id", &self.service_path_id())
+            .field("service_index", &self.service_index())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Nsh {
+    /// The preceding type for NSH must be `Ethernet`.
+    type Envelope = Ethernet;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the NSH, including the context headers.
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.length() as usize * 4
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Nsh {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the Ethernet's payload as an NSH packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`ether_type`] is not set to [`EtherTypes::Nsh`].
+    /// Returns an error if the version is not 0, or if the length is less
+    /// than the base and service path headers. Returns an error if the
+    /// payload does not have sufficient data for the NSH, including the
+    /// context headers.
+    ///
+    /// [`ether_type`]: Ethernet::ether_type
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.ether_type() == EtherTypes::Nsh,
+            anyhow!("not an NSH packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Nsh {
+            envelope,
+            header,
+            offset,
+        };
+
+        ensure!(
+            packet.version() == 0,
+            anyhow!("unsupported NSH version {}.", packet.version())
+        );
+        ensure!(
+            packet.header_len() >= NshHeader::size_of(),
+            anyhow!("invalid NSH length {}.", packet.length())
+        );
+
+        // makes sure the context headers are in the buffer.
+        let _ = packet
+            .mbuf()
+            .read_data_slice::<u8>(offset, packet.header_len())?;
+
+        Ok(packet)
+    }
+
+    /// Prepends an NSH with MD type 2 and no context headers to the
+    /// beginning of the Ethernet's payload.
+    ///
+    /// [`ether_type`] is set to [`EtherTypes::Nsh`], and the next protocol
+    /// is set to Ethernet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`ether_type`]: Ethernet::ether_type
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, NshHeader::size_of())?;
+        let header = mbuf.write_data(offset, &NshHeader::default())?;
+
+        envelope.set_ether_type(EtherTypes::Nsh);
+
+        let mut packet = Nsh {
+            envelope,
+            header,
+            offset,
+        };
+        packet.set_length((NshHeader::size_of() / 4) as u8);
+
+        Ok(packet)
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// An NSH variable length context header.
+pub struct NshMetadata<'a> {
+    class: u16,
+    metadata_type: u8,
+    value: &'a [u8],
+}
+
+impl<'a> NshMetadata<'a> {
+    /// Returns the metadata class.
+    #[inline]
+    pub fn class(&self) -> u16 {
+        self.class
+    }
+
+    /// Returns the metadata type.
+    #[inline]
+    pub fn metadata_type(&self) -> u8 {
+        self.metadata_type
+    }
+
+    /// Returns the value, excluding the padding.
+    #[inline]
+    pub fn value(&self) -> &'a [u8] {
+        self.value
+    }
+}
+
+impl fmt::Debug for NshMetadata<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NshMetadata")
+            .field("class", &format!("0x{:04x}", self.class()))
+            .field("metadata_type", &self.metadata_type())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the NSH variable length context
+/// headers.
+pub struct NshMetadataIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> NshMetadataIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<NshMetadata<'a>>> {
+        if self.end <= self.offset {
+            return Ok(None);
+        }
+
+        let header = self.mbuf.read_data::<MetadataHeader>(self.offset)?;
+        let header = unsafe { *header.as_ref() };
+        let len = (header.length & 0x7f) as usize;
+        let value_offset = self.offset + MetadataHeader::size_of();
+        // the value is padded to a 4-byte boundary.
+        let padded_len = (len + 3) & !3;
+
+        ensure!(
+            value_offset + padded_len <= self.end,
+            anyhow!("invalid NSH metadata length {}.", len)
+        );
+
+        let value: &'a [u8] = if len > 0 {
+            let data = self.mbuf.read_data_slice::<u8>(value_offset, len)?;
+            unsafe { &*data.as_ptr() }
+        } else {
+            &[]
+        };
+
+        self.offset = value_offset + padded_len;
+
+        Ok(Some(NshMetadata {
+            class: header.class.into(),
+            metadata_type: header.metadata_type,
+            value,
+        }))
+    }
+}
+
+impl fmt::Debug for NshMetadataIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NshMetadataIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+/// NSH variable length context header class, type and length.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct MetadataHeader {
+    class: u16be,
+    metadata_type: u8,
+    length: u8,
+}
+
+/// NSH base header and service path header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct NshHeader {
+    ver_to_length: u16be,
+    md_type: u8,
+    next_protocol: u8,
+    spi_si: u32be,
+}
+
+impl Default for NshHeader {
+    fn default() -> NshHeader {
+        NshHeader {
+            // ttl = 63.
+            ver_to_length: u16be::from(0x0fc0),
+            md_type: MD_TYPE_2,
+            next_protocol: NEXT_ETHERNET,
+            spi_si: u32be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, NSH_PACKET};
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn size_of_nsh_header() {
+        assert_eq!(8, NshHeader::size_of());
+        assert_eq!(4, MetadataHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_md_type_1_nsh_packet() {
+        let packet = Mbuf::from_bytes(&NSH_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let nsh = ethernet.parse::<Nsh>().unwrap();
+
+        assert_eq!(0, nsh.version());
+        assert!(!nsh.oam());
+        assert_eq!(63, nsh.ttl());
+        assert_eq!(6, nsh.length());
+        assert_eq!(24, nsh.header_len());
+        assert_eq!(MD_TYPE_1, nsh.md_type());
+        assert_eq!(Some(NextProtocol::Ipv4), nsh.next_protocol());
+        assert_eq!(42, nsh.service_path_id());
+        assert_eq!(255, nsh.service_index());
+        assert_eq!(Some([1, 2, 3, 4]), nsh.context_headers());
+        assert!(nsh.metadata().next().unwrap().is_none());
+
+        let ethernet = nsh.decap().unwrap();
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert_eq!(Ipv4Addr::new(139, 133, 217, 110), ipv4.src());
+    }
+
+    #[capsule::test]
+    fn decrement_service_index() {
+        let mut bytes = NSH_PACKET;
+        // spi = 42, si = 1
+        bytes[21] = 0x01;
+
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut nsh = ethernet.parse::<Nsh>().unwrap();
+
+        assert!(nsh.decrement_si().is_ok());
+        assert_eq!(0, nsh.service_index());
+        assert_eq!(42, nsh.service_path_id());
+        assert!(nsh.decrement_si().is_err());
+    }
+
+    #[capsule::test]
+    fn push_md_type_2_nsh_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let mut nsh = ethernet.push::<Nsh>().unwrap();
+        nsh.set_service_path_id(7);
+        nsh.set_service_index(254);
+        nsh.set_next_protocol(NextProtocol::Ipv4);
+
+        assert_eq!(EtherTypes::Nsh, nsh.envelope().ether_type());
+        assert_eq!(MD_TYPE_2, nsh.md_type());
+        assert_eq!(2, nsh.length());
+        assert_eq!(7, nsh.service_path_id());
+        assert_eq!(254, nsh.service_index());
+        assert_eq!(Some(NextProtocol::Ipv4), nsh.next_protocol());
+        assert_eq!(None, nsh.context_headers());
+        assert!(nsh.metadata().next().unwrap().is_none());
+    }
+}
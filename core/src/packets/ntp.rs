@@ -0,0 +1,628 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Network Time Protocol.
+
+use crate::packets::ip::IpPacket;
+use crate::packets::types::u32be;
+use crate::packets::{Internal, Packet, Udp};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The UDP port of NTP.
+pub const NTP_PORT: u16 = 123;
+
+/// Seconds between the NTP epoch, 1 January 1900, and the UNIX epoch.
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+// Masks.
+const LI: u8 = 0b1100_0000;
+const VN: u8 = 0b0011_1000;
+const MODE: u8 = 0b0000_0111;
+
+/// Network Time Protocol packet based on [IETF RFC 5905].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |LI | VN  |Mode |    Stratum     |     Poll      |  Precision   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         Root Delay                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         Root Dispersion                       |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                          Reference ID                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// +                     Reference Timestamp (64)                  +
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// +                      Origin Timestamp (64)                    +
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// +                      Receive Timestamp (64)                   +
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// +                      Transmit Timestamp (64)                  +
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Leap Indicator (LI)*: (2 bits)
+///      Warning of an impending leap second to be inserted or deleted in
+///      the last minute of the current month.
+///
+/// - *Version Number (VN)*: (3 bits)
+///      The NTP version number, currently 4.
+///
+/// - *Mode*: (3 bits)
+///      The association mode, for example 3 for a client request and 4 for
+///      a server response.
+///
+/// - *Stratum*: (8 bits)
+///      The stratum of the server clock, 1 for a primary server.
+///
+/// - *Poll*: (8 bits)
+///      Signed integer of the maximum interval between successive messages,
+///      in log2 seconds.
+///
+/// - *Precision*: (8 bits)
+///      Signed integer of the precision of the system clock, in log2
+///      seconds.
+///
+/// - *Root Delay*: (32 bits)
+///      Total round-trip delay to the reference clock, in NTP short format.
+///
+/// - *Root Dispersion*: (32 bits)
+///      Total dispersion to the reference clock, in NTP short format.
+///
+/// - *Reference ID*: (32 bits)
+///      Code identifying the particular server or reference clock.
+///
+/// - *Reference Timestamp*: (64 bits)
+///      Time when the system clock was last set or corrected.
+///
+/// - *Origin Timestamp*: (64 bits)
+///      Time at the client when the request departed for the server.
+///
+/// - *Receive Timestamp*: (64 bits)
+///      Time at the server when the request arrived from the client.
+///
+/// - *Transmit Timestamp*: (64 bits)
+///      Time at the server when the response left for the client, or time
+///      at the client when the request left for the server.
+///
+/// The optional extension fields and the message authentication code are
+/// the payload of the packet.
+///
+/// [IETF RFC 5905]: https://tools.ietf.org/html/rfc5905
+pub struct Ntp<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<NtpHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Ntp<E> {
+    #[inline]
+    fn header(&self) -> &NtpHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut NtpHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the leap indicator.
+    #[inline]
+    pub fn leap_indicator(&self) -> u8 {
+        (self.header().li_vn_mode & LI) >> 6
+    }
+
+    /// Sets the leap indicator.
+    #[inline]
+    pub fn set_leap_indicator(&mut self, li: u8) {
+        self.header_mut().li_vn_mode = (self.header().li_vn_mode & !LI) | ((li << 6) & LI);
+    }
+
+    /// Returns the version number.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        (self.header().li_vn_mode & VN) >> 3
+    }
+
+    /// Sets the version number.
+    #[inline]
+    pub fn set_version(&mut self, version: u8) {
+        self.header_mut().li_vn_mode = (self.header().li_vn_mode & !VN) | ((version << 3) & VN);
+    }
+
+    /// Returns the association mode.
+    #[inline]
+    pub fn mode(&self) -> Mode {
+        Mode::new(self.header().li_vn_mode & MODE)
+    }
+
+    /// Sets the association mode.
+    #[inline]
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.header_mut().li_vn_mode = (self.header().li_vn_mode & !MODE) | (mode.0 & MODE);
+    }
+
+    /// Returns the stratum.
+    #[inline]
+    pub fn stratum(&self) -> u8 {
+        self.header().stratum
+    }
+
+    /// Sets the stratum.
+    #[inline]
+    pub fn set_stratum(&mut self, stratum: u8) {
+        self.header_mut().stratum = stratum
+    }
+
+    /// Returns the poll interval in log2 seconds.
+    #[inline]
+    pub fn poll(&self) -> i8 {
+        self.header().poll
+    }
+
+    /// Sets the poll interval in log2 seconds.
+    #[inline]
+    pub fn set_poll(&mut self, poll: i8) {
+        self.header_mut().poll = poll
+    }
+
+    /// Returns the precision in log2 seconds.
+    #[inline]
+    pub fn precision(&self) -> i8 {
+        self.header().precision
+    }
+
+    /// Sets the precision in log2 seconds.
+    #[inline]
+    pub fn set_precision(&mut self, precision: i8) {
+        self.header_mut().precision = precision
+    }
+
+    /// Returns the root delay in NTP short format.
+    #[inline]
+    pub fn root_delay(&self) -> u32 {
+        self.header().root_delay.into()
+    }
+
+    /// Sets the root delay in NTP short format.
+    #[inline]
+    pub fn set_root_delay(&mut self, delay: u32) {
+        self.header_mut().root_delay = delay.into()
+    }
+
+    /// Returns the root dispersion in NTP short format.
+    #[inline]
+    pub fn root_dispersion(&self) -> u32 {
+        self.header().root_dispersion.into()
+    }
+
+    /// Sets the root dispersion in NTP short format.
+    #[inline]
+    pub fn set_root_dispersion(&mut self, dispersion: u32) {
+        self.header_mut().root_dispersion = dispersion.into()
+    }
+
+    /// Returns the reference ID.
+    #[inline]
+    pub fn reference_id(&self) -> u32 {
+        self.header().reference_id.into()
+    }
+
+    /// Sets the reference ID.
+    #[inline]
+    pub fn set_reference_id(&mut self, id: u32) {
+        self.header_mut().reference_id = id.into()
+    }
+
+    /// Returns the reference timestamp.
+    #[inline]
+    pub fn reference_timestamp(&self) -> NtpTimestamp {
+        self.header().reference_ts.into()
+    }
+
+    /// Sets the reference timestamp.
+    #[inline]
+    pub fn set_reference_timestamp(&mut self, ts: NtpTimestamp) {
+        self.header_mut().reference_ts = ts.into()
+    }
+
+    /// Returns the origin timestamp.
+    #[inline]
+    pub fn origin_timestamp(&self) -> NtpTimestamp {
+        self.header().origin_ts.into()
+    }
+
+    /// Sets the origin timestamp.
+    #[inline]
+    pub fn set_origin_timestamp(&mut self, ts: NtpTimestamp) {
+        self.header_mut().origin_ts = ts.into()
+    }
+
+    /// Returns the receive timestamp.
+    #[inline]
+    pub fn receive_timestamp(&self) -> NtpTimestamp {
+        self.header().receive_ts.into()
+    }
+
+    /// Sets the receive timestamp.
+    #[inline]
+    pub fn set_receive_timestamp(&mut self, ts: NtpTimestamp) {
+        self.header_mut().receive_ts = ts.into()
+    }
+
+    /// Returns the transmit timestamp.
+    #[inline]
+    pub fn transmit_timestamp(&self) -> NtpTimestamp {
+        self.header().transmit_ts.into()
+    }
+
+    /// Sets the transmit timestamp.
+    #[inline]
+    pub fn set_transmit_timestamp(&mut self, ts: NtpTimestamp) {
+        self.header_mut().transmit_ts = ts.into()
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Ntp<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ntp")
+            .field("leap_indicator", &self.leap_indicator())
+            .field("version", &self.version())
+            .field("mode", &format!("{}", self.mode()))
+            .field("stratum", &self.stratum())
+            .field("poll", &self.poll())
+            .field("precision", &self.precision())
+            .field("root_delay", &self.root_delay())
+            .field("root_dispersion", &self.root_dispersion())
+            .field("reference_id", &format!("0x{:08x}", self.reference_id()))
+            .field("reference_timestamp", &self.reference_timestamp())
+            .field("origin_timestamp", &self.origin_timestamp())
+            .field("receive_timestamp", &self.receive_timestamp())
+            .field("transmit_timestamp", &self.transmit_timestamp())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Ntp<E> {
+    /// The preceding type for NTP must be `Udp`.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        NtpHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Ntp {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP payload as an NTP packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither the source nor the destination port is
+    /// [`NTP_PORT`]. Returns an error if the payload does not have
+    /// sufficient data for the NTP header.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.src_port() == NTP_PORT || envelope.dst_port() == NTP_PORT,
+            anyhow!("not an NTP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        Ok(Ntp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an NTP packet to the beginning of the UDP's payload.
+    ///
+    /// The version is set to 4 and all the other fields are zeroed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, NtpHeader::size_of())?;
+        let header = mbuf.write_data(offset, &NtpHeader::default())?;
+
+        Ok(Ntp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// NTP association mode.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct Mode(pub u8);
+
+impl Mode {
+    /// Creates a new association mode.
+    pub fn new(value: u8) -> Self {
+        Mode(value)
+    }
+}
+
+/// Supported association modes.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod Modes {
+    use super::Mode;
+
+    /// Symmetric active.
+    pub const SymmetricActive: Mode = Mode(1);
+    /// Symmetric passive.
+    pub const SymmetricPassive: Mode = Mode(2);
+    /// Client.
+    pub const Client: Mode = Mode(3);
+    /// Server.
+    pub const Server: Mode = Mode(4);
+    /// Broadcast.
+    pub const Broadcast: Mode = Mode(5);
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Modes::SymmetricActive => "Symmetric Active".to_string(),
+                Modes::SymmetricPassive => "Symmetric Passive".to_string(),
+                Modes::Client => "Client".to_string(),
+                Modes::Server => "Server".to_string(),
+                Modes::Broadcast => "Broadcast".to_string(),
+                _ => {
+                    let mode = self.0;
+                    format!("{}", mode)
+                }
+            }
+        )
+    }
+}
+
+/// NTP timestamp in the 64-bit fixed-point format.
+///
+/// The timestamp is the seconds since the NTP epoch, 1 January 1900, and
+/// the fraction of a second in units of 2^-32 seconds. The era is assumed
+/// to be 0, so the timestamps wrap in 2036.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NtpTimestamp {
+    /// Seconds since the NTP epoch.
+    pub seconds: u32,
+    /// Fraction of a second.
+    pub fraction: u32,
+}
+
+impl NtpTimestamp {
+    /// Creates a new timestamp.
+    pub fn new(seconds: u32, fraction: u32) -> Self {
+        NtpTimestamp { seconds, fraction }
+    }
+
+    /// Returns whether the timestamp is 0, which means the time is unknown.
+    pub fn is_zero(&self) -> bool {
+        self.seconds == 0 && self.fraction == 0
+    }
+
+    /// Converts the timestamp to a `SystemTime`.
+    pub fn to_system_time(&self) -> SystemTime {
+        let nanos = (u64::from(self.fraction) * 1_000_000_000) >> 32;
+        let since_ntp_epoch = Duration::new(u64::from(self.seconds), nanos as u32);
+        let ntp_epoch = UNIX_EPOCH - Duration::from_secs(NTP_UNIX_OFFSET);
+        ntp_epoch + since_ntp_epoch
+    }
+
+    /// Converts a `SystemTime` to a timestamp.
+    ///
+    /// Returns `None` if the time is before the NTP epoch or after the end
+    /// of era 0.
+    pub fn from_system_time(time: SystemTime) -> Option<Self> {
+        let ntp_epoch = UNIX_EPOCH - Duration::from_secs(NTP_UNIX_OFFSET);
+        let since_ntp_epoch = time.duration_since(ntp_epoch).ok()?;
+
+        let seconds = since_ntp_epoch.as_secs();
+        if seconds > u64::from(u32::MAX) {
+            return None;
+        }
+        let fraction = (u64::from(since_ntp_epoch.subsec_nanos()) << 32) / 1_000_000_000;
+
+        Some(NtpTimestamp::new(seconds as u32, fraction as u32))
+    }
+}
+
+impl From<[u32be; 2]> for NtpTimestamp {
+    fn from(ts: [u32be; 2]) -> Self {
+        NtpTimestamp::new(ts[0].into(), ts[1].into())
+    }
+}
+
+impl From<NtpTimestamp> for [u32be; 2] {
+    fn from(ts: NtpTimestamp) -> Self {
+        [ts.seconds.into(), ts.fraction.into()]
+    }
+}
+
+/// NTP header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct NtpHeader {
+    li_vn_mode: u8,
+    stratum: u8,
+    poll: i8,
+    precision: i8,
+    root_delay: u32be,
+    root_dispersion: u32be,
+    reference_id: u32be,
+    reference_ts: [u32be; 2],
+    origin_ts: [u32be; 2],
+    receive_ts: [u32be; 2],
+    transmit_ts: [u32be; 2],
+}
+
+impl Default for NtpHeader {
+    fn default() -> NtpHeader {
+        NtpHeader {
+            li_vn_mode: 4 << 3,
+            stratum: 0,
+            poll: 0,
+            precision: 0,
+            root_delay: u32be::default(),
+            root_dispersion: u32be::default(),
+            reference_id: u32be::default(),
+            reference_ts: [u32be::default(); 2],
+            origin_ts: [u32be::default(); 2],
+            receive_ts: [u32be::default(); 2],
+            transmit_ts: [u32be::default(); 2],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, NTP_REQUEST_PACKET};
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_ntp_header() {
+        assert_eq!(48, NtpHeader::size_of());
+    }
+
+    #[test]
+    fn ntp_timestamp_to_system_time() {
+        let ts = NtpTimestamp::new(0xe308_8e80, 0x8000_0000);
+        let time = UNIX_EPOCH + Duration::new(1_600_000_000, 500_000_000);
+        assert_eq!(time, ts.to_system_time());
+        assert_eq!(Some(ts), NtpTimestamp::from_system_time(time));
+
+        let ts = NtpTimestamp::new(NTP_UNIX_OFFSET as u32, 0);
+        assert_eq!(UNIX_EPOCH, ts.to_system_time());
+    }
+
+    #[capsule::test]
+    fn parse_ntp_packet() {
+        let packet = Mbuf::from_bytes(&NTP_REQUEST_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let ntp = udp.parse::<Ntp<Ipv4>>().unwrap();
+
+        assert_eq!(0, ntp.leap_indicator());
+        assert_eq!(4, ntp.version());
+        assert_eq!(Modes::Client, ntp.mode());
+        assert_eq!(0, ntp.stratum());
+        assert_eq!(6, ntp.poll());
+        assert_eq!(-20, ntp.precision());
+        assert!(ntp.origin_timestamp().is_zero());
+        assert_eq!(
+            NtpTimestamp::new(0xe308_8e80, 0x8000_0000),
+            ntp.transmit_timestamp()
+        );
+        assert_eq!(
+            UNIX_EPOCH + Duration::new(1_600_000_000, 500_000_000),
+            ntp.transmit_timestamp().to_system_time()
+        );
+    }
+
+    #[capsule::test]
+    fn parse_non_ntp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Ntp<Ipv4>>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_ntp_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let mut ntp = udp.push::<Ntp<Ipv4>>().unwrap();
+
+        ntp.set_mode(Modes::Server);
+        ntp.set_stratum(1);
+        ntp.set_leap_indicator(3);
+
+        assert_eq!(48, ntp.len());
+        assert_eq!(4, ntp.version());
+        assert_eq!(Modes::Server, ntp.mode());
+        assert_eq!(3, ntp.leap_indicator());
+        assert_eq!(1, ntp.stratum());
+    }
+}
@@ -0,0 +1,512 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Open Shortest Path First.
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::ProtocolNumbers;
+use crate::packets::types::u16be;
+use crate::packets::{checksum, Internal, Packet};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::ptr::NonNull;
+
+/// Offset of the authentication field in the OSPF header.
+const AUTHENTICATION_OFFSET: usize = 16;
+
+/// Open Shortest Path First version 2 packet header based on
+/// [IETF RFC 2328].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   Version #   |     Type      |         Packet length         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                          Router ID                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                           Area ID                             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           Checksum            |             AuType            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                       Authentication                          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                       Authentication                          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Version #*: (8 bits)
+///      The OSPF version number, set to 2.
+///
+/// - *Type*: (8 bits)
+///      The OSPF packet type.
+///
+/// - *Packet length*: (16 bits)
+///      The length of the OSPF packet in bytes, including the header.
+///
+/// - *Router ID*: (32 bits)
+///      The router ID of the packet's source.
+///
+/// - *Area ID*: (32 bits)
+///      Identifies the area that this packet belongs to.
+///
+/// - *Checksum*: (16 bits)
+///      The standard IP checksum of the entire contents of the packet,
+///      excluding the 64-bit authentication field.
+///
+/// - *AuType*: (16 bits)
+///      Identifies the authentication procedure to be used for the packet.
+///
+/// - *Authentication*: (64 bits)
+///      A 64-bit field for use by the authentication scheme.
+///
+/// The type-specific body follows the header, and is the payload of the
+/// packet. Use [`body`] to read the body up to the packet length.
+///
+/// [IETF RFC 2328]: https://tools.ietf.org/html/rfc2328#appendix-A.3.1
+/// [`body`]: Ospf::body
+pub struct Ospf {
+    envelope: Ipv4,
+    header: NonNull<OspfHeader>,
+    offset: usize,
+}
+
+impl Ospf {
+    #[inline]
+    fn header(&self) -> &OspfHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut OspfHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the version.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        self.header().version
+    }
+
+    /// Returns the packet type.
+    #[inline]
+    pub fn msg_type(&self) -> OspfType {
+        OspfType::new(self.header().msg_type)
+    }
+
+    /// Sets the packet type.
+    #[inline]
+    pub fn set_msg_type(&mut self, msg_type: OspfType) {
+        self.header_mut().msg_type = msg_type.0;
+    }
+
+    /// Returns the packet length, including the header.
+    #[inline]
+    pub fn length(&self) -> u16 {
+        self.header().length.into()
+    }
+
+    #[inline]
+    fn set_length(&mut self, length: u16) {
+        self.header_mut().length = length.into();
+    }
+
+    /// Returns the router ID.
+    #[inline]
+    pub fn router_id(&self) -> Ipv4Addr {
+        self.header().router_id
+    }
+
+    /// Sets the router ID.
+    #[inline]
+    pub fn set_router_id(&mut self, router_id: Ipv4Addr) {
+        self.header_mut().router_id = router_id;
+    }
+
+    /// Returns the area ID.
+    #[inline]
+    pub fn area_id(&self) -> Ipv4Addr {
+        self.header().area_id
+    }
+
+    /// Sets the area ID.
+    #[inline]
+    pub fn set_area_id(&mut self, area_id: Ipv4Addr) {
+        self.header_mut().area_id = area_id;
+    }
+
+    /// Returns the checksum.
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        self.header().checksum.into()
+    }
+
+    /// Returns the authentication type.
+    #[inline]
+    pub fn autype(&self) -> u16 {
+        self.header().autype.into()
+    }
+
+    /// Sets the authentication type.
+    #[inline]
+    pub fn set_autype(&mut self, autype: u16) {
+        self.header_mut().autype = autype.into();
+    }
+
+    /// Returns the authentication data.
+    #[inline]
+    pub fn authentication(&self) -> [u8; 8] {
+        self.header().authentication
+    }
+
+    /// Sets the authentication data.
+    #[inline]
+    pub fn set_authentication(&mut self, authentication: [u8; 8]) {
+        self.header_mut().authentication = authentication;
+    }
+
+    /// Returns the offset where the type-specific body begins.
+    #[inline]
+    pub fn body_offset(&self) -> usize {
+        self.payload_offset()
+    }
+
+    /// Returns the type-specific body, up to the packet length.
+    #[inline]
+    pub fn body(&self) -> &[u8] {
+        let len = (self.length() as usize)
+            .saturating_sub(OspfHeader::size_of())
+            .min(self.payload_len());
+        if len > 0 {
+            // the body is in the buffer, should never run out.
+            let data = self
+                .mbuf()
+                .read_data_slice::<u8>(self.body_offset(), len)
+                .unwrap();
+            unsafe { &*data.as_ptr() }
+        } else {
+            &[]
+        }
+    }
+
+    /// Computes the checksum over the OSPF packet, excluding the
+    /// authentication field.
+    #[inline]
+    pub fn compute_checksum(&mut self) {
+        self.header_mut().checksum = u16be::default();
+
+        let len = (self.length() as usize).min(self.len());
+        if let Ok(data) = self.mbuf().read_data_slice::<u8>(self.offset(), len) {
+            let mut data = unsafe { data.as_ref() }.to_vec();
+            let auth_end = (AUTHENTICATION_OFFSET + 8).min(data.len());
+            data[AUTHENTICATION_OFFSET..auth_end]
+                .iter_mut()
+                .for_each(|b| *b = 0);
+            let checksum = checksum::compute(0, &data);
+            self.header_mut().checksum = checksum.into();
+        } else {
+            // we are reading till the end of buffer, should never run out
+            unreachable!()
+        }
+    }
+}
+
+impl fmt::Debug for Ospf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ospf")
+            .field("version", &self.version())
+            .field("type", &format!("{}", self.msg_type()))
+            .field("length", &self.length())
+            .field("router_id", &self.router_id())
+            .field("area_id", &self.area_id())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("autype", &self.autype())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Ospf {
+    /// The preceding type for an OSPF packet must be IPv4.
+    type Envelope = Ipv4;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the common header. The type-specific body is
+    /// the payload.
+    #[inline]
+    fn header_len(&self) -> usize {
+        OspfHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Ospf {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the IPv4 payload as an OSPF packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the [`protocol`] is not [`ProtocolNumbers::Ospf`].
+    /// Returns an error if the payload does not have sufficient data for
+    /// the OSPF header, or the version is not 2.
+    ///
+    /// [`protocol`]: Ipv4::protocol
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.protocol() == ProtocolNumbers::Ospf,
+            anyhow!("not an OSPF packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data::<OspfHeader>(offset)?;
+
+        let version = unsafe { header.as_ref() }.version;
+        ensure!(
+            version == 2,
+            anyhow!("unsupported OSPF version {}.", version)
+        );
+
+        Ok(Ospf {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends an OSPFv2 header to the beginning of the IPv4's payload.
+    ///
+    /// [`protocol`] is set to [`ProtocolNumbers::Ospf`] and the packet type
+    /// is set to [`OspfTypes::Hello`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`protocol`]: Ipv4::protocol
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, OspfHeader::size_of())?;
+        let header = mbuf.write_data(offset, &OspfHeader::default())?;
+
+        envelope.set_protocol(ProtocolNumbers::Ospf);
+
+        Ok(Ospf {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`length`] is set to the length of the OSPF packet.
+    /// * [`checksum`] is computed based on the OSPF packet.
+    ///
+    /// [`length`]: Ospf::length
+    /// [`checksum`]: Ospf::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        let len = self.len();
+        self.set_length(len as u16);
+        self.compute_checksum();
+    }
+}
+
+/// Type of OSPF packet.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct OspfType(pub u8);
+
+impl OspfType {
+    /// Creates a new OSPF packet type.
+    pub fn new(value: u8) -> Self {
+        OspfType(value)
+    }
+}
+
+/// Supported OSPF packet types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod OspfTypes {
+    use super::OspfType;
+
+    /// Discovers and maintains neighbor relationships.
+    pub const Hello: OspfType = OspfType(1);
+    /// Summarizes the database contents.
+    pub const DatabaseDescription: OspfType = OspfType(2);
+    /// Requests link state advertisements.
+    pub const LinkStateRequest: OspfType = OspfType(3);
+    /// Floods link state advertisements.
+    pub const LinkStateUpdate: OspfType = OspfType(4);
+    /// Acknowledges flooded link state advertisements.
+    pub const LinkStateAck: OspfType = OspfType(5);
+}
+
+impl fmt::Display for OspfType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                OspfTypes::Hello => "Hello".to_string(),
+                OspfTypes::DatabaseDescription => "Database Description".to_string(),
+                OspfTypes::LinkStateRequest => "Link State Request".to_string(),
+                OspfTypes::LinkStateUpdate => "Link State Update".to_string(),
+                OspfTypes::LinkStateAck => "Link State Acknowledgment".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("{}", t)
+                }
+            }
+        )
+    }
+}
+
+/// OSPF common header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct OspfHeader {
+    version: u8,
+    msg_type: u8,
+    length: u16be,
+    router_id: Ipv4Addr,
+    area_id: Ipv4Addr,
+    checksum: u16be,
+    autype: u16be,
+    authentication: [u8; 8],
+}
+
+impl Default for OspfHeader {
+    fn default() -> OspfHeader {
+        OspfHeader {
+            version: 2,
+            msg_type: OspfTypes::Hello.0,
+            length: u16be::default(),
+            router_id: Ipv4Addr::UNSPECIFIED,
+            area_id: Ipv4Addr::UNSPECIFIED,
+            checksum: u16be::default(),
+            autype: u16be::default(),
+            authentication: [0; 8],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, OSPF_HELLO_PACKET};
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_ospf_header() {
+        assert_eq!(24, OspfHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_ospf_hello_packet() {
+        let packet = Mbuf::from_bytes(&OSPF_HELLO_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let ospf = ipv4.parse::<Ospf>().unwrap();
+
+        assert_eq!(2, ospf.version());
+        assert_eq!(OspfTypes::Hello, ospf.msg_type());
+        assert_eq!(44, ospf.length());
+        assert_eq!(Ipv4Addr::new(1, 1, 1, 1), ospf.router_id());
+        assert_eq!(Ipv4Addr::new(0, 0, 0, 1), ospf.area_id());
+        assert_eq!(0x38f2, ospf.checksum());
+        assert_eq!(0, ospf.autype());
+        assert_eq!([0; 8], ospf.authentication());
+        assert_eq!(58, ospf.body_offset());
+        assert_eq!(20, ospf.body().len());
+        assert_eq!(&[0xff, 0xff, 0xff, 0x00], &ospf.body()[..4]);
+    }
+
+    #[capsule::test]
+    fn compute_ospf_checksum() {
+        let packet = Mbuf::from_bytes(&OSPF_HELLO_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut ospf = ipv4.parse::<Ospf>().unwrap();
+
+        // the authentication field is excluded from the checksum.
+        ospf.set_authentication([1; 8]);
+        ospf.compute_checksum();
+        assert_eq!(0x38f2, ospf.checksum());
+    }
+
+    #[capsule::test]
+    fn parse_non_ospf_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Ospf>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_and_reconcile_ospf_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut ospf = ipv4.push::<Ospf>().unwrap();
+        ospf.set_router_id(Ipv4Addr::new(2, 2, 2, 2));
+        ospf.reconcile();
+
+        assert_eq!(ProtocolNumbers::Ospf, ospf.envelope().protocol());
+        assert_eq!(2, ospf.version());
+        assert_eq!(OspfTypes::Hello, ospf.msg_type());
+        assert_eq!(24, ospf.length());
+        assert!(ospf.body().is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! QUIC long header parsing.
+//!
+//! QUIC runs over UDP. The functions in this module operate on the UDP
+//! payload. Only the version-independent fields of the long header and the
+//! version 1 packet types are decoded, the rest of the packet is protected.
+
+use crate::ensure;
+use anyhow::Result;
+use thiserror::Error;
+
+/// The header form bit of the first byte, set for a long header.
+const HEADER_FORM: u8 = 0x80;
+
+/// The maximum length of a connection ID in QUIC version 1.
+const MAX_CID_LEN: usize = 20;
+
+/// Error indicating a QUIC parsing failure.
+#[derive(Debug, Error)]
+pub enum QuicError {
+    /// The packet has a short header.
+    #[error("QUIC packet does not have a long header.")]
+    NotLongHeader,
+
+    /// The data ends before the end of the long header.
+    #[error("QUIC long header is truncated.")]
+    Truncated,
+
+    /// The connection ID is longer than the version allows.
+    #[error("QUIC connection ID length {0} is invalid.")]
+    BadConnectionIdLength(usize),
+}
+
+/// Long header packet types of QUIC version 1.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PacketType {
+    /// Initial packet.
+    Initial,
+    /// 0-RTT packet.
+    ZeroRtt,
+    /// Handshake packet.
+    Handshake,
+    /// Retry packet.
+    Retry,
+}
+
+/// QUIC long header based on [IETF RFC 8999] and [IETF RFC 9000].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |1|F| T | Spec  |                   Version ...
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  ... Version  | DCID Length   |  Destination Connection ID ...
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | SCID Length   |    Source Connection ID ...
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Header Form*: (1 bit)
+///      Set to 1 for a long header.
+///
+/// - *Fixed Bit (F)*: (1 bit)
+///      Set to 1 in QUIC version 1, except for version negotiation.
+///
+/// - *Long Packet Type (T)*: (2 bits)
+///      The packet type in QUIC version 1.
+///
+/// - *Type-Specific Bits (Spec)*: (4 bits)
+///      For Initial, 0-RTT and Handshake packets, the low 2 bits are the
+///      packet number length minus one.
+///
+/// - *Version*: (32 bits)
+///      The QUIC version. 0 indicates a version negotiation packet.
+///
+/// - *DCID Length*: (8 bits)
+///      The length of the destination connection ID in octets.
+///
+/// - *Destination Connection ID*: (0-255 octets)
+///
+/// - *SCID Length*: (8 bits)
+///      The length of the source connection ID in octets.
+///
+/// - *Source Connection ID*: (0-255 octets)
+///
+/// [IETF RFC 8999]: https://tools.ietf.org/html/rfc8999#section-5.1
+/// [IETF RFC 9000]: https://tools.ietf.org/html/rfc9000#section-17.2
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LongHeader<'a> {
+    first: u8,
+    version: u32,
+    dcid: &'a [u8],
+    scid: &'a [u8],
+}
+
+impl<'a> LongHeader<'a> {
+    /// Returns whether the UDP payload begins with a long header.
+    pub fn is_long_header(data: &[u8]) -> bool {
+        data.first().map_or(false, |&b| b & HEADER_FORM != 0)
+    }
+
+    /// Parses the long header at the beginning of the UDP payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QuicError::NotLongHeader` if the header form bit is not set.
+    /// Returns `QuicError::Truncated` if the payload ends before the end of
+    /// the source connection ID. Returns `QuicError::BadConnectionIdLength`
+    /// if a connection ID of a version 1 packet is longer than 20 octets.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        ensure!(Self::is_long_header(data), QuicError::NotLongHeader);
+        ensure!(data.len() >= 6, QuicError::Truncated);
+
+        let first = data[0];
+        let version = u32::from_be_bytes([data[1], data[2], data[3], data[4]]);
+
+        let (dcid, rest) = connection_id(&data[5..], version)?;
+        ensure!(!rest.is_empty(), QuicError::Truncated);
+        let (scid, _) = connection_id(rest, version)?;
+
+        Ok(LongHeader {
+            first,
+            version,
+            dcid,
+            scid,
+        })
+    }
+
+    /// Returns the QUIC version.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns whether the packet is a version negotiation packet.
+    pub fn is_version_negotiation(&self) -> bool {
+        self.version == 0
+    }
+
+    /// Returns the destination connection ID.
+    pub fn dcid(&self) -> &'a [u8] {
+        self.dcid
+    }
+
+    /// Returns the source connection ID.
+    pub fn scid(&self) -> &'a [u8] {
+        self.scid
+    }
+
+    /// Returns the packet type of a QUIC version 1 packet.
+    ///
+    /// Returns `None` for other versions, since the packet type is
+    /// version-specific.
+    pub fn packet_type(&self) -> Option<PacketType> {
+        if self.version != 1 {
+            return None;
+        }
+
+        Some(match (self.first >> 4) & 0x03 {
+            0 => PacketType::Initial,
+            1 => PacketType::ZeroRtt,
+            2 => PacketType::Handshake,
+            _ => PacketType::Retry,
+        })
+    }
+
+    /// Returns the length of the packet number in octets.
+    ///
+    /// Returns `None` if the packet is not an Initial, 0-RTT or Handshake
+    /// packet of QUIC version 1. The value is only meaningful after the
+    /// header protection is removed.
+    pub fn packet_number_len(&self) -> Option<usize> {
+        match self.packet_type()? {
+            PacketType::Retry => None,
+            _ => Some((self.first & 0x03) as usize + 1),
+        }
+    }
+}
+
+/// Splits the length-prefixed connection ID from the rest of the data.
+fn connection_id(data: &[u8], version: u32) -> Result<(&[u8], &[u8])> {
+    let len = data[0] as usize;
+    ensure!(
+        version != 1 || len <= MAX_CID_LEN,
+        QuicError::BadConnectionIdLength(len)
+    );
+    ensure!(data.len() > len, QuicError::Truncated);
+    Ok((&data[1..=len], &data[len + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The long header of the client Initial packet from IETF RFC 9001,
+    /// appendix A.2.
+    #[rustfmt::skip]
+    const INITIAL_HEADER: [u8; 22] = [
+        // long header, Initial, packet number length = 4
+        0xc3,
+        // version = 1
+        0x00, 0x00, 0x00, 0x01,
+        // dcid length = 8, dcid
+        0x08, 0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08,
+        // scid length = 0
+        0x00,
+        // token length = 0
+        0x00,
+        // length = 1182
+        0x44, 0x9e,
+        // packet number = 2
+        0x00, 0x00, 0x00, 0x02,
+    ];
+
+    #[test]
+    fn parse_initial_long_header() {
+        let header = LongHeader::parse(&INITIAL_HEADER).unwrap();
+
+        assert_eq!(1, header.version());
+        assert!(!header.is_version_negotiation());
+        assert_eq!(
+            &[0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08],
+            header.dcid()
+        );
+        assert!(header.scid().is_empty());
+        assert_eq!(Some(PacketType::Initial), header.packet_type());
+        assert_eq!(Some(4), header.packet_number_len());
+    }
+
+    #[test]
+    fn parse_version_negotiation() {
+        let mut data = INITIAL_HEADER;
+        data[1..5].copy_from_slice(&[0, 0, 0, 0]);
+        let header = LongHeader::parse(&data).unwrap();
+
+        assert!(header.is_version_negotiation());
+        assert_eq!(None, header.packet_type());
+        assert_eq!(None, header.packet_number_len());
+    }
+
+    #[test]
+    fn parse_bad_long_header() {
+        // short header
+        let mut data = INITIAL_HEADER;
+        data[0] = 0x43;
+        assert!(LongHeader::parse(&data).is_err());
+
+        // truncated dcid
+        assert!(LongHeader::parse(&INITIAL_HEADER[..10]).is_err());
+
+        // dcid too long for version 1
+        let mut data = INITIAL_HEADER;
+        data[5] = 21;
+        assert!(LongHeader::parse(&data).is_err());
+    }
+}
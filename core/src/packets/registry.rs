@@ -0,0 +1,233 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! A registry of protocol handlers for table-driven parse dispatch.
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::{IpPacket, ProtocolNumber};
+use crate::packets::{EtherType, EtherTypes, Ethernet, Packet};
+use crate::pcap::{LinkType, LinkTypes};
+use crate::Mbuf;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Handler closure for a layer of type `T`.
+pub type LayerHandler<T> = dyn Fn(&T) -> Result<()>;
+
+/// A registry that maps protocol identifiers to handler closures.
+///
+/// Handlers are registered once at startup, keyed by the [`EtherType`] of
+/// the Ethernet frame or by the [`ProtocolNumber`] of the IP packet. Each
+/// handler receives the parsed packet of the layer where it is registered,
+/// and can [`peek`] into the payload to parse the next layer.
+///
+/// [`peek`]: Packet::peek
+///
+/// # Example
+///
+/// ```
+/// let mut registry = ParserRegistry::new();
+/// registry.register_ipv4(ProtocolNumbers::Udp, |ipv4| {
+///     let udp = ipv4.peek::<Udp4>()?;
+///     ...
+///     Ok(())
+/// });
+///
+/// registry.dispatch(mbuf)?;
+/// ```
+pub struct ParserRegistry {
+    link_type: LinkType,
+    ether_types: HashMap<EtherType, Box<LayerHandler<Ethernet>>>,
+    ipv4: HashMap<ProtocolNumber, Box<LayerHandler<Ipv4>>>,
+    ipv6: HashMap<ProtocolNumber, Box<LayerHandler<Ipv6>>>,
+}
+
+impl ParserRegistry {
+    /// Creates a new, empty registry for Ethernet frames.
+    pub fn new() -> Self {
+        ParserRegistry::default()
+    }
+
+    /// Returns the link-layer header type of the dispatched packets.
+    pub fn link_type(&self) -> LinkType {
+        self.link_type
+    }
+
+    /// Sets the link-layer header type of the dispatched packets.
+    ///
+    /// Set it to [`LinkTypes::Raw`] for packets from a source without
+    /// link-layer framing, such as a tun device. See [`Mbuf::parse_link`]
+    /// for the supported types.
+    ///
+    /// [`LinkTypes::Raw`]: LinkTypes::Raw
+    /// [`Mbuf::parse_link`]: Mbuf::parse_link
+    pub fn set_link_type(&mut self, link_type: LinkType) {
+        self.link_type = link_type;
+    }
+
+    /// Registers a handler for Ethernet frames of the given ether type.
+    ///
+    /// Replaces the existing handler for the ether type, if any.
+    pub fn register_ether_type<F>(&mut self, ether_type: EtherType, handler: F)
+    where
+        F: Fn(&Ethernet) -> Result<()> + 'static,
+    {
+        self.ether_types.insert(ether_type, Box::new(handler));
+    }
+
+    /// Registers a handler for IPv4 packets of the given next protocol.
+    ///
+    /// Replaces the existing handler for the protocol, if any.
+    pub fn register_ipv4<F>(&mut self, protocol: ProtocolNumber, handler: F)
+    where
+        F: Fn(&Ipv4) -> Result<()> + 'static,
+    {
+        self.ipv4.insert(protocol, Box::new(handler));
+    }
+
+    /// Registers a handler for IPv6 packets of the given next header.
+    ///
+    /// Replaces the existing handler for the protocol, if any.
+    pub fn register_ipv6<F>(&mut self, protocol: ProtocolNumber, handler: F)
+    where
+        F: Fn(&Ipv6) -> Result<()> + 'static,
+    {
+        self.ipv6.insert(protocol, Box::new(handler));
+    }
+
+    /// Walks the packet from Ethernet to IP, invoking the registered
+    /// handler of each layer.
+    ///
+    /// The packet is parsed according to the [`link_type`] of the registry.
+    /// An IP packet without link-layer framing is dispatched as if it is
+    /// enveloped in an Ethernet frame.
+    ///
+    /// Returns whether any handler was invoked. An IP layer is only parsed
+    /// if there is a handler registered for its next protocol.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a layer fails to parse, or a handler returns an
+    /// error. The remaining handlers are not invoked.
+    ///
+    /// [`link_type`]: ParserRegistry::link_type
+    pub fn dispatch(&self, mbuf: Mbuf) -> Result<bool> {
+        let ethernet = mbuf.parse_link(self.link_type)?;
+        let ether_type = ethernet.ether_type();
+        let mut handled = false;
+
+        if let Some(handler) = self.ether_types.get(&ether_type) {
+            handler(&ethernet)?;
+            handled = true;
+        }
+
+        match ether_type {
+            EtherTypes::Ipv4 if !self.ipv4.is_empty() => {
+                let ipv4 = ethernet.parse::<Ipv4>()?;
+                if let Some(handler) = self.ipv4.get(&ipv4.next_protocol()) {
+                    handler(&ipv4)?;
+                    handled = true;
+                }
+            }
+            EtherTypes::Ipv6 if !self.ipv6.is_empty() => {
+                let ipv6 = ethernet.parse::<Ipv6>()?;
+                if let Some(handler) = self.ipv6.get(&ipv6.next_protocol()) {
+                    handler(&ipv6)?;
+                    handled = true;
+                }
+            }
+            _ => (),
+        }
+
+        Ok(handled)
+    }
+}
+
+impl Default for ParserRegistry {
+    fn default() -> Self {
+        ParserRegistry {
+            link_type: LinkTypes::Ethernet,
+            ether_types: HashMap::new(),
+            ipv4: HashMap::new(),
+            ipv6: HashMap::new(),
+        }
+    }
+}
+
+impl fmt::Debug for ParserRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParserRegistry")
+            .field("link_type", &format!("{}", self.link_type))
+            .field("ether_types", &self.ether_types.keys().collect::<Vec<_>>())
+            .field("ipv4", &self.ipv4.keys().collect::<Vec<_>>())
+            .field("ipv6", &self.ipv6.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::ProtocolNumbers;
+    use crate::packets::Udp4;
+    use crate::testils::byte_arrays::{IPV4_TCP_PACKET, IPV4_UDP_PACKET};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[capsule::test]
+    fn dispatch_to_udp_handler() {
+        let ports = Rc::new(Cell::new(None));
+
+        let mut registry = ParserRegistry::new();
+        let captured = Rc::clone(&ports);
+        registry.register_ipv4(ProtocolNumbers::Udp, move |ipv4| {
+            let udp = ipv4.peek::<Udp4>()?;
+            captured.set(Some((udp.src_port(), udp.dst_port())));
+            Ok(())
+        });
+
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        assert!(registry.dispatch(packet).unwrap());
+        assert_eq!(Some((39376, 1087)), ports.take());
+
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        assert!(!registry.dispatch(packet).unwrap());
+        assert_eq!(None, ports.take());
+    }
+
+    #[capsule::test]
+    fn dispatch_raw_ip_packet() {
+        let ports = Rc::new(Cell::new(None));
+
+        let mut registry = ParserRegistry::new();
+        registry.set_link_type(LinkTypes::Raw);
+        let captured = Rc::clone(&ports);
+        registry.register_ipv4(ProtocolNumbers::Udp, move |ipv4| {
+            let udp = ipv4.peek::<Udp4>()?;
+            captured.set(Some((udp.src_port(), udp.dst_port())));
+            Ok(())
+        });
+
+        // the IPv4 packet without the Ethernet header.
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET[14..]).unwrap();
+        assert!(registry.dispatch(packet).unwrap());
+        assert_eq!(Some((39376, 1087)), ports.take());
+    }
+}
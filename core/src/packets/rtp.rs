@@ -0,0 +1,570 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Real-time Transport Protocol.
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::IpPacket;
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{Internal, Packet, Udp};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+// Masks.
+const VERSION: u8 = 0b1100_0000;
+const FLAGS_P: u8 = 0b0010_0000;
+const FLAGS_X: u8 = 0b0001_0000;
+const CSRC_COUNT: u8 = 0b0000_1111;
+const MARKER: u8 = 0b1000_0000;
+const PAYLOAD_TYPE: u8 = 0b0111_1111;
+
+/// Real-time Transport Protocol packet based on [IETF RFC 3550].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P|X|  CC   |M|     PT      |       sequence number         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                           timestamp                           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           synchronization source (SSRC) identifier            |
+/// +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+/// |            contributing source (CSRC) identifiers             |
+/// |                             ....                              |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Version (V)*: (2 bits)
+///      The version of RTP, set to 2.
+///
+/// - *Padding (P)*: (1 bit)
+///      If set, the packet ends with padding octets. The last octet of the
+///      padding is the count of padding octets, including itself.
+///
+/// - *Extension (X)*: (1 bit)
+///      If set, the fixed header is followed by exactly one header
+///      extension.
+///
+/// - *CSRC Count (CC)*: (4 bits)
+///      The number of CSRC identifiers that follow the fixed header.
+///
+/// - *Marker (M)*: (1 bit)
+///      The interpretation is defined by a profile, for example to mark
+///      the frame boundaries.
+///
+/// - *Payload Type (PT)*: (7 bits)
+///      The format of the payload.
+///
+/// - *Sequence Number*: (16 bits)
+///      Increments by one for each packet sent.
+///
+/// - *Timestamp*: (32 bits)
+///      The sampling instant of the first octet of the payload.
+///
+/// - *SSRC*: (32 bits)
+///      Identifies the synchronization source.
+///
+/// - *CSRC List*: (0 to 15 items, 32 bits each)
+///      Identifies the contributing sources of the payload.
+///
+/// The header extension, if present, begins with a 16-bit profile-defined
+/// identifier and a 16-bit length in 32-bit words, excluding the four
+/// octets of the extension header.
+///
+/// RTP does not have a well-known port. The packet is parsed from any UDP
+/// payload that has the version set to 2.
+///
+/// [IETF RFC 3550]: https://tools.ietf.org/html/rfc3550#section-5.1
+pub struct Rtp<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<RtpHeader>,
+    offset: usize,
+    header_len: usize,
+}
+
+impl<E: IpPacket> Rtp<E> {
+    #[inline]
+    fn header(&self) -> &RtpHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut RtpHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the version.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        (self.header().flags & VERSION) >> 6
+    }
+
+    /// Returns a flag indicating whether the packet ends with padding.
+    #[inline]
+    pub fn padding(&self) -> bool {
+        self.header().flags & FLAGS_P != 0
+    }
+
+    /// Returns a flag indicating whether the header extension is present.
+    #[inline]
+    pub fn extension_present(&self) -> bool {
+        self.header().flags & FLAGS_X != 0
+    }
+
+    /// Returns the number of CSRC identifiers.
+    #[inline]
+    pub fn csrc_count(&self) -> u8 {
+        self.header().flags & CSRC_COUNT
+    }
+
+    /// Returns the marker bit.
+    #[inline]
+    pub fn marker(&self) -> bool {
+        self.header().marker_pt & MARKER != 0
+    }
+
+    /// Sets the marker bit.
+    #[inline]
+    pub fn set_marker(&mut self, marker: bool) {
+        if marker {
+            self.header_mut().marker_pt |= MARKER;
+        } else {
+            self.header_mut().marker_pt &= !MARKER;
+        }
+    }
+
+    /// Returns the payload type.
+    #[inline]
+    pub fn payload_type(&self) -> u8 {
+        self.header().marker_pt & PAYLOAD_TYPE
+    }
+
+    /// Sets the payload type.
+    #[inline]
+    pub fn set_payload_type(&mut self, payload_type: u8) {
+        self.header_mut().marker_pt =
+            (self.header().marker_pt & MARKER) | (payload_type & PAYLOAD_TYPE);
+    }
+
+    /// Returns the sequence number.
+    #[inline]
+    pub fn sequence(&self) -> u16 {
+        self.header().sequence.into()
+    }
+
+    /// Sets the sequence number.
+    #[inline]
+    pub fn set_sequence(&mut self, sequence: u16) {
+        self.header_mut().sequence = sequence.into();
+    }
+
+    /// Returns the timestamp.
+    #[inline]
+    pub fn timestamp(&self) -> u32 {
+        self.header().timestamp.into()
+    }
+
+    /// Sets the timestamp.
+    #[inline]
+    pub fn set_timestamp(&mut self, timestamp: u32) {
+        self.header_mut().timestamp = timestamp.into();
+    }
+
+    /// Returns the synchronization source identifier.
+    #[inline]
+    pub fn ssrc(&self) -> u32 {
+        self.header().ssrc.into()
+    }
+
+    /// Sets the synchronization source identifier.
+    #[inline]
+    pub fn set_ssrc(&mut self, ssrc: u32) {
+        self.header_mut().ssrc = ssrc.into();
+    }
+
+    /// Returns the contributing source identifiers.
+    #[inline]
+    pub fn csrcs(&self) -> Vec<u32> {
+        let count = self.csrc_count() as usize;
+        if count > 0 {
+            // the list is checked when parsed.
+            let csrcs = self
+                .mbuf()
+                .read_data_slice::<u32be>(self.offset + RtpHeader::size_of(), count)
+                .unwrap();
+            let csrcs = unsafe { &*csrcs.as_ptr() };
+            csrcs.iter().map(|&csrc| csrc.into()).collect()
+        } else {
+            vec![]
+        }
+    }
+
+    #[inline]
+    fn extension_offset(&self) -> usize {
+        self.offset + RtpHeader::size_of() + self.csrc_count() as usize * 4
+    }
+
+    /// Returns the profile-defined identifier of the header extension if
+    /// present.
+    #[inline]
+    pub fn extension_profile(&self) -> Option<u16> {
+        if self.extension_present() {
+            // the extension is checked when parsed.
+            let ext = self
+                .mbuf()
+                .read_data::<RtpExtensionHeader>(self.extension_offset())
+                .unwrap();
+            Some(unsafe { ext.as_ref() }.profile.into())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the data of the header extension if present.
+    #[inline]
+    pub fn extension_data(&self) -> Option<&[u8]> {
+        if self.extension_present() {
+            let offset = self.extension_offset() + RtpExtensionHeader::size_of();
+            let len = self.payload_offset() - offset;
+            if len > 0 {
+                // the extension is checked when parsed.
+                let data = self.mbuf().read_data_slice::<u8>(offset, len).unwrap();
+                Some(unsafe { &*data.as_ptr() })
+            } else {
+                Some(&[])
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the payload following the CSRC list and the header
+    /// extension, without the padding.
+    #[inline]
+    pub fn payload(&self) -> &[u8] {
+        if self.payload_len() > 0 {
+            // the payload is in the buffer, should never run out.
+            let data = self
+                .mbuf()
+                .read_data_slice::<u8>(self.payload_offset(), self.payload_len())
+                .unwrap();
+            let data = unsafe { &*data.as_ptr() };
+
+            if self.padding() {
+                let pad_len = data[data.len() - 1] as usize;
+                &data[..data.len().saturating_sub(pad_len)]
+            } else {
+                data
+            }
+        } else {
+            &[]
+        }
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Rtp<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("rtp")
+            .field("version", &self.version())
+            .field("padding", &self.padding())
+            .field("extension_present", &self.extension_present())
+            .field("csrc_count", &self.csrc_count())
+            .field("marker", &self.marker())
+            .field("payload_type", &self.payload_type())
+            .field("sequence", &self.sequence())
+            .field("timestamp", &self.timestamp())
+            .field("ssrc", &format!("0x{:08x}", self.ssrc()))
+            .field("csrcs", &self.csrcs())
+            .field("extension_profile", &self.extension_profile())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Rtp<E> {
+    /// The preceding type for RTP must be `Udp`.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the RTP header, including the CSRC list and
+    /// the header extension.
+    #[inline]
+    fn header_len(&self) -> usize {
+        self.header_len
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Rtp {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+            header_len: self.header_len,
+        }
+    }
+
+    /// Parses the UDP payload as an RTP packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the version is not 2. Returns an error if the
+    /// payload does not have sufficient data for the header, the CSRC list
+    /// and the header extension.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data::<RtpHeader>(offset)?;
+
+        let flags = unsafe { header.as_ref() }.flags;
+        ensure!(flags & VERSION == 2 << 6, anyhow!("not an RTP packet."));
+
+        let mut header_len = RtpHeader::size_of();
+        let count = (flags & CSRC_COUNT) as usize;
+        if count > 0 {
+            let _ = mbuf.read_data_slice::<u32be>(offset + header_len, count)?;
+            header_len += count * 4;
+        }
+
+        if flags & FLAGS_X != 0 {
+            let ext = mbuf.read_data::<RtpExtensionHeader>(offset + header_len)?;
+            header_len += RtpExtensionHeader::size_of();
+
+            let len = u16::from(unsafe { ext.as_ref() }.length) as usize * 4;
+            if len > 0 {
+                let _ = mbuf.read_data_slice::<u8>(offset + header_len, len)?;
+                header_len += len;
+            }
+        }
+
+        Ok(Rtp {
+            envelope,
+            header,
+            offset,
+            header_len,
+        })
+    }
+
+    /// Prepends an RTP header without CSRC identifiers and header extension
+    /// to the beginning of the UDP's payload.
+    ///
+    /// The version is set to 2 and all the other fields are zeroed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, RtpHeader::size_of())?;
+        let header = mbuf.write_data(offset, &RtpHeader::default())?;
+
+        Ok(Rtp {
+            envelope,
+            header,
+            offset,
+            header_len: RtpHeader::size_of(),
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// A type alias for an RTP packet over IPv4.
+pub type Rtp4 = Rtp<Ipv4>;
+
+/// A type alias for an RTP packet over IPv6.
+pub type Rtp6 = Rtp<Ipv6>;
+
+/// RTP fixed header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct RtpHeader {
+    flags: u8,
+    marker_pt: u8,
+    sequence: u16be,
+    timestamp: u32be,
+    ssrc: u32be,
+}
+
+impl Default for RtpHeader {
+    fn default() -> RtpHeader {
+        RtpHeader {
+            flags: 2 << 6,
+            marker_pt: 0,
+            sequence: u16be::default(),
+            timestamp: u32be::default(),
+            ssrc: u32be::default(),
+        }
+    }
+}
+
+/// RTP header extension header.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct RtpExtensionHeader {
+    profile: u16be,
+    length: u16be,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::IPV4_UDP_PACKET;
+    use crate::Mbuf;
+
+    /// An RTP packet with two CSRC identifiers and a 4-byte payload.
+    #[rustfmt::skip]
+    const RTP_HEADER: [u8; 24] = [
+        // version = 2, cc = 2
+        0x82,
+        // marker = 1, payload type = 96
+        0xe0,
+        // sequence = 4660
+        0x12, 0x34,
+        // timestamp = 4000
+        0x00, 0x00, 0x0f, 0xa0,
+        // ssrc
+        0xde, 0xad, 0xbe, 0xef,
+        // csrcs
+        0x11, 0x11, 0x11, 0x11,
+        0x22, 0x22, 0x22, 0x22,
+        // payload
+        0x01, 0x02, 0x03, 0x04,
+    ];
+
+    /// Returns the Ethernet, IPv4 and UDP headers of `IPV4_UDP_PACKET`
+    /// followed by the RTP data.
+    fn rtp_packet(rtp: &[u8]) -> Mbuf {
+        let mut bytes = IPV4_UDP_PACKET[..42].to_vec();
+        bytes.extend_from_slice(rtp);
+        Mbuf::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn size_of_rtp_header() {
+        assert_eq!(12, RtpHeader::size_of());
+        assert_eq!(4, RtpExtensionHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_rtp_packet() {
+        let packet = rtp_packet(&RTP_HEADER);
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let rtp = udp.parse::<Rtp4>().unwrap();
+
+        assert_eq!(2, rtp.version());
+        assert!(!rtp.padding());
+        assert!(!rtp.extension_present());
+        assert_eq!(2, rtp.csrc_count());
+        assert!(rtp.marker());
+        assert_eq!(96, rtp.payload_type());
+        assert_eq!(4660, rtp.sequence());
+        assert_eq!(4000, rtp.timestamp());
+        assert_eq!(0xdead_beef, rtp.ssrc());
+        assert_eq!(vec![0x1111_1111, 0x2222_2222], rtp.csrcs());
+        assert_eq!(None, rtp.extension_profile());
+        assert_eq!(20, rtp.header_len());
+        assert_eq!(&[0x01, 0x02, 0x03, 0x04], rtp.payload());
+    }
+
+    #[capsule::test]
+    fn parse_rtp_packet_with_extension() {
+        let mut bytes = RTP_HEADER[..20].to_vec();
+        bytes[0] |= FLAGS_X;
+        // profile = 0xbede, length = 1
+        bytes.extend_from_slice(&[0xbe, 0xde, 0x00, 0x01, 0x10, 0xaa, 0x00, 0x00]);
+        bytes.extend_from_slice(&RTP_HEADER[20..]);
+
+        let packet = rtp_packet(&bytes);
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let rtp = udp.parse::<Rtp4>().unwrap();
+
+        assert_eq!(Some(0xbede), rtp.extension_profile());
+        assert_eq!(Some(&[0x10, 0xaa, 0x00, 0x00][..]), rtp.extension_data());
+        assert_eq!(28, rtp.header_len());
+        assert_eq!(&[0x01, 0x02, 0x03, 0x04], rtp.payload());
+    }
+
+    #[capsule::test]
+    fn parse_non_rtp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Rtp4>().is_err());
+
+        // truncated csrc list
+        let packet = rtp_packet(&RTP_HEADER[..16]);
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Rtp4>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_rtp_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let mut rtp = udp.push::<Rtp4>().unwrap();
+        rtp.set_payload_type(0);
+        rtp.set_marker(true);
+        rtp.set_ssrc(7);
+
+        assert_eq!(2, rtp.version());
+        assert_eq!(0, rtp.csrc_count());
+        assert!(rtp.marker());
+        assert_eq!(0, rtp.payload_type());
+        assert_eq!(7, rtp.ssrc());
+        assert!(rtp.payload().is_empty());
+    }
+}
@@ -0,0 +1,589 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Stream Control Transmission Protocol.
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::{Flow, IpPacket, ProtocolNumbers};
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{checksum, Internal, Packet};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// Stream Control Transmission Protocol packet based on [IETF RFC 4960].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Source Port Number        |     Destination Port Number   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                      Verification Tag                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                           Checksum                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                            Chunk #1                           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                              ...                              |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                            Chunk #n                           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Source Port Number*: (16 bits)
+///      This is the SCTP sender's port number. It can be used by the
+///      receiver in combination with the source IP address, the SCTP
+///      destination port, and possibly the destination IP address to
+///      identify the association to which this packet belongs.
+///
+/// - *Destination Port Number*: (16 bits)
+///      This is the SCTP port number to which this packet is destined.
+///
+/// - *Verification Tag*: (32 bits)
+///      The receiver of this packet uses the Verification Tag to validate
+///      the sender of this SCTP packet.
+///
+/// - *Checksum*: (32 bits)
+///      This field contains the CRC32c checksum of this SCTP packet,
+///      computed over the whole packet with the checksum field set to 0.
+///      Unlike the other fields, the checksum is transmitted least
+///      significant byte first, see [IETF RFC 4960 appendix B].
+///
+/// - *Chunks*: (variable)
+///      Each chunk has a type, flags, a length and a value. The length
+///      does not include the padding that aligns the next chunk to a
+///      4-byte boundary.
+///
+/// [IETF RFC 4960]: https://tools.ietf.org/html/rfc4960#section-3
+/// [IETF RFC 4960 appendix B]: https://tools.ietf.org/html/rfc4960#appendix-B
+pub struct Sctp<E: IpPacket> {
+    envelope: E,
+    header: NonNull<SctpHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Sctp<E> {
+    #[inline]
+    fn header(&self) -> &SctpHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut SctpHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the source port.
+    #[inline]
+    pub fn src_port(&self) -> u16 {
+        self.header().src_port.into()
+    }
+
+    /// Sets the source port.
+    #[inline]
+    pub fn set_src_port(&mut self, src_port: u16) {
+        self.header_mut().src_port = src_port.into();
+    }
+
+    /// Returns the destination port.
+    #[inline]
+    pub fn dst_port(&self) -> u16 {
+        self.header().dst_port.into()
+    }
+
+    /// Sets the destination port.
+    #[inline]
+    pub fn set_dst_port(&mut self, dst_port: u16) {
+        self.header_mut().dst_port = dst_port.into();
+    }
+
+    /// Returns the verification tag.
+    #[inline]
+    pub fn verification_tag(&self) -> u32 {
+        self.header().verification_tag.into()
+    }
+
+    /// Sets the verification tag.
+    #[inline]
+    pub fn set_verification_tag(&mut self, tag: u32) {
+        self.header_mut().verification_tag = tag.into();
+    }
+
+    /// Returns the CRC32c checksum.
+    #[inline]
+    pub fn checksum(&self) -> u32 {
+        u32::from_le(self.header().checksum)
+    }
+
+    #[inline]
+    fn set_checksum(&mut self, checksum: u32) {
+        self.header_mut().checksum = checksum.to_le();
+    }
+
+    /// Computes the CRC32c checksum over the whole packet.
+    #[inline]
+    pub fn compute_checksum(&mut self) {
+        self.set_checksum(0);
+
+        if let Ok(data) = self.mbuf().read_data_slice(self.offset, self.len()) {
+            let data = unsafe { data.as_ref() };
+            let checksum = checksum::crc32c(data);
+            self.set_checksum(checksum);
+        } else {
+            // we are reading till the end of buffer, should never run out
+            unreachable!()
+        }
+    }
+
+    /// Returns the 5-tuple that uniquely identifies a SCTP association.
+    #[inline]
+    pub fn flow(&self) -> Flow {
+        Flow::new(
+            self.envelope().src(),
+            self.envelope().dst(),
+            self.src_port(),
+            self.dst_port(),
+            ProtocolNumbers::Sctp,
+        )
+    }
+
+    /// Returns an iterator over the chunks.
+    #[inline]
+    pub fn chunks(&self) -> SctpChunksIterator<'_> {
+        SctpChunksIterator {
+            mbuf: self.mbuf(),
+            offset: self.payload_offset(),
+        }
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Sctp<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("sctp")
+            .field("src_port", &self.src_port())
+            .field("dst_port", &self.dst_port())
+            .field(
+                "verification_tag",
+                &format!("0x{:08x}", self.verification_tag()),
+            )
+            .field("checksum", &format!("0x{:08x}", self.checksum()))
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Sctp<E> {
+    /// The preceding packet type for a SCTP packet can be either an [IPv4]
+    /// packet, an [IPv6] packet, or any IPv6 extension packets.
+    ///
+    /// [IPv4]: crate::packets::ip::v4::Ipv4
+    /// [IPv6]: crate::packets::ip::v6::Ipv6
+    type Envelope = E;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the common header. The chunks are the
+    /// payload.
+    #[inline]
+    fn header_len(&self) -> usize {
+        SctpHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Sctp::<E> {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the envelope's payload as a SCTP packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the envelope's [`next_protocol`] is not set to
+    /// [`ProtocolNumbers::Sctp`]. Returns an error if the payload does not
+    /// have sufficient data for the SCTP common header.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    /// [`ProtocolNumbers::Sctp`]: crate::packets::ip::ProtocolNumbers::Sctp
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.next_protocol() == ProtocolNumbers::Sctp,
+            anyhow!("not a SCTP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        Ok(Sctp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends a SCTP packet to the beginning of the envelope's payload.
+    ///
+    /// The envelope's [`next_protocol`] is set to [`ProtocolNumbers::Sctp`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`next_protocol`]: IpPacket::next_protocol
+    /// [`ProtocolNumbers::Sctp`]: crate::packets::ip::ProtocolNumbers::Sctp
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, SctpHeader::size_of())?;
+        let header = mbuf.write_data(offset, &SctpHeader::default())?;
+
+        envelope.set_next_protocol(ProtocolNumbers::Sctp);
+
+        Ok(Sctp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`checksum`] is computed with CRC32c over the full packet.
+    ///
+    /// [`checksum`]: Sctp::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        self.compute_checksum();
+    }
+}
+
+/// A type alias for an IPv4 SCTP packet.
+pub type Sctp4 = Sctp<Ipv4>;
+
+/// A type alias for an IPv6 SCTP packet.
+pub type Sctp6 = Sctp<Ipv6>;
+
+/// [IANA] assigned SCTP chunk type.
+///
+/// [IANA]: https://www.iana.org/assignments/sctp-parameters/sctp-parameters.xhtml#sctp-parameters-1
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct ChunkType(pub u8);
+
+impl ChunkType {
+    /// Creates a new chunk type.
+    pub fn new(value: u8) -> Self {
+        ChunkType(value)
+    }
+}
+
+/// Supported chunk types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod ChunkTypes {
+    use super::ChunkType;
+
+    /// Payload data.
+    pub const Data: ChunkType = ChunkType(0);
+    /// Initiation.
+    pub const Init: ChunkType = ChunkType(1);
+    /// Initiation acknowledgement.
+    pub const InitAck: ChunkType = ChunkType(2);
+    /// Selective acknowledgement.
+    pub const Sack: ChunkType = ChunkType(3);
+    /// Heartbeat request.
+    pub const Heartbeat: ChunkType = ChunkType(4);
+    /// Heartbeat acknowledgement.
+    pub const HeartbeatAck: ChunkType = ChunkType(5);
+    /// Abort.
+    pub const Abort: ChunkType = ChunkType(6);
+    /// Shutdown.
+    pub const Shutdown: ChunkType = ChunkType(7);
+    /// Shutdown acknowledgement.
+    pub const ShutdownAck: ChunkType = ChunkType(8);
+    /// Operation error.
+    pub const Error: ChunkType = ChunkType(9);
+    /// State cookie.
+    pub const CookieEcho: ChunkType = ChunkType(10);
+    /// Cookie acknowledgement.
+    pub const CookieAck: ChunkType = ChunkType(11);
+    /// Shutdown complete.
+    pub const ShutdownComplete: ChunkType = ChunkType(14);
+}
+
+impl fmt::Display for ChunkType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                ChunkTypes::Data => "DATA".to_string(),
+                ChunkTypes::Init => "INIT".to_string(),
+                ChunkTypes::InitAck => "INIT ACK".to_string(),
+                ChunkTypes::Sack => "SACK".to_string(),
+                ChunkTypes::Heartbeat => "HEARTBEAT".to_string(),
+                ChunkTypes::HeartbeatAck => "HEARTBEAT ACK".to_string(),
+                ChunkTypes::Abort => "ABORT".to_string(),
+                ChunkTypes::Shutdown => "SHUTDOWN".to_string(),
+                ChunkTypes::ShutdownAck => "SHUTDOWN ACK".to_string(),
+                ChunkTypes::Error => "ERROR".to_string(),
+                ChunkTypes::CookieEcho => "COOKIE ECHO".to_string(),
+                ChunkTypes::CookieAck => "COOKIE ACK".to_string(),
+                ChunkTypes::ShutdownComplete => "SHUTDOWN COMPLETE".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("{}", t)
+                }
+            }
+        )
+    }
+}
+
+/// A SCTP chunk.
+pub struct SctpChunk<'a> {
+    chunk_type: ChunkType,
+    flags: u8,
+    value: &'a [u8],
+}
+
+impl<'a> SctpChunk<'a> {
+    /// Returns the chunk type.
+    #[inline]
+    pub fn chunk_type(&self) -> ChunkType {
+        self.chunk_type
+    }
+
+    /// Returns the chunk flags.
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Returns the chunk length, including the type, flags and length
+    /// fields but excluding the padding.
+    #[inline]
+    pub fn length(&self) -> u16 {
+        (CHUNK_HEADER_LEN + self.value.len()) as u16
+    }
+
+    /// Returns the chunk value, excluding the padding.
+    #[inline]
+    pub fn value(&self) -> &'a [u8] {
+        self.value
+    }
+}
+
+impl fmt::Debug for SctpChunk<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SctpChunk")
+            .field("chunk_type", &format!("{}", self.chunk_type()))
+            .field("flags", &format!("0x{:02x}", self.flags()))
+            .field("length", &self.length())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the chunks of a SCTP packet.
+pub struct SctpChunksIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+}
+
+impl<'a> SctpChunksIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<SctpChunk<'a>>> {
+        if self.mbuf.data_len() <= self.offset {
+            return Ok(None);
+        }
+
+        let header = unsafe { *self.mbuf.read_data::<ChunkHeader>(self.offset)?.as_ref() };
+        let length = u16::from(header.length) as usize;
+        ensure!(
+            length >= CHUNK_HEADER_LEN,
+            anyhow!("invalid SCTP chunk length {}.", length)
+        );
+
+        let value_len = length - CHUNK_HEADER_LEN;
+        let value: &'a [u8] = if value_len > 0 {
+            let value = self
+                .mbuf
+                .read_data_slice::<u8>(self.offset + CHUNK_HEADER_LEN, value_len)?;
+            unsafe { &*value.as_ptr() }
+        } else {
+            &[]
+        };
+
+        // chunks are padded to a multiple of 4 bytes. the padding of the
+        // last chunk may be omitted.
+        self.offset += (length + 3) & !3;
+
+        Ok(Some(SctpChunk {
+            chunk_type: ChunkType(header.chunk_type),
+            flags: header.flags,
+            value,
+        }))
+    }
+}
+
+impl fmt::Debug for SctpChunksIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SctpChunksIterator")
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+const CHUNK_HEADER_LEN: usize = 4;
+
+/// SCTP chunk type, flags and length.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct ChunkHeader {
+    chunk_type: u8,
+    flags: u8,
+    length: u16be,
+}
+
+/// SCTP common header.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct SctpHeader {
+    src_port: u16be,
+    dst_port: u16be,
+    verification_tag: u32be,
+    checksum: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, SCTP_PACKET};
+
+    #[test]
+    fn size_of_sctp_header() {
+        assert_eq!(12, SctpHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_sctp_packet() {
+        let packet = Mbuf::from_bytes(&SCTP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let sctp = ipv4.parse::<Sctp4>().unwrap();
+
+        assert_eq!(5000, sctp.src_port());
+        assert_eq!(80, sctp.dst_port());
+        assert_eq!(0x1234_5678, sctp.verification_tag());
+        assert_eq!(0xb3e4_1c79, sctp.checksum());
+    }
+
+    #[capsule::test]
+    fn parse_non_sctp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Sctp4>().is_err());
+    }
+
+    #[capsule::test]
+    fn iterate_sctp_chunks() {
+        let packet = Mbuf::from_bytes(&SCTP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let sctp = ipv4.parse::<Sctp4>().unwrap();
+        let mut iter = sctp.chunks();
+
+        let sack = iter.next().unwrap().unwrap();
+        assert_eq!(ChunkTypes::Sack, sack.chunk_type());
+        assert_eq!(16, sack.length());
+
+        // the data chunk is padded from 21 to 24 bytes.
+        let data = iter.next().unwrap().unwrap();
+        assert_eq!(ChunkTypes::Data, data.chunk_type());
+        assert_eq!(0x03, data.flags());
+        assert_eq!(21, data.length());
+        assert_eq!(b"hello", &data.value()[12..]);
+
+        assert!(iter.next().unwrap().is_none());
+    }
+
+    #[capsule::test]
+    fn compute_sctp_checksum() {
+        let packet = Mbuf::from_bytes(&SCTP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut sctp = ipv4.parse::<Sctp4>().unwrap();
+
+        let expected = sctp.checksum();
+        // no payload change but force a checksum recompute anyway
+        sctp.compute_checksum();
+        assert_eq!(expected, sctp.checksum());
+    }
+
+    #[capsule::test]
+    fn push_sctp_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut sctp = ipv4.push::<Sctp4>().unwrap();
+        sctp.set_src_port(5000);
+        sctp.set_dst_port(80);
+        sctp.reconcile_all();
+
+        assert_eq!(SctpHeader::size_of(), sctp.len());
+        assert_eq!(ProtocolNumbers::Sctp, sctp.envelope().protocol());
+        assert_ne!(0, sctp.checksum());
+    }
+}
@@ -0,0 +1,324 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Session Initiation Protocol message peeking.
+//!
+//! SIP is commonly carried over UDP. The functions in this module operate
+//! on the UDP payload and only read the start line and the header fields.
+//! Since a message may be truncated or split across datagrams, a header
+//! field line is only read if it ends with a line terminator.
+//!
+//! Compact header field names, for example `i` for `Call-ID`, are
+//! recognized.
+
+use crate::ensure;
+use anyhow::Result;
+use std::str;
+use thiserror::Error;
+
+/// The SIP version in the start line.
+const SIP_VERSION: &str = "SIP/2.0";
+
+/// Header field names and their compact forms.
+const COMPACT_FORMS: [(&str, &str); 8] = [
+    ("Call-ID", "i"),
+    ("Contact", "m"),
+    ("Content-Length", "l"),
+    ("Content-Type", "c"),
+    ("From", "f"),
+    ("Subject", "s"),
+    ("To", "t"),
+    ("Via", "v"),
+];
+
+/// Error indicating a SIP parsing failure.
+#[derive(Debug, Error)]
+pub enum SipError {
+    /// The payload does not begin with a SIP request line or status line.
+    #[error("Payload is not a SIP message.")]
+    NotSip,
+}
+
+/// The start line of a SIP message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StartLine<'a> {
+    /// The request line of a request, for example
+    /// `INVITE sip:bob@biloxi.com SIP/2.0`.
+    Request {
+        /// The method, for example `INVITE` or `BYE`.
+        method: &'a str,
+        /// The request URI.
+        uri: &'a str,
+    },
+    /// The status line of a response, for example `SIP/2.0 180 Ringing`.
+    Response {
+        /// The 3-digit status code.
+        status_code: u16,
+        /// The reason phrase.
+        reason: &'a str,
+    },
+}
+
+/// SIP message based on [IETF RFC 3261].
+///
+/// ```
+/// INVITE sip:bob@biloxi.com SIP/2.0
+/// Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds
+/// Call-ID: a84b4c76e66710@pc33.atlanta.com
+/// CSeq: 314159 INVITE
+/// ...
+/// ```
+///
+/// - *Start Line*:
+///      Either a request line with the method, the request URI and the
+///      version, or a status line with the version, the status code and
+///      the reason phrase.
+///
+/// - *Header Fields*:
+///      `name: value` lines, terminated by an empty line.
+///
+/// - *Message Body*:
+///      Optional, for example a session description. It is not parsed.
+///
+/// [IETF RFC 3261]: https://tools.ietf.org/html/rfc3261#section-7
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SipMessage<'a> {
+    start_line: StartLine<'a>,
+    headers: &'a [u8],
+}
+
+impl<'a> SipMessage<'a> {
+    /// Returns whether the UDP payload begins with a SIP request line or
+    /// status line.
+    pub fn is_sip(data: &[u8]) -> bool {
+        SipMessage::parse(data).is_ok()
+    }
+
+    /// Parses the start line of the SIP message at the beginning of the
+    /// UDP payload.
+    ///
+    /// The header fields are read on demand.
+    ///
+    /// # Errors
+    ///
+    /// Returns `SipError::NotSip` if the payload does not begin with a
+    /// request line or a status line.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let (line, headers) = match next_line(data) {
+            Some((line, rest)) => (line, rest),
+            // the start line may be the only line in the payload.
+            None => (data, &[][..]),
+        };
+
+        let line = str::from_utf8(line).map_err(|_| SipError::NotSip)?;
+        let start_line = start_line(line)?;
+
+        Ok(SipMessage {
+            start_line,
+            headers,
+        })
+    }
+
+    /// Returns the start line.
+    pub fn start_line(&self) -> StartLine<'a> {
+        self.start_line
+    }
+
+    /// Returns the method if the message is a request.
+    pub fn method(&self) -> Option<&'a str> {
+        match self.start_line {
+            StartLine::Request { method, .. } => Some(method),
+            StartLine::Response { .. } => None,
+        }
+    }
+
+    /// Returns the status code if the message is a response.
+    pub fn status_code(&self) -> Option<u16> {
+        match self.start_line {
+            StartLine::Request { .. } => None,
+            StartLine::Response { status_code, .. } => Some(status_code),
+        }
+    }
+
+    /// Returns the value of the first header field with the name.
+    ///
+    /// The name is case-insensitive and also matches the compact form of
+    /// the name. Returns `None` if the header field is not present or is
+    /// not complete in the payload.
+    pub fn header(&self, name: &str) -> Option<&'a str> {
+        let compact = COMPACT_FORMS
+            .iter()
+            .find(|(full, _)| full.eq_ignore_ascii_case(name))
+            .map(|&(_, compact)| compact);
+
+        let mut data = self.headers;
+        while let Some((line, rest)) = next_line(data) {
+            data = rest;
+
+            // an empty line ends the header fields.
+            if line.is_empty() {
+                break;
+            }
+
+            if let Ok(line) = str::from_utf8(line) {
+                if let Some(pos) = line.find(':') {
+                    let field = line[..pos].trim();
+                    if field.eq_ignore_ascii_case(name)
+                        || compact.map_or(false, |c| field.eq_ignore_ascii_case(c))
+                    {
+                        return Some(line[pos + 1..].trim());
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the value of the `Call-ID` header field.
+    pub fn call_id(&self) -> Option<&'a str> {
+        self.header("Call-ID")
+    }
+
+    /// Returns the sequence number and the method of the `CSeq` header
+    /// field.
+    ///
+    /// Returns `None` if the header field is not present or is malformed.
+    pub fn cseq(&self) -> Option<(u32, &'a str)> {
+        let mut parts = self.header("CSeq")?.split_whitespace();
+        let seq = parts.next()?.parse().ok()?;
+        let method = parts.next()?;
+        Some((seq, method))
+    }
+}
+
+/// Splits the first line terminated by LF from the rest of the data.
+///
+/// The trailing CR of a CRLF is removed. Returns `None` if the data has no
+/// line terminator.
+fn next_line(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let pos = data.iter().position(|&b| b == b'\n')?;
+    let line = match &data[..pos] {
+        [line @ .., b'\r'] => line,
+        line => line,
+    };
+    Some((line, &data[pos + 1..]))
+}
+
+/// Parses a request line or a status line.
+fn start_line(line: &str) -> Result<StartLine<'_>> {
+    let mut parts = line.splitn(3, ' ');
+    let first = parts.next().unwrap_or("");
+    let second = parts.next().unwrap_or("");
+    let third = parts.next().unwrap_or("");
+
+    if first == SIP_VERSION {
+        ensure!(
+            second.len() == 3 && second.bytes().all(|b| b.is_ascii_digit()),
+            SipError::NotSip
+        );
+        let status_code = second.parse().map_err(|_| SipError::NotSip)?;
+        ensure!((100..700).contains(&status_code), SipError::NotSip);
+
+        Ok(StartLine::Response {
+            status_code,
+            reason: third,
+        })
+    } else {
+        ensure!(
+            !first.is_empty() && first.bytes().all(|b| b.is_ascii_uppercase()),
+            SipError::NotSip
+        );
+        ensure!(!second.is_empty() && third == SIP_VERSION, SipError::NotSip);
+
+        Ok(StartLine::Request {
+            method: first,
+            uri: second,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The INVITE request from IETF RFC 3261, section 4, without the body.
+    const INVITE: &[u8] = b"INVITE sip:bob@biloxi.com SIP/2.0\r\n\
+        Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bK776asdhds\r\n\
+        Max-Forwards: 70\r\n\
+        To: Bob <sip:bob@biloxi.com>\r\n\
+        From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+        Call-ID: a84b4c76e66710@pc33.atlanta.com\r\n\
+        CSeq: 314159 INVITE\r\n\
+        Contact: <sip:alice@pc33.atlanta.com>\r\n\
+        Content-Type: application/sdp\r\n\
+        Content-Length: 142\r\n\
+        \r\n";
+
+    #[test]
+    fn parse_invite_request() {
+        let message = SipMessage::parse(INVITE).unwrap();
+
+        assert_eq!(
+            StartLine::Request {
+                method: "INVITE",
+                uri: "sip:bob@biloxi.com",
+            },
+            message.start_line()
+        );
+        assert_eq!(Some("INVITE"), message.method());
+        assert_eq!(None, message.status_code());
+        assert_eq!(Some("a84b4c76e66710@pc33.atlanta.com"), message.call_id());
+        assert_eq!(Some((314159, "INVITE")), message.cseq());
+        assert_eq!(Some("70"), message.header("max-forwards"));
+        assert_eq!(None, message.header("Subject"));
+    }
+
+    #[test]
+    fn parse_response_with_compact_headers() {
+        let data = b"SIP/2.0 180 Ringing\r\ni: a84b4c76e66710\r\nCSeq: 1 INVITE\r\n\r\n";
+        let message = SipMessage::parse(data).unwrap();
+
+        assert_eq!(None, message.method());
+        assert_eq!(Some(180), message.status_code());
+        assert_eq!(Some("a84b4c76e66710"), message.call_id());
+        assert_eq!(Some((1, "INVITE")), message.cseq());
+    }
+
+    #[test]
+    fn parse_truncated_message() {
+        // cut in the middle of the Call-ID header field.
+        let end = INVITE.windows(8).position(|w| w == b"Call-ID:").unwrap() + 12;
+        let message = SipMessage::parse(&INVITE[..end]).unwrap();
+
+        assert_eq!(Some("INVITE"), message.method());
+        assert_eq!(None, message.call_id());
+        assert_eq!(None, message.cseq());
+    }
+
+    #[test]
+    fn parse_non_sip_payload() {
+        assert!(!SipMessage::is_sip(
+            b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n"
+        ));
+        assert!(!SipMessage::is_sip(b"SIP/2.0 99 Too Low\r\n"));
+        assert!(!SipMessage::is_sip(b"INVITE sip:bob@biloxi.com SI"));
+        assert!(!SipMessage::is_sip(&[0xff, 0xfe, 0x0d, 0x0a]));
+        assert!(!SipMessage::is_sip(&[]));
+    }
+}
@@ -0,0 +1,588 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Spanning Tree Protocol.
+
+use crate::net::MacAddr;
+use crate::packets::llc::Llc;
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{Internal, Packet};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The LLC service access point of the spanning tree protocol.
+pub const STP_SAP: u8 = 0x42;
+
+// Flags.
+const FLAGS_TC: u8 = 0b0000_0001;
+const FLAGS_TCA: u8 = 0b1000_0000;
+
+/// Spanning Tree Protocol bridge protocol data unit based on [IEEE 802.1D].
+///
+/// A configuration BPDU has the following format. A topology change
+/// notification BPDU only has the first 4 octets. A rapid spanning tree
+/// BPDU is a configuration BPDU followed by the 1-octet version 1 length.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          Protocol ID          |    Version    |   BPDU Type   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     Flags     |                 Root ID ...                   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                          ... Root ID ...                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | ... Root ID   |            Root Path Cost ...                 |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | ... Cost      |               Bridge ID ...                   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                        ... Bridge ID ...                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | ... Bridge ID |            Port ID            |  Message Age  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Message Age  |            Max Age            |  Hello Time   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Hello Time   |         Forward Delay         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Protocol ID*: (16 bits)
+///      Set to 0 for the spanning tree protocol.
+///
+/// - *Version*: (8 bits)
+///      0 for STP, 2 for RSTP and 3 for MSTP.
+///
+/// - *BPDU Type*: (8 bits)
+///      The type of the BPDU.
+///
+/// - *Flags*: (8 bits)
+///      The topology change flag is the least significant bit, and the
+///      topology change acknowledgment flag is the most significant bit.
+///
+/// - *Root ID*: (64 bits)
+///      The bridge ID of the root bridge, a 16-bit priority followed by
+///      the MAC address of the bridge.
+///
+/// - *Root Path Cost*: (32 bits)
+///      The cost of the path to the root bridge.
+///
+/// - *Bridge ID*: (64 bits)
+///      The bridge ID of the transmitting bridge.
+///
+/// - *Port ID*: (16 bits)
+///      The port through which the BPDU was transmitted.
+///
+/// - *Message Age, Max Age, Hello Time, Forward Delay*: (16 bits each)
+///      The timer values, in units of 1/256 second.
+///
+/// BPDUs are carried in 802.3 frames with an LLC header, with both the
+/// DSAP and the SSAP set to [`STP_SAP`].
+///
+/// [IEEE 802.1D]: https://standards.ieee.org/standard/802_1D-2004.html
+pub struct Stp {
+    envelope: Llc,
+    header: NonNull<StpHeader>,
+    offset: usize,
+}
+
+impl Stp {
+    #[inline]
+    fn header(&self) -> &StpHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    /// Returns the protocol identifier.
+    #[inline]
+    pub fn protocol_id(&self) -> u16 {
+        self.header().protocol_id.into()
+    }
+
+    /// Returns the protocol version.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        self.header().version
+    }
+
+    /// Returns the BPDU type.
+    #[inline]
+    pub fn bpdu_type(&self) -> BpduType {
+        BpduType::new(self.header().bpdu_type)
+    }
+
+    #[inline]
+    fn config(&self) -> Option<&StpConfig> {
+        if self.bpdu_type() == BpduTypes::Tcn {
+            None
+        } else {
+            // the configuration is checked when parsed.
+            let config = self
+                .mbuf()
+                .read_data::<StpConfig>(self.offset + StpHeader::size_of())
+                .unwrap();
+            Some(unsafe { &*config.as_ptr() })
+        }
+    }
+
+    #[inline]
+    fn config_mut(&mut self) -> Option<&mut StpConfig> {
+        if self.bpdu_type() == BpduTypes::Tcn {
+            None
+        } else {
+            let offset = self.offset + StpHeader::size_of();
+            let config = self.mbuf_mut().read_data::<StpConfig>(offset).unwrap();
+            Some(unsafe { &mut *config.as_ptr() })
+        }
+    }
+
+    /// Returns the flags. Returns `None` for a topology change
+    /// notification.
+    #[inline]
+    pub fn flags(&self) -> Option<u8> {
+        self.config().map(|config| config.flags)
+    }
+
+    /// Returns whether the topology change flag is set.
+    #[inline]
+    pub fn topology_change(&self) -> bool {
+        self.flags().map_or(false, |flags| flags & FLAGS_TC != 0)
+    }
+
+    /// Returns whether the topology change acknowledgment flag is set.
+    #[inline]
+    pub fn topology_change_ack(&self) -> bool {
+        self.flags().map_or(false, |flags| flags & FLAGS_TCA != 0)
+    }
+
+    /// Sets the flags. Has no effect on a topology change notification.
+    #[inline]
+    pub fn set_flags(&mut self, flags: u8) {
+        if let Some(config) = self.config_mut() {
+            config.flags = flags;
+        }
+    }
+
+    /// Returns the root bridge ID.
+    #[inline]
+    pub fn root_id(&self) -> Option<BridgeId> {
+        self.config().map(|config| config.root_id.into())
+    }
+
+    /// Sets the root bridge ID. Has no effect on a topology change
+    /// notification.
+    #[inline]
+    pub fn set_root_id(&mut self, root_id: BridgeId) {
+        if let Some(config) = self.config_mut() {
+            config.root_id = root_id.into();
+        }
+    }
+
+    /// Returns the root path cost.
+    #[inline]
+    pub fn root_path_cost(&self) -> Option<u32> {
+        self.config().map(|config| config.root_path_cost.into())
+    }
+
+    /// Sets the root path cost. Has no effect on a topology change
+    /// notification.
+    #[inline]
+    pub fn set_root_path_cost(&mut self, cost: u32) {
+        if let Some(config) = self.config_mut() {
+            config.root_path_cost = cost.into();
+        }
+    }
+
+    /// Returns the transmitting bridge ID.
+    #[inline]
+    pub fn bridge_id(&self) -> Option<BridgeId> {
+        self.config().map(|config| config.bridge_id.into())
+    }
+
+    /// Sets the transmitting bridge ID. Has no effect on a topology change
+    /// notification.
+    #[inline]
+    pub fn set_bridge_id(&mut self, bridge_id: BridgeId) {
+        if let Some(config) = self.config_mut() {
+            config.bridge_id = bridge_id.into();
+        }
+    }
+
+    /// Returns the port ID.
+    #[inline]
+    pub fn port_id(&self) -> Option<u16> {
+        self.config().map(|config| config.port_id.into())
+    }
+
+    /// Sets the port ID. Has no effect on a topology change notification.
+    #[inline]
+    pub fn set_port_id(&mut self, port_id: u16) {
+        if let Some(config) = self.config_mut() {
+            config.port_id = port_id.into();
+        }
+    }
+
+    /// Returns the message age in units of 1/256 second.
+    #[inline]
+    pub fn message_age(&self) -> Option<u16> {
+        self.config().map(|config| config.message_age.into())
+    }
+
+    /// Returns the max age in units of 1/256 second.
+    #[inline]
+    pub fn max_age(&self) -> Option<u16> {
+        self.config().map(|config| config.max_age.into())
+    }
+
+    /// Returns the hello time in units of 1/256 second.
+    #[inline]
+    pub fn hello_time(&self) -> Option<u16> {
+        self.config().map(|config| config.hello_time.into())
+    }
+
+    /// Returns the forward delay in units of 1/256 second.
+    #[inline]
+    pub fn forward_delay(&self) -> Option<u16> {
+        self.config().map(|config| config.forward_delay.into())
+    }
+}
+
+impl fmt::Debug for Stp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("stp")
+            .field("protocol_id", &self.protocol_id())
+            .field("version", &self.version())
+            .field("bpdu_type", &format!("{}", self.bpdu_type()))
+            .field("flags", &self.flags())
+            .field("root_id", &self.root_id().map(|id| id.to_string()))
+            .field("root_path_cost", &self.root_path_cost())
+            .field("bridge_id", &self.bridge_id().map(|id| id.to_string()))
+            .field("port_id", &self.port_id())
+            .field("message_age", &self.message_age())
+            .field("max_age", &self.max_age())
+            .field("hello_time", &self.hello_time())
+            .field("forward_delay", &self.forward_delay())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Stp {
+    /// The preceding type for STP must be `Llc`.
+    type Envelope = Llc;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the BPDU.
+    #[inline]
+    fn header_len(&self) -> usize {
+        bpdu_len(self.bpdu_type())
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Stp {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the LLC payload as a BPDU.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the DSAP or the SSAP is not [`STP_SAP`], or the
+    /// protocol ID is not 0. Returns an error if the payload does not have
+    /// sufficient data for the BPDU.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.dsap() == STP_SAP && envelope.ssap() == STP_SAP,
+            anyhow!("not an STP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data::<StpHeader>(offset)?;
+
+        let header_ref = unsafe { header.as_ref() };
+        ensure!(
+            u16::from(header_ref.protocol_id) == 0,
+            anyhow!("not an STP packet.")
+        );
+
+        let len = bpdu_len(BpduType::new(header_ref.bpdu_type));
+        let _ = mbuf.read_data_slice::<u8>(offset, len)?;
+
+        Ok(Stp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends a configuration BPDU to the beginning of the LLC's payload.
+    ///
+    /// The DSAP and the SSAP of the LLC header are set to [`STP_SAP`]. All
+    /// the BPDU fields are zeroed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let len = StpHeader::size_of() + StpConfig::size_of();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, len)?;
+        let header = mbuf.write_data(offset, &StpHeader::default())?;
+        let _ = mbuf.write_data(offset + StpHeader::size_of(), &StpConfig::default())?;
+
+        envelope.set_dsap(STP_SAP);
+        envelope.set_ssap(STP_SAP);
+
+        Ok(Stp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// Returns the length of the BPDU of the type.
+fn bpdu_len(bpdu_type: BpduType) -> usize {
+    match bpdu_type {
+        BpduTypes::Tcn => StpHeader::size_of(),
+        // the version 1 length follows the configuration.
+        BpduTypes::Rst => StpHeader::size_of() + StpConfig::size_of() + 1,
+        _ => StpHeader::size_of() + StpConfig::size_of(),
+    }
+}
+
+/// A bridge identifier, the priority followed by the MAC address.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BridgeId {
+    priority: u16,
+    addr: MacAddr,
+}
+
+impl BridgeId {
+    /// Creates a new bridge identifier.
+    pub fn new(priority: u16, addr: MacAddr) -> Self {
+        BridgeId { priority, addr }
+    }
+
+    /// Returns the priority, including the system ID extension.
+    pub fn priority(&self) -> u16 {
+        self.priority
+    }
+
+    /// Returns the MAC address.
+    pub fn addr(&self) -> MacAddr {
+        self.addr
+    }
+}
+
+impl From<[u8; 8]> for BridgeId {
+    fn from(bytes: [u8; 8]) -> Self {
+        let mut addr = [0; 6];
+        addr.copy_from_slice(&bytes[2..]);
+        BridgeId {
+            priority: u16::from_be_bytes([bytes[0], bytes[1]]),
+            addr: addr.into(),
+        }
+    }
+}
+
+impl From<BridgeId> for [u8; 8] {
+    fn from(id: BridgeId) -> Self {
+        let mut bytes = [0; 8];
+        bytes[..2].copy_from_slice(&id.priority.to_be_bytes());
+        bytes[2..].copy_from_slice(&id.addr.octets());
+        bytes
+    }
+}
+
+impl fmt::Display for BridgeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04x}.{}", self.priority, self.addr)
+    }
+}
+
+/// Type of BPDU.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct BpduType(pub u8);
+
+impl BpduType {
+    /// Creates a new BPDU type.
+    pub fn new(value: u8) -> Self {
+        BpduType(value)
+    }
+}
+
+/// Supported BPDU types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod BpduTypes {
+    use super::BpduType;
+
+    /// Configuration BPDU.
+    pub const Config: BpduType = BpduType(0x00);
+    /// Rapid spanning tree BPDU.
+    pub const Rst: BpduType = BpduType(0x02);
+    /// Topology change notification BPDU.
+    pub const Tcn: BpduType = BpduType(0x80);
+}
+
+impl fmt::Display for BpduType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                BpduTypes::Config => "Configuration".to_string(),
+                BpduTypes::Rst => "Rapid Spanning Tree".to_string(),
+                BpduTypes::Tcn => "Topology Change Notification".to_string(),
+                _ => format!("0x{:02x}", self.0),
+            }
+        )
+    }
+}
+
+/// BPDU common header.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct StpHeader {
+    protocol_id: u16be,
+    version: u8,
+    bpdu_type: u8,
+}
+
+/// Configuration BPDU fields.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct StpConfig {
+    flags: u8,
+    root_id: [u8; 8],
+    root_path_cost: u32be,
+    bridge_id: [u8; 8],
+    port_id: u16be,
+    message_age: u16be,
+    max_age: u16be,
+    hello_time: u16be,
+    forward_delay: u16be,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::{EtherType, Ethernet};
+    use crate::testils::byte_arrays::STP_CONFIG_PACKET;
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_stp_header() {
+        assert_eq!(4, StpHeader::size_of());
+        assert_eq!(31, StpConfig::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_config_bpdu() {
+        let packet = Mbuf::from_bytes(&STP_CONFIG_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let llc = ethernet.parse::<Llc>().unwrap();
+        let stp = llc.parse::<Stp>().unwrap();
+
+        assert_eq!(0, stp.protocol_id());
+        assert_eq!(0, stp.version());
+        assert_eq!(BpduTypes::Config, stp.bpdu_type());
+        assert!(!stp.topology_change());
+        assert_eq!(
+            Some(BridgeId::new(0x8000, MacAddr::new(0, 0, 0, 0, 0, 1))),
+            stp.root_id()
+        );
+        assert_eq!("8000.00:00:00:00:00:01", stp.root_id().unwrap().to_string());
+        assert_eq!(Some(4), stp.root_path_cost());
+        assert_eq!(
+            Some(BridgeId::new(0x8000, MacAddr::new(0, 0, 0, 0, 0, 2))),
+            stp.bridge_id()
+        );
+        assert_eq!(Some(0x8001), stp.port_id());
+        assert_eq!(Some(256), stp.message_age());
+        assert_eq!(Some(20 * 256), stp.max_age());
+        assert_eq!(Some(2 * 256), stp.hello_time());
+        assert_eq!(Some(15 * 256), stp.forward_delay());
+        assert_eq!(35, stp.header_len());
+    }
+
+    #[capsule::test]
+    fn parse_tcn_bpdu() {
+        let mut bytes = STP_CONFIG_PACKET[..21].to_vec();
+        // length = 7, type = TCN
+        bytes[13] = 0x07;
+        bytes[20] = 0x80;
+
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let llc = ethernet.parse::<Llc>().unwrap();
+        let stp = llc.parse::<Stp>().unwrap();
+
+        assert_eq!(BpduTypes::Tcn, stp.bpdu_type());
+        assert_eq!(None, stp.root_id());
+        assert_eq!(4, stp.header_len());
+    }
+
+    #[capsule::test]
+    fn push_config_bpdu() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let llc = ethernet.push::<Llc>().unwrap();
+        let mut stp = llc.push::<Stp>().unwrap();
+        let root_id = BridgeId::new(0x1000, MacAddr::new(0, 0, 0, 0, 0, 7));
+        stp.set_root_id(root_id);
+        stp.reconcile_all();
+
+        assert_eq!(Some(root_id), stp.root_id());
+        assert_eq!(STP_SAP, stp.envelope().dsap());
+        assert_eq!(EtherType::new(38), stp.envelope().envelope().ether_type());
+    }
+}
@@ -0,0 +1,701 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Session Traversal Utilities for NAT.
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::IpPacket;
+use crate::packets::types::{u16be, u32be};
+use crate::packets::{Internal, Packet, Udp};
+use crate::{ensure, Mbuf, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::ptr::NonNull;
+
+/// The IANA assigned UDP port of STUN.
+pub const STUN_PORT: u16 = 3478;
+
+/// The magic cookie that follows the message length.
+pub const MAGIC_COOKIE: u32 = 0x2112_a442;
+
+// Address families of the mapped address attributes.
+const FAMILY_IPV4: u8 = 0x01;
+const FAMILY_IPV6: u8 = 0x02;
+
+/// Session Traversal Utilities for NAT message based on [IETF RFC 5389].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |0 0|     STUN Message Type     |         Message Length        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         Magic Cookie                          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                                                               |
+/// |                     Transaction ID (96 bits)                  |
+/// |                                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *STUN Message Type*: (14 bits)
+///      The method and the class of the message, interleaved.
+///
+/// - *Message Length*: (16 bits)
+///      The length of the message in octets, excluding the 20-byte header.
+///      The attributes are padded to a multiple of 4 octets, so the length
+///      is always a multiple of 4.
+///
+/// - *Magic Cookie*: (32 bits)
+///      The fixed value `0x2112A442`.
+///
+/// - *Transaction ID*: (96 bits)
+///      Identifier used to associate requests and responses.
+///
+/// The attributes follow the header. Each attribute is a 16-bit type, a
+/// 16-bit length of the value, and the value padded to a multiple of 4
+/// octets.
+///
+/// STUN is commonly multiplexed with other protocols on the same port. The
+/// message is parsed from any UDP payload that has the magic cookie.
+///
+/// [IETF RFC 5389]: https://tools.ietf.org/html/rfc5389#section-6
+pub struct Stun<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<StunHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Stun<E> {
+    #[inline]
+    fn header(&self) -> &StunHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut StunHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the message type.
+    #[inline]
+    pub fn message_type(&self) -> MessageType {
+        MessageType::new(self.header().message_type.into())
+    }
+
+    /// Sets the message type.
+    #[inline]
+    pub fn set_message_type(&mut self, message_type: MessageType) {
+        self.header_mut().message_type = message_type.0.into();
+    }
+
+    /// Returns the length of the attributes.
+    #[inline]
+    pub fn length(&self) -> u16 {
+        self.header().length.into()
+    }
+
+    #[inline]
+    fn set_length(&mut self, length: u16) {
+        self.header_mut().length = length.into();
+    }
+
+    /// Returns the transaction ID.
+    #[inline]
+    pub fn transaction_id(&self) -> [u8; 12] {
+        self.header().transaction_id
+    }
+
+    /// Sets the transaction ID.
+    #[inline]
+    pub fn set_transaction_id(&mut self, transaction_id: [u8; 12]) {
+        self.header_mut().transaction_id = transaction_id;
+    }
+
+    /// Returns an iterator over the attributes.
+    #[inline]
+    pub fn attributes(&self) -> StunAttributesIterator<'_> {
+        let offset = self.payload_offset();
+        let end = (offset + self.length() as usize).min(self.mbuf().data_len());
+        StunAttributesIterator {
+            mbuf: self.mbuf(),
+            offset,
+            end,
+        }
+    }
+
+    /// Returns the value of the first attribute with the attribute type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an attribute is malformed.
+    pub fn attribute(&self, attribute_type: AttributeType) -> Result<Option<&[u8]>> {
+        let mut iter = self.attributes();
+        while let Some(attribute) = iter.next()? {
+            if attribute.attribute_type() == attribute_type {
+                return Ok(Some(attribute.value()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the mapped address of the XOR-MAPPED-ADDRESS attribute.
+    ///
+    /// The port is un-XORed against the most significant 16 bits of the
+    /// magic cookie. An IPv4 address is un-XORed against the magic cookie,
+    /// and an IPv6 address against the magic cookie concatenated with the
+    /// transaction ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an attribute is malformed.
+    pub fn xor_mapped_address(&self) -> Result<Option<SocketAddr>> {
+        let value = match self.attribute(AttributeTypes::XorMappedAddress)? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        let mut mask = [0; 16];
+        mask[..4].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        mask[4..].copy_from_slice(&self.transaction_id());
+
+        let (family, port, mut addr) = mapped_address(value)?;
+        let port = port ^ (MAGIC_COOKIE >> 16) as u16;
+        addr.iter_mut().zip(mask.iter()).for_each(|(b, m)| *b ^= m);
+
+        Ok(Some(to_socket_addr(family, port, &addr)))
+    }
+
+    /// Returns the mapped address of the MAPPED-ADDRESS attribute.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an attribute is malformed.
+    pub fn mapped_address(&self) -> Result<Option<SocketAddr>> {
+        match self.attribute(AttributeTypes::MappedAddress)? {
+            Some(value) => {
+                let (family, port, addr) = mapped_address(value)?;
+                Ok(Some(to_socket_addr(family, port, &addr)))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Decodes the family, the port and the address of a mapped address
+/// attribute value.
+fn mapped_address(value: &[u8]) -> Result<(u8, u16, Vec<u8>)> {
+    ensure!(
+        value.len() >= 4,
+        anyhow!("invalid STUN mapped address length.")
+    );
+
+    let family = value[1];
+    let port = u16::from_be_bytes([value[2], value[3]]);
+    let addr_len = match family {
+        FAMILY_IPV4 => 4,
+        FAMILY_IPV6 => 16,
+        _ => return Err(anyhow!("invalid STUN address family {}.", family)),
+    };
+    ensure!(
+        value.len() == 4 + addr_len,
+        anyhow!("invalid STUN mapped address length.")
+    );
+
+    Ok((family, port, value[4..].to_vec()))
+}
+
+fn to_socket_addr(family: u8, port: u16, addr: &[u8]) -> SocketAddr {
+    let ip = if family == FAMILY_IPV4 {
+        IpAddr::V4(Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]))
+    } else {
+        let mut octets = [0; 16];
+        octets.copy_from_slice(addr);
+        IpAddr::V6(Ipv6Addr::from(octets))
+    };
+    SocketAddr::new(ip, port)
+}
+
+impl<E: IpPacket> fmt::Debug for Stun<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("stun")
+            .field("message_type", &format!("{}", self.message_type()))
+            .field("length", &self.length())
+            .field("transaction_id", &self.transaction_id())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Stun<E> {
+    /// The preceding type for STUN must be `Udp`.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the fixed header. The attributes are the
+    /// payload.
+    #[inline]
+    fn header_len(&self) -> usize {
+        StunHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Stun {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP payload as a STUN message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload does not have sufficient data for
+    /// the header, the two most significant bits are not zeroes, or the
+    /// magic cookie does not match.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        let packet = Stun {
+            envelope,
+            header,
+            offset,
+        };
+
+        let cookie = u32::from(packet.header().magic_cookie);
+        ensure!(
+            packet.message_type().0 & 0xc000 == 0 && cookie == MAGIC_COOKIE,
+            anyhow!("not a STUN packet.")
+        );
+
+        Ok(packet)
+    }
+
+    /// Prepends a STUN message without attributes to the beginning of the
+    /// UDP's payload.
+    ///
+    /// The magic cookie is set and the message type is set to
+    /// [`MessageTypes::BindingRequest`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, StunHeader::size_of())?;
+        let header = mbuf.write_data(offset, &StunHeader::default())?;
+
+        Ok(Stun {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`length`] is set to the length of the attributes.
+    ///
+    /// [`length`]: Stun::length
+    #[inline]
+    fn reconcile(&mut self) {
+        let len = self.payload_len();
+        self.set_length(len as u16);
+    }
+}
+
+/// A type alias for a STUN message over IPv4.
+pub type Stun4 = Stun<Ipv4>;
+
+/// A type alias for a STUN message over IPv6.
+pub type Stun6 = Stun<Ipv6>;
+
+/// STUN message type.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct MessageType(pub u16);
+
+impl MessageType {
+    /// Creates a new message type.
+    pub fn new(value: u16) -> Self {
+        MessageType(value)
+    }
+
+    /// Returns the method, with the class bits removed.
+    pub fn method(&self) -> u16 {
+        let t = self.0;
+        (t & 0x000f) | ((t & 0x00e0) >> 1) | ((t & 0x3e00) >> 2)
+    }
+
+    /// Returns the class, 0 for request, 1 for indication, 2 for success
+    /// response and 3 for error response.
+    pub fn class(&self) -> u8 {
+        let t = self.0;
+        (((t & 0x0100) >> 7) | ((t & 0x0010) >> 4)) as u8
+    }
+}
+
+/// Supported STUN message types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod MessageTypes {
+    use super::MessageType;
+
+    /// Binding request.
+    pub const BindingRequest: MessageType = MessageType(0x0001);
+    /// Binding indication.
+    pub const BindingIndication: MessageType = MessageType(0x0011);
+    /// Binding success response.
+    pub const BindingSuccessResponse: MessageType = MessageType(0x0101);
+    /// Binding error response.
+    pub const BindingErrorResponse: MessageType = MessageType(0x0111);
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                MessageTypes::BindingRequest => "Binding Request".to_string(),
+                MessageTypes::BindingIndication => "Binding Indication".to_string(),
+                MessageTypes::BindingSuccessResponse => "Binding Success Response".to_string(),
+                MessageTypes::BindingErrorResponse => "Binding Error Response".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("0x{:04x}", t)
+                }
+            }
+        )
+    }
+}
+
+/// STUN attribute type.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct AttributeType(pub u16);
+
+impl AttributeType {
+    /// Creates a new attribute type.
+    pub fn new(value: u16) -> Self {
+        AttributeType(value)
+    }
+}
+
+/// Supported STUN attribute types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod AttributeTypes {
+    use super::AttributeType;
+
+    /// Mapped address.
+    pub const MappedAddress: AttributeType = AttributeType(0x0001);
+    /// User name.
+    pub const Username: AttributeType = AttributeType(0x0006);
+    /// HMAC-SHA1 message integrity.
+    pub const MessageIntegrity: AttributeType = AttributeType(0x0008);
+    /// Error code.
+    pub const ErrorCode: AttributeType = AttributeType(0x0009);
+    /// XOR-ed mapped address.
+    pub const XorMappedAddress: AttributeType = AttributeType(0x0020);
+    /// Software.
+    pub const Software: AttributeType = AttributeType(0x8022);
+    /// CRC-32 fingerprint.
+    pub const Fingerprint: AttributeType = AttributeType(0x8028);
+}
+
+impl fmt::Display for AttributeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                AttributeTypes::MappedAddress => "MAPPED-ADDRESS".to_string(),
+                AttributeTypes::Username => "USERNAME".to_string(),
+                AttributeTypes::MessageIntegrity => "MESSAGE-INTEGRITY".to_string(),
+                AttributeTypes::ErrorCode => "ERROR-CODE".to_string(),
+                AttributeTypes::XorMappedAddress => "XOR-MAPPED-ADDRESS".to_string(),
+                AttributeTypes::Software => "SOFTWARE".to_string(),
+                AttributeTypes::Fingerprint => "FINGERPRINT".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("0x{:04x}", t)
+                }
+            }
+        )
+    }
+}
+
+/// A STUN attribute.
+pub struct StunAttribute<'a> {
+    attribute_type: AttributeType,
+    value: &'a [u8],
+}
+
+impl<'a> StunAttribute<'a> {
+    /// Returns the attribute type.
+    #[inline]
+    pub fn attribute_type(&self) -> AttributeType {
+        self.attribute_type
+    }
+
+    /// Returns the attribute value, excluding the type, the length and the
+    /// padding.
+    #[inline]
+    pub fn value(&self) -> &'a [u8] {
+        self.value
+    }
+}
+
+impl fmt::Debug for StunAttribute<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StunAttribute")
+            .field("attribute_type", &format!("{}", self.attribute_type()))
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+/// An iterator that iterates through the STUN attributes.
+///
+/// The iteration stops at the end of the message length.
+pub struct StunAttributesIterator<'a> {
+    mbuf: &'a Mbuf,
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> StunAttributesIterator<'a> {
+    /// Advances the iterator and returns the next value.
+    ///
+    /// Returns `Ok(None)` when iteration is finished; returns `Err` when a
+    /// parse error is encountered during iteration.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<StunAttribute<'a>>> {
+        if self.offset >= self.end {
+            return Ok(None);
+        }
+
+        let header = self.mbuf.read_data::<StunAttributeHeader>(self.offset)?;
+        let header = unsafe { header.as_ref() };
+        let attribute_type = AttributeType::new(header.attribute_type.into());
+        let len = u16::from(header.length) as usize;
+
+        let offset = self.offset + StunAttributeHeader::size_of();
+        ensure!(
+            offset + len <= self.end,
+            anyhow!("STUN attribute {} exceeds the message.", attribute_type)
+        );
+        let value: &'a [u8] = if len > 0 {
+            let data = self.mbuf.read_data_slice::<u8>(offset, len)?;
+            unsafe { &*data.as_ptr() }
+        } else {
+            &[]
+        };
+
+        // the value is padded to a multiple of 4 octets.
+        self.offset = offset + ((len + 3) & !3);
+        Ok(Some(StunAttribute {
+            attribute_type,
+            value,
+        }))
+    }
+}
+
+impl fmt::Debug for StunAttributesIterator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StunAttributesIterator")
+            .field("offset", &self.offset)
+            .field("end", &self.end)
+            .finish()
+    }
+}
+
+/// STUN message header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct StunHeader {
+    message_type: u16be,
+    length: u16be,
+    magic_cookie: u32be,
+    transaction_id: [u8; 12],
+}
+
+impl Default for StunHeader {
+    fn default() -> StunHeader {
+        StunHeader {
+            message_type: MessageTypes::BindingRequest.0.into(),
+            length: u16be::default(),
+            magic_cookie: MAGIC_COOKIE.into(),
+            transaction_id: [0; 12],
+        }
+    }
+}
+
+/// STUN attribute type and length.
+#[derive(Clone, Copy, Debug, Default, SizeOf)]
+#[repr(C, packed)]
+struct StunAttributeHeader {
+    attribute_type: u16be,
+    length: u16be,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::IPV4_UDP_PACKET;
+    use std::str::FromStr;
+
+    /// A binding success response with the mapped address from IETF RFC
+    /// 5769, section 2.2.
+    #[rustfmt::skip]
+    const BINDING_RESPONSE: [u8; 48] = [
+        // type = binding success response, length = 28
+        0x01, 0x01, 0x00, 0x1c,
+        // magic cookie
+        0x21, 0x12, 0xa4, 0x42,
+        // transaction id
+        0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34, 0xd6, 0x86, 0xfa, 0x87, 0xdf, 0xae,
+        // SOFTWARE, length = 11, "test vector" and one byte of padding
+        0x80, 0x22, 0x00, 0x0b,
+        0x74, 0x65, 0x73, 0x74, 0x20, 0x76, 0x65, 0x63, 0x74, 0x6f, 0x72, 0x20,
+        // XOR-MAPPED-ADDRESS, length = 8, IPv4, 192.0.2.1:32853
+        0x00, 0x20, 0x00, 0x08,
+        0x00, 0x01, 0xa1, 0x47, 0xe1, 0x12, 0xa6, 0x43,
+    ];
+
+    /// Returns the Ethernet, IPv4 and UDP headers of `IPV4_UDP_PACKET`
+    /// followed by the STUN message.
+    fn stun_packet(stun: &[u8]) -> Mbuf {
+        let mut bytes = IPV4_UDP_PACKET[..42].to_vec();
+        bytes.extend_from_slice(stun);
+        Mbuf::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn size_of_stun_header() {
+        assert_eq!(20, StunHeader::size_of());
+        assert_eq!(4, StunAttributeHeader::size_of());
+    }
+
+    #[test]
+    fn method_and_class() {
+        assert_eq!(1, MessageTypes::BindingSuccessResponse.method());
+        assert_eq!(2, MessageTypes::BindingSuccessResponse.class());
+        assert_eq!(3, MessageTypes::BindingErrorResponse.class());
+        assert_eq!(1, MessageTypes::BindingIndication.class());
+    }
+
+    #[capsule::test]
+    fn parse_binding_response() {
+        let packet = stun_packet(&BINDING_RESPONSE);
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let stun = udp.parse::<Stun4>().unwrap();
+
+        assert_eq!(MessageTypes::BindingSuccessResponse, stun.message_type());
+        assert_eq!(28, stun.length());
+        assert_eq!(
+            [0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34, 0xd6, 0x86, 0xfa, 0x87, 0xdf, 0xae],
+            stun.transaction_id()
+        );
+
+        let mut types = vec![];
+        let mut iter = stun.attributes();
+        while let Some(attribute) = iter.next().unwrap() {
+            types.push(attribute.attribute_type());
+        }
+        assert_eq!(
+            vec![AttributeTypes::Software, AttributeTypes::XorMappedAddress],
+            types
+        );
+
+        let software = stun.attribute(AttributeTypes::Software).unwrap();
+        assert_eq!(Some(&b"test vector"[..]), software);
+
+        assert_eq!(
+            Some(SocketAddr::from_str("192.0.2.1:32853").unwrap()),
+            stun.xor_mapped_address().unwrap()
+        );
+        assert_eq!(None, stun.mapped_address().unwrap());
+    }
+
+    #[capsule::test]
+    fn parse_non_stun_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Stun4>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_and_reconcile_stun_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let udp = ipv4.push::<Udp<Ipv4>>().unwrap();
+        let mut stun = udp.push::<Stun4>().unwrap();
+        stun.set_transaction_id([1; 12]);
+        stun.reconcile();
+
+        assert_eq!(MessageTypes::BindingRequest, stun.message_type());
+        assert_eq!(0, stun.length());
+        assert_eq!([1; 12], stun.transaction_id());
+        assert_eq!(None, stun.xor_mapped_address().unwrap());
+
+        // parses back the pushed packet
+        let udp = stun.deparse();
+        assert!(udp.peek::<Stun4>().is_ok());
+    }
+}
@@ -16,12 +16,13 @@
 * SPDX-License-Identifier: Apache-2.0
 */
 
+use crate::ffi;
 use crate::packets::ip::v4::Ipv4;
 use crate::packets::ip::v6::Ipv6;
 use crate::packets::ip::{Flow, IpPacket, ProtocolNumbers};
 use crate::packets::types::{u16be, u32be};
-use crate::packets::{checksum, Internal, Packet};
-use crate::{ensure, SizeOf};
+use crate::packets::{checksum, Ethernet, Internal, Packet};
+use crate::{bitfield, ensure, Mbuf, SizeOf};
 use anyhow::{anyhow, Result};
 use std::fmt;
 use std::net::IpAddr;
@@ -182,18 +183,18 @@ impl<E: IpPacket> Tcp<E> {
         self.header_mut().ack_no = ack_no.into();
     }
 
-    /// Returns the number of 32 bit words in the TCP Header. This indicates
-    /// where the data begins.
-    #[inline]
-    pub fn data_offset(&self) -> u8 {
-        (self.header().offset_to_ns & 0xf0) >> 4
-    }
+    bitfield! {
+        offset_to_ns: u8 {
+            /// Returns the number of 32 bit words in the TCP Header. This indicates
+            /// where the data begins.
+            #[inline]
+            pub get data_offset -> u8 = 4..8;
 
-    // TODO: support tcp header options.
-    #[allow(dead_code)]
-    #[inline]
-    fn set_data_offset(&mut self, data_offset: u8) {
-        self.header_mut().offset_to_ns = (self.header().offset_to_ns & 0x0f) | (data_offset << 4);
+            // TODO: support tcp header options.
+            #[allow(dead_code)]
+            #[inline]
+            set set_data_offset(u8) = 4..8;
+        }
     }
 
     /// Returns the nonce sum bit.
@@ -458,23 +459,223 @@ impl<E: IpPacket> Tcp<E> {
         Ok(())
     }
 
+    /// Sets the source port and incrementally updates the checksum.
+    ///
+    /// It updates the checksum using the incremental method, so a correct
+    /// checksum stays correct without recomputing it over the whole
+    /// packet.
     #[inline]
-    fn compute_checksum(&mut self) {
-        self.set_checksum(0);
+    pub fn set_src_port_checked(&mut self, src_port: u16) {
+        let checksum = checksum::compute_inc(self.checksum(), &[self.src_port()], &[src_port]);
+        self.set_checksum(checksum);
+        self.set_src_port(src_port);
+    }
 
+    /// Sets the destination port and incrementally updates the checksum.
+    ///
+    /// It updates the checksum using the incremental method, so a correct
+    /// checksum stays correct without recomputing it over the whole
+    /// packet.
+    #[inline]
+    pub fn set_dst_port_checked(&mut self, dst_port: u16) {
+        let checksum = checksum::compute_inc(self.checksum(), &[self.dst_port()], &[dst_port]);
+        self.set_checksum(checksum);
+        self.set_dst_port(dst_port);
+    }
+
+    /// Returns whether the checksum is correct.
+    ///
+    /// The checksum is verified over the pseudo-header, the header and the
+    /// payload.
+    #[inline]
+    pub fn validate_checksum(&self) -> bool {
+        match self.mbuf().read_data_slice(self.offset, self.len()) {
+            Ok(data) => {
+                let data = unsafe { data.as_ref() };
+                let pseudo_header_sum = self
+                    .envelope()
+                    .pseudo_header(data.len() as u16, ProtocolNumbers::Tcp)
+                    .sum();
+                checksum::compute(pseudo_header_sum, data) == 0
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the checksum over the pseudo-header, the header and the
+    /// payload, without writing it to the packet.
+    #[inline]
+    pub fn calculate_checksum(&self) -> u16 {
         if let Ok(data) = self.mbuf().read_data_slice(self.offset, self.len()) {
             let data = unsafe { data.as_ref() };
             let pseudo_header_sum = self
                 .envelope()
                 .pseudo_header(data.len() as u16, ProtocolNumbers::Tcp)
                 .sum();
-            let checksum = checksum::compute(pseudo_header_sum, data);
-            self.set_checksum(checksum);
+            // the checksum field is at offset 16 of the header.
+            checksum::compute_cleared(pseudo_header_sum, data, 16)
         } else {
             // we are reading till the end of buffer, should never run out
             unreachable!()
         }
     }
+
+    #[inline]
+    fn compute_checksum(&mut self) {
+        let checksum = self.calculate_checksum();
+        self.set_checksum(checksum);
+    }
+
+    /// Requests the network device to compute the checksum on transmit.
+    ///
+    /// Sets the `PKT_TX_TCP_CKSUM` offload flag, the IP version flag, and the
+    /// layer-2 and layer-3 header lengths on the buffer. As DPDK requires,
+    /// the checksum field is primed with the pseudo-header sum instead of
+    /// the full checksum. The port must be configured with the matching
+    /// `DEV_TX_OFFLOAD_TCP_CKSUM` capability. Because [`reconcile`]
+    /// recomputes the checksum in software, it should not be called after
+    /// requesting the offload.
+    ///
+    /// The device locates the headers from the layer-2 and layer-3 lengths,
+    /// so the IP header must directly follow the Ethernet header. If it
+    /// does not, for example behind an authentication header or an IPv6
+    /// extension header, the checksum is computed in software instead and
+    /// no offload is requested.
+    ///
+    /// [`reconcile`]: Packet::reconcile
+    #[inline]
+    pub fn offload_checksum(&mut self) {
+        let l2_len = self.envelope().offset();
+        let on_ethernet = self
+            .mbuf()
+            .peek::<Ethernet>()
+            .map(|ethernet| ethernet.header_len() == l2_len)
+            .unwrap_or(false);
+        if !on_ethernet {
+            self.compute_checksum();
+            return;
+        }
+
+        let pseudo_header_sum = self
+            .envelope()
+            .pseudo_header(self.len() as u16, ProtocolNumbers::Tcp)
+            .sum();
+        self.header_mut().checksum = pseudo_header_sum.into();
+
+        let ip_flag = match self.envelope().src() {
+            IpAddr::V4(_) => ffi::PKT_TX_IPV4,
+            IpAddr::V6(_) => ffi::PKT_TX_IPV6,
+        };
+        let l3_len = self.envelope().header_len();
+        self.mbuf_mut()
+            .set_tx_offload(ip_flag | ffi::PKT_TX_TCP_CKSUM, l2_len, l3_len);
+    }
+
+    /// Splits the payload into segments of at most `mss` bytes, each
+    /// copied into a new frame with the headers of the original.
+    ///
+    /// `reparse` parses a copied frame back to TCP, and may adjust the
+    /// envelope headers of the `n`-th segment.
+    fn segment_with<F>(self, mss: usize, reparse: F) -> Result<Vec<Mbuf>>
+    where
+        F: Fn(Mbuf, usize) -> Result<Tcp<E>>,
+    {
+        ensure!(mss > 0, anyhow!("MSS must be greater than 0."));
+
+        let data = self.to_vec();
+        let header_len = (self.data_offset() as usize * 4).min(data.len());
+        if data.len() - header_len <= mss {
+            return Ok(vec![self.reset()]);
+        }
+
+        // all the headers, including the TCP options, are copied into
+        // every segment.
+        let mut frame = self
+            .mbuf()
+            .read_data_slice::<u8>(0, self.offset)
+            .map(|data| unsafe { data.as_ref() }.to_vec())?;
+        frame.extend_from_slice(&data[..header_len]);
+        let headers_len = frame.len();
+
+        let payload = &data[header_len..];
+        let seq_no = self.seq_no();
+        let last = (payload.len() - 1) / mss;
+
+        let mut segments = vec![];
+        for (n, chunk) in payload.chunks(mss).enumerate() {
+            frame.truncate(headers_len);
+            frame.extend_from_slice(chunk);
+
+            let mut tcp = reparse(Mbuf::from_bytes(&frame)?, n)?;
+            tcp.set_seq_no(seq_no.wrapping_add((n * mss) as u32));
+            if n < last {
+                tcp.unset_psh();
+                tcp.unset_fin();
+            }
+            tcp.reconcile_all();
+
+            segments.push(tcp.reset());
+        }
+
+        Ok(segments)
+    }
+}
+
+impl Tcp<Ipv4> {
+    /// Segments the packet into multiple packets with payloads no larger
+    /// than `mss`, emulating TCP segmentation offload in software.
+    ///
+    /// Every segment is a copy of the Ethernet, the IPv4 and the TCP
+    /// headers, including the TCP options, followed by its slice of the
+    /// payload. The sequence numbers advance with the payload, and the IPv4
+    /// identification is incremented per segment. The PSH and FIN flags are
+    /// cleared on all but the last segment. The lengths and the checksums
+    /// of every segment are recomputed.
+    ///
+    /// The payload is copied from all the segments of a chained buffer.
+    /// Returns the packet unchanged if the payload already fits within
+    /// `mss`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `mss` is 0, or if the allocation of a segment
+    /// fails.
+    pub fn segment(self, mss: usize) -> Result<Vec<Mbuf>> {
+        let identification = self.envelope().identification();
+        self.segment_with(mss, |mbuf, n| {
+            let ethernet = mbuf.parse::<Ethernet>()?;
+            let mut ipv4 = ethernet.parse::<Ipv4>()?;
+            ipv4.set_identification(identification.wrapping_add(n as u16));
+            ipv4.parse::<Tcp4>()
+        })
+    }
+}
+
+impl Tcp<Ipv6> {
+    /// Segments the packet into multiple packets with payloads no larger
+    /// than `mss`, emulating TCP segmentation offload in software.
+    ///
+    /// Every segment is a copy of the Ethernet, the IPv6 and the TCP
+    /// headers, including the TCP options, followed by its slice of the
+    /// payload. The sequence numbers advance with the payload. The PSH and
+    /// FIN flags are cleared on all but the last segment. The lengths and
+    /// the checksums of every segment are recomputed.
+    ///
+    /// The payload is copied from all the segments of a chained buffer.
+    /// Returns the packet unchanged if the payload already fits within
+    /// `mss`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `mss` is 0, or if the allocation of a segment
+    /// fails.
+    pub fn segment(self, mss: usize) -> Result<Vec<Mbuf>> {
+        self.segment_with(mss, |mbuf, _| {
+            let ethernet = mbuf.parse::<Ethernet>()?;
+            let ipv6 = ethernet.parse::<Ipv6>()?;
+            ipv6.parse::<Tcp6>()
+        })
+    }
 }
 
 impl<E: IpPacket> fmt::Debug for Tcp<E> {
@@ -665,9 +866,7 @@ impl Default for TcpHeader {
 mod tests {
     use super::*;
     use crate::packets::ip::v6::SegmentRouting;
-    use crate::packets::Ethernet;
     use crate::testils::byte_arrays::{IPV4_TCP_PACKET, IPV4_UDP_PACKET, SR_TCP_PACKET};
-    use crate::Mbuf;
     use std::net::{Ipv4Addr, Ipv6Addr};
 
     #[test]
@@ -764,6 +963,20 @@ mod tests {
         assert!(tcp.set_src_ip(Ipv6Addr::UNSPECIFIED.into()).is_err());
     }
 
+    #[capsule::test]
+    fn calculate_checksum_without_writing() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut tcp = ipv4.parse::<Tcp4>().unwrap();
+
+        assert_eq!(0xa92c, tcp.calculate_checksum());
+
+        tcp.set_checksum(0x1234);
+        assert_eq!(0xa92c, tcp.calculate_checksum());
+        assert_eq!(0x1234, tcp.checksum());
+    }
+
     #[capsule::test]
     fn compute_checksum() {
         let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
@@ -777,6 +990,73 @@ mod tests {
         assert_eq!(expected, tcp.checksum());
     }
 
+    #[capsule::test]
+    fn set_port_with_checksum_fixup() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut tcp = ipv4.parse::<Tcp4>().unwrap();
+        assert!(tcp.validate_checksum());
+
+        tcp.set_src_port_checked(1234);
+        tcp.set_dst_port_checked(56789);
+        assert_eq!(1234, tcp.src_port());
+        assert_eq!(56789, tcp.dst_port());
+        assert!(tcp.validate_checksum());
+
+        // the incremental update is exact.
+        let checksum = tcp.checksum();
+        tcp.reconcile();
+        assert_eq!(checksum, tcp.checksum());
+
+        // without the fixup, the checksum goes stale.
+        tcp.set_dst_port(80);
+        assert!(!tcp.validate_checksum());
+    }
+
+    #[capsule::test]
+    fn offload_checksum() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut tcp = ipv4.parse::<Tcp4>().unwrap();
+
+        tcp.offload_checksum();
+
+        let expected = tcp
+            .envelope()
+            .pseudo_header(tcp.len() as u16, ProtocolNumbers::Tcp)
+            .sum();
+        assert_eq!(expected, tcp.checksum());
+
+        let mbuf = tcp.mbuf();
+        assert_eq!(ffi::PKT_TX_IPV4, mbuf.ol_flags() & ffi::PKT_TX_IPV4);
+        assert_eq!(
+            ffi::PKT_TX_TCP_CKSUM,
+            mbuf.ol_flags() & ffi::PKT_TX_TCP_CKSUM
+        );
+        assert_eq!(14, mbuf.l2_len());
+        assert_eq!(20, mbuf.l3_len());
+    }
+
+    #[capsule::test]
+    fn offload_checksum_behind_extension_header() {
+        let packet = Mbuf::from_bytes(&SR_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv6 = ethernet.parse::<Ipv6>().unwrap();
+        let srh = ipv6.parse::<SegmentRouting<Ipv6>>().unwrap();
+        let mut tcp = srh.parse::<Tcp<SegmentRouting<Ipv6>>>().unwrap();
+
+        tcp.offload_checksum();
+
+        // falls back to the software checksum.
+        let checksum = tcp.checksum();
+        assert_ne!(0, checksum);
+        tcp.compute_checksum();
+        assert_eq!(tcp.checksum(), checksum);
+        assert_eq!(0, tcp.mbuf().ol_flags() & ffi::PKT_TX_TCP_CKSUM);
+    }
+
     #[capsule::test]
     fn push_tcp_packet() {
         let packet = Mbuf::new().unwrap();
@@ -790,4 +1070,69 @@ mod tests {
         // make sure the next protocol is fixed
         assert_eq!(ProtocolNumbers::Tcp, tcp.envelope().next_protocol());
     }
+
+    #[capsule::test]
+    fn segment_tcp_payload() {
+        let payload = (0..4000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+        #[rustfmt::skip]
+        let mut bytes = vec![
+            // Ethernet header
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+            0x08, 0x00,
+            // IPv4 header, total length = 4040
+            0x45, 0x00, 0x0f, 0xc8,
+            0x12, 0x34, 0x40, 0x00,
+            0x40, 0x06, 0x00, 0x00,
+            0x0a, 0x00, 0x00, 0x01,
+            0x0a, 0x00, 0x00, 0x02,
+            // TCP header, seq no = 0xffff_fc00, flags = ACK, PSH, FIN
+            0x04, 0xd2, 0x00, 0x50,
+            0xff, 0xff, 0xfc, 0x00,
+            0x00, 0x00, 0x00, 0x01,
+            0x50, 0x19, 0xff, 0xff,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        bytes.extend_from_slice(&payload);
+
+        // the payload does not fit in a single buffer.
+        let mut packet = Mbuf::from_bytes(&bytes[..1000]).unwrap();
+        packet
+            .chain(Mbuf::from_bytes(&bytes[1000..2500]).unwrap())
+            .unwrap();
+        packet
+            .chain(Mbuf::from_bytes(&bytes[2500..]).unwrap())
+            .unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let tcp = ipv4.parse::<Tcp4>().unwrap();
+
+        let segments = tcp.segment(1460).unwrap();
+        assert_eq!(3, segments.len());
+
+        let mut seq_no = 0xffff_fc00u32;
+        let mut reassembled = vec![];
+        for (n, segment) in segments.into_iter().enumerate() {
+            let ethernet = segment.parse::<Ethernet>().unwrap();
+            let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+            let tcp = ipv4.parse::<Tcp4>().unwrap();
+
+            assert!(tcp.payload_len() <= 1460);
+            assert_eq!(seq_no, tcp.seq_no());
+            assert_eq!(0x1234 + n as u16, tcp.envelope().identification());
+            assert_eq!(tcp.envelope().len(), tcp.envelope().total_length() as usize);
+            assert!(tcp.validate_checksum());
+            assert!(tcp.ack());
+            assert_eq!(n == 2, tcp.psh());
+            assert_eq!(n == 2, tcp.fin());
+
+            // the sequence number wraps around.
+            seq_no = seq_no.wrapping_add(tcp.payload_len() as u32);
+            reassembled.extend_from_slice(&tcp.to_vec()[20..]);
+        }
+
+        assert_eq!(0x0000_0ba0, seq_no);
+        assert_eq!(payload, reassembled);
+    }
 }
@@ -0,0 +1,389 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Transport Layer Security record and ClientHello parsing.
+//!
+//! TLS runs over TCP, so a record does not align with the TCP segments. The
+//! functions in this module operate on the reassembled TCP payload. When
+//! the payload ends before the end of a record, `TlsError::Incomplete` is
+//! returned with the number of additional bytes needed.
+
+use crate::ensure;
+use anyhow::Result;
+use std::fmt;
+use std::str;
+use thiserror::Error;
+
+/// Length of the TLS record header.
+pub const RECORD_HEADER_LEN: usize = 5;
+
+/// The handshake message type of ClientHello.
+const CLIENT_HELLO: u8 = 1;
+
+/// The extension type of server name indication.
+const SERVER_NAME: u16 = 0;
+
+/// The server name type of a DNS hostname.
+const HOST_NAME: u8 = 0;
+
+/// Error indicating a TLS parsing failure.
+#[derive(Debug, Error)]
+pub enum TlsError {
+    /// The data ends before the end of the record.
+    #[error("Need {0} more bytes to parse the TLS record.")]
+    Incomplete(usize),
+
+    /// The record is not a handshake record with a ClientHello.
+    #[error("TLS record is not a ClientHello.")]
+    NotClientHello,
+
+    /// The record is malformed.
+    #[error("TLS record is malformed.")]
+    Malformed,
+}
+
+/// The content type of a TLS record.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct ContentType(pub u8);
+
+impl ContentType {
+    /// Creates a new content type.
+    pub fn new(value: u8) -> Self {
+        ContentType(value)
+    }
+}
+
+/// Supported TLS record content types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod ContentTypes {
+    use super::ContentType;
+
+    /// Change cipher spec.
+    pub const ChangeCipherSpec: ContentType = ContentType(20);
+    /// Alert.
+    pub const Alert: ContentType = ContentType(21);
+    /// Handshake.
+    pub const Handshake: ContentType = ContentType(22);
+    /// Application data.
+    pub const ApplicationData: ContentType = ContentType(23);
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                ContentTypes::ChangeCipherSpec => "ChangeCipherSpec".to_string(),
+                ContentTypes::Alert => "Alert".to_string(),
+                ContentTypes::Handshake => "Handshake".to_string(),
+                ContentTypes::ApplicationData => "ApplicationData".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("{}", t)
+                }
+            }
+        )
+    }
+}
+
+/// TLS record header based on [IETF RFC 8446].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | Content Type  |            Version            |    Length     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |    Length     |           Fragment ...
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Content Type*: (8 bits)
+///      The higher-level protocol used to process the fragment.
+///
+/// - *Version*: (16 bits)
+///      The legacy record version, `0x0301` for the initial ClientHello
+///      and `0x0303` for all other records.
+///
+/// - *Length*: (16 bits)
+///      The length of the fragment in octets.
+///
+/// [IETF RFC 8446]: https://tools.ietf.org/html/rfc8446#section-5.1
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RecordHeader {
+    content_type: ContentType,
+    version: u16,
+    length: u16,
+}
+
+impl RecordHeader {
+    /// Parses the record header at the beginning of the data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TlsError::Incomplete` if the data is shorter than the
+    /// record header.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        ensure!(
+            data.len() >= RECORD_HEADER_LEN,
+            TlsError::Incomplete(RECORD_HEADER_LEN - data.len())
+        );
+
+        Ok(RecordHeader {
+            content_type: ContentType::new(data[0]),
+            version: u16::from_be_bytes([data[1], data[2]]),
+            length: u16::from_be_bytes([data[3], data[4]]),
+        })
+    }
+
+    /// Returns the content type.
+    pub fn content_type(&self) -> ContentType {
+        self.content_type
+    }
+
+    /// Returns the legacy record version.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Returns the length of the fragment.
+    pub fn length(&self) -> u16 {
+        self.length
+    }
+
+    /// Returns the length of the record, including the header.
+    pub fn record_len(&self) -> usize {
+        RECORD_HEADER_LEN + self.length as usize
+    }
+}
+
+/// Returns the server name of the SNI extension of the ClientHello at the
+/// beginning of the data.
+///
+/// The ClientHello can be fragmented across consecutive handshake records.
+/// Returns `None` if the ClientHello does not have a server name.
+///
+/// # Errors
+///
+/// Returns `TlsError::Incomplete` if the data ends before the end of the
+/// ClientHello. Returns `TlsError::NotClientHello` if the data does not
+/// begin with a ClientHello. Returns `TlsError::Malformed` if the lengths
+/// in the ClientHello are inconsistent.
+pub fn client_hello_sni(data: &[u8]) -> Result<Option<String>> {
+    let message = handshake_message(data)?;
+    let mut reader = Reader::new(&message);
+
+    ensure!(reader.u8()? == CLIENT_HELLO, TlsError::NotClientHello);
+    let _length = reader.u24()?;
+
+    // legacy version and random.
+    reader.take(2 + 32)?;
+    // legacy session id.
+    let len = reader.u8()? as usize;
+    reader.take(len)?;
+    // cipher suites.
+    let len = reader.u16()? as usize;
+    reader.take(len)?;
+    // legacy compression methods.
+    let len = reader.u8()? as usize;
+    reader.take(len)?;
+
+    // a ClientHello without extensions.
+    if reader.is_empty() {
+        return Ok(None);
+    }
+
+    let len = reader.u16()? as usize;
+    let mut extensions = Reader::new(reader.take(len)?);
+    while !extensions.is_empty() {
+        let extension_type = extensions.u16()?;
+        let len = extensions.u16()? as usize;
+        let mut extension = Reader::new(extensions.take(len)?);
+
+        if extension_type == SERVER_NAME {
+            let len = extension.u16()? as usize;
+            let mut names = Reader::new(extension.take(len)?);
+            while !names.is_empty() {
+                let name_type = names.u8()?;
+                let len = names.u16()? as usize;
+                let name = names.take(len)?;
+                if name_type == HOST_NAME {
+                    let name = str::from_utf8(name).map_err(|_| TlsError::Malformed)?;
+                    return Ok(Some(name.to_string()));
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Reassembles the first handshake message from the consecutive handshake
+/// records at the beginning of the data.
+fn handshake_message(data: &[u8]) -> Result<Vec<u8>> {
+    let mut message = Vec::new();
+    let mut offset = 0;
+
+    loop {
+        let header = RecordHeader::parse(&data[offset..])?;
+        ensure!(
+            header.content_type() == ContentTypes::Handshake,
+            TlsError::NotClientHello
+        );
+
+        let end = offset + header.record_len();
+        ensure!(end <= data.len(), TlsError::Incomplete(end - data.len()));
+        message.extend_from_slice(&data[offset + RECORD_HEADER_LEN..end]);
+        offset = end;
+
+        // the message header is the type followed by a 24-bit length.
+        if message.len() >= 4 {
+            let len = 4 + u32::from_be_bytes([0, message[1], message[2], message[3]]) as usize;
+            if message.len() >= len {
+                message.truncate(len);
+                return Ok(message);
+            }
+        }
+    }
+}
+
+/// A reader over a byte slice that fails on reads past the end.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        ensure!(len <= self.data.len(), TlsError::Malformed);
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u24(&mut self) -> Result<u32> {
+        let bytes = self.take(3)?;
+        Ok(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ClientHello with the ALPN and the SNI extensions.
+    #[rustfmt::skip]
+    const CLIENT_HELLO_RECORD: [u8; 81] = [
+    // record header
+        // content type = handshake, version = 0x0301, length = 76
+        0x16, 0x03, 0x01, 0x00, 0x4c,
+    // handshake header
+        // type = ClientHello, length = 72
+        0x01, 0x00, 0x00, 0x48,
+    // ClientHello
+        // legacy version = 0x0303
+        0x03, 0x03,
+        // random
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+        0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+        0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17,
+        0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+        // legacy session id length = 0
+        0x00,
+        // cipher suites length = 2, TLS_AES_128_GCM_SHA256
+        0x00, 0x02, 0x13, 0x01,
+        // compression methods length = 1, null
+        0x01, 0x00,
+        // extensions length = 29
+        0x00, 0x1d,
+        // ALPN, length = 5, "h2"
+        0x00, 0x10, 0x00, 0x05, 0x00, 0x03, 0x02, 0x68, 0x32,
+        // SNI, length = 16, list length = 14, host name length = 11
+        0x00, 0x00, 0x00, 0x10, 0x00, 0x0e, 0x00, 0x00, 0x0b,
+        // "example.com"
+        0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x2e, 0x63, 0x6f, 0x6d,
+    ];
+
+    #[test]
+    fn parse_record_header() {
+        let header = RecordHeader::parse(&CLIENT_HELLO_RECORD).unwrap();
+        assert_eq!(ContentTypes::Handshake, header.content_type());
+        assert_eq!(0x0301, header.version());
+        assert_eq!(76, header.length());
+        assert_eq!(81, header.record_len());
+        assert_eq!("Handshake", header.content_type().to_string());
+    }
+
+    #[test]
+    fn parse_client_hello_sni() {
+        let sni = client_hello_sni(&CLIENT_HELLO_RECORD).unwrap();
+        assert_eq!(Some("example.com".to_string()), sni);
+    }
+
+    #[test]
+    fn parse_fragmented_client_hello() {
+        // splits the handshake message across two records.
+        let fragment = &CLIENT_HELLO_RECORD[5..];
+        let mut data = vec![0x16, 0x03, 0x01, 0x00, 0x20];
+        data.extend_from_slice(&fragment[..32]);
+        data.extend_from_slice(&[0x16, 0x03, 0x01, 0x00, (fragment.len() - 32) as u8]);
+        data.extend_from_slice(&fragment[32..]);
+
+        let sni = client_hello_sni(&data).unwrap();
+        assert_eq!(Some("example.com".to_string()), sni);
+    }
+
+    #[test]
+    fn parse_incomplete_client_hello() {
+        let err = client_hello_sni(&CLIENT_HELLO_RECORD[..3]).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<TlsError>(),
+            Some(TlsError::Incomplete(2))
+        ));
+
+        let err = client_hello_sni(&CLIENT_HELLO_RECORD[..60]).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<TlsError>(),
+            Some(TlsError::Incomplete(21))
+        ));
+
+        // not a handshake record.
+        let mut data = CLIENT_HELLO_RECORD;
+        data[0] = 23;
+        assert!(client_hello_sni(&data).is_err());
+    }
+}
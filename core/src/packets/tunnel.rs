@@ -0,0 +1,450 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Generic encapsulation and decapsulation of tunneled packets.
+
+use crate::ensure;
+use crate::packets::geneve::{Geneve, GENEVE_PORT};
+use crate::packets::gre::Gre;
+use crate::packets::gtpu::{Gtpu, GTPU_PORT};
+use crate::packets::ip::{extract_flow, AnyIp, Flow, IpPacket, ProtocolNumbers};
+use crate::packets::layer::{Layer, LayerIterator};
+use crate::packets::mpls::Mpls;
+use crate::packets::vxlan::{Vxlan, VxlanGpe, VXLAN_GPE_PORT, VXLAN_PORT};
+use crate::packets::{EtherType, EtherTypes, Ethernet, Packet, Udp};
+use crate::Mbuf;
+use anyhow::{anyhow, Result};
+
+/// A trait implemented by the tunnel protocols that wrap an inner packet
+/// in outer headers.
+///
+/// The trait is built on the [`push`] and [`remove`] primitives, so it can
+/// be used to write pipelines that are generic over the tunnel protocol.
+///
+/// # Example
+///
+/// ```
+/// fn reencap<T: Encap<Inner = Ethernet>>(tunnel: T, params: T::Params) -> Result<T> {
+///     let inner = tunnel.decapsulate()?;
+///     T::encapsulate(inner, params)
+/// }
+/// ```
+///
+/// [`push`]: Packet::push
+/// [`remove`]: Packet::remove
+pub trait Encap: Packet + Sized {
+    /// The type of the inner packet.
+    type Inner: Packet;
+
+    /// The protocol specific parameters of the encapsulation.
+    type Params;
+
+    /// Wraps the inner packet in the outer headers of the tunnel.
+    ///
+    /// The derivable fields of the outer headers are not reconciled. The
+    /// caller should set the outer addresses before calling
+    /// [`reconcile_all`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    fn encapsulate(inner: Self::Inner, params: Self::Params) -> Result<Self>;
+
+    /// Removes the outer headers of the tunnel and returns the inner
+    /// packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload is not a valid inner packet.
+    fn decapsulate(self) -> Result<Self::Inner>;
+}
+
+/// The protocol of the payload of a tunnel that is not limited to
+/// carrying Ethernet frames.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NextProtocol {
+    /// An Ethernet frame.
+    Ethernet,
+    /// An IPv4 packet.
+    Ipv4,
+    /// An IPv6 packet.
+    Ipv6,
+    /// A network service header.
+    Nsh,
+    /// An MPLS label stack.
+    Mpls,
+}
+
+impl NextProtocol {
+    /// Returns the next protocol matching the Ethernet type, or `None` if
+    /// the Ethernet type is not one of the supported protocols.
+    ///
+    /// Ethernet frames are indicated by
+    /// [`EtherTypes::TransparentEthernetBridging`].
+    pub fn from_ether_type(ether_type: EtherType) -> Option<Self> {
+        match ether_type {
+            EtherTypes::TransparentEthernetBridging => Some(NextProtocol::Ethernet),
+            EtherTypes::Ipv4 => Some(NextProtocol::Ipv4),
+            EtherTypes::Ipv6 => Some(NextProtocol::Ipv6),
+            EtherTypes::Nsh => Some(NextProtocol::Nsh),
+            EtherTypes::Mpls => Some(NextProtocol::Mpls),
+            _ => None,
+        }
+    }
+
+    /// Returns the Ethernet type of the next protocol.
+    pub fn ether_type(self) -> EtherType {
+        match self {
+            NextProtocol::Ethernet => EtherTypes::TransparentEthernetBridging,
+            NextProtocol::Ipv4 => EtherTypes::Ipv4,
+            NextProtocol::Ipv6 => EtherTypes::Ipv6,
+            NextProtocol::Nsh => EtherTypes::Nsh,
+            NextProtocol::Mpls => EtherTypes::Mpls,
+        }
+    }
+}
+
+/// Removes the outer headers of the tunnel and returns an Ethernet frame
+/// that can be parsed as the next protocol.
+///
+/// An inner Ethernet frame is returned as is. Any other payload is moved
+/// behind the outer Ethernet header, with the Ethernet type set to the
+/// next protocol, the same way a GTP-U tunnel is decapsulated.
+pub(crate) fn decap_next<T: Packet>(tunnel: T, next: NextProtocol) -> Result<Ethernet> {
+    let outer_len = tunnel.payload_offset();
+
+    if next == NextProtocol::Ethernet {
+        let mut mbuf = tunnel.reset();
+        mbuf.shrink(0, outer_len)?;
+        mbuf.parse::<Ethernet>()
+    } else {
+        let mut ethernet = tunnel.reset().parse::<Ethernet>()?;
+        let offset = ethernet.payload_offset();
+        ethernet.mbuf_mut().shrink(offset, outer_len - offset)?;
+        ethernet.set_ether_type(next.ether_type());
+        Ok(ethernet)
+    }
+}
+
+/// The maximum number of nested tunnels removed by [`peel`].
+const MAX_TUNNEL_DEPTH: usize = 8;
+
+/// The layers of a tunneled packet, and the innermost frame left after
+/// removing all the tunnels.
+#[derive(Debug)]
+pub struct TunnelChain {
+    layers: Vec<Layer>,
+    depth: usize,
+    outer_flow: Option<Flow>,
+    inner_flow: Option<Flow>,
+    inner: Mbuf,
+}
+
+impl TunnelChain {
+    /// Returns the layers of the packet, from the outermost Ethernet
+    /// header to the innermost known header.
+    ///
+    /// The offsets of the layers are relative to the original packet.
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Returns the number of tunnels removed.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the 5-tuple of the outermost IP packet, or `None` if it is
+    /// not a TCP or UDP packet.
+    pub fn outer_flow(&self) -> Option<Flow> {
+        self.outer_flow
+    }
+
+    /// Returns the 5-tuple of the innermost IP packet, or `None` if it is
+    /// not a TCP or UDP packet.
+    pub fn inner_flow(&self) -> Option<Flow> {
+        self.inner_flow
+    }
+
+    /// Consumes the chain and returns the innermost frame.
+    pub fn into_inner(self) -> Mbuf {
+        self.inner
+    }
+}
+
+/// The tunnel protocols detected by [`peel`].
+#[derive(Clone, Copy, Debug)]
+enum TunnelType {
+    Vxlan,
+    VxlanGpe,
+    Geneve,
+    Gre,
+    Gtpu,
+    Mpls,
+}
+
+/// Repeatedly detects and removes the known tunnels of a packet until
+/// the payload is no longer tunneled.
+///
+/// The known tunnels are VXLAN, VXLAN-GPE, Geneve, GRE, GTP-U and MPLS.
+/// The UDP based tunnels are detected by their well-known destination
+/// ports.
+///
+/// # Errors
+///
+/// Returns an error if a detected tunnel fails to decapsulate, or if
+/// the packet is nested more than 8 tunnels deep.
+///
+/// # Example
+///
+/// ```
+/// let chain = tunnel::peel(mbuf)?;
+/// for layer in chain.layers() {
+///     println!("{}: {}", layer.offset(), layer);
+/// }
+/// ```
+pub fn peel(mbuf: Mbuf) -> Result<TunnelChain> {
+    let outer_flow = extract_flow(&mbuf).ok();
+    let mut layers = Vec::new();
+    let mut depth = 0;
+    let mut mbuf = mbuf;
+
+    // the offset of the current frame in the original packet, and the
+    // length of the outer Ethernet header kept by the last tunnel.
+    let mut shift = 0;
+    let mut skip = 0;
+
+    loop {
+        layers.extend(
+            LayerIterator::new(&mbuf)
+                .filter(|layer| layer.offset() >= skip)
+                .map(|layer| layer.shift(shift)),
+        );
+
+        let tunnel = match detect(&mbuf) {
+            Some(tunnel) => tunnel,
+            None => break,
+        };
+        ensure!(
+            depth < MAX_TUNNEL_DEPTH,
+            anyhow!("more than {} nested tunnels.", MAX_TUNNEL_DEPTH)
+        );
+
+        let data_len = mbuf.data_len();
+        let (layer, outer_len, inner) = strip(mbuf, tunnel)?;
+        layers.push(layer.shift(shift));
+
+        // the inner frame either replaces the outer headers entirely, or
+        // is moved behind the outer Ethernet header.
+        let removed = data_len - inner.mbuf().data_len();
+        skip = if removed == outer_len {
+            0
+        } else {
+            inner.payload_offset()
+        };
+        shift += removed;
+        depth += 1;
+        mbuf = inner.reset();
+    }
+
+    Ok(TunnelChain {
+        layers,
+        depth,
+        outer_flow,
+        inner_flow: extract_flow(&mbuf).ok(),
+        inner: mbuf,
+    })
+}
+
+fn detect(mbuf: &Mbuf) -> Option<TunnelType> {
+    let ethernet = mbuf.peek::<Ethernet>().ok()?;
+    if ethernet.ether_type() == EtherTypes::Mpls {
+        return Some(TunnelType::Mpls);
+    }
+
+    let ip = ethernet.peek::<AnyIp>().ok()?;
+    match ip.next_protocol() {
+        ProtocolNumbers::Gre => Some(TunnelType::Gre),
+        ProtocolNumbers::Udp => match ip.peek::<Udp<AnyIp>>().ok()?.dst_port() {
+            VXLAN_PORT => Some(TunnelType::Vxlan),
+            VXLAN_GPE_PORT => Some(TunnelType::VxlanGpe),
+            GENEVE_PORT => Some(TunnelType::Geneve),
+            GTPU_PORT => Some(TunnelType::Gtpu),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Removes the tunnel, returning its layer, the length of the outer
+/// headers and the decapsulated frame.
+fn strip(mbuf: Mbuf, tunnel: TunnelType) -> Result<(Layer, usize, Ethernet)> {
+    let ethernet = mbuf.parse::<Ethernet>()?;
+
+    if let TunnelType::Mpls = tunnel {
+        let mpls = ethernet.parse::<Mpls>()?;
+        let layer = Layer::new("MPLS", &mpls, format!("label {}", mpls.label()));
+        return Ok((layer, mpls.payload_offset(), mpls.decap()?));
+    }
+
+    let ip = ethernet.parse::<AnyIp>()?;
+    if let TunnelType::Gre = tunnel {
+        let gre = ip.parse::<Gre<AnyIp>>()?;
+        let layer = Layer::new("GRE", &gre, format!("protocol {}", gre.protocol_type()));
+        return Ok((layer, gre.payload_offset(), gre.decap()?));
+    }
+
+    let udp = ip.parse::<Udp<AnyIp>>()?;
+    match tunnel {
+        TunnelType::Vxlan => {
+            let vxlan = udp.parse::<Vxlan<AnyIp>>()?;
+            let layer = Layer::new("VXLAN", &vxlan, format!("vni {}", vxlan.vni()));
+            Ok((layer, vxlan.payload_offset(), vxlan.decap()?))
+        }
+        TunnelType::VxlanGpe => {
+            let gpe = udp.parse::<VxlanGpe<AnyIp>>()?;
+            let layer = Layer::new("VXLAN-GPE", &gpe, format!("vni {}", gpe.vni()));
+            Ok((layer, gpe.payload_offset(), gpe.decap()?))
+        }
+        TunnelType::Geneve => {
+            let geneve = udp.parse::<Geneve<AnyIp>>()?;
+            let layer = Layer::new("Geneve", &geneve, format!("vni {}", geneve.vni()));
+            Ok((layer, geneve.payload_offset(), geneve.decap()?))
+        }
+        TunnelType::Gtpu => {
+            let gtpu = udp.parse::<Gtpu<AnyIp>>()?;
+            let layer = Layer::new("GTP-U", &gtpu, format!("teid {}", gtpu.teid()));
+            Ok((layer, gtpu.payload_offset(), gtpu.decap()?))
+        }
+        TunnelType::Gre | TunnelType::Mpls => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::geneve::Geneve4;
+    use crate::packets::gre::Gre4;
+    use crate::packets::gtpu::Gtpu4;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::vxlan::Vxlan4;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, VXLAN_PACKET};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn round_trip<T>(params: T::Params) -> Vec<u8>
+    where
+        T: Encap<Inner = Ethernet>,
+    {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let inner = packet.parse::<Ethernet>().unwrap();
+
+        let mut tunnel = T::encapsulate(inner, params).unwrap();
+        tunnel.reconcile_all();
+        assert!(tunnel.mbuf().data_len() > IPV4_UDP_PACKET.len());
+
+        tunnel.decapsulate().unwrap().to_vec()
+    }
+
+    #[capsule::test]
+    fn vxlan_encap_round_trip() {
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), round_trip::<Vxlan4>(100));
+    }
+
+    #[capsule::test]
+    fn geneve_encap_round_trip() {
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), round_trip::<Geneve4>(100));
+    }
+
+    #[capsule::test]
+    fn gre_encap_round_trip() {
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), round_trip::<Gre4>(()));
+    }
+
+    #[capsule::test]
+    fn gtpu_encap_round_trip() {
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), round_trip::<Gtpu4>(7));
+    }
+
+    #[capsule::test]
+    fn vxlan_encap_outer_headers() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let inner = packet.parse::<Ethernet>().unwrap();
+
+        let mut vxlan = Vxlan4::encapsulate(inner, 100).unwrap();
+        vxlan
+            .envelope_mut()
+            .envelope_mut()
+            .set_dst(Ipv4Addr::new(192, 168, 0, 2));
+        vxlan.reconcile_all();
+
+        let ipv4: &Ipv4 = vxlan.envelope().envelope();
+        assert_eq!(Ipv4Addr::new(192, 168, 0, 2), ipv4.dst());
+        assert_eq!(100, vxlan.vni());
+
+        let inner = vxlan.decapsulate().unwrap();
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), inner.to_vec());
+    }
+
+    #[capsule::test]
+    fn peel_vxlan_packet() {
+        let packet = Mbuf::from_bytes(&VXLAN_PACKET).unwrap();
+        let chain = peel(packet).unwrap();
+        assert_eq!(1, chain.depth());
+
+        let layers = chain
+            .layers()
+            .iter()
+            .map(|layer| (layer.name(), layer.offset()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("Ethernet", 0),
+                ("IPv4", 14),
+                ("UDP", 34),
+                ("VXLAN", 42),
+                ("Ethernet", 50),
+                ("IPv4", 64),
+                ("UDP", 84),
+            ],
+            layers
+        );
+        assert_eq!("VXLAN vni 100", chain.layers()[3].to_string());
+
+        let outer = chain.outer_flow().unwrap();
+        assert_eq!(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)), outer.src_ip());
+        assert_eq!(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 2)), outer.dst_ip());
+        assert_eq!(49152, outer.src_port());
+        assert_eq!(4789, outer.dst_port());
+        assert_eq!(ProtocolNumbers::Udp, outer.protocol());
+
+        let inner = chain.inner_flow().unwrap();
+        assert_eq!(
+            IpAddr::V4(Ipv4Addr::new(139, 133, 217, 110)),
+            inner.src_ip()
+        );
+        assert_eq!(IpAddr::V4(Ipv4Addr::new(139, 133, 233, 2)), inner.dst_ip());
+        assert_eq!(39376, inner.src_port());
+        assert_eq!(1087, inner.dst_port());
+        assert_eq!(ProtocolNumbers::Udp, inner.protocol());
+
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), chain.into_inner().to_vec());
+    }
+}
@@ -16,15 +16,16 @@
 * SPDX-License-Identifier: Apache-2.0
 */
 
+use crate::ffi;
 use crate::packets::ip::v4::Ipv4;
 use crate::packets::ip::v6::Ipv6;
 use crate::packets::ip::{Flow, IpPacket, ProtocolNumbers};
-use crate::packets::types::u16be;
-use crate::packets::{checksum, Internal, Packet};
+use crate::packets::{checksum, Ethernet, Internal, Packet, PacketError, ValidateLength};
 use crate::{ensure, SizeOf};
 use anyhow::{anyhow, Result};
+use capsule_headers::UdpHeader;
 use std::fmt;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr};
 use std::ptr::NonNull;
 
 /// User Datagram Protocol packet based on [IETF RFC 768].
@@ -88,43 +89,56 @@ impl<E: IpPacket> Udp<E> {
     /// Returns the source port.
     #[inline]
     pub fn src_port(&self) -> u16 {
-        self.header().src_port.into()
+        self.header().src_port()
     }
 
     /// Sets the source port.
     #[inline]
     pub fn set_src_port(&mut self, src_port: u16) {
-        self.header_mut().src_port = src_port.into();
+        self.header_mut().set_src_port(src_port);
     }
 
     /// Returns the destination port.
     #[inline]
     pub fn dst_port(&self) -> u16 {
-        self.header().dst_port.into()
+        self.header().dst_port()
     }
 
     /// Sets the destination port.
     #[inline]
     pub fn set_dst_port(&mut self, dst_port: u16) {
-        self.header_mut().dst_port = dst_port.into();
+        self.header_mut().set_dst_port(dst_port);
     }
 
     /// Returns the length in octets of this user datagram including this
     /// header and the data.
     #[inline]
     pub fn length(&self) -> u16 {
-        self.header().length.into()
+        self.header().length()
     }
 
     #[inline]
     fn set_length(&mut self, length: u16) {
-        self.header_mut().length = length.into()
+        self.header_mut().set_length(length)
+    }
+
+    /// Sets the length to the header length plus the payload length.
+    ///
+    /// The length must be derived again after the payload is resized.
+    /// Followed by [`compute_checksum`], the datagram is correct after
+    /// any edits.
+    ///
+    /// [`compute_checksum`]: Udp::compute_checksum
+    #[inline]
+    pub fn set_length_from_payload(&mut self) {
+        let length = self.header_len() + self.payload_len();
+        self.set_length(length as u16);
     }
 
     /// Returns the checksum.
     #[inline]
     pub fn checksum(&self) -> u16 {
-        self.header().checksum.into()
+        self.header().checksum()
     }
 
     /// Sets the checksum.
@@ -134,16 +148,16 @@ impl<E: IpPacket> Udp<E> {
         // all ones. An all zero transmitted checksum value means that the
         // transmitter generated no checksum. To set the checksum value to
         // `0`, use `no_checksum` instead of `set_checksum`.
-        self.header_mut().checksum = match checksum {
-            0 => u16be::from(0xFFFF),
-            _ => checksum.into(),
-        }
+        self.header_mut().set_checksum(match checksum {
+            0 => 0xFFFF,
+            _ => checksum,
+        })
     }
 
     /// Sets checksum to 0 indicating no checksum generated.
     #[inline]
     pub fn no_checksum(&mut self) {
-        self.header_mut().checksum = u16be::default();
+        self.header_mut().set_checksum(0);
     }
 
     /// Returns the 5-tuple that uniquely identifies a UDP connection.
@@ -200,23 +214,148 @@ impl<E: IpPacket> Udp<E> {
         Ok(())
     }
 
+    /// Sets the source port and incrementally updates the checksum.
+    ///
+    /// It updates the checksum using the incremental method, so a correct
+    /// checksum stays correct without recomputing it over the whole
+    /// packet.
+    ///
+    /// If the packet has no checksum, the checksum is left as 0.
     #[inline]
-    fn compute_checksum(&mut self) {
-        self.no_checksum();
+    pub fn set_src_port_checked(&mut self, src_port: u16) {
+        if self.checksum() != 0 {
+            let checksum = checksum::compute_inc(self.checksum(), &[self.src_port()], &[src_port]);
+            // an incremental result of 0 is written as 0xFFFF.
+            self.set_checksum(checksum);
+        }
+        self.set_src_port(src_port);
+    }
+
+    /// Sets the destination port and incrementally updates the checksum.
+    ///
+    /// It updates the checksum using the incremental method, so a correct
+    /// checksum stays correct without recomputing it over the whole
+    /// packet.
+    ///
+    /// If the packet has no checksum, the checksum is left as 0.
+    #[inline]
+    pub fn set_dst_port_checked(&mut self, dst_port: u16) {
+        if self.checksum() != 0 {
+            let checksum = checksum::compute_inc(self.checksum(), &[self.dst_port()], &[dst_port]);
+            // an incremental result of 0 is written as 0xFFFF.
+            self.set_checksum(checksum);
+        }
+        self.set_dst_port(dst_port);
+    }
 
+    /// Returns whether the checksum is correct.
+    ///
+    /// The checksum is verified over the pseudo-header, the header and the
+    /// payload. A packet with no checksum is considered valid.
+    #[inline]
+    pub fn validate_checksum(&self) -> bool {
+        self.validate_checksum_with(|packet_len| {
+            self.envelope()
+                .pseudo_header(packet_len, ProtocolNumbers::Udp)
+                .sum()
+        })
+    }
+
+    /// Validates the checksum with the pseudo-header sum returned by the
+    /// function for the packet length.
+    #[inline]
+    fn validate_checksum_with<F: FnOnce(u16) -> u16>(&self, pseudo_header_sum: F) -> bool {
+        if self.checksum() == 0 {
+            return true;
+        }
+
+        match self.mbuf().read_data_slice(self.offset, self.len()) {
+            Ok(data) => {
+                let data = unsafe { data.as_ref() };
+                checksum::compute(pseudo_header_sum(data.len() as u16), data) == 0
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the checksum over the pseudo-header, the header and the
+    /// payload, without writing it to the packet.
+    ///
+    /// The value is the same one [`compute_checksum`] writes. A computed
+    /// checksum of zero is returned as all ones.
+    ///
+    /// [`compute_checksum`]: Udp::compute_checksum
+    #[inline]
+    pub fn calculate_checksum(&self) -> u16 {
         if let Ok(data) = self.mbuf().read_data_slice(self.offset, self.len()) {
             let data = unsafe { data.as_ref() };
             let pseudo_header_sum = self
                 .envelope()
                 .pseudo_header(data.len() as u16, ProtocolNumbers::Udp)
                 .sum();
-            let checksum = checksum::compute(pseudo_header_sum, data);
-            self.set_checksum(checksum);
+            // the checksum field is at offset 6 of the header.
+            match checksum::compute_cleared(pseudo_header_sum, data, 6) {
+                0 => 0xFFFF,
+                checksum => checksum,
+            }
         } else {
             // we are reading till the end of buffer, should never run out
             unreachable!()
         }
     }
+
+    /// Computes the checksum over the pseudo-header, the header and the
+    /// payload.
+    #[inline]
+    pub fn compute_checksum(&mut self) {
+        let checksum = self.calculate_checksum();
+        self.set_checksum(checksum);
+    }
+
+    /// Requests the network device to compute the checksum on transmit.
+    ///
+    /// Sets the `PKT_TX_UDP_CKSUM` offload flag, the IP version flag, and the
+    /// layer-2 and layer-3 header lengths on the buffer. As DPDK requires,
+    /// the checksum field is primed with the pseudo-header sum instead of
+    /// the full checksum. The port must be configured with the matching
+    /// `DEV_TX_OFFLOAD_UDP_CKSUM` capability. Because [`reconcile`]
+    /// recomputes the checksum in software, it should not be called after
+    /// requesting the offload.
+    ///
+    /// The device locates the headers from the layer-2 and layer-3 lengths,
+    /// so the IP header must directly follow the Ethernet header. If it
+    /// does not, for example behind an authentication header or an IPv6
+    /// extension header, the checksum is computed in software instead and
+    /// no offload is requested.
+    ///
+    /// [`reconcile`]: Packet::reconcile
+    #[inline]
+    pub fn offload_checksum(&mut self) {
+        let l2_len = self.envelope().offset();
+        let on_ethernet = self
+            .mbuf()
+            .peek::<Ethernet>()
+            .map(|ethernet| ethernet.header_len() == l2_len)
+            .unwrap_or(false);
+        if !on_ethernet {
+            self.compute_checksum();
+            return;
+        }
+
+        let pseudo_header_sum = self
+            .envelope()
+            .pseudo_header(self.len() as u16, ProtocolNumbers::Udp)
+            .sum();
+        self.header_mut().set_checksum(pseudo_header_sum);
+
+        let ip_flag = match self.envelope().src() {
+            IpAddr::V4(_) => ffi::PKT_TX_IPV4,
+            IpAddr::V6(_) => ffi::PKT_TX_IPV6,
+        };
+        let l3_len = self.envelope().header_len();
+        self.mbuf_mut()
+            .set_tx_offload(ip_flag | ffi::PKT_TX_UDP_CKSUM, l2_len, l3_len);
+    }
 }
 
 impl<E: IpPacket> fmt::Debug for Udp<E> {
@@ -278,11 +417,14 @@ impl<E: IpPacket> Packet for Udp<E> {
     /// not set to [`ProtocolNumbers::Udp`]. If the envelope is IPv6 or an
     /// extension header, returns an error if [`next_header`] is not set to
     /// `ProtocolNumbers::Udp`. Returns an error if the payload does not
-    /// have sufficient data for the UDP header.
+    /// have sufficient data for the UDP header. Returns
+    /// `PacketError::LengthTooShort` if [`length`] is less than the header
+    /// length.
     ///
     /// [`Ipv4::protocol`]: crate::packets::ip::v4::Ipv4::protocol
     /// [`ProtocolNumbers::Udp`]: crate::packets::ip::ProtocolNumbers::Udp
     /// [`next_header`]: crate::packets::ip::v6::Ipv6Packet::next_header
+    /// [`length`]: Udp::length
     #[inline]
     fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
         ensure!(
@@ -294,11 +436,24 @@ impl<E: IpPacket> Packet for Udp<E> {
         let offset = envelope.payload_offset();
         let header = mbuf.read_data(offset)?;
 
-        Ok(Udp {
+        let packet = Udp {
             envelope,
             header,
             offset,
-        })
+        };
+
+        // a zero length is accepted. it's the length of a newly pushed
+        // header not reconciled yet, or of an IPv6 jumbogram.
+        let len = packet.length() as usize;
+        ensure!(
+            len == 0 || len >= UdpHeader::size_of(),
+            PacketError::LengthTooShort {
+                len,
+                header_len: UdpHeader::size_of(),
+            }
+        );
+
+        Ok(packet)
     }
 
     /// Prepends an UDP packet to the beginning of the envelope's payload.
@@ -348,33 +503,73 @@ impl<E: IpPacket> Packet for Udp<E> {
     /// [`pseudo-header`]: crate::packets::checksum::PseudoHeader
     #[inline]
     fn reconcile(&mut self) {
-        let len = self.len() as u16;
-        self.set_length(len);
+        self.set_length_from_payload();
         self.compute_checksum();
     }
 }
 
+impl<E: IpPacket> ValidateLength for Udp<E> {
+    /// Returns whether the length covers the header and does not exceed
+    /// the data in all the segments of the buffer.
+    #[inline]
+    fn validate_length(&self) -> bool {
+        let length = self.length() as usize;
+        length >= self.header_len() && length <= self.mbuf().pkt_len().saturating_sub(self.offset)
+    }
+}
+
 /// A type alias for an IPv4 UDP packet.
 pub type Udp4 = Udp<Ipv4>;
 
 /// A type alias for an IPv6 UDP packet.
 pub type Udp6 = Udp<Ipv6>;
 
-/// UDP header.
-#[derive(Clone, Copy, Debug, Default, SizeOf)]
-#[repr(C)]
-struct UdpHeader {
-    src_port: u16be,
-    dst_port: u16be,
-    length: u16be,
-    checksum: u16be,
+/// Validates the checksums of a burst of IPv4 UDP packets.
+///
+/// The results are the same as calling [`validate_checksum`] on each packet
+/// and are returned in the same order. While the current packet is being
+/// validated, the next packet is prefetched into the cache. Consecutive
+/// packets between the same addresses and of the same length share the
+/// pseudo-header sum instead of computing it again.
+///
+/// [`validate_checksum`]: Udp::validate_checksum
+pub fn validate_checksums(packets: &[Udp4]) -> Vec<bool> {
+    let mut results = Vec::with_capacity(packets.len());
+    let mut shared: Option<(Ipv4Addr, Ipv4Addr, u16, u16)> = None;
+
+    for (i, udp) in packets.iter().enumerate() {
+        if let Some(next) = packets.get(i + 1) {
+            next.prefetch();
+        }
+
+        let src = udp.envelope().src();
+        let dst = udp.envelope().dst();
+        let valid = udp.validate_checksum_with(|packet_len| match shared {
+            Some((s, d, len, sum)) if s == src && d == dst && len == packet_len => sum,
+            _ => {
+                let sum = udp
+                    .envelope()
+                    .pseudo_header(packet_len, ProtocolNumbers::Udp)
+                    .sum();
+                shared = Some((src, dst, packet_len, sum));
+                sum
+            }
+        });
+        results.push(valid);
+    }
+
+    results
+}
+
+impl SizeOf for UdpHeader {
+    const SIZE: usize = std::mem::size_of::<Self>();
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::packets::Ethernet;
-    use crate::testils::byte_arrays::{IPV4_TCP_PACKET, IPV4_UDP_PACKET};
+    use crate::packets::ah::Ah;
+    use crate::testils::byte_arrays::{AH_PACKET, IPV4_TCP_PACKET, IPV4_UDP_PACKET};
     use crate::Mbuf;
     use std::net::{Ipv4Addr, Ipv6Addr};
 
@@ -383,6 +578,15 @@ mod tests {
         assert_eq!(8, UdpHeader::size_of());
     }
 
+    #[test]
+    fn size_of_udp_header_in_const_context() {
+        const LEN: usize = UdpHeader::SIZE;
+
+        let buf = [0u8; UdpHeader::SIZE];
+        assert_eq!(8, LEN);
+        assert_eq!(LEN, buf.len());
+    }
+
     #[capsule::test]
     fn parse_udp_packet() {
         let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
@@ -396,6 +600,52 @@ mod tests {
         assert_eq!(0x7228, udp.checksum());
     }
 
+    #[capsule::test]
+    fn validate_udp_length() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp4>().unwrap();
+        assert!(udp.validate_length());
+
+        // length overstates the buffer by one byte.
+        let mut bytes = IPV4_UDP_PACKET;
+        bytes[39] += 1;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert!(!ipv4.peek::<Udp4>().unwrap().validate_length());
+        assert!(ipv4.parse_strict::<Udp4>().is_err());
+
+        // the payload continues in a second segment.
+        let mut packet = Mbuf::from_bytes(&IPV4_UDP_PACKET[..45]).unwrap();
+        packet
+            .chain(Mbuf::from_bytes(&IPV4_UDP_PACKET[45..]).unwrap())
+            .unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert!(ipv4.parse_strict::<Udp4>().is_ok());
+    }
+
+    #[capsule::test]
+    fn parse_udp_packet_with_short_length() {
+        let mut bytes = IPV4_UDP_PACKET;
+        // udp length = 4, less than the header
+        bytes[38] = 0x00;
+        bytes[39] = 0x04;
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let err = ipv4.parse::<Udp4>().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PacketError>(),
+            Some(PacketError::LengthTooShort {
+                len: 4,
+                header_len: 8,
+            })
+        ));
+    }
+
     #[capsule::test]
     fn parse_non_udp_packet() {
         let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
@@ -443,6 +693,40 @@ mod tests {
         assert!(udp.set_src_ip(Ipv6Addr::UNSPECIFIED.into()).is_err());
     }
 
+    #[capsule::test]
+    fn set_length_from_extended_payload() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut udp = ipv4.parse::<Udp4>().unwrap();
+        assert_eq!(18, udp.length());
+
+        let offset = udp.mbuf().data_len();
+        udp.mbuf_mut().extend(offset, 6).unwrap();
+        udp.mbuf_mut().write_data_slice(offset, b"world!").unwrap();
+        assert_eq!(16, udp.payload_len());
+
+        udp.set_length_from_payload();
+        udp.compute_checksum();
+        assert_eq!(24, udp.length());
+        assert!(udp.validate_length());
+        assert!(udp.validate_checksum());
+    }
+
+    #[capsule::test]
+    fn calculate_checksum_without_writing() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut udp = ipv4.parse::<Udp4>().unwrap();
+
+        assert_eq!(0x7228, udp.calculate_checksum());
+
+        udp.set_checksum(0x1234);
+        assert_eq!(0x7228, udp.calculate_checksum());
+        assert_eq!(0x1234, udp.checksum());
+    }
+
     #[capsule::test]
     fn compute_checksum() {
         let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
@@ -456,6 +740,126 @@ mod tests {
         assert_eq!(expected, udp.checksum());
     }
 
+    #[capsule::test]
+    fn validate_checksums_of_burst() {
+        let mut bad_payload = IPV4_UDP_PACKET;
+        bad_payload[50] ^= 0xff;
+        let mut bad_src = IPV4_UDP_PACKET;
+        bad_src[26] ^= 0xff;
+
+        let packets = [IPV4_UDP_PACKET, bad_payload, IPV4_UDP_PACKET, bad_src]
+            .iter()
+            .map(|bytes| {
+                let packet = Mbuf::from_bytes(bytes).unwrap();
+                let ethernet = packet.parse::<Ethernet>().unwrap();
+                let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+                ipv4.parse::<Udp4>().unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let expected = packets
+            .iter()
+            .map(|udp| udp.validate_checksum())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![true, false, true, false], expected);
+        assert_eq!(expected, validate_checksums(&packets));
+    }
+
+    #[capsule::test]
+    fn set_port_with_checksum_fixup() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut udp = ipv4.parse::<Udp4>().unwrap();
+        assert!(udp.validate_checksum());
+
+        udp.set_src_port_checked(1234);
+        udp.set_dst_port_checked(56789);
+        assert_eq!(1234, udp.src_port());
+        assert_eq!(56789, udp.dst_port());
+        assert!(udp.validate_checksum());
+
+        // the incremental update is exact.
+        let checksum = udp.checksum();
+        udp.reconcile();
+        assert_eq!(checksum, udp.checksum());
+
+        // without the fixup, the checksum goes stale.
+        udp.set_dst_port(80);
+        assert!(!udp.validate_checksum());
+    }
+
+    #[capsule::test]
+    fn set_port_with_checksum_fixup_to_zero() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut udp = ipv4.parse::<Udp4>().unwrap();
+
+        // picks the port that cancels out the rest of the sum.
+        let src_port = checksum::compute_inc(udp.checksum(), &[udp.src_port()], &[0]);
+        assert_eq!(
+            0,
+            checksum::compute_inc(udp.checksum(), &[udp.src_port()], &[src_port])
+        );
+
+        // a zero checksum means no checksum, so it's sent as all ones.
+        udp.set_src_port_checked(src_port);
+        assert_eq!(0xFFFF, udp.checksum());
+        assert!(udp.validate_checksum());
+
+        // same for the destination port, from the original checksum.
+        udp.set_src_port_checked(39376);
+        assert_eq!(0x7228, udp.checksum());
+        let dst_port = checksum::compute_inc(udp.checksum(), &[udp.dst_port()], &[0]);
+        udp.set_dst_port_checked(dst_port);
+        assert_eq!(0xFFFF, udp.checksum());
+        assert!(udp.validate_checksum());
+    }
+
+    #[capsule::test]
+    fn offload_checksum() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut udp = ipv4.parse::<Udp4>().unwrap();
+
+        udp.offload_checksum();
+
+        let expected = udp
+            .envelope()
+            .pseudo_header(udp.len() as u16, ProtocolNumbers::Udp)
+            .sum();
+        assert_eq!(expected, udp.checksum());
+
+        let mbuf = udp.mbuf();
+        assert_eq!(ffi::PKT_TX_IPV4, mbuf.ol_flags() & ffi::PKT_TX_IPV4);
+        assert_eq!(
+            ffi::PKT_TX_UDP_CKSUM,
+            mbuf.ol_flags() & ffi::PKT_TX_UDP_CKSUM
+        );
+        assert_eq!(14, mbuf.l2_len());
+        assert_eq!(20, mbuf.l3_len());
+    }
+
+    #[capsule::test]
+    fn offload_checksum_behind_extension_header() {
+        let packet = Mbuf::from_bytes(&AH_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let ah = ipv4.parse::<Ah<Ipv4>>().unwrap();
+        let mut udp = ah.parse::<Udp<Ah<Ipv4>>>().unwrap();
+
+        udp.set_checksum(0x1234);
+        udp.offload_checksum();
+
+        // falls back to the software checksum.
+        let checksum = udp.checksum();
+        udp.compute_checksum();
+        assert_eq!(udp.checksum(), checksum);
+        assert_eq!(0, udp.mbuf().ol_flags() & ffi::PKT_TX_UDP_CKSUM);
+    }
+
     #[capsule::test]
     fn push_udp_packet() {
         let packet = Mbuf::new().unwrap();
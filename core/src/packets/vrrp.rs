@@ -0,0 +1,482 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Virtual Router Redundancy Protocol.
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::{IpPacket, ProtocolNumbers};
+use crate::packets::types::u16be;
+use crate::packets::{checksum, Internal, Packet};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::ptr::NonNull;
+
+/// The VRRP advertisement message type.
+pub const ADVERTISEMENT: u8 = 1;
+
+/// Virtual Router Redundancy Protocol packet based on [IETF RFC 3768] and
+/// [IETF RFC 5798].
+///
+/// VRRPv2 messages have the authentication type and the advertisement
+/// interval in seconds.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |Version| Type  | Virtual Rtr ID|   Priority    | Count IP Addrs|
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   Auth Type   |   Adver Int   |          Checksum             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         IP Address (1)                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                            .                                  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         IP Address (n)                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                     Authentication Data (1)                   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                     Authentication Data (2)                   |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// VRRPv3 messages replace them with the maximum advertisement interval
+/// in centiseconds, and do not have the authentication data.
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |Version| Type  | Virtual Rtr ID|   Priority    |Count IPvX Addr|
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |(rsvd) |     Max Adver Int     |          Checksum             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Version*: (4 bits)
+///      The VRRP protocol version, 2 or 3.
+///
+/// - *Type*: (4 bits)
+///      The type of the VRRP packet. The only type defined is 1,
+///      advertisement.
+///
+/// - *Virtual Rtr ID*: (8 bits)
+///      Identifies the virtual router this packet is reporting status for.
+///
+/// - *Priority*: (8 bits)
+///      The sending router's priority for the virtual router. 255 is
+///      reserved for the router that owns the addresses, and 0 indicates
+///      the current master has stopped participating.
+///
+/// - *Count IP Addrs*: (8 bits)
+///      The number of IP addresses contained in this advertisement.
+///
+/// - *Auth Type*: (8 bits)
+///      The authentication method of VRRPv2, deprecated.
+///
+/// - *Advertisement Interval*: (8 bits in VRRPv2, 12 bits in VRRPv3)
+///      The time interval between advertisements, in seconds for VRRPv2
+///      and in centiseconds for VRRPv3.
+///
+/// - *Checksum*: (16 bits)
+///      The 16-bit one's complement of the one's complement sum of the
+///      entire VRRP message. For VRRPv3, the sum includes the IPv4
+///      pseudo-header.
+///
+/// - *IP Address(es)*: (32 bits each)
+///      The virtual IP addresses associated with the virtual router.
+///
+/// [IETF RFC 3768]: https://tools.ietf.org/html/rfc3768#section-5.1
+/// [IETF RFC 5798]: https://tools.ietf.org/html/rfc5798#section-5.1
+pub struct Vrrp {
+    envelope: Ipv4,
+    header: NonNull<VrrpHeader>,
+    offset: usize,
+}
+
+impl Vrrp {
+    #[inline]
+    fn header(&self) -> &VrrpHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut VrrpHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the protocol version.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        self.header().version_type >> 4
+    }
+
+    /// Returns the message type.
+    #[inline]
+    pub fn msg_type(&self) -> u8 {
+        self.header().version_type & 0x0f
+    }
+
+    /// Returns the virtual router identifier.
+    #[inline]
+    pub fn vrid(&self) -> u8 {
+        self.header().vrid
+    }
+
+    /// Sets the virtual router identifier.
+    #[inline]
+    pub fn set_vrid(&mut self, vrid: u8) {
+        self.header_mut().vrid = vrid;
+    }
+
+    /// Returns the priority.
+    #[inline]
+    pub fn priority(&self) -> u8 {
+        self.header().priority
+    }
+
+    /// Sets the priority.
+    #[inline]
+    pub fn set_priority(&mut self, priority: u8) {
+        self.header_mut().priority = priority;
+    }
+
+    /// Returns the number of virtual IP addresses.
+    #[inline]
+    pub fn count_ip_addrs(&self) -> u8 {
+        self.header().count_ip_addrs
+    }
+
+    /// Returns the authentication type of a VRRPv2 message. Returns `None`
+    /// for VRRPv3 messages.
+    #[inline]
+    pub fn auth_type(&self) -> Option<u8> {
+        if self.version() == 2 {
+            Some((u16::from(self.header().adver_int) >> 8) as u8)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the advertisement interval, in seconds for VRRPv2 and in
+    /// centiseconds for VRRPv3.
+    #[inline]
+    pub fn adver_int(&self) -> u16 {
+        let adver_int = u16::from(self.header().adver_int);
+        if self.version() == 2 {
+            adver_int & 0x00ff
+        } else {
+            adver_int & 0x0fff
+        }
+    }
+
+    /// Returns the checksum.
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        self.header().checksum.into()
+    }
+
+    /// Returns the virtual IP addresses.
+    #[inline]
+    pub fn addrs(&self) -> &[Ipv4Addr] {
+        let count = self.count_ip_addrs() as usize;
+        if count > 0 {
+            // the addresses are checked when parsed.
+            let addrs = self
+                .mbuf()
+                .read_data_slice::<Ipv4Addr>(self.payload_offset(), count)
+                .unwrap();
+            unsafe { &*addrs.as_ptr() }
+        } else {
+            &[]
+        }
+    }
+
+    /// Computes the checksum over the VRRP message.
+    ///
+    /// The computation of a VRRPv3 checksum includes the IPv4
+    /// pseudo-header.
+    #[inline]
+    pub fn compute_checksum(&mut self) {
+        self.header_mut().checksum = u16be::default();
+
+        let pseudo_header_sum = if self.version() == 3 {
+            self.envelope()
+                .pseudo_header(self.len() as u16, ProtocolNumbers::Vrrp)
+                .sum()
+        } else {
+            0
+        };
+
+        if let Ok(data) = self.mbuf().read_data_slice(self.offset(), self.len()) {
+            let data = unsafe { data.as_ref() };
+            let checksum = checksum::compute(pseudo_header_sum, data);
+            self.header_mut().checksum = checksum.into();
+        } else {
+            // we are reading till the end of buffer, should never run out
+            unreachable!()
+        }
+    }
+}
+
+impl fmt::Debug for Vrrp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("vrrp")
+            .field("version", &self.version())
+            .field("type", &self.msg_type())
+            .field("vrid", &self.vrid())
+            .field("priority", &self.priority())
+            .field("count_ip_addrs", &self.count_ip_addrs())
+            .field("auth_type", &self.auth_type())
+            .field("adver_int", &self.adver_int())
+            .field("checksum", &format!("0x{:04x}", self.checksum()))
+            .field("addrs", &self.addrs())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl Packet for Vrrp {
+    /// The preceding type for a VRRP packet must be IPv4.
+    type Envelope = Ipv4;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the fixed header. The virtual IP addresses
+    /// and the authentication data are the payload.
+    #[inline]
+    fn header_len(&self) -> usize {
+        VrrpHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Vrrp {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the IPv4 payload as a VRRP packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the [`protocol`] is not [`ProtocolNumbers::Vrrp`].
+    /// Returns an error if the version is neither 2 nor 3. Returns an error
+    /// if the payload does not have sufficient data for the header and the
+    /// virtual IP addresses.
+    ///
+    /// [`protocol`]: Ipv4::protocol
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.protocol() == ProtocolNumbers::Vrrp,
+            anyhow!("not a VRRP packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data::<VrrpHeader>(offset)?;
+
+        let header_ref = unsafe { header.as_ref() };
+        let version = header_ref.version_type >> 4;
+        ensure!(
+            version == 2 || version == 3,
+            anyhow!("unsupported VRRP version {}.", version)
+        );
+
+        let count = header_ref.count_ip_addrs as usize;
+        if count > 0 {
+            let _ = mbuf.read_data_slice::<Ipv4Addr>(offset + VrrpHeader::size_of(), count)?;
+        }
+
+        Ok(Vrrp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends a VRRPv2 advertisement header without virtual IP addresses
+    /// and authentication data to the beginning of the IPv4's payload.
+    ///
+    /// [`protocol`] is set to [`ProtocolNumbers::Vrrp`]. The priority is
+    /// set to the default of 100 and the advertisement interval to 1
+    /// second.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`protocol`]: Ipv4::protocol
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, VrrpHeader::size_of())?;
+        let header = mbuf.write_data(offset, &VrrpHeader::default())?;
+
+        envelope.set_protocol(ProtocolNumbers::Vrrp);
+
+        Ok(Vrrp {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+
+    /// Reconciles the derivable header fields against the changes made to
+    /// the packet.
+    ///
+    /// * [`checksum`] is computed based on the whole VRRP message.
+    ///
+    /// [`checksum`]: Vrrp::checksum
+    #[inline]
+    fn reconcile(&mut self) {
+        self.compute_checksum();
+    }
+}
+
+/// VRRP fixed header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct VrrpHeader {
+    version_type: u8,
+    vrid: u8,
+    priority: u8,
+    count_ip_addrs: u8,
+    adver_int: u16be,
+    checksum: u16be,
+}
+
+impl Default for VrrpHeader {
+    fn default() -> VrrpHeader {
+        VrrpHeader {
+            version_type: (2 << 4) | ADVERTISEMENT,
+            vrid: 0,
+            priority: 100,
+            count_ip_addrs: 0,
+            adver_int: 1u16.into(),
+            checksum: u16be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::Ethernet;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, VRRP_PACKET};
+    use crate::Mbuf;
+
+    #[test]
+    fn size_of_vrrp_header() {
+        assert_eq!(8, VrrpHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_vrrp_packet() {
+        let packet = Mbuf::from_bytes(&VRRP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let vrrp = ipv4.parse::<Vrrp>().unwrap();
+
+        assert_eq!(2, vrrp.version());
+        assert_eq!(ADVERTISEMENT, vrrp.msg_type());
+        assert_eq!(1, vrrp.vrid());
+        assert_eq!(100, vrrp.priority());
+        assert_eq!(2, vrrp.count_ip_addrs());
+        assert_eq!(Some(0), vrrp.auth_type());
+        assert_eq!(1, vrrp.adver_int());
+        assert_eq!(0xf5ae, vrrp.checksum());
+        assert_eq!(
+            &[
+                Ipv4Addr::new(192, 168, 1, 254),
+                Ipv4Addr::new(192, 168, 1, 253)
+            ],
+            vrrp.addrs()
+        );
+        // the addresses are followed by 8 bytes of authentication data.
+        assert_eq!(16, vrrp.payload_len());
+    }
+
+    #[capsule::test]
+    fn compute_vrrp_checksum() {
+        let packet = Mbuf::from_bytes(&VRRP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut vrrp = ipv4.parse::<Vrrp>().unwrap();
+
+        vrrp.compute_checksum();
+        assert_eq!(0xf5ae, vrrp.checksum());
+
+        vrrp.set_priority(200);
+        vrrp.compute_checksum();
+        assert_ne!(0xf5ae, vrrp.checksum());
+    }
+
+    #[capsule::test]
+    fn parse_non_vrrp_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+
+        assert!(ipv4.parse::<Vrrp>().is_err());
+    }
+
+    #[capsule::test]
+    fn push_vrrp_packet() {
+        let packet = Mbuf::new().unwrap();
+        let ethernet = packet.push::<Ethernet>().unwrap();
+        let ipv4 = ethernet.push::<Ipv4>().unwrap();
+        let mut vrrp = ipv4.push::<Vrrp>().unwrap();
+        vrrp.set_vrid(7);
+        vrrp.reconcile();
+
+        assert_eq!(ProtocolNumbers::Vrrp, vrrp.envelope().protocol());
+        assert_eq!(2, vrrp.version());
+        assert_eq!(7, vrrp.vrid());
+        assert_eq!(100, vrrp.priority());
+        assert_eq!(1, vrrp.adver_int());
+        assert!(vrrp.addrs().is_empty());
+    }
+}
@@ -0,0 +1,763 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Virtual eXtensible Local Area Network.
+
+use crate::packets::ip::v4::Ipv4;
+use crate::packets::ip::v6::Ipv6;
+use crate::packets::ip::IpPacket;
+use crate::packets::tunnel::{self, Encap, NextProtocol};
+use crate::packets::types::u32be;
+use crate::packets::{Ethernet, Internal, Packet, Udp};
+use crate::{ensure, SizeOf};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::ptr::NonNull;
+
+/// The IANA assigned UDP port of VXLAN.
+pub const VXLAN_PORT: u16 = 4789;
+
+/// The IANA assigned UDP port of VXLAN-GPE.
+pub const VXLAN_GPE_PORT: u16 = 4790;
+
+// Masks.
+const FLAGS_I: u8 = 0b0000_1000;
+const FLAGS_P: u8 = 0b0000_0100;
+const FLAGS_B: u8 = 0b0000_0010;
+const FLAGS_O: u8 = 0b0000_0001;
+const GPE_VERSION: u8 = 0b0011_0000;
+const VNI_MAX: u32 = 0x00ff_ffff;
+
+// VXLAN-GPE next protocols.
+const GPE_IPV4: u8 = 0x01;
+const GPE_IPV6: u8 = 0x02;
+const GPE_ETHERNET: u8 = 0x03;
+const GPE_NSH: u8 = 0x04;
+const GPE_MPLS: u8 = 0x05;
+
+/// Virtual eXtensible Local Area Network header based on [IETF RFC 7348].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |R|R|R|R|I|R|R|R|            Reserved                           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                VXLAN Network Identifier (VNI) |   Reserved    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Flags*: (8 bits)
+///      The I flag must be set to 1 for a valid VXLAN Network ID (VNI).
+///      The other 7 bits are reserved and must be set to zero.
+///
+/// - *VXLAN Network Identifier (VNI)*: (24 bits)
+///      Used to designate the individual VXLAN overlay network on which the
+///      communicating VMs are situated.
+///
+/// The payload of the VXLAN packet is the inner Ethernet frame. Use
+/// [`decap`] to remove the outer headers and parse the inner frame, and
+/// [`encap`] to wrap a frame in the outer headers.
+///
+/// [IETF RFC 7348]: https://tools.ietf.org/html/rfc7348#section-5
+/// [`decap`]: Vxlan::decap
+/// [`encap`]: Vxlan::encap
+pub struct Vxlan<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<VxlanHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> Vxlan<E> {
+    #[inline]
+    fn header(&self) -> &VxlanHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut VxlanHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the flags.
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.header().flags
+    }
+
+    /// Sets the flags.
+    #[inline]
+    pub fn set_flags(&mut self, flags: u8) {
+        self.header_mut().flags = flags
+    }
+
+    /// Returns a flag indicating whether the VNI is valid.
+    #[inline]
+    pub fn has_vni(&self) -> bool {
+        self.flags() & FLAGS_I != 0
+    }
+
+    /// Returns the VXLAN network identifier.
+    #[inline]
+    pub fn vni(&self) -> u32 {
+        u32::from(self.header().vni_reserved) >> 8
+    }
+
+    /// Sets the VXLAN network identifier and the I flag.
+    ///
+    /// Only the lower 24 bits of the `vni` are used.
+    #[inline]
+    pub fn set_vni(&mut self, vni: u32) {
+        self.header_mut().vni_reserved = ((vni & VNI_MAX) << 8).into();
+        self.header_mut().flags |= FLAGS_I;
+    }
+
+    /// Removes the outer headers and returns the inner Ethernet frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload is not an Ethernet frame.
+    #[inline]
+    pub fn decap(self) -> Result<Ethernet> {
+        let outer_len = self.payload_offset();
+        let mut mbuf = self.reset();
+        mbuf.shrink(0, outer_len)?;
+        mbuf.parse::<Ethernet>()
+    }
+}
+
+impl<E: IpPacket<Envelope = Ethernet>> Vxlan<E> {
+    /// Encapsulates the Ethernet frame in outer Ethernet, IP, UDP and VXLAN
+    /// headers.
+    ///
+    /// The UDP destination port is set to [`VXLAN_PORT`]. The outer
+    /// addresses and the UDP source port should be set afterwards through
+    /// the envelopes, followed by [`reconcile_all`] to fix the lengths and
+    /// the checksums.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    #[inline]
+    pub fn encap(inner: Ethernet, vni: u32) -> Result<Self> {
+        let mut vxlan = inner
+            .reset()
+            .push::<Ethernet>()?
+            .push::<E>()?
+            .push::<Udp<E>>()?
+            .push::<Vxlan<E>>()?;
+        vxlan.set_vni(vni);
+        Ok(vxlan)
+    }
+}
+
+impl<E: IpPacket<Envelope = Ethernet>> Encap for Vxlan<E> {
+    type Inner = Ethernet;
+
+    /// The VXLAN network identifier.
+    type Params = u32;
+
+    #[inline]
+    fn encapsulate(inner: Self::Inner, vni: Self::Params) -> Result<Self> {
+        Vxlan::encap(inner, vni)
+    }
+
+    #[inline]
+    fn decapsulate(self) -> Result<Self::Inner> {
+        self.decap()
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for Vxlan<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("vxlan")
+            .field("flags", &format!("0x{:02x}", self.flags()))
+            .field("vni", &self.vni())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for Vxlan<E> {
+    /// The preceding type for VXLAN must be `Udp`.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        VxlanHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        Vxlan {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP payload as a VXLAN packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the UDP destination port is not [`VXLAN_PORT`].
+    /// Returns an error if the payload does not have sufficient data for
+    /// the VXLAN header.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.dst_port() == VXLAN_PORT,
+            anyhow!("not a VXLAN packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        Ok(Vxlan {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends a VXLAN header to the beginning of the UDP's payload.
+    ///
+    /// The UDP destination port is set to [`VXLAN_PORT`] and the I flag is
+    /// set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, VxlanHeader::size_of())?;
+        let header = mbuf.write_data(offset, &VxlanHeader::default())?;
+
+        envelope.set_dst_port(VXLAN_PORT);
+
+        Ok(Vxlan {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// A type alias for a VXLAN packet over IPv4.
+pub type Vxlan4 = Vxlan<Ipv4>;
+
+/// A type alias for a VXLAN packet over IPv6.
+pub type Vxlan6 = Vxlan<Ipv6>;
+
+/// VXLAN Generic Protocol Extension header based on
+/// [IETF draft-ietf-nvo3-vxlan-gpe].
+///
+/// ```
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |R|R|Ver|I|P|B|O|       Reserved                |Next Protocol  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                VXLAN Network Identifier (VNI) |   Reserved    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// - *Ver*: (2 bits)
+///      The version of VXLAN-GPE, currently 0.
+///
+/// - *I*: (1 bit)
+///      Set to 1 for a valid VXLAN Network ID (VNI).
+///
+/// - *P*: (1 bit)
+///      Set to 1 when the Next Protocol field is present.
+///
+/// - *B*: (1 bit)
+///      Set to 1 for the BUM traffic, broadcast, unknown unicast and
+///      multicast.
+///
+/// - *O*: (1 bit)
+///      Set to 1 to indicate that the packet is an OAM packet.
+///
+/// - *Next Protocol*: (8 bits)
+///      The protocol of the payload. 1 is IPv4, 2 is IPv6, 3 is Ethernet,
+///      4 is NSH and 5 is MPLS.
+///
+/// - *VXLAN Network Identifier (VNI)*: (24 bits)
+///      Used to designate the individual VXLAN overlay network.
+///
+/// Unlike [`Vxlan`], the payload is not always an Ethernet frame. Use
+/// [`decap`] to remove the outer headers and get an Ethernet frame that
+/// can be parsed as the [`next_protocol`].
+///
+/// [IETF draft-ietf-nvo3-vxlan-gpe]: https://tools.ietf.org/html/draft-ietf-nvo3-vxlan-gpe-12
+/// [`decap`]: VxlanGpe::decap
+/// [`next_protocol`]: VxlanGpe::next_protocol
+pub struct VxlanGpe<E: IpPacket> {
+    envelope: Udp<E>,
+    header: NonNull<VxlanGpeHeader>,
+    offset: usize,
+}
+
+impl<E: IpPacket> VxlanGpe<E> {
+    #[inline]
+    fn header(&self) -> &VxlanGpeHeader {
+        unsafe { self.header.as_ref() }
+    }
+
+    #[inline]
+    fn header_mut(&mut self) -> &mut VxlanGpeHeader {
+        unsafe { self.header.as_mut() }
+    }
+
+    /// Returns the flags.
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.header().flags
+    }
+
+    /// Sets the flags.
+    #[inline]
+    pub fn set_flags(&mut self, flags: u8) {
+        self.header_mut().flags = flags
+    }
+
+    /// Returns the version.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        (self.flags() & GPE_VERSION) >> 4
+    }
+
+    /// Returns a flag indicating whether the VNI is valid.
+    #[inline]
+    pub fn has_vni(&self) -> bool {
+        self.flags() & FLAGS_I != 0
+    }
+
+    /// Returns a flag indicating whether the packet is BUM traffic.
+    #[inline]
+    pub fn bum(&self) -> bool {
+        self.flags() & FLAGS_B != 0
+    }
+
+    /// Returns a flag indicating whether the packet is an OAM packet.
+    #[inline]
+    pub fn oam(&self) -> bool {
+        self.flags() & FLAGS_O != 0
+    }
+
+    /// Returns the VXLAN network identifier.
+    #[inline]
+    pub fn vni(&self) -> u32 {
+        u32::from(self.header().vni_reserved) >> 8
+    }
+
+    /// Sets the VXLAN network identifier and the I flag.
+    ///
+    /// Only the lower 24 bits of the `vni` are used.
+    #[inline]
+    pub fn set_vni(&mut self, vni: u32) {
+        self.header_mut().vni_reserved = ((vni & VNI_MAX) << 8).into();
+        self.header_mut().flags |= FLAGS_I;
+    }
+
+    /// Returns the protocol of the payload, or `None` if the P flag is not
+    /// set or the next protocol is not supported.
+    #[inline]
+    pub fn next_protocol(&self) -> Option<NextProtocol> {
+        if self.flags() & FLAGS_P == 0 {
+            return None;
+        }
+
+        match self.header().next_protocol {
+            GPE_IPV4 => Some(NextProtocol::Ipv4),
+            GPE_IPV6 => Some(NextProtocol::Ipv6),
+            GPE_ETHERNET => Some(NextProtocol::Ethernet),
+            GPE_NSH => Some(NextProtocol::Nsh),
+            GPE_MPLS => Some(NextProtocol::Mpls),
+            _ => None,
+        }
+    }
+
+    /// Sets the protocol of the payload and the P flag.
+    #[inline]
+    pub fn set_next_protocol(&mut self, next: NextProtocol) {
+        self.header_mut().next_protocol = match next {
+            NextProtocol::Ipv4 => GPE_IPV4,
+            NextProtocol::Ipv6 => GPE_IPV6,
+            NextProtocol::Ethernet => GPE_ETHERNET,
+            NextProtocol::Nsh => GPE_NSH,
+            NextProtocol::Mpls => GPE_MPLS,
+        };
+        self.header_mut().flags |= FLAGS_P;
+    }
+
+    /// Removes the outer headers and returns an Ethernet frame that can be
+    /// parsed as the [`next_protocol`].
+    ///
+    /// If the payload is an Ethernet frame, the inner frame is returned.
+    /// Otherwise, the outer Ethernet frame is returned with the payload
+    /// following the Ethernet header and the Ethernet type set to the
+    /// next protocol.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the next protocol is not supported, or if the
+    /// payload is not an Ethernet frame when the next protocol is Ethernet.
+    ///
+    /// [`next_protocol`]: VxlanGpe::next_protocol
+    #[inline]
+    pub fn decap(self) -> Result<Ethernet> {
+        let next = self
+            .next_protocol()
+            .ok_or_else(|| anyhow!("unknown VXLAN-GPE payload type."))?;
+        tunnel::decap_next(self, next)
+    }
+}
+
+impl<E: IpPacket<Envelope = Ethernet>> VxlanGpe<E> {
+    /// Encapsulates the Ethernet frame in outer Ethernet, IP, UDP and
+    /// VXLAN-GPE headers.
+    ///
+    /// The UDP destination port is set to [`VXLAN_GPE_PORT`] and the next
+    /// protocol is set to Ethernet. The outer addresses and the UDP source
+    /// port should be set afterwards through the envelopes, followed by
+    /// [`reconcile_all`] to fix the lengths and the checksums.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    ///
+    /// [`reconcile_all`]: Packet::reconcile_all
+    #[inline]
+    pub fn encap(inner: Ethernet, vni: u32) -> Result<Self> {
+        let mut gpe = inner
+            .reset()
+            .push::<Ethernet>()?
+            .push::<E>()?
+            .push::<Udp<E>>()?
+            .push::<VxlanGpe<E>>()?;
+        gpe.set_vni(vni);
+        Ok(gpe)
+    }
+}
+
+impl<E: IpPacket<Envelope = Ethernet>> Encap for VxlanGpe<E> {
+    type Inner = Ethernet;
+
+    /// The VXLAN network identifier.
+    type Params = u32;
+
+    #[inline]
+    fn encapsulate(inner: Self::Inner, vni: Self::Params) -> Result<Self> {
+        VxlanGpe::encap(inner, vni)
+    }
+
+    #[inline]
+    fn decapsulate(self) -> Result<Self::Inner> {
+        self.decap()
+    }
+}
+
+impl<E: IpPacket> fmt::Debug for VxlanGpe<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("vxlan-gpe")
+            .field("flags", &format!("0x{:02x}", self.flags()))
+            .field("next_protocol", &self.next_protocol())
+            .field("vni", &self.vni())
+            .field("$offset", &self.offset())
+            .field("$len", &self.len())
+            .field("$header_len", &self.header_len())
+            .finish()
+    }
+}
+
+impl<E: IpPacket> Packet for VxlanGpe<E> {
+    /// The preceding type for VXLAN-GPE must be `Udp`.
+    type Envelope = Udp<E>;
+
+    #[inline]
+    fn envelope(&self) -> &Self::Envelope {
+        &self.envelope
+    }
+
+    #[inline]
+    fn envelope_mut(&mut self) -> &mut Self::Envelope {
+        &mut self.envelope
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    fn header_len(&self) -> usize {
+        VxlanGpeHeader::size_of()
+    }
+
+    #[inline]
+    unsafe fn clone(&self, internal: Internal) -> Self {
+        VxlanGpe {
+            envelope: self.envelope.clone(internal),
+            header: self.header,
+            offset: self.offset,
+        }
+    }
+
+    /// Parses the UDP payload as a VXLAN-GPE packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the UDP destination port is not
+    /// [`VXLAN_GPE_PORT`]. Returns an error if the payload does not have
+    /// sufficient data for the VXLAN-GPE header.
+    #[inline]
+    fn try_parse(envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        ensure!(
+            envelope.dst_port() == VXLAN_GPE_PORT,
+            anyhow!("not a VXLAN-GPE packet.")
+        );
+
+        let mbuf = envelope.mbuf();
+        let offset = envelope.payload_offset();
+        let header = mbuf.read_data(offset)?;
+
+        Ok(VxlanGpe {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    /// Prepends a VXLAN-GPE header to the beginning of the UDP's payload.
+    ///
+    /// The UDP destination port is set to [`VXLAN_GPE_PORT`]. The I and P
+    /// flags are set, and the next protocol is set to Ethernet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer does not have enough free space.
+    #[inline]
+    fn try_push(mut envelope: Self::Envelope, _internal: Internal) -> Result<Self> {
+        let offset = envelope.payload_offset();
+        let mbuf = envelope.mbuf_mut();
+
+        mbuf.extend(offset, VxlanGpeHeader::size_of())?;
+        let header = mbuf.write_data(offset, &VxlanGpeHeader::default())?;
+
+        envelope.set_dst_port(VXLAN_GPE_PORT);
+
+        Ok(VxlanGpe {
+            envelope,
+            header,
+            offset,
+        })
+    }
+
+    #[inline]
+    fn deparse(self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// A type alias for a VXLAN-GPE packet over IPv4.
+pub type VxlanGpe4 = VxlanGpe<Ipv4>;
+
+/// A type alias for a VXLAN-GPE packet over IPv6.
+pub type VxlanGpe6 = VxlanGpe<Ipv6>;
+
+/// VXLAN header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct VxlanHeader {
+    flags: u8,
+    reserved: [u8; 3],
+    vni_reserved: u32be,
+}
+
+impl Default for VxlanHeader {
+    fn default() -> VxlanHeader {
+        VxlanHeader {
+            flags: FLAGS_I,
+            reserved: [0; 3],
+            vni_reserved: u32be::default(),
+        }
+    }
+}
+
+/// VXLAN-GPE header.
+#[derive(Clone, Copy, Debug, SizeOf)]
+#[repr(C, packed)]
+struct VxlanGpeHeader {
+    flags: u8,
+    reserved: [u8; 2],
+    next_protocol: u8,
+    vni_reserved: u32be,
+}
+
+impl Default for VxlanGpeHeader {
+    fn default() -> VxlanGpeHeader {
+        VxlanGpeHeader {
+            flags: FLAGS_I | FLAGS_P,
+            reserved: [0; 2],
+            next_protocol: GPE_ETHERNET,
+            vni_reserved: u32be::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::MacAddr;
+    use crate::packets::EtherTypes;
+    use crate::testils::byte_arrays::{IPV4_UDP_PACKET, VXLAN_PACKET};
+    use crate::Mbuf;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn size_of_vxlan_header() {
+        assert_eq!(8, VxlanHeader::size_of());
+        assert_eq!(8, VxlanGpeHeader::size_of());
+    }
+
+    #[capsule::test]
+    fn parse_vxlan_packet() {
+        let packet = Mbuf::from_bytes(&VXLAN_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let vxlan = udp.parse::<Vxlan4>().unwrap();
+
+        assert_eq!(0x08, vxlan.flags());
+        assert!(vxlan.has_vni());
+        assert_eq!(100, vxlan.vni());
+
+        let inner = vxlan.decap().unwrap();
+        assert_eq!(MacAddr::new(0, 0, 0, 0, 0, 2), inner.src());
+        assert_eq!(MacAddr::new(0, 0, 0, 0, 0, 1), inner.dst());
+        let ipv4 = inner.parse::<Ipv4>().unwrap();
+        assert_eq!(Ipv4Addr::new(139, 133, 217, 110), ipv4.src());
+    }
+
+    #[capsule::test]
+    fn parse_non_vxlan_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+
+        assert!(udp.parse::<Vxlan4>().is_err());
+    }
+
+    #[capsule::test]
+    fn encap_and_decap_vxlan_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let inner = packet.parse::<Ethernet>().unwrap();
+
+        let mut vxlan = Vxlan4::encap(inner, 100).unwrap();
+        vxlan.envelope_mut().set_src_port(49152);
+        vxlan
+            .envelope_mut()
+            .envelope_mut()
+            .set_src(Ipv4Addr::new(192, 168, 0, 1));
+        vxlan
+            .envelope_mut()
+            .envelope_mut()
+            .set_dst(Ipv4Addr::new(192, 168, 0, 2));
+        vxlan.reconcile_all();
+
+        assert_eq!(100, vxlan.vni());
+        assert_eq!(VXLAN_PORT, vxlan.envelope().dst_port());
+        assert_eq!(68, vxlan.envelope().length());
+        assert_eq!(88, vxlan.envelope().envelope().total_length());
+
+        let inner = vxlan.decap().unwrap();
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), inner.to_vec());
+    }
+
+    #[capsule::test]
+    fn encap_and_decap_vxlan_gpe_packet() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let inner = packet.parse::<Ethernet>().unwrap();
+
+        let mut gpe = VxlanGpe4::encap(inner, 100).unwrap();
+        gpe.reconcile_all();
+
+        assert_eq!(VXLAN_GPE_PORT, gpe.envelope().dst_port());
+        assert_eq!(0, gpe.version());
+        assert_eq!(100, gpe.vni());
+        assert_eq!(Some(NextProtocol::Ethernet), gpe.next_protocol());
+
+        // parses the encapsulated packet from the top again.
+        let ethernet = gpe.reset().parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp<Ipv4>>().unwrap();
+        let gpe = udp.parse::<VxlanGpe4>().unwrap();
+
+        let inner = gpe.decap().unwrap();
+        assert_eq!(IPV4_UDP_PACKET.to_vec(), inner.to_vec());
+    }
+
+    #[capsule::test]
+    fn decap_vxlan_gpe_ipv4_payload() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let inner = packet.parse::<Ethernet>().unwrap();
+
+        // moves the inner IPv4 packet right behind the VXLAN-GPE header.
+        let mut gpe = VxlanGpe4::encap(inner, 100).unwrap();
+        let offset = gpe.payload_offset();
+        gpe.mbuf_mut().shrink(offset, 14).unwrap();
+        gpe.set_next_protocol(NextProtocol::Ipv4);
+        assert_eq!(Some(NextProtocol::Ipv4), gpe.next_protocol());
+
+        let ethernet = gpe.decap().unwrap();
+        assert_eq!(EtherTypes::Ipv4, ethernet.ether_type());
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        assert_eq!(Ipv4Addr::new(139, 133, 217, 110), ipv4.src());
+        assert_eq!(IPV4_UDP_PACKET[14..].to_vec(), ipv4.to_vec());
+    }
+}
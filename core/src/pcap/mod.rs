@@ -0,0 +1,167 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Reading and writing packets in the classic libpcap file format.
+//!
+//! The file format is described in the [Wireshark wiki].
+//!
+//! [Wireshark wiki]: https://wiki.wireshark.org/Development/LibpcapFileFormat
+
+#[cfg(feature = "pcap-dump")]
+mod dump;
+mod reader;
+mod writer;
+
+#[cfg(feature = "pcap-dump")]
+pub(crate) use self::dump::capture_queue;
+pub use self::reader::*;
+pub use self::writer::*;
+
+use std::fmt;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Magic number of a pcap file with microsecond resolution timestamps.
+const MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+
+/// Magic number of a pcap file with nanosecond resolution timestamps.
+const MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+
+/// Length of the pcap global header.
+const GLOBAL_HEADER_LEN: usize = 24;
+
+/// Length of the pcap per-packet record header.
+const RECORD_HEADER_LEN: usize = 16;
+
+/// Largest captured length of a record that is read, the same limit as
+/// the maximum snapshot length of libpcap.
+const MAX_CAPTURE_LEN: u32 = 262_144;
+
+/// Error indicating a malformed pcap file.
+#[derive(Debug, Error)]
+pub enum PcapFileError {
+    /// The magic number is not a known pcap magic number.
+    #[error("Unknown pcap magic number 0x{0:08x}.")]
+    BadMagicNumber(u32),
+
+    /// The file ends in the middle of a header or a packet.
+    #[error("Pcap file is truncated.")]
+    Truncated,
+
+    /// The captured length of a packet exceeds the snapshot length.
+    #[error("Captured length {0} exceeds the snapshot length {1}.")]
+    BadCaptureLength(u32, u32),
+
+    /// The captured length of a packet exceeds the largest length read.
+    #[error("Captured length {0} exceeds the maximum length {}.", MAX_CAPTURE_LEN)]
+    CaptureTooLarge(u32),
+}
+
+/// Link-layer header type of the packets in a pcap file.
+///
+/// A list of supported types is available in the [tcpdump docs].
+///
+/// [tcpdump docs]: https://www.tcpdump.org/linktypes.html
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct LinkType(pub u32);
+
+impl LinkType {
+    /// Creates a new link-layer header type.
+    pub fn new(value: u32) -> Self {
+        LinkType(value)
+    }
+}
+
+/// Supported link-layer header types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod LinkTypes {
+    use super::LinkType;
+
+    /// BSD loopback encapsulation.
+    pub const Null: LinkType = LinkType(0);
+    /// IEEE 802.3 Ethernet.
+    pub const Ethernet: LinkType = LinkType(1);
+    /// Raw IP, the packet begins with an IPv4 or IPv6 header.
+    pub const Raw: LinkType = LinkType(101);
+    /// Linux "cooked" capture encapsulation.
+    pub const LinuxSll: LinkType = LinkType(113);
+}
+
+impl fmt::Display for LinkType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match *self {
+                LinkTypes::Null => "Null".to_string(),
+                LinkTypes::Ethernet => "Ethernet".to_string(),
+                LinkTypes::Raw => "Raw".to_string(),
+                LinkTypes::LinuxSll => "Linux SLL".to_string(),
+                _ => {
+                    let t = self.0;
+                    format!("{}", t)
+                }
+            }
+        )
+    }
+}
+
+/// A packet record in a pcap file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Record {
+    timestamp: Duration,
+    orig_len: u32,
+    data: Vec<u8>,
+}
+
+impl Record {
+    /// Creates a new record for the packet data captured at timestamp.
+    ///
+    /// The timestamp is the duration since the UNIX epoch.
+    pub fn new(timestamp: Duration, data: Vec<u8>) -> Self {
+        Record {
+            timestamp,
+            orig_len: data.len() as u32,
+            data,
+        }
+    }
+
+    /// Returns the time when the packet was captured, as the duration
+    /// since the UNIX epoch.
+    pub fn timestamp(&self) -> Duration {
+        self.timestamp
+    }
+
+    /// Returns the length of the packet on the wire. It can be larger than
+    /// the captured data if the packet was truncated to the snapshot length.
+    pub fn orig_len(&self) -> u32 {
+        self.orig_len
+    }
+
+    /// Returns the captured packet data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consumes the record and returns the captured packet data.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
@@ -0,0 +1,378 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::{
+    LinkType, PcapFileError, Record, GLOBAL_HEADER_LEN, MAGIC_MICROS, MAGIC_NANOS, MAX_CAPTURE_LEN,
+    RECORD_HEADER_LEN,
+};
+use crate::ensure;
+use anyhow::Result;
+use std::convert::TryInto;
+use std::fmt;
+use std::io::{ErrorKind, Read};
+use std::time::Duration;
+
+/// Reader of packets from a pcap file.
+///
+/// Both the little-endian and the big-endian file formats are supported,
+/// with either microsecond or nanosecond resolution timestamps.
+///
+/// # Example
+///
+/// ```
+/// let mut reader = Reader::new(File::open("capture.pcap")?)?;
+///
+/// while let Some(record) = reader.next()? {
+///     let packet = Mbuf::from_bytes(record.data())?;
+///     ...
+/// }
+/// ```
+pub struct Reader<R: Read> {
+    reader: R,
+    swapped: bool,
+    nanos: bool,
+    version: (u16, u16),
+    snaplen: u32,
+    link_type: LinkType,
+}
+
+impl<R: Read> Reader<R> {
+    /// Creates a new reader and reads the pcap global header.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PcapFileError::BadMagicNumber` if the source is not a pcap
+    /// file. Returns `PcapFileError::Truncated` if the source ends before the
+    /// end of the global header.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut header = [0; GLOBAL_HEADER_LEN];
+        ensure!(
+            read_full(&mut reader, &mut header)? == GLOBAL_HEADER_LEN,
+            PcapFileError::Truncated
+        );
+
+        let magic = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let (swapped, nanos) = match magic {
+            MAGIC_MICROS => (false, false),
+            MAGIC_NANOS => (false, true),
+            _ if magic.swap_bytes() == MAGIC_MICROS => (true, false),
+            _ if magic.swap_bytes() == MAGIC_NANOS => (true, true),
+            _ => return Err(PcapFileError::BadMagicNumber(magic).into()),
+        };
+
+        let mut pcap = Reader {
+            reader,
+            swapped,
+            nanos,
+            version: (0, 0),
+            snaplen: 0,
+            link_type: LinkType::default(),
+        };
+
+        pcap.version = (pcap.u16_at(&header, 4), pcap.u16_at(&header, 6));
+        pcap.snaplen = pcap.u32_at(&header, 16);
+        pcap.link_type = LinkType::new(pcap.u32_at(&header, 20));
+
+        Ok(pcap)
+    }
+
+    /// Returns the major and minor version of the file format.
+    pub fn version(&self) -> (u16, u16) {
+        self.version
+    }
+
+    /// Returns the maximum length of the captured packets.
+    pub fn snaplen(&self) -> u32 {
+        self.snaplen
+    }
+
+    /// Returns the link-layer header type of the packets.
+    ///
+    /// If the type is [`LinkTypes::Ethernet`], the packets begin with an
+    /// Ethernet frame.
+    ///
+    /// [`LinkTypes::Ethernet`]: super::LinkTypes::Ethernet
+    pub fn link_type(&self) -> LinkType {
+        self.link_type
+    }
+
+    /// Returns whether the timestamps have nanosecond resolution.
+    pub fn is_nanos(&self) -> bool {
+        self.nanos
+    }
+
+    /// Reads the next packet record.
+    ///
+    /// Returns `None` when there are no more records.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PcapFileError::Truncated` if the source ends in the middle
+    /// of a record. Returns `PcapFileError::BadCaptureLength` if the record
+    /// is larger than the snapshot length. Returns
+    /// `PcapFileError::CaptureTooLarge` if the record is larger than 256 KiB,
+    /// the maximum snapshot length of libpcap, no matter the snapshot length
+    /// of the file.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<Record>> {
+        let mut header = [0; RECORD_HEADER_LEN];
+        match read_full(&mut self.reader, &mut header)? {
+            0 => return Ok(None),
+            RECORD_HEADER_LEN => (),
+            _ => return Err(PcapFileError::Truncated.into()),
+        }
+
+        let ts_sec = self.u32_at(&header, 0);
+        let ts_frac = self.u32_at(&header, 4);
+        let incl_len = self.u32_at(&header, 8);
+        let orig_len = self.u32_at(&header, 12);
+
+        // some writers set the snaplen to 0 to indicate no limit.
+        ensure!(
+            self.snaplen == 0 || incl_len <= self.snaplen,
+            PcapFileError::BadCaptureLength(incl_len, self.snaplen)
+        );
+        // caps the allocation so a crafted length cannot exhaust the memory.
+        ensure!(
+            incl_len <= MAX_CAPTURE_LEN,
+            PcapFileError::CaptureTooLarge(incl_len)
+        );
+
+        let mut data = vec![0; incl_len as usize];
+        ensure!(
+            read_full(&mut self.reader, &mut data)? == data.len(),
+            PcapFileError::Truncated
+        );
+
+        let timestamp = if self.nanos {
+            Duration::new(u64::from(ts_sec), ts_frac)
+        } else {
+            Duration::new(u64::from(ts_sec), 0) + Duration::from_micros(u64::from(ts_frac))
+        };
+
+        Ok(Some(Record {
+            timestamp,
+            orig_len,
+            data,
+        }))
+    }
+
+    #[inline]
+    fn u16_at(&self, bytes: &[u8], offset: usize) -> u16 {
+        let value = u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap());
+        if self.swapped {
+            value.swap_bytes()
+        } else {
+            value
+        }
+    }
+
+    #[inline]
+    fn u32_at(&self, bytes: &[u8], offset: usize) -> u32 {
+        let value = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        if self.swapped {
+            value.swap_bytes()
+        } else {
+            value
+        }
+    }
+}
+
+impl<R: Read> fmt::Debug for Reader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("pcap::Reader")
+            .field("version", &self.version)
+            .field("swapped", &self.swapped)
+            .field("nanos", &self.nanos)
+            .field("snaplen", &self.snaplen)
+            .field("link_type", &format!("{}", self.link_type))
+            .finish()
+    }
+}
+
+/// Reads until the buffer is full or the source is exhausted, and returns
+/// the number of bytes read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut read = 0;
+
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::{Ethernet, Packet};
+    use crate::pcap::LinkTypes;
+    use crate::testils::byte_arrays::IPV4_UDP_PACKET;
+    use crate::Mbuf;
+
+    /// A pcap file in little-endian with microsecond resolution timestamps,
+    /// containing one `IPV4_UDP_PACKET`.
+    #[rustfmt::skip]
+    const LE_GLOBAL_HEADER: [u8; 24] = [
+        // magic number
+        0xd4, 0xc3, 0xb2, 0xa1,
+        // version 2.4
+        0x02, 0x00, 0x04, 0x00,
+        // thiszone and sigfigs
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // snaplen = 65535
+        0xff, 0xff, 0x00, 0x00,
+        // network = ethernet
+        0x01, 0x00, 0x00, 0x00,
+    ];
+
+    #[rustfmt::skip]
+    const LE_RECORD_HEADER: [u8; 16] = [
+        // ts_sec = 1_000_000_000
+        0x00, 0xca, 0x9a, 0x3b,
+        // ts_usec = 500
+        0xf4, 0x01, 0x00, 0x00,
+        // incl_len = 52
+        0x34, 0x00, 0x00, 0x00,
+        // orig_len = 52
+        0x34, 0x00, 0x00, 0x00,
+    ];
+
+    fn swap_words(bytes: &[u8], lens: &[usize]) -> Vec<u8> {
+        let mut offset = 0;
+        let mut swapped = Vec::new();
+        for &len in lens {
+            swapped.extend(bytes[offset..offset + len].iter().rev());
+            offset += len;
+        }
+        swapped
+    }
+
+    fn le_pcap() -> Vec<u8> {
+        let mut pcap = LE_GLOBAL_HEADER.to_vec();
+        pcap.extend_from_slice(&LE_RECORD_HEADER);
+        pcap.extend_from_slice(&IPV4_UDP_PACKET);
+        pcap
+    }
+
+    fn be_pcap() -> Vec<u8> {
+        let mut pcap = swap_words(&LE_GLOBAL_HEADER, &[4, 2, 2, 4, 4, 4, 4]);
+        pcap.extend(swap_words(&LE_RECORD_HEADER, &[4, 4, 4, 4]));
+        pcap.extend_from_slice(&IPV4_UDP_PACKET);
+        pcap
+    }
+
+    #[test]
+    fn read_little_endian_pcap() {
+        let pcap = le_pcap();
+        let mut reader = Reader::new(pcap.as_slice()).unwrap();
+
+        assert_eq!((2, 4), reader.version());
+        assert_eq!(65535, reader.snaplen());
+        assert_eq!(LinkTypes::Ethernet, reader.link_type());
+        assert!(!reader.is_nanos());
+
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!(Duration::new(1_000_000_000, 500_000), record.timestamp());
+        assert_eq!(52, record.orig_len());
+        assert_eq!(&IPV4_UDP_PACKET[..], record.data());
+
+        assert!(reader.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn read_big_endian_pcap() {
+        let pcap = be_pcap();
+        let mut reader = Reader::new(pcap.as_slice()).unwrap();
+
+        assert_eq!((2, 4), reader.version());
+        assert_eq!(65535, reader.snaplen());
+        assert_eq!(LinkTypes::Ethernet, reader.link_type());
+
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!(Duration::new(1_000_000_000, 500_000), record.timestamp());
+        assert_eq!(&IPV4_UDP_PACKET[..], record.data());
+
+        assert!(reader.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn read_nanosecond_pcap() {
+        let mut pcap = le_pcap();
+        pcap[..4].copy_from_slice(&MAGIC_NANOS.to_le_bytes());
+        let mut reader = Reader::new(pcap.as_slice()).unwrap();
+        assert!(reader.is_nanos());
+
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!(Duration::new(1_000_000_000, 500), record.timestamp());
+    }
+
+    #[test]
+    fn read_bad_pcap() {
+        // not a pcap file
+        assert!(Reader::new(&IPV4_UDP_PACKET[..]).is_err());
+
+        // truncated global header
+        let pcap = le_pcap();
+        assert!(Reader::new(&pcap[..10]).is_err());
+
+        // truncated packet
+        let mut reader = Reader::new(&pcap[..50]).unwrap();
+        assert!(reader.next().is_err());
+    }
+
+    #[test]
+    fn read_record_larger_than_snaplen() {
+        // snaplen = 40
+        let mut pcap = le_pcap();
+        pcap[16] = 40;
+        pcap[17] = 0;
+        let mut reader = Reader::new(pcap.as_slice()).unwrap();
+        let err = reader.next().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PcapFileError>(),
+            Some(PcapFileError::BadCaptureLength(52, 40))
+        ));
+
+        // snaplen = 0 and incl_len = 0xffff_ffff
+        pcap[16..20].copy_from_slice(&[0; 4]);
+        pcap[32..36].copy_from_slice(&[0xff; 4]);
+        let mut reader = Reader::new(pcap.as_slice()).unwrap();
+        let err = reader.next().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PcapFileError>(),
+            Some(PcapFileError::CaptureTooLarge(0xffff_ffff))
+        ));
+    }
+
+    #[capsule::test]
+    fn read_pcap_into_mbuf() {
+        let pcap = le_pcap();
+        let mut reader = Reader::new(pcap.as_slice()).unwrap();
+        let record = reader.next().unwrap().unwrap();
+
+        let packet = Mbuf::from_bytes(record.data()).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        assert_eq!("00:00:00:00:00:02", ethernet.src().to_string());
+    }
+}
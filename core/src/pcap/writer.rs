@@ -0,0 +1,233 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use super::{LinkType, LinkTypes, Record, GLOBAL_HEADER_LEN, MAGIC_MICROS, RECORD_HEADER_LEN};
+use crate::Mbuf;
+use anyhow::Result;
+use std::cmp;
+use std::fmt;
+use std::io::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The default snapshot length, large enough to hold any packet.
+pub const DEFAULT_SNAPLEN: u32 = 65535;
+
+/// Writer of packets to a pcap file.
+///
+/// The file is written in little-endian with microsecond resolution
+/// timestamps, which is the format most tools expect. Packets longer than
+/// the snapshot length are truncated.
+///
+/// # Example
+///
+/// ```
+/// let mut writer = Writer::new(File::create("capture.pcap")?)?;
+///
+/// writer.write_mbuf(&packet.reset())?;
+/// writer.flush()?;
+/// ```
+pub struct Writer<W: Write> {
+    writer: W,
+    snaplen: u32,
+    link_type: LinkType,
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a new writer for Ethernet frames with the default snapshot
+    /// length, and writes the pcap global header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header cannot be written.
+    pub fn new(writer: W) -> Result<Self> {
+        Writer::with_options(writer, DEFAULT_SNAPLEN, LinkTypes::Ethernet)
+    }
+
+    /// Creates a new writer with the snapshot length and the link-layer
+    /// header type, and writes the pcap global header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header cannot be written.
+    pub fn with_options(mut writer: W, snaplen: u32, link_type: LinkType) -> Result<Self> {
+        let mut header = [0; GLOBAL_HEADER_LEN];
+        header[..4].copy_from_slice(&MAGIC_MICROS.to_le_bytes());
+        header[4..6].copy_from_slice(&2u16.to_le_bytes());
+        header[6..8].copy_from_slice(&4u16.to_le_bytes());
+        // thiszone and sigfigs are always 0.
+        header[16..20].copy_from_slice(&snaplen.to_le_bytes());
+        header[20..].copy_from_slice(&link_type.0.to_le_bytes());
+        writer.write_all(&header)?;
+
+        Ok(Writer {
+            writer,
+            snaplen,
+            link_type,
+        })
+    }
+
+    /// Returns the maximum length of the written packets.
+    pub fn snaplen(&self) -> u32 {
+        self.snaplen
+    }
+
+    /// Returns the link-layer header type of the packets.
+    pub fn link_type(&self) -> LinkType {
+        self.link_type
+    }
+
+    /// Appends a packet record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record cannot be written.
+    pub fn write_record(&mut self, record: &Record) -> Result<()> {
+        let data = record.data();
+        let incl_len = cmp::min(data.len(), self.snaplen as usize);
+        let timestamp = record.timestamp();
+
+        let mut header = [0; RECORD_HEADER_LEN];
+        header[..4].copy_from_slice(&(timestamp.as_secs() as u32).to_le_bytes());
+        header[4..8].copy_from_slice(&timestamp.subsec_micros().to_le_bytes());
+        header[8..12].copy_from_slice(&(incl_len as u32).to_le_bytes());
+        header[12..].copy_from_slice(&record.orig_len().to_le_bytes());
+
+        self.writer.write_all(&header)?;
+        self.writer.write_all(&data[..incl_len])?;
+        Ok(())
+    }
+
+    /// Appends the bytes as a packet captured at timestamp.
+    ///
+    /// The timestamp is the duration since the UNIX epoch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record cannot be written.
+    pub fn write_at(&mut self, timestamp: Duration, data: &[u8]) -> Result<()> {
+        self.write_record(&Record::new(timestamp, data.to_vec()))
+    }
+
+    /// Appends the bytes as a packet captured now.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record cannot be written.
+    pub fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.write_at(now(), data)
+    }
+
+    /// Appends the content of the message buffer as a packet captured now.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record cannot be written.
+    pub fn write_mbuf(&mut self, mbuf: &Mbuf) -> Result<()> {
+        self.write_record(&Record::new(now(), mbuf.copy_data(0)))
+    }
+
+    /// Flushes the buffered records to the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying writer fails to flush.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Flushes and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying writer fails to flush.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> fmt::Debug for Writer<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("pcap::Writer")
+            .field("snaplen", &self.snaplen)
+            .field("link_type", &format!("{}", self.link_type))
+            .finish()
+    }
+}
+
+/// Returns the current time as the duration since the UNIX epoch.
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pcap::Reader;
+    use crate::testils::byte_arrays::{IPV4_TCP_PACKET, IPV4_UDP_PACKET};
+
+    #[test]
+    fn write_and_read_back() {
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer
+            .write_at(Duration::new(1_000_000_000, 500_000), &IPV4_UDP_PACKET)
+            .unwrap();
+        writer.write(&IPV4_TCP_PACKET).unwrap();
+        let pcap = writer.into_inner().unwrap();
+
+        let mut reader = Reader::new(pcap.as_slice()).unwrap();
+        assert_eq!(DEFAULT_SNAPLEN, reader.snaplen());
+        assert_eq!(LinkTypes::Ethernet, reader.link_type());
+
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!(Duration::new(1_000_000_000, 500_000), record.timestamp());
+        assert_eq!(&IPV4_UDP_PACKET[..], record.data());
+
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!(&IPV4_TCP_PACKET[..], record.data());
+
+        assert!(reader.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn write_truncated_to_snaplen() {
+        let mut writer = Writer::with_options(Vec::new(), 14, LinkTypes::Ethernet).unwrap();
+        writer.write(&IPV4_UDP_PACKET).unwrap();
+        let pcap = writer.into_inner().unwrap();
+
+        let mut reader = Reader::new(pcap.as_slice()).unwrap();
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!(&IPV4_UDP_PACKET[..14], record.data());
+        assert_eq!(IPV4_UDP_PACKET.len() as u32, record.orig_len());
+    }
+
+    #[capsule::test]
+    fn write_mbuf() {
+        let packet = Mbuf::from_bytes(&IPV4_UDP_PACKET).unwrap();
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        writer.write_mbuf(&packet).unwrap();
+        let pcap = writer.into_inner().unwrap();
+
+        let mut reader = Reader::new(pcap.as_slice()).unwrap();
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!(&IPV4_UDP_PACKET[..], record.data());
+    }
+}
@@ -0,0 +1,335 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Detection of out-of-order delivery within a flow.
+//!
+//! The detector follows the sequence numbers of each flow, the TCP
+//! sequence number or the RTP sequence number, and classifies every
+//! arriving packet against the packets seen before it.
+
+use crate::packets::ip::{FiveTuple, IpPacket};
+use crate::packets::rtp::Rtp;
+use crate::packets::{Packet, Tcp};
+use std::collections::{HashMap, VecDeque};
+
+/// The default reordering window, in sequence numbers.
+pub const DEFAULT_WINDOW: u32 = 65_535;
+
+/// The most sequence numbers remembered per flow.
+pub const MAX_REMEMBERED: usize = 256;
+
+/// How a packet arrived relative to the packets seen before it in the
+/// same flow.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Arrival {
+    /// The first packet seen of the flow.
+    First,
+    /// The packet is ahead of every packet seen, possibly after a gap.
+    InOrder,
+    /// The packet is behind the highest sequence number seen, and was not
+    /// seen before.
+    OutOfOrder,
+    /// The packet repeats a sequence number seen within the window.
+    Retransmission,
+}
+
+/// A detector of out-of-order delivery and retransmissions, keyed by the
+/// 5-tuple of the flow.
+///
+/// The sequence numbers seen of a flow are remembered within the window
+/// behind the highest one, measured in sequence numbers. For TCP, it's
+/// in bytes, and for RTP, in packets. A packet that repeats a remembered
+/// sequence number is a retransmission. A packet further behind than the
+/// window is reported as out of order, since the detector no longer
+/// knows whether it was seen. At most [`MAX_REMEMBERED`] sequence numbers
+/// are remembered per flow, the oldest arrivals are forgotten first.
+///
+/// Sequence numbers wrap around, and are compared with serial number
+/// arithmetic. So the window cannot be more than half of the sequence
+/// number space, and is capped at 32767 for RTP.
+///
+/// The flows are never expired on their own. Use [`remove`] once a flow
+/// ends.
+///
+/// The detector is not synchronized. It's meant to be owned by a single
+/// pipeline on one core, with the flows distributed by RSS.
+///
+/// [`remove`]: ReorderDetector::remove
+#[derive(Debug)]
+pub struct ReorderDetector {
+    flows: HashMap<FiveTuple, FlowState>,
+    window: u32,
+}
+
+impl ReorderDetector {
+    /// Creates a new detector with the [`DEFAULT_WINDOW`].
+    pub fn new() -> Self {
+        ReorderDetector::with_window(DEFAULT_WINDOW)
+    }
+
+    /// Creates a new detector with a custom window.
+    pub fn with_window(window: u32) -> Self {
+        ReorderDetector {
+            flows: HashMap::new(),
+            window,
+        }
+    }
+
+    /// Returns the number of flows tracked.
+    pub fn len(&self) -> usize {
+        self.flows.len()
+    }
+
+    /// Returns whether no flow is tracked.
+    pub fn is_empty(&self) -> bool {
+        self.flows.is_empty()
+    }
+
+    /// Stops tracking `flow`. Returns whether the flow was tracked.
+    pub fn remove(&mut self, flow: &FiveTuple) -> bool {
+        self.flows.remove(flow).is_some()
+    }
+
+    /// Classifies a TCP segment by its sequence number.
+    ///
+    /// Returns `None` for a segment without payload, such as a pure
+    /// acknowledgment, which repeats the sequence number of the next
+    /// segment and would be mistaken for a retransmission.
+    pub fn track_tcp<E: IpPacket>(&mut self, tcp: &Tcp<E>) -> Option<Arrival> {
+        if tcp.payload_len() > 0 {
+            Some(self.track(tcp.flow(), tcp.seq_no(), Sequence::Tcp))
+        } else {
+            None
+        }
+    }
+
+    /// Classifies an RTP packet by its sequence number.
+    pub fn track_rtp<E: IpPacket>(&mut self, rtp: &Rtp<E>) -> Arrival {
+        let flow = rtp.envelope().flow();
+        self.track(flow, rtp.sequence().into(), Sequence::Rtp)
+    }
+
+    fn track(&mut self, flow: FiveTuple, seq: u32, space: Sequence) -> Arrival {
+        let window = self.window.min(space.max_window());
+        match self.flows.get_mut(&flow) {
+            Some(state) => state.track(seq, window, space),
+            None => {
+                self.flows.insert(flow, FlowState::new(seq));
+                Arrival::First
+            }
+        }
+    }
+}
+
+impl Default for ReorderDetector {
+    fn default() -> Self {
+        ReorderDetector::new()
+    }
+}
+
+/// The sequence numbers of a flow.
+#[derive(Debug)]
+struct FlowState {
+    highest: u32,
+    seen: VecDeque<u32>,
+}
+
+impl FlowState {
+    fn new(seq: u32) -> Self {
+        let mut seen = VecDeque::new();
+        seen.push_back(seq);
+        FlowState { highest: seq, seen }
+    }
+
+    fn track(&mut self, seq: u32, window: u32, space: Sequence) -> Arrival {
+        let window = i64::from(window);
+        let ahead = space.distance(seq, self.highest);
+
+        if ahead > 0 {
+            self.highest = seq;
+            self.seen.retain(|&s| space.distance(seq, s) <= window);
+            self.remember(seq);
+            Arrival::InOrder
+        } else if self.seen.contains(&seq) {
+            Arrival::Retransmission
+        } else {
+            // only remembers what can still be told apart.
+            if -ahead <= window {
+                self.remember(seq);
+            }
+            Arrival::OutOfOrder
+        }
+    }
+
+    fn remember(&mut self, seq: u32) {
+        if self.seen.len() == MAX_REMEMBERED {
+            self.seen.pop_front();
+        }
+        self.seen.push_back(seq);
+    }
+}
+
+/// The sequence number space of a protocol.
+#[derive(Clone, Copy, Debug)]
+enum Sequence {
+    /// The 32-bit TCP sequence number, counted in bytes.
+    Tcp,
+    /// The 16-bit RTP sequence number, counted in packets.
+    Rtp,
+}
+
+impl Sequence {
+    /// Returns how far sequence number `a` is ahead of `b`.
+    fn distance(self, a: u32, b: u32) -> i64 {
+        match self {
+            Sequence::Tcp => i64::from(a.wrapping_sub(b) as i32),
+            Sequence::Rtp => i64::from((a as u16).wrapping_sub(b as u16) as i16),
+        }
+    }
+
+    /// Returns the largest window serial number arithmetic can tell apart.
+    fn max_window(self) -> u32 {
+        match self {
+            Sequence::Tcp => i32::MAX as u32,
+            Sequence::Rtp => i16::MAX as u32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::{Ethernet, Tcp4, Udp4};
+    use crate::testils::byte_arrays::{IPV4_TCP_PACKET, IPV4_UDP_PACKET};
+    use crate::Mbuf;
+
+    #[test]
+    fn sequence_distance_wraps_around() {
+        assert_eq!(1, Sequence::Tcp.distance(0, u32::MAX));
+        assert_eq!(-1, Sequence::Tcp.distance(u32::MAX, 0));
+        assert_eq!(1, Sequence::Rtp.distance(0, 65_535));
+        assert_eq!(-2, Sequence::Rtp.distance(65_534, 0));
+    }
+
+    #[capsule::test]
+    fn detect_reordered_tcp_segments() {
+        let mut bytes = IPV4_TCP_PACKET.to_vec();
+        bytes.extend_from_slice(&[0; 100]);
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let mut tcp = ipv4.parse::<Tcp4>().unwrap();
+
+        let mut detector = ReorderDetector::new();
+        let mut track = |seq_no| {
+            tcp.set_seq_no(seq_no);
+            detector.track_tcp(&tcp).unwrap()
+        };
+
+        // in order, across the wrap around.
+        assert_eq!(Arrival::First, track(u32::MAX - 99));
+        assert_eq!(Arrival::InOrder, track(0));
+        assert_eq!(Arrival::InOrder, track(100));
+
+        // 300 is ahead of 200, which arrives late.
+        assert_eq!(Arrival::InOrder, track(300));
+        assert_eq!(Arrival::OutOfOrder, track(200));
+        assert_eq!(Arrival::Retransmission, track(200));
+        assert_eq!(Arrival::Retransmission, track(300));
+        assert_eq!(Arrival::InOrder, track(400));
+    }
+
+    #[capsule::test]
+    fn skip_tcp_segment_without_payload() {
+        let packet = Mbuf::from_bytes(&IPV4_TCP_PACKET).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let tcp = ipv4.parse::<Tcp4>().unwrap();
+
+        let mut detector = ReorderDetector::new();
+        assert!(detector.track_tcp(&tcp).is_none());
+        assert!(detector.is_empty());
+    }
+
+    /// Returns an RTP packet over the headers of `IPV4_UDP_PACKET`.
+    fn rtp_packet() -> Rtp<Ipv4> {
+        let mut bytes = IPV4_UDP_PACKET[..42].to_vec();
+        // version = 2, payload type = 96, sequence = 0, timestamp, ssrc
+        bytes.extend_from_slice(&[0x80, 0x60, 0, 0, 0, 0, 0, 0, 0xde, 0xad, 0xbe, 0xef]);
+        let packet = Mbuf::from_bytes(&bytes).unwrap();
+        let ethernet = packet.parse::<Ethernet>().unwrap();
+        let ipv4 = ethernet.parse::<Ipv4>().unwrap();
+        let udp = ipv4.parse::<Udp4>().unwrap();
+        udp.parse::<Rtp<Ipv4>>().unwrap()
+    }
+
+    #[capsule::test]
+    fn detect_reordered_rtp_packets() {
+        let mut rtp = rtp_packet();
+        let flow = rtp.envelope().flow();
+
+        let mut detector = ReorderDetector::with_window(4);
+        let mut track = |sequence| {
+            rtp.set_sequence(sequence);
+            detector.track_rtp(&rtp)
+        };
+
+        assert_eq!(Arrival::First, track(65_534));
+        assert_eq!(Arrival::InOrder, track(65_535));
+        assert_eq!(Arrival::InOrder, track(0));
+        assert_eq!(Arrival::InOrder, track(1));
+        assert_eq!(Arrival::OutOfOrder, track(65_533));
+        assert_eq!(Arrival::Retransmission, track(0));
+
+        // behind the window, the detector cannot tell it was seen.
+        assert_eq!(Arrival::InOrder, track(10));
+        assert_eq!(Arrival::OutOfOrder, track(1));
+
+        assert_eq!(1, detector.len());
+        assert!(detector.remove(&flow));
+        assert!(detector.is_empty());
+    }
+
+    #[capsule::test]
+    fn bound_remembered_rtp_sequences() {
+        let mut rtp = rtp_packet();
+        let flow = rtp.envelope().flow();
+        let mut detector = ReorderDetector::new();
+
+        // more than the whole sequence number space, in order.
+        for i in 0..70_000u32 {
+            rtp.set_sequence(i as u16);
+            let expected = if i == 0 {
+                Arrival::First
+            } else {
+                Arrival::InOrder
+            };
+            assert_eq!(expected, detector.track_rtp(&rtp));
+            assert!(detector.flows[&flow].seen.len() <= MAX_REMEMBERED);
+        }
+
+        // a recent sequence number is still remembered, but one older
+        // than the remembered ones can no longer be told apart.
+        rtp.set_sequence((70_000u32 - 10) as u16);
+        assert_eq!(Arrival::Retransmission, detector.track_rtp(&rtp));
+        rtp.set_sequence((70_000u32 - 1000) as u16);
+        assert_eq!(Arrival::OutOfOrder, detector.track_rtp(&rtp));
+        assert_eq!(MAX_REMEMBERED, detector.flows[&flow].seen.len());
+    }
+}
@@ -0,0 +1,141 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Software computation of the receive side scaling (RSS) hash.
+//!
+//! Implements the Toeplitz hash function that NICs use to distribute the
+//! received packets across the queues, as described in the [Microsoft RSS]
+//! specification.
+//!
+//! [Microsoft RSS]: https://docs.microsoft.com/en-us/windows-hardware/drivers/network/rss-hashing-functions
+
+use crate::packets::ip::FiveTuple;
+use std::net::IpAddr;
+
+/// The conventional 40-byte RSS key from the Microsoft specification.
+///
+/// This is also the default key of many NIC drivers.
+#[rustfmt::skip]
+pub const DEFAULT_KEY: [u8; 40] = [
+    0x6d, 0x5a, 0x56, 0xda, 0x25, 0x5b, 0x0e, 0xc2,
+    0x41, 0x67, 0x25, 0x3d, 0x43, 0xa3, 0x8f, 0xb0,
+    0xd0, 0xca, 0x2b, 0xcb, 0xae, 0x7b, 0x30, 0xb4,
+    0x77, 0xcb, 0x2d, 0xa3, 0x80, 0x30, 0xf2, 0x0c,
+    0x6a, 0x42, 0xb7, 0x3b, 0xbe, 0xac, 0x01, 0xfa,
+];
+
+/// Computes the Toeplitz hash of the 5-tuple.
+///
+/// The input is the source address, the destination address, the source
+/// port and the destination port, all in network byte order. This is the
+/// same input a NIC uses to hash a TCP or UDP packet.
+///
+/// The key must be at least 4 bytes longer than the input, which is 12
+/// bytes for IPv4 and 36 bytes for IPv6. The [`DEFAULT_KEY`] is long enough
+/// for both.
+pub fn toeplitz(key: &[u8], tuple: &FiveTuple) -> u32 {
+    let mut input = Vec::with_capacity(36);
+    append_ip(&mut input, tuple.src_ip());
+    append_ip(&mut input, tuple.dst_ip());
+    input.extend_from_slice(&tuple.src_port().to_be_bytes());
+    input.extend_from_slice(&tuple.dst_port().to_be_bytes());
+    hash(key, &input)
+}
+
+/// Computes the Toeplitz hash of arbitrary input bytes.
+///
+/// If the key is shorter than 4 bytes longer than the input, the missing
+/// key bits are treated as 0.
+pub fn hash(key: &[u8], input: &[u8]) -> u32 {
+    let key_bit = |idx: usize| -> u32 {
+        key.get(idx / 8)
+            .map(|&byte| u32::from(byte >> (7 - idx % 8)) & 1)
+            .unwrap_or_default()
+    };
+
+    // the left-most 32 bits of the key, slides one bit per input bit.
+    let mut window = (0..32).fold(0u32, |window, idx| (window << 1) | key_bit(idx));
+    let mut result = 0;
+
+    for (i, &byte) in input.iter().enumerate() {
+        for bit in 0..8 {
+            if byte & (0x80 >> bit) != 0 {
+                result ^= window;
+            }
+            window = (window << 1) | key_bit(32 + i * 8 + bit);
+        }
+    }
+
+    result
+}
+
+fn append_ip(input: &mut Vec<u8>, addr: IpAddr) {
+    match addr {
+        IpAddr::V4(addr) => input.extend_from_slice(&addr.octets()),
+        IpAddr::V6(addr) => input.extend_from_slice(&addr.octets()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::ProtocolNumbers;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn tuple(src: IpAddr, src_port: u16, dst: IpAddr, dst_port: u16) -> FiveTuple {
+        FiveTuple::new(src, dst, src_port, dst_port, ProtocolNumbers::Tcp)
+    }
+
+    // test vectors from the Microsoft RSS specification.
+    #[test]
+    fn toeplitz_ipv4_test_vectors() {
+        #[rustfmt::skip]
+        let vectors = [
+            ([66, 9, 149, 187], 2794, [161, 142, 100, 80], 1766, 0x323e_8fc2, 0x51cc_c178),
+            ([199, 92, 111, 2], 14230, [65, 69, 140, 83], 4739, 0xd718_262a, 0xc626_b0ea),
+            ([24, 19, 198, 95], 12898, [12, 22, 207, 184], 38024, 0xd2d0_a5de, 0x5c2b_394a),
+            ([38, 27, 205, 30], 48228, [209, 142, 163, 6], 2217, 0x8298_9176, 0xafc7_327f),
+            ([153, 39, 163, 191], 44251, [202, 188, 127, 2], 1303, 0x5d18_09c5, 0x10e8_28a2),
+        ];
+
+        for &(src, src_port, dst, dst_port, ip_hash, tcp_hash) in vectors.iter() {
+            let src = Ipv4Addr::from(src);
+            let dst = Ipv4Addr::from(dst);
+
+            let mut input = src.octets().to_vec();
+            input.extend_from_slice(&dst.octets());
+            assert_eq!(ip_hash, hash(&DEFAULT_KEY, &input));
+
+            let tuple = tuple(src.into(), src_port, dst.into(), dst_port);
+            assert_eq!(tcp_hash, toeplitz(&DEFAULT_KEY, &tuple));
+        }
+    }
+
+    #[test]
+    fn toeplitz_ipv6_test_vector() {
+        let src: Ipv6Addr = "3ffe:2501:200:1fff::7".parse().unwrap();
+        let dst: Ipv6Addr = "3ffe:2501:200:3::1".parse().unwrap();
+
+        let mut input = src.octets().to_vec();
+        input.extend_from_slice(&dst.octets());
+        assert_eq!(0x2cc1_8cd5, hash(&DEFAULT_KEY, &input));
+
+        let tuple = tuple(src.into(), 2794, dst.into(), 1766);
+        assert_eq!(0x4020_7d3d, toeplitz(&DEFAULT_KEY, &tuple));
+    }
+}
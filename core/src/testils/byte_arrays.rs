@@ -88,6 +88,90 @@ pub const ARP4_PACKET: [u8; 42] = [
     0x8b, 0x85, 0xe9, 0x02
 ];
 
+/// An LLDP frame with the mandatory TLVs, a port description and a system
+/// name.
+#[rustfmt::skip]
+pub const LLDP_PACKET: [u8; 53] = [
+// Ethernet header
+    0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x88, 0xcc,
+// LLDPDU
+    // chassis id, length = 7, subtype = MAC address, 00:00:00:00:00:02
+    0x02, 0x07, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    // port id, length = 5, subtype = interface name, "eth0"
+    0x04, 0x05, 0x05, 0x65, 0x74, 0x68, 0x30,
+    // ttl, length = 2, 120 seconds
+    0x06, 0x02, 0x00, 0x78,
+    // port description, length = 6, "uplink"
+    0x08, 0x06, 0x75, 0x70, 0x6c, 0x69, 0x6e, 0x6b,
+    // system name, length = 7, "switch1"
+    0x0a, 0x07, 0x73, 0x77, 0x69, 0x74, 0x63, 0x68, 0x31,
+    // end of LLDPDU
+    0x00, 0x00,
+];
+
+/// An 802.3 frame with a configuration BPDU from bridge
+/// 8000.00:00:00:00:00:02 to root 8000.00:00:00:00:00:01.
+#[rustfmt::skip]
+pub const STP_CONFIG_PACKET: [u8; 52] = [
+// 802.3 header
+    0x01, 0x80, 0xc2, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    // length = 38
+    0x00, 0x26,
+// LLC header
+    // dsap = 0x42, ssap = 0x42, control = UI
+    0x42, 0x42, 0x03,
+// BPDU
+    // protocol id = 0, version = 0, type = config
+    0x00, 0x00, 0x00, 0x00,
+    // flags = 0
+    0x00,
+    // root id = 8000.00:00:00:00:00:01
+    0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    // root path cost = 4
+    0x00, 0x00, 0x00, 0x04,
+    // bridge id = 8000.00:00:00:00:00:02
+    0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    // port id = 0x8001
+    0x80, 0x01,
+    // message age = 1, max age = 20, hello time = 2, forward delay = 15
+    0x01, 0x00, 0x14, 0x00, 0x02, 0x00, 0x0f, 0x00,
+];
+
+/// An 802.3 frame with a CDP version 2 advertisement from "switch01".
+#[rustfmt::skip]
+pub const CDP_PACKET: [u8; 96] = [
+// 802.3 header
+    0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcc,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    // length = 82
+    0x00, 0x52,
+// LLC/SNAP header
+    // dsap = 0xaa, ssap = 0xaa, control = UI
+    0xaa, 0xaa, 0x03,
+    // oui = 00:00:0c, protocol id = CDP
+    0x00, 0x00, 0x0c, 0x20, 0x00,
+// CDP header
+    // version = 2, ttl = 180, checksum = 0x840c
+    0x02, 0xb4, 0x84, 0x0c,
+// TLVs
+    // device id, length = 12, "switch01"
+    0x00, 0x01, 0x00, 0x0c, 0x73, 0x77, 0x69, 0x74, 0x63, 0x68, 0x30, 0x31,
+    // addresses, length = 17, 1 address, NLPID IP, 192.168.0.1
+    0x00, 0x02, 0x00, 0x11, 0x00, 0x00, 0x00, 0x01,
+    0x01, 0x01, 0xcc, 0x00, 0x04, 0xc0, 0xa8, 0x00, 0x01,
+    // port id, length = 9, "Gi0/1"
+    0x00, 0x03, 0x00, 0x09, 0x47, 0x69, 0x30, 0x2f, 0x31,
+    // capabilities, length = 8, switch and IGMP
+    0x00, 0x04, 0x00, 0x08, 0x00, 0x00, 0x00, 0x28,
+    // software version, length = 12, "IOS 15.2"
+    0x00, 0x05, 0x00, 0x0c, 0x49, 0x4f, 0x53, 0x20, 0x31, 0x35, 0x2e, 0x32,
+    // platform, length = 12, "WS-C2960"
+    0x00, 0x06, 0x00, 0x0c, 0x57, 0x53, 0x2d, 0x43, 0x32, 0x39, 0x36, 0x30,
+];
+
 /// An IPv4 TCP packet.
 #[rustfmt::skip]
 pub const IPV4_TCP_PACKET: [u8; 58] = [
@@ -150,6 +234,701 @@ pub const IPV4_UDP_PACKET: [u8; 52] = [
     0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
 ];
 
+/// The IPv4 UDP packet of `IPV4_UDP_PACKET` with a no-operation, a router
+/// alert and an end of options list option.
+#[rustfmt::skip]
+pub const IPV4_OPTIONS_PACKET: [u8; 60] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    // version = 4, ihl = 7
+    0x47, 0x00,
+    // IPv4 payload length
+    0x00, 0x2e,
+    // ident = 43849, flags = 4, frag_offset = 0
+    0xab, 0x49, 0x40, 0x00,
+    // ttl = 255, protocol = UDP, checksum = 0xef64
+    0xff, 0x11, 0xef, 0x64,
+    // src = 139.133.217.110
+    0x8b, 0x85, 0xd9, 0x6e,
+    // dst = 139.133.233.2
+    0x8b, 0x85, 0xe9, 0x02,
+// IPv4 options
+    // no-operation
+    0x01,
+    // router alert, length = 4, value = 0
+    0x94, 0x04, 0x00, 0x00,
+    // end of options list, padding
+    0x00, 0x00, 0x00,
+// UDP header
+    // src_port = 39376, dst_port = 1087
+    0x99, 0xd0, 0x04, 0x3f,
+    // UDP length = 18, checksum = 0x7228
+    0x00, 0x12, 0x72, 0x28,
+    // UDP payload
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
+/// A DHCP discover packet.
+#[rustfmt::skip]
+pub const DHCP_DISCOVER_PACKET: [u8; 308] = [
+// Ethernet header
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x01, 0x26,
+    // ident = 43062, flags = 0, frag_offset = 0
+    0xa8, 0x36, 0x00, 0x00,
+    // ttl = 250, protocol = UDP, checksum = 0x1791
+    0xfa, 0x11, 0x17, 0x91,
+    // src = 0.0.0.0
+    0x00, 0x00, 0x00, 0x00,
+    // dst = 255.255.255.255
+    0xff, 0xff, 0xff, 0xff,
+// UDP header
+    // src_port = 68, dst_port = 67
+    0x00, 0x44, 0x00, 0x43,
+    // UDP length = 274, checksum = 0x6a1e
+    0x01, 0x12, 0x6a, 0x1e,
+// DHCP message
+    // op = request, htype = ethernet, hlen = 6, hops = 0
+    0x01, 0x01, 0x06, 0x00,
+    // xid = 0x3903f326
+    0x39, 0x03, 0xf3, 0x26,
+    // secs = 0, flags = 0
+    0x00, 0x00, 0x00, 0x00,
+    // ciaddr, yiaddr, siaddr, giaddr = 0.0.0.0
+    0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00,
+    // chaddr = 00:0b:82:01:fc:42
+    0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // sname and file
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // magic cookie
+    0x63, 0x82, 0x53, 0x63,
+    // message type = discover
+    0x35, 0x01, 0x01,
+    // client identifier
+    0x3d, 0x07, 0x01, 0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42,
+    // requested ip address = 0.0.0.0
+    0x32, 0x04, 0x00, 0x00, 0x00, 0x00,
+    // parameter request list
+    0x37, 0x04, 0x01, 0x03, 0x06, 0x2a,
+    // end and pad
+    0xff, 0x00
+];
+
+/// A DHCPv6 solicit packet.
+#[rustfmt::skip]
+pub const DHCPV6_SOLICIT_PACKET: [u8; 114] = [
+// Ethernet header
+    0x33, 0x33, 0x00, 0x01, 0x00, 0x02,
+    0x00, 0x0c, 0x29, 0x8f, 0x95, 0x05,
+    0x86, 0xdd,
+// IPv6 header
+    // version, dscp, ecn, flow label
+    0x60, 0x00, 0x00, 0x00,
+    // payload length
+    0x00, 0x3c,
+    // next header = UDP
+    0x11,
+    // hop limit
+    0x01,
+    // src addr = fe80::20c:29ff:fe8f:9505
+    0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x0c, 0x29, 0xff, 0xfe, 0x8f, 0x95, 0x05,
+    // dst addr = ff02::1:2
+    0xff, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02,
+// UDP header
+    // src_port = 546, dst_port = 547
+    0x02, 0x22, 0x02, 0x23,
+    // UDP length = 60, checksum = 0x257f
+    0x00, 0x3c, 0x25, 0x7f,
+// DHCPv6 message
+    // msg_type = solicit, transaction_id = 0x100874
+    0x01, 0x10, 0x08, 0x74,
+    // client identifier, DUID-LLT
+    0x00, 0x01, 0x00, 0x0e,
+    0x00, 0x01, 0x00, 0x01, 0x1c, 0x39, 0xcf, 0x88, 0x08, 0x00, 0x27, 0x2f, 0xe8, 0xf9,
+    // elapsed time = 0
+    0x00, 0x08, 0x00, 0x02, 0x00, 0x00,
+    // identity association for non-temporary addresses
+    0x00, 0x03, 0x00, 0x0c,
+    0x0c, 0x29, 0xfe, 0x8f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // option request = DNS servers, domain search list
+    0x00, 0x06, 0x00, 0x04, 0x00, 0x17, 0x00, 0x18
+];
+
+/// An NTP client request packet.
+#[rustfmt::skip]
+pub const NTP_REQUEST_PACKET: [u8; 90] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x4c,
+    // ident = 4660, flags = 4, frag_offset = 0
+    0x12, 0x34, 0x40, 0x00,
+    // ttl = 64, protocol = UDP, checksum = 0xa511
+    0x40, 0x11, 0xa5, 0x11,
+    // src = 192.168.1.10
+    0xc0, 0xa8, 0x01, 0x0a,
+    // dst = 192.168.1.1
+    0xc0, 0xa8, 0x01, 0x01,
+// UDP header
+    // src_port = 50000, dst_port = 123
+    0xc3, 0x50, 0x00, 0x7b,
+    // UDP length = 56, checksum = 0x9ce0
+    0x00, 0x38, 0x9c, 0xe0,
+// NTP message
+    // li = 0, version = 4, mode = 3, stratum = 0, poll = 6, precision = -20
+    0x23, 0x00, 0x06, 0xec,
+    // root delay, root dispersion, reference id
+    0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00,
+    // reference, origin and receive timestamps
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // transmit timestamp = 1600000000.5 unix time
+    0xe3, 0x08, 0x8e, 0x80, 0x80, 0x00, 0x00, 0x00
+];
+
+/// An IPv4 SCTP packet with a SACK chunk and a DATA chunk.
+#[rustfmt::skip]
+pub const SCTP_PACKET: [u8; 86] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x48,
+    // ident = 1, flags = 4, frag_offset = 0
+    0x00, 0x01, 0x40, 0x00,
+    // ttl = 64, protocol = SCTP, checksum = 0x262f
+    0x40, 0x84, 0x26, 0x2f,
+    // src = 10.0.0.1
+    0x0a, 0x00, 0x00, 0x01,
+    // dst = 10.0.0.2
+    0x0a, 0x00, 0x00, 0x02,
+// SCTP common header
+    // src_port = 5000, dst_port = 80
+    0x13, 0x88, 0x00, 0x50,
+    // verification tag = 0x12345678
+    0x12, 0x34, 0x56, 0x78,
+    // checksum = 0xb3e41c79, in little-endian
+    0x79, 0x1c, 0xe4, 0xb3,
+// SACK chunk
+    // type = 3, flags = 0, length = 16
+    0x03, 0x00, 0x00, 0x10,
+    // cumulative tsn ack = 16, a_rwnd = 65536
+    0x00, 0x00, 0x00, 0x10, 0x00, 0x01, 0x00, 0x00,
+    // gap ack blocks = 0, duplicate tsns = 0
+    0x00, 0x00, 0x00, 0x00,
+// DATA chunk
+    // type = 0, flags = B|E, length = 21
+    0x00, 0x03, 0x00, 0x15,
+    // tsn = 17, stream id = 0, stream seq = 0, ppid = 0
+    0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // user data and padding
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x00, 0x00, 0x00
+];
+
+/// A VXLAN packet, with VNI 100, encapsulating `IPV4_UDP_PACKET`.
+#[rustfmt::skip]
+pub const VXLAN_PACKET: [u8; 102] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0b,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0a,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x58,
+    // ident = 0, flags = 4, frag_offset = 0
+    0x00, 0x00, 0x40, 0x00,
+    // ttl = 64, protocol = UDP, checksum = 0xb941
+    0x40, 0x11, 0xb9, 0x41,
+    // src = 192.168.0.1
+    0xc0, 0xa8, 0x00, 0x01,
+    // dst = 192.168.0.2
+    0xc0, 0xa8, 0x00, 0x02,
+// UDP header
+    // src_port = 49152, dst_port = 4789
+    0xc0, 0x00, 0x12, 0xb5,
+    // UDP length = 68, checksum = 0
+    0x00, 0x44, 0x00, 0x00,
+// VXLAN header
+    // flags = I, reserved
+    0x08, 0x00, 0x00, 0x00,
+    // vni = 100, reserved
+    0x00, 0x00, 0x64, 0x00,
+// Inner Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// Inner IPv4 header
+    0x45, 0x00, 0x00, 0x26, 0xab, 0x49, 0x40, 0x00, 0xff, 0x11, 0xf7, 0x00,
+    0x8b, 0x85, 0xd9, 0x6e, 0x8b, 0x85, 0xe9, 0x02,
+// Inner UDP header
+    0x99, 0xd0, 0x04, 0x3f, 0x00, 0x12, 0x72, 0x28,
+    // UDP payload
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
+/// A GRE packet, with key 1234, encapsulating the IPv4 packet of
+/// `IPV4_UDP_PACKET`.
+#[rustfmt::skip]
+pub const GRE_PACKET: [u8; 80] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0b,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0a,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x42,
+    // ident = 0, flags = 4, frag_offset = 0
+    0x00, 0x00, 0x40, 0x00,
+    // ttl = 64, protocol = GRE, checksum = 0xb939
+    0x40, 0x2f, 0xb9, 0x39,
+    // src = 192.168.0.1
+    0xc0, 0xa8, 0x00, 0x01,
+    // dst = 192.168.0.2
+    0xc0, 0xa8, 0x00, 0x02,
+// GRE header
+    // flags = K, version = 0, protocol_type = IPv4
+    0x20, 0x00, 0x08, 0x00,
+    // key = 1234
+    0x00, 0x00, 0x04, 0xd2,
+// Inner IPv4 header
+    0x45, 0x00, 0x00, 0x26, 0xab, 0x49, 0x40, 0x00, 0xff, 0x11, 0xf7, 0x00,
+    0x8b, 0x85, 0xd9, 0x6e, 0x8b, 0x85, 0xe9, 0x02,
+// Inner UDP header
+    0x99, 0xd0, 0x04, 0x3f, 0x00, 0x12, 0x72, 0x28,
+    // UDP payload
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
+/// A Geneve packet, with VNI 100 and one option, encapsulating
+/// `IPV4_UDP_PACKET`.
+#[rustfmt::skip]
+pub const GENEVE_PACKET: [u8; 110] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0b,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0a,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x60,
+    // ident = 0, flags = 4, frag_offset = 0
+    0x00, 0x00, 0x40, 0x00,
+    // ttl = 64, protocol = UDP, checksum = 0xb939
+    0x40, 0x11, 0xb9, 0x39,
+    // src = 192.168.0.1
+    0xc0, 0xa8, 0x00, 0x01,
+    // dst = 192.168.0.2
+    0xc0, 0xa8, 0x00, 0x02,
+// UDP header
+    // src_port = 49152, dst_port = 6081
+    0xc0, 0x00, 0x17, 0xc1,
+    // UDP length = 76, checksum = 0
+    0x00, 0x4c, 0x00, 0x00,
+// Geneve header
+    // version = 0, opt_len = 2, flags = 0, protocol_type = 0x6558
+    0x02, 0x00, 0x65, 0x58,
+    // vni = 100, reserved
+    0x00, 0x00, 0x64, 0x00,
+// Geneve option
+    // class = 0x0102, type = 0x80, length = 1
+    0x01, 0x02, 0x80, 0x01,
+    // data
+    0xde, 0xad, 0xbe, 0xef,
+// Inner Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// Inner IPv4 header
+    0x45, 0x00, 0x00, 0x26, 0xab, 0x49, 0x40, 0x00, 0xff, 0x11, 0xf7, 0x00,
+    0x8b, 0x85, 0xd9, 0x6e, 0x8b, 0x85, 0xe9, 0x02,
+// Inner UDP header
+    0x99, 0xd0, 0x04, 0x3f, 0x00, 0x12, 0x72, 0x28,
+    // UDP payload
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
+/// A Geneve packet, with VNI 100 and one option, carrying the IPv4 packet
+/// of `IPV4_UDP_PACKET` without an inner Ethernet header.
+#[rustfmt::skip]
+pub const GENEVE_IPV4_PACKET: [u8; 96] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0b,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0a,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x52,
+    // ident = 0, flags = 4, frag_offset = 0
+    0x00, 0x00, 0x40, 0x00,
+    // ttl = 64, protocol = UDP, checksum = 0xb947
+    0x40, 0x11, 0xb9, 0x47,
+    // src = 192.168.0.1
+    0xc0, 0xa8, 0x00, 0x01,
+    // dst = 192.168.0.2
+    0xc0, 0xa8, 0x00, 0x02,
+// UDP header
+    // src_port = 49152, dst_port = 6081
+    0xc0, 0x00, 0x17, 0xc1,
+    // UDP length = 62, checksum = 0
+    0x00, 0x3e, 0x00, 0x00,
+// Geneve header
+    // version = 0, opt_len = 2, flags = 0, protocol_type = 0x0800
+    0x02, 0x00, 0x08, 0x00,
+    // vni = 100, reserved
+    0x00, 0x00, 0x64, 0x00,
+// Geneve option
+    // class = 0x0102, type = 0x80, length = 1
+    0x01, 0x02, 0x80, 0x01,
+    // data
+    0xde, 0xad, 0xbe, 0xef,
+// Inner IPv4 header
+    0x45, 0x00, 0x00, 0x26, 0xab, 0x49, 0x40, 0x00, 0xff, 0x11, 0xf7, 0x00,
+    0x8b, 0x85, 0xd9, 0x6e, 0x8b, 0x85, 0xe9, 0x02,
+// Inner UDP header
+    0x99, 0xd0, 0x04, 0x3f, 0x00, 0x12, 0x72, 0x28,
+    // UDP payload
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
+/// An NSH packet, with MD type 1, service path 42 and service index 255,
+/// carrying the IPv4 packet of `IPV4_UDP_PACKET`.
+#[rustfmt::skip]
+pub const NSH_PACKET: [u8; 76] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x89, 0x4f,
+// NSH base header
+    // version = 0, O = 0, ttl = 63, length = 6
+    0x0f, 0xc6,
+    // md type = 1, next protocol = IPv4
+    0x01, 0x01,
+// NSH service path header
+    // spi = 42, si = 255
+    0x00, 0x00, 0x2a, 0xff,
+// NSH fixed context headers
+    0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x02,
+    0x00, 0x00, 0x00, 0x03,
+    0x00, 0x00, 0x00, 0x04,
+// IPv4 header
+    0x45, 0x00, 0x00, 0x26, 0xab, 0x49, 0x40, 0x00, 0xff, 0x11, 0xf7, 0x00,
+    0x8b, 0x85, 0xd9, 0x6e, 0x8b, 0x85, 0xe9, 0x02,
+// UDP header
+    0x99, 0xd0, 0x04, 0x3f, 0x00, 0x12, 0x72, 0x28,
+    // UDP payload
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
+/// A MPLS packet, with a two-label stack, carrying the IPv4 packet of
+/// `IPV4_UDP_PACKET`.
+#[rustfmt::skip]
+pub const MPLS_PACKET: [u8; 60] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x88, 0x47,
+// MPLS label stack
+    // label = 100, tc = 0, bos = 0, ttl = 64
+    0x00, 0x06, 0x40, 0x40,
+    // label = 200, tc = 5, bos = 1, ttl = 63
+    0x00, 0x0c, 0x8b, 0x3f,
+// IPv4 header
+    0x45, 0x00, 0x00, 0x26, 0xab, 0x49, 0x40, 0x00, 0xff, 0x11, 0xf7, 0x00,
+    0x8b, 0x85, 0xd9, 0x6e, 0x8b, 0x85, 0xe9, 0x02,
+// UDP header
+    0x99, 0xd0, 0x04, 0x3f, 0x00, 0x12, 0x72, 0x28,
+    // UDP payload
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
+/// A GTP-U G-PDU packet, with TEID 0x12345678 and a sequence number,
+/// carrying the IPv4 packet of `IPV4_UDP_PACKET`.
+#[rustfmt::skip]
+pub const GTPU_PACKET: [u8; 92] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0b,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0a,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x4e,
+    // ident = 0, flags = 4, frag_offset = 0
+    0x00, 0x00, 0x40, 0x00,
+    // ttl = 64, protocol = UDP, checksum = 0xb94b
+    0x40, 0x11, 0xb9, 0x4b,
+    // src = 192.168.0.1
+    0xc0, 0xa8, 0x00, 0x01,
+    // dst = 192.168.0.2
+    0xc0, 0xa8, 0x00, 0x02,
+// UDP header
+    // src_port = 2152, dst_port = 2152
+    0x08, 0x68, 0x08, 0x68,
+    // UDP length = 58, checksum = 0
+    0x00, 0x3a, 0x00, 0x00,
+// GTP-U header
+    // version = 1, PT = 1, S = 1, message type = G-PDU, length = 42
+    0x32, 0xff, 0x00, 0x2a,
+    // teid = 0x12345678
+    0x12, 0x34, 0x56, 0x78,
+    // sequence = 1, N-PDU = 0, next extension type = 0
+    0x00, 0x01, 0x00, 0x00,
+// Inner IPv4 header
+    0x45, 0x00, 0x00, 0x26, 0xab, 0x49, 0x40, 0x00, 0xff, 0x11, 0xf7, 0x00,
+    0x8b, 0x85, 0xd9, 0x6e, 0x8b, 0x85, 0xe9, 0x02,
+// Inner UDP header
+    0x99, 0xd0, 0x04, 0x3f, 0x00, 0x12, 0x72, 0x28,
+    // UDP payload
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x68, 0x65, 0x6c, 0x6c, 0x6f
+];
+
+/// An IGMPv2 membership report packet for group 239.1.2.3.
+#[rustfmt::skip]
+pub const IGMPV2_REPORT_PACKET: [u8; 42] = [
+// Ethernet header
+    0x01, 0x00, 0x5e, 0x01, 0x02, 0x03,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x1c,
+    // ident = 0, flags = 0, frag_offset = 0
+    0x00, 0x00, 0x00, 0x00,
+    // ttl = 1, protocol = IGMP, checksum = 0x0833
+    0x01, 0x02, 0x08, 0x33,
+    // src = 192.168.0.1
+    0xc0, 0xa8, 0x00, 0x01,
+    // dst = 239.1.2.3
+    0xef, 0x01, 0x02, 0x03,
+// IGMP message
+    // type = v2 membership report, max_resp_time = 0, checksum = 0xf8fa
+    0x16, 0x00, 0xf8, 0xfa,
+    // group = 239.1.2.3
+    0xef, 0x01, 0x02, 0x03,
+];
+
+/// An IGMPv3 membership report packet with one group record.
+#[rustfmt::skip]
+pub const IGMPV3_REPORT_PACKET: [u8; 58] = [
+// Ethernet header
+    0x01, 0x00, 0x5e, 0x00, 0x00, 0x16,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x2c,
+    // ident = 0, flags = 0, frag_offset = 0
+    0x00, 0x00, 0x00, 0x00,
+    // ttl = 1, protocol = IGMP, checksum = 0x1911
+    0x01, 0x02, 0x19, 0x11,
+    // src = 192.168.0.1
+    0xc0, 0xa8, 0x00, 0x01,
+    // dst = 224.0.0.22
+    0xe0, 0x00, 0x00, 0x16,
+// IGMP message
+    // type = v3 membership report, reserved, checksum = 0xd7f4
+    0x22, 0x00, 0xd7, 0xf4,
+    // reserved, number of group records = 1
+    0x00, 0x00, 0x00, 0x01,
+// Group record
+    // record type = mode is include, aux data len = 0, number of sources = 2
+    0x01, 0x00, 0x00, 0x02,
+    // multicast address = 239.1.2.3
+    0xef, 0x01, 0x02, 0x03,
+    // source addresses = 10.0.0.1, 10.0.0.2
+    0x0a, 0x00, 0x00, 0x01,
+    0x0a, 0x00, 0x00, 0x02,
+];
+
+/// A VRRPv2 advertisement packet for virtual router 1, with two virtual
+/// addresses.
+#[rustfmt::skip]
+pub const VRRP_PACKET: [u8; 58] = [
+// Ethernet header
+    0x01, 0x00, 0x5e, 0x00, 0x00, 0x12,
+    0x00, 0x00, 0x5e, 0x00, 0x01, 0x01,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0xc0,
+    // IPv4 payload length
+    0x00, 0x2c,
+    // ident = 0, flags = 0, frag_offset = 0
+    0x00, 0x00, 0x00, 0x00,
+    // ttl = 255, protocol = VRRP, checksum = 0x18e6
+    0xff, 0x70, 0x18, 0xe6,
+    // src = 192.168.1.1
+    0xc0, 0xa8, 0x01, 0x01,
+    // dst = 224.0.0.18
+    0xe0, 0x00, 0x00, 0x12,
+// VRRP message
+    // version = 2, type = advertisement, vrid = 1, priority = 100, count = 2
+    0x21, 0x01, 0x64, 0x02,
+    // auth type = 0, adver int = 1, checksum = 0xf5ae
+    0x00, 0x01, 0xf5, 0xae,
+    // addresses = 192.168.1.254, 192.168.1.253
+    0xc0, 0xa8, 0x01, 0xfe,
+    0xc0, 0xa8, 0x01, 0xfd,
+    // authentication data
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// An OSPFv2 hello packet from router 1.1.1.1 in area 0.0.0.1.
+#[rustfmt::skip]
+pub const OSPF_HELLO_PACKET: [u8; 78] = [
+// Ethernet header
+    0x01, 0x00, 0x5e, 0x00, 0x00, 0x05,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0xc0,
+    // IPv4 payload length
+    0x00, 0x40,
+    // ident = 0, flags = 0, frag_offset = 0
+    0x00, 0x00, 0x00, 0x00,
+    // ttl = 1, protocol = OSPF, checksum = 0x16f7
+    0x01, 0x59, 0x16, 0xf7,
+    // src = 192.168.1.1
+    0xc0, 0xa8, 0x01, 0x01,
+    // dst = 224.0.0.5
+    0xe0, 0x00, 0x00, 0x05,
+// OSPF header
+    // version = 2, type = hello, length = 44
+    0x02, 0x01, 0x00, 0x2c,
+    // router id = 1.1.1.1
+    0x01, 0x01, 0x01, 0x01,
+    // area id = 0.0.0.1
+    0x00, 0x00, 0x00, 0x01,
+    // checksum = 0x38f2, autype = 0
+    0x38, 0xf2, 0x00, 0x00,
+    // authentication
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+// OSPF hello
+    // network mask = 255.255.255.0
+    0xff, 0xff, 0xff, 0x00,
+    // hello interval = 10, options = E, priority = 1
+    0x00, 0x0a, 0x02, 0x01,
+    // dead interval = 40
+    0x00, 0x00, 0x00, 0x28,
+    // designated router = 192.168.1.1
+    0xc0, 0xa8, 0x01, 0x01,
+    // backup designated router = 0.0.0.0
+    0x00, 0x00, 0x00, 0x00,
+];
+
+/// An IPv4 ESP packet, with NULL encryption and a 12-byte ICV, carrying
+/// a UDP packet.
+#[rustfmt::skip]
+pub const ESP_PACKET: [u8; 70] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0b,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0a,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x38,
+    // ident = 0, flags = 4, frag_offset = 0
+    0x00, 0x00, 0x40, 0x00,
+    // ttl = 64, protocol = ESP, checksum = 0xb940
+    0x40, 0x32, 0xb9, 0x40,
+    // src = 192.168.0.1
+    0xc0, 0xa8, 0x00, 0x01,
+    // dst = 192.168.0.2
+    0xc0, 0xa8, 0x00, 0x02,
+// ESP header
+    // spi = 0x1234
+    0x00, 0x00, 0x12, 0x34,
+    // sequence = 1
+    0x00, 0x00, 0x00, 0x01,
+// ESP payload
+    // UDP header, src_port = 39376, dst_port = 1087, length = 13, checksum = 0
+    0x99, 0xd0, 0x04, 0x3f, 0x00, 0x0d, 0x00, 0x00,
+    // UDP payload
+    0x68, 0x65, 0x6c, 0x6c, 0x6f,
+// ESP trailer
+    // padding
+    0x01,
+    // pad length = 1, next header = UDP
+    0x01, 0x11,
+// ICV
+    0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+];
+
+/// An IPv4 AH packet, with a 12-byte ICV, carrying a UDP packet.
+#[rustfmt::skip]
+pub const AH_PACKET: [u8; 71] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0b,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0a,
+    0x08, 0x00,
+// IPv4 header
+    0x45, 0x00,
+    // IPv4 payload length
+    0x00, 0x39,
+    // ident = 0, flags = 4, frag_offset = 0
+    0x00, 0x00, 0x40, 0x00,
+    // ttl = 64, protocol = AH, checksum = 0xb93e
+    0x40, 0x33, 0xb9, 0x3e,
+    // src = 192.168.0.1
+    0xc0, 0xa8, 0x00, 0x01,
+    // dst = 192.168.0.2
+    0xc0, 0xa8, 0x00, 0x02,
+// AH header
+    // next header = UDP, payload length = 4, reserved
+    0x11, 0x04, 0x00, 0x00,
+    // spi = 0x1234
+    0x00, 0x00, 0x12, 0x34,
+    // sequence = 1
+    0x00, 0x00, 0x00, 0x01,
+    // ICV
+    0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb,
+// UDP header
+    // src_port = 39376, dst_port = 1087
+    0x99, 0xd0, 0x04, 0x3f,
+    // UDP length = 13, checksum = 0x9c9e
+    0x00, 0x0d, 0x9c, 0x9e,
+    // UDP payload
+    0x68, 0x65, 0x6c, 0x6c, 0x6f,
+];
+
 /// An IPv6 TCP packet.
 #[rustfmt::skip]
 pub const IPV6_TCP_PACKET: [u8; 78] = [
@@ -185,6 +964,44 @@ pub const IPV6_TCP_PACKET: [u8; 78] = [
     0x02, 0x04, 0x05, 0xb4
 ];
 
+/// An IPv6 UDP packet with a hop-by-hop options header carrying a jumbo
+/// payload option.
+///
+/// The payload is not larger than 65,535 octets, but the packet is
+/// otherwise formatted as an IPv6 jumbogram.
+#[rustfmt::skip]
+pub const IPV6_JUMBOGRAM_PACKET: [u8; 82] = [
+// Ethernet header
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x86, 0xDD,
+// IPv6 header
+    // version, dscp, ecn, flow label
+    0x60, 0x00, 0x00, 0x00,
+    // payload length = 0
+    0x00, 0x00,
+    // next header = hop-by-hop options
+    0x00,
+    // hop limit
+    0x40,
+    // src addr = 2001:db8::1
+    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    // dst addr = 2001:db8::2
+    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+// Hop-by-hop options header
+    // next header = UDP, hdr ext len = 0
+    0x11, 0x00,
+    // jumbo payload, length = 4, jumbo payload length = 28
+    0xc2, 0x04, 0x00, 0x00, 0x00, 0x1c,
+// UDP header
+    // src_port = 1234, dst_port = 5678
+    0x04, 0xd2, 0x16, 0x2e,
+    // UDP length = 0, checksum = 0xf775
+    0x00, 0x00, 0xf7, 0x75,
+    // UDP payload
+    0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x20, 0x77, 0x6f, 0x72, 0x6c, 0x64, 0x21
+];
+
 /// An IPv6 segment routing TCP packet.
 #[rustfmt::skip]
 pub const SR_TCP_PACKET: [u8; 170] = [
@@ -398,3 +1215,44 @@ pub const ROUTER_SOLICIT_PACKET: [u8; 70] = [
     // source link-layer address option
     0x01, 0x01, 0x70, 0x3a, 0xcb, 0x1b, 0xf9, 0x7a
 ];
+
+/// An MLDv2 multicast listener report packet with one address record.
+#[rustfmt::skip]
+pub const MLDV2_REPORT_PACKET: [u8; 106] = [
+// Ethernet header
+    0x33, 0x33, 0x00, 0x00, 0x00, 0x16,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    0x86, 0xDD,
+// IPv6 header
+    // version, dscp, ecn, flow label
+    0x60, 0x00, 0x00, 0x00,
+    // payload length = 52
+    0x00, 0x34,
+    // next header = hop-by-hop options
+    0x00,
+    // hop limit
+    0x01,
+    // src addr = fe80::2
+    0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    // dst addr = ff02::16
+    0xff, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x16,
+// Hop-by-hop options header
+    // next header = ICMPv6, hdr ext len = 0
+    0x3a, 0x00,
+    // router alert, length = 2, value = MLD
+    0x05, 0x02, 0x00, 0x00,
+    // padN, length = 0
+    0x01, 0x00,
+// ICMPv6 header
+    // type = v2 multicast listener report, code = 0, checksum = 0x4537
+    0x8f, 0x00, 0x45, 0x37,
+    // reserved, number of address records = 1
+    0x00, 0x00, 0x00, 0x01,
+// Multicast address record
+    // record type = mode is include, aux data len = 0, number of sources = 1
+    0x01, 0x00, 0x00, 0x01,
+    // multicast address = ff05::1:3
+    0xff, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x03,
+    // source address = 2001:db8::1
+    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+];
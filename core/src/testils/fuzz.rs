@@ -0,0 +1,256 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Randomized generators of malformed packets for fuzzing the parsers.
+//!
+//! The generated frames are structurally plausible Ethernet frames that
+//! carry an IPv4 or IPv6 packet with a TCP or UDP payload. A portion of
+//! the frames are corrupted with truncation, bad length fields or bad
+//! checksums. They are returned as raw bytes, ready for [`Mbuf::from_bytes`],
+//! to test that parsing never panics or reads out of bounds.
+//!
+//! [`Mbuf::from_bytes`]: crate::Mbuf::from_bytes
+
+use crate::packets::checksum::{self, PseudoHeader};
+use crate::packets::ip::{ProtocolNumber, ProtocolNumbers};
+use proptest::arbitrary::any;
+use proptest::collection::vec;
+use proptest::prop_oneof;
+use proptest::strategy::{Just, Strategy};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// A corruption applied to a generated frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Corruption {
+    /// The frame is well-formed.
+    None,
+    /// The frame is truncated to the length.
+    Truncate(usize),
+    /// The IP length field is overwritten with the value.
+    BadIpLength(u16),
+    /// The transport length field, or the TCP data offset, is overwritten
+    /// with the value.
+    BadTransportLength(u16),
+    /// The transport checksum is overwritten with the value.
+    BadChecksum(u16),
+}
+
+/// Returns a strategy for the corruptions, where most of the frames are
+/// well-formed.
+pub fn corruption() -> impl Strategy<Value = Corruption> {
+    prop_oneof![
+        4 => Just(Corruption::None),
+        1 => (0..100usize).prop_map(Corruption::Truncate),
+        1 => any::<u16>().prop_map(Corruption::BadIpLength),
+        1 => any::<u16>().prop_map(Corruption::BadTransportLength),
+        1 => any::<u16>().prop_map(Corruption::BadChecksum),
+    ]
+}
+
+/// Returns a strategy that generates random, possibly corrupted, frames.
+///
+/// # Example
+///
+/// ```
+/// let mut gen = Rvg::deterministic();
+/// let frame = gen.generate(&fuzz::frame());
+/// let packet = Mbuf::from_bytes(&frame)?;
+/// ```
+pub fn frame() -> impl Strategy<Value = Vec<u8>> {
+    (
+        any::<([u8; 6], [u8; 6])>(),
+        any::<([u8; 16], [u8; 16])>(),
+        any::<(u16, u16)>(),
+        any::<(bool, bool)>(),
+        vec(any::<u8>(), 0..64),
+        corruption(),
+    )
+        .prop_map(|(macs, addrs, ports, (v6, tcp), payload, corruption)| {
+            let protocol = if tcp {
+                ProtocolNumbers::Tcp
+            } else {
+                ProtocolNumbers::Udp
+            };
+            let transport = transport(protocol, ports, &payload);
+
+            let (src_mac, dst_mac) = macs;
+            let mut frame = dst_mac.to_vec();
+            frame.extend_from_slice(&src_mac);
+            frame.extend_from_slice(if v6 { &[0x86, 0xdd] } else { &[0x08, 0x00] });
+
+            let (src, dst) = addrs;
+            let (ip, pseudo_header) = if v6 {
+                ipv6(src.into(), dst.into(), protocol, transport.len())
+            } else {
+                let src = Ipv4Addr::new(src[0], src[1], src[2], src[3]);
+                let dst = Ipv4Addr::new(dst[0], dst[1], dst[2], dst[3]);
+                ipv4(src, dst, protocol, transport.len())
+            };
+            frame.extend_from_slice(&ip);
+
+            let transport_offset = frame.len();
+            let checksum = checksum::compute(pseudo_header.sum(), &transport);
+            frame.extend_from_slice(&transport);
+
+            let checksum_offset = transport_offset + if tcp { 16 } else { 6 };
+            write_u16(&mut frame, checksum_offset, checksum);
+
+            let ip_len_offset = if v6 { 18 } else { 16 };
+            corrupt(&mut frame, corruption, ip_len_offset, transport_offset, tcp);
+            frame
+        })
+}
+
+fn write_u16(frame: &mut [u8], offset: usize, value: u16) {
+    frame[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+fn transport(protocol: ProtocolNumber, ports: (u16, u16), payload: &[u8]) -> Vec<u8> {
+    let mut bytes = ports.0.to_be_bytes().to_vec();
+    bytes.extend_from_slice(&ports.1.to_be_bytes());
+
+    if protocol == ProtocolNumbers::Tcp {
+        // seq_no, ack_no, data_offset = 5, flags = SYN, window, checksum
+        // and urgent pointer.
+        bytes.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x02, 0xff, 0xff]);
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+    } else {
+        let len = (8 + payload.len()) as u16;
+        bytes.extend_from_slice(&len.to_be_bytes());
+        bytes.extend_from_slice(&[0, 0]);
+    }
+
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+fn ipv4(
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+    protocol: ProtocolNumber,
+    len: usize,
+) -> (Vec<u8>, PseudoHeader) {
+    let total_length = (20 + len) as u16;
+    let mut bytes = vec![0x45, 0x00];
+    bytes.extend_from_slice(&total_length.to_be_bytes());
+    bytes.extend_from_slice(&[0x00, 0x00, 0x40, 0x00, 0x40, protocol.0, 0x00, 0x00]);
+    bytes.extend_from_slice(&src.octets());
+    bytes.extend_from_slice(&dst.octets());
+
+    let checksum = checksum::compute(0, &bytes);
+    write_u16(&mut bytes, 10, checksum);
+
+    let pseudo_header = PseudoHeader::V4 {
+        src,
+        dst,
+        packet_len: len as u16,
+        protocol,
+    };
+    (bytes, pseudo_header)
+}
+
+fn ipv6(
+    src: Ipv6Addr,
+    dst: Ipv6Addr,
+    protocol: ProtocolNumber,
+    len: usize,
+) -> (Vec<u8>, PseudoHeader) {
+    let mut bytes = vec![0x60, 0x00, 0x00, 0x00];
+    bytes.extend_from_slice(&(len as u16).to_be_bytes());
+    bytes.extend_from_slice(&[protocol.0, 0x40]);
+    bytes.extend_from_slice(&src.octets());
+    bytes.extend_from_slice(&dst.octets());
+
+    let pseudo_header = PseudoHeader::V6 {
+        src,
+        dst,
+        packet_len: len as u16,
+        protocol,
+    };
+    (bytes, pseudo_header)
+}
+
+fn corrupt(
+    frame: &mut Vec<u8>,
+    corruption: Corruption,
+    ip_len_offset: usize,
+    transport_offset: usize,
+    tcp: bool,
+) {
+    match corruption {
+        Corruption::None => (),
+        Corruption::Truncate(len) => frame.truncate(len.max(1)),
+        Corruption::BadIpLength(len) => write_u16(frame, ip_len_offset, len),
+        Corruption::BadTransportLength(len) => {
+            if tcp {
+                // the data offset is the high nibble.
+                frame[transport_offset + 12] = (len as u8) << 4;
+            } else {
+                write_u16(frame, transport_offset + 4, len);
+            }
+        }
+        Corruption::BadChecksum(checksum) => {
+            let offset = transport_offset + if tcp { 16 } else { 6 };
+            write_u16(frame, offset, checksum);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::ip::v4::Ipv4;
+    use crate::packets::ip::v6::Ipv6;
+    use crate::packets::layer::LayerIterator;
+    use crate::packets::registry::ParserRegistry;
+    use crate::packets::{Packet, Tcp, Udp, ValidateLength};
+    use crate::testils::Rvg;
+    use crate::Mbuf;
+
+    #[capsule::test]
+    fn parse_fuzzed_frames_without_panic() {
+        let mut registry = ParserRegistry::new();
+        registry.register_ipv4(ProtocolNumbers::Udp, |ipv4| {
+            let udp = ipv4.peek::<Udp<Ipv4>>()?;
+            let _ = (udp.validate_length(), udp.validate_checksum(), udp.to_vec());
+            Ok(())
+        });
+        registry.register_ipv4(ProtocolNumbers::Tcp, |ipv4| {
+            let tcp = ipv4.peek::<Tcp<Ipv4>>()?;
+            let _ = (tcp.validate_checksum(), tcp.to_vec());
+            Ok(())
+        });
+        registry.register_ipv6(ProtocolNumbers::Udp, |ipv6| {
+            let udp = ipv6.peek::<Udp<Ipv6>>()?;
+            let _ = (udp.validate_length(), udp.validate_checksum(), udp.to_vec());
+            Ok(())
+        });
+        registry.register_ipv6(ProtocolNumbers::Tcp, |ipv6| {
+            let tcp = ipv6.peek::<Tcp<Ipv6>>()?;
+            let _ = (tcp.validate_checksum(), tcp.to_vec());
+            Ok(())
+        });
+
+        let mut gen = Rvg::deterministic();
+        for frame in gen.generate_vec(&frame(), 500) {
+            let packet = Mbuf::from_bytes(&frame).unwrap();
+            let _ = LayerIterator::new(&packet).count();
+            let _ = registry.dispatch(packet);
+        }
+    }
+}
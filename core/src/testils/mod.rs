@@ -20,6 +20,7 @@
 
 pub mod byte_arrays;
 pub mod criterion;
+pub mod fuzz;
 mod packet;
 pub mod proptest;
 mod rvg;
@@ -19,19 +19,12 @@
 //! Implementations of `proptest.arbitrary.Arbitrary` trait for
 //! various types.
 
-use crate::dpdk::Mbuf;
-use crate::net::MacAddr;
-use proptest::arbitrary::{any, Arbitrary, StrategyFor};
-use proptest::strategy::{MapInto, Strategy};
-
-impl Arbitrary for MacAddr {
-    type Parameters = ();
-    type Strategy = MapInto<StrategyFor<[u8; 6]>, Self>;
+//!
+//! The implementation for `MacAddr` is in `capsule-headers`, behind its
+//! `proptest` feature.
 
-    fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
-        any::<[u8; 6]>().prop_map_into()
-    }
-}
+use crate::dpdk::Mbuf;
+use proptest::arbitrary::Arbitrary;
 
 impl Arbitrary for Mbuf {
     type Parameters = ();
@@ -60,6 +60,24 @@ int _rte_pktmbuf_alloc_bulk(
     struct rte_mbuf **mbufs,
     unsigned count);
 
+/**
+ * Chain an mbuf to another, thereby creating a segmented packet.
+ */
+int _rte_pktmbuf_chain(struct rte_mbuf *head, struct rte_mbuf *tail);
+
+/**
+ * Linearize data in mbuf. This function moves the mbuf data in the first
+ * segment if there is enough tailroom. The subsequent segments are
+ * unchained and freed.
+ */
+int _rte_pktmbuf_linearize(struct rte_mbuf *mbuf);
+
+/**
+ * Create a "clone" of the given packet mbuf. The clone is an indirect
+ * mbuf that shares the data of the original.
+ */
+struct rte_mbuf *_rte_pktmbuf_clone(struct rte_mbuf *md, struct rte_mempool *mp);
+
 /**
  * Put several objects back in the mempool.
  */
@@ -26094,6 +26094,21 @@ extern "C" {
         count: ::std::os::raw::c_uint,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    #[doc = " Chain an mbuf to another, thereby creating a segmented packet."]
+    pub fn _rte_pktmbuf_chain(head: *mut rte_mbuf, tail: *mut rte_mbuf) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    #[doc = " Linearize data in mbuf. This function moves the mbuf data in the first"]
+    #[doc = " segment if there is enough tailroom. The subsequent segments are"]
+    #[doc = " unchained and freed."]
+    pub fn _rte_pktmbuf_linearize(mbuf: *mut rte_mbuf) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    #[doc = " Create a \"clone\" of the given packet mbuf. The clone is an indirect"]
+    #[doc = " mbuf that shares the data of the original."]
+    pub fn _rte_pktmbuf_clone(md: *mut rte_mbuf, mp: *mut rte_mempool) -> *mut rte_mbuf;
+}
 extern "C" {
     #[doc = " Put several objects back in the mempool."]
     pub fn _rte_mempool_put_bulk(
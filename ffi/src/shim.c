@@ -40,6 +40,18 @@ int _rte_pktmbuf_alloc_bulk(
     return rte_pktmbuf_alloc_bulk(pool, mbufs, count);
 }
 
+int _rte_pktmbuf_chain(struct rte_mbuf *head, struct rte_mbuf *tail) {
+    return rte_pktmbuf_chain(head, tail);
+}
+
+int _rte_pktmbuf_linearize(struct rte_mbuf *mbuf) {
+    return rte_pktmbuf_linearize(mbuf);
+}
+
+struct rte_mbuf *_rte_pktmbuf_clone(struct rte_mbuf *md, struct rte_mempool *mp) {
+    return rte_pktmbuf_clone(md, mp);
+}
+
 void _rte_mempool_put_bulk(
     struct rte_mempool *mp,
     void *const *obj_table,
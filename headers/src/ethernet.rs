@@ -0,0 +1,365 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::types::u16be;
+use crate::MacAddr;
+use core::fmt;
+
+/// The length of an Ethernet header without VLAN tags.
+pub const ETH_HEADER_SIZE: usize = 14;
+
+/// The length of a VLAN tag.
+pub const VLAN_TAG_SIZE: usize = 4;
+
+/// The tag protocol identifier of an 802.1Q tagged frame.
+pub const VLAN_802_1Q: u16 = 0x8100;
+
+/// The tag protocol identifier of an 802.1ad tagged frame.
+pub const VLAN_802_1AD: u16 = 0x88a8;
+
+/// The protocol identifier of the Ethernet frame payload.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, packed)]
+pub struct EtherType(pub u16);
+
+impl EtherType {
+    /// Creates an Ethernet payload protocol identifier.
+    pub fn new(value: u16) -> Self {
+        EtherType(value)
+    }
+}
+
+/// Supported Ethernet payload protocol types.
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+pub mod EtherTypes {
+    use super::EtherType;
+
+    /// Address resolution protocol.
+    pub const Arp: EtherType = EtherType(0x0806);
+    /// Internet Protocol version 4.
+    pub const Ipv4: EtherType = EtherType(0x0800);
+    /// Internet Protocol version 6.
+    pub const Ipv6: EtherType = EtherType(0x86DD);
+    /// Transparent Ethernet bridging, the payload is an Ethernet frame.
+    pub const TransparentEthernetBridging: EtherType = EtherType(0x6558);
+    /// Multiprotocol label switching unicast.
+    pub const Mpls: EtherType = EtherType(0x8847);
+    /// Link layer discovery protocol.
+    pub const Lldp: EtherType = EtherType(0x88CC);
+    /// Network service header.
+    pub const Nsh: EtherType = EtherType(0x894F);
+}
+
+impl fmt::Display for EtherType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            EtherTypes::Arp => f.write_str("ARP"),
+            EtherTypes::Ipv4 => f.write_str("IPv4"),
+            EtherTypes::Ipv6 => f.write_str("IPv6"),
+            EtherTypes::TransparentEthernetBridging => f.write_str("TEB"),
+            EtherTypes::Mpls => f.write_str("MPLS"),
+            EtherTypes::Lldp => f.write_str("LLDP"),
+            EtherTypes::Nsh => f.write_str("NSH"),
+            _ => {
+                let t = self.0;
+                write!(f, "0x{:04x}", t)
+            }
+        }
+    }
+}
+
+/// VLAN tag of an 802.1Q or 802.1ad tagged frame.
+///
+/// The tag is accessed in place through [`EthernetHeader::vlan`] and
+/// [`EthernetHeader::inner_vlan`].
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C, packed)]
+pub struct Vlan {
+    tpid: u16be,
+    tci: u16be,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+impl Vlan {
+    /// Returns the tag protocol identifier, either 802.1q (Dot1q) or 802.1ad (QinQ).
+    #[inline]
+    pub fn tpid(&self) -> u16 {
+        self.tpid.into()
+    }
+
+    /// Returns the priority code point.
+    #[inline]
+    pub fn pcp(&self) -> u8 {
+        let tci: u16 = self.tci.into();
+        (tci >> 13) as u8
+    }
+
+    /// Sets the priority code point.
+    ///
+    /// Only the lower 3 bits of `pcp` are used. The DEI and the VID are
+    /// not changed.
+    #[inline]
+    pub fn set_pcp(&mut self, pcp: u8) {
+        self.tci = (self.tci & u16be::from(0x1fff)) | u16be::from(u16::from(pcp & 0x07) << 13);
+    }
+
+    /// Returns whether the frame is eligible to be dropped in the presence
+    /// of congestion.
+    #[inline]
+    pub fn dei(&self) -> bool {
+        self.tci & u16be::from(0x1000) > u16be::MIN
+    }
+
+    /// Sets the drop eligible indicator.
+    ///
+    /// The PCP and the VID are not changed.
+    #[inline]
+    pub fn set_dei(&mut self, dei: bool) {
+        if dei {
+            self.tci |= u16be::from(0x1000);
+        } else {
+            self.tci &= !u16be::from(0x1000);
+        }
+    }
+
+    /// Returns the VLAN identifier.
+    #[inline]
+    pub fn vid(&self) -> u16 {
+        (self.tci & u16be::from(0x0fff)).into()
+    }
+
+    /// Sets the VLAN identifier.
+    ///
+    /// Only the lower 12 bits of `vid` are used. The PCP and the DEI are
+    /// not changed.
+    #[inline]
+    pub fn set_vid(&mut self, vid: u16) {
+        self.tci = (self.tci & u16be::from(0xf000)) | u16be::from(vid & 0x0fff);
+    }
+}
+
+/// Dot1q chunk for a VLAN header.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C, packed)]
+struct Dot1q {
+    tag: Vlan,
+    ether_type: u16be,
+}
+
+/// QinQ chunk for a VLAN header.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C, packed)]
+struct Qinq {
+    stag: Vlan,
+    ctag: Vlan,
+    ether_type: u16be,
+}
+
+/// The Ethernet header chunk follows the source MAC.
+#[allow(missing_debug_implementations)]
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+union Chunk {
+    ether_type: u16be,
+    dot1q: Dot1q,
+    qinq: Qinq,
+}
+
+impl Default for Chunk {
+    fn default() -> Chunk {
+        Chunk {
+            ether_type: u16be::default(),
+        }
+    }
+}
+
+/// Ethernet header.
+///
+/// The memory layout has room for two VLAN tags, so it's larger than the
+/// header of an untagged frame. Use [`header_len`] for the length of the
+/// header as tagged.
+///
+/// [`header_len`]: EthernetHeader::header_len
+#[allow(missing_debug_implementations)]
+#[derive(Clone, Copy, Default)]
+#[repr(C, packed)]
+pub struct EthernetHeader {
+    dst: MacAddr,
+    src: MacAddr,
+    chunk: Chunk,
+}
+
+impl EthernetHeader {
+    /// Returns the source MAC address.
+    #[inline]
+    pub fn src(&self) -> MacAddr {
+        self.src
+    }
+
+    /// Sets the source MAC address.
+    #[inline]
+    pub fn set_src(&mut self, src: MacAddr) {
+        self.src = src
+    }
+
+    /// Returns the destination MAC address.
+    #[inline]
+    pub fn dst(&self) -> MacAddr {
+        self.dst
+    }
+
+    /// Sets the destination MAC address.
+    #[inline]
+    pub fn set_dst(&mut self, dst: MacAddr) {
+        self.dst = dst
+    }
+
+    /// Returns the marker that indicates whether the frame is VLAN.
+    #[inline]
+    fn vlan_marker(&self) -> u16 {
+        unsafe { self.chunk.ether_type.into() }
+    }
+
+    /// Returns the protocol identifier of the payload.
+    #[inline]
+    pub fn ether_type(&self) -> EtherType {
+        let ether_type = unsafe {
+            match self.vlan_marker() {
+                VLAN_802_1Q => self.chunk.dot1q.ether_type,
+                VLAN_802_1AD => self.chunk.qinq.ether_type,
+                _ => self.chunk.ether_type,
+            }
+        };
+
+        EtherType::new(ether_type.into())
+    }
+
+    /// Sets the protocol identifier of the payload.
+    #[inline]
+    pub fn set_ether_type(&mut self, ether_type: EtherType) {
+        let ether_type = ether_type.0.into();
+        match self.vlan_marker() {
+            VLAN_802_1Q => self.chunk.dot1q.ether_type = ether_type,
+            VLAN_802_1AD => self.chunk.qinq.ether_type = ether_type,
+            _ => self.chunk.ether_type = ether_type,
+        }
+    }
+
+    /// Returns whether the frame is VLAN Dot1q (802.1Q) tagged.
+    #[inline]
+    pub fn is_dot1q(&self) -> bool {
+        self.vlan_marker() == VLAN_802_1Q
+    }
+
+    /// Returns whether the frame is VLAN QinQ (802.1ad) tagged.
+    #[inline]
+    pub fn is_qinq(&self) -> bool {
+        self.vlan_marker() == VLAN_802_1AD
+    }
+
+    /// Returns the VLAN tag of a Dot1q frame, or the outer S-TAG of a QinQ
+    /// frame.
+    #[inline]
+    pub fn vlan(&self) -> Option<&Vlan> {
+        unsafe {
+            match self.vlan_marker() {
+                VLAN_802_1Q => Some(&self.chunk.dot1q.tag),
+                VLAN_802_1AD => Some(&self.chunk.qinq.stag),
+                _ => None,
+            }
+        }
+    }
+
+    /// Returns the mutable VLAN tag of a Dot1q frame, or the outer S-TAG of
+    /// a QinQ frame.
+    #[inline]
+    pub fn vlan_mut(&mut self) -> Option<&mut Vlan> {
+        unsafe {
+            match self.vlan_marker() {
+                VLAN_802_1Q => Some(&mut self.chunk.dot1q.tag),
+                VLAN_802_1AD => Some(&mut self.chunk.qinq.stag),
+                _ => None,
+            }
+        }
+    }
+
+    /// Returns the inner C-TAG of a QinQ frame.
+    #[inline]
+    pub fn inner_vlan(&self) -> Option<&Vlan> {
+        if self.is_qinq() {
+            unsafe { Some(&self.chunk.qinq.ctag) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the mutable inner C-TAG of a QinQ frame.
+    #[inline]
+    pub fn inner_vlan_mut(&mut self) -> Option<&mut Vlan> {
+        if self.is_qinq() {
+            unsafe { Some(&mut self.chunk.qinq.ctag) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the length of the header, including the VLAN tags.
+    #[inline]
+    pub fn header_len(&self) -> usize {
+        if self.is_dot1q() {
+            ETH_HEADER_SIZE + VLAN_TAG_SIZE
+        } else if self.is_qinq() {
+            ETH_HEADER_SIZE + VLAN_TAG_SIZE * 2
+        } else {
+            ETH_HEADER_SIZE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ether_type_to_string() {
+        assert_eq!("ARP", EtherTypes::Arp.to_string());
+        assert_eq!("IPv4", EtherTypes::Ipv4.to_string());
+        assert_eq!("IPv6", EtherTypes::Ipv6.to_string());
+        assert_eq!("0x0000", EtherType::new(0).to_string());
+    }
+
+    #[test]
+    fn size_of_vlan_tag() {
+        assert_eq!(VLAN_TAG_SIZE, core::mem::size_of::<Vlan>());
+    }
+
+    #[test]
+    fn read_dot1q_header() {
+        let mut header = EthernetHeader::default();
+        header.set_ether_type(EtherType::new(VLAN_802_1Q));
+        assert!(header.is_dot1q());
+        assert_eq!(18, header.header_len());
+
+        header.vlan_mut().unwrap().set_vid(100);
+        header.set_ether_type(EtherTypes::Arp);
+        assert_eq!(100, header.vlan().unwrap().vid());
+        assert_eq!(EtherTypes::Arp, header.ether_type());
+        assert!(header.inner_vlan().is_none());
+    }
+}
@@ -0,0 +1,55 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+//! Header definitions for [`Capsule`] that don't need DPDK.
+//!
+//! The header structs, the byte-order wrapper types and the MAC address
+//! only depend on `core`, so they can be reused without linking DPDK, for
+//! example on an embedded agent. [`Capsule`] wraps them in mbuf-backed
+//! packets.
+//!
+//! ## Features
+//!
+//! - `std`: Enabled by default. Adds the conversions that need the
+//!   standard library, such as parsing a MAC address from a string.
+//!   Disable the default features to build with `#![no_std]`.
+//! - `serde`: Makes `MacAddr` deserializable, together with `std`.
+//! - `proptest`: Implements `Arbitrary` for the header types.
+//!
+//! [`Capsule`]: https://crates.io/crates/capsule
+
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+#![warn(
+    missing_debug_implementations,
+    missing_docs,
+    rust_2018_idioms,
+    unreachable_pub
+)]
+#![deny(broken_intra_doc_links)]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+#[cfg(feature = "proptest")]
+mod arbitrary;
+mod ethernet;
+mod mac;
+pub mod types;
+mod udp;
+
+pub use self::ethernet::*;
+pub use self::mac::*;
+pub use self::udp::*;
@@ -0,0 +1,290 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use core::convert::From;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::net::{Ipv4Addr, Ipv6Addr};
+#[cfg(feature = "std")]
+use std::str::FromStr;
+#[cfg(feature = "std")]
+use thiserror::Error;
+
+/// Ethernet MAC address.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C, packed)]
+pub struct MacAddr([u8; 6]);
+
+impl MacAddr {
+    /// A MAC address representing an unspecified address: 00:00:00:00:00:00.
+    pub const UNSPECIFIED: Self = MacAddr([0, 0, 0, 0, 0, 0]);
+
+    /// A MAC address representing the broadcast address: ff:ff:ff:ff:ff:ff.
+    pub const BROADCAST: Self = MacAddr([0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+
+    /// Creates a MAC address from 6 octets.
+    #[allow(clippy::many_single_char_names)]
+    pub fn new(a: u8, b: u8, c: u8, d: u8, e: u8, f: u8) -> Self {
+        MacAddr([a, b, c, d, e, f])
+    }
+
+    /// Returns the six bytes the MAC address consists of.
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+
+    /// Returns the Ethernet multicast MAC address of an IPv4 multicast
+    /// group, `01:00:5e` followed by the low 23 bits of the group address,
+    /// as defined in [IETF RFC 1112].
+    ///
+    /// Returns `None` if the address is not a multicast address.
+    ///
+    /// [IETF RFC 1112]: https://tools.ietf.org/html/rfc1112#section-6.4
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn from_ipv4_multicast(addr: Ipv4Addr) -> Option<Self> {
+        if addr.is_multicast() {
+            let o = addr.octets();
+            Some(MacAddr([0x01, 0x00, 0x5e, o[1] & 0x7f, o[2], o[3]]))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the Ethernet multicast MAC address of an IPv6 multicast
+    /// group, `33:33` followed by the low 32 bits of the group address,
+    /// as defined in [IETF RFC 2464].
+    ///
+    /// Returns `None` if the address is not a multicast address.
+    ///
+    /// [IETF RFC 2464]: https://tools.ietf.org/html/rfc2464#section-7
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn from_ipv6_multicast(addr: Ipv6Addr) -> Option<Self> {
+        if addr.is_multicast() {
+            let o = addr.octets();
+            Some(MacAddr([0x33, 0x33, o[12], o[13], o[14], o[15]]))
+        } else {
+            None
+        }
+    }
+
+    /// Returns whether the address is a multicast address, with the
+    /// least significant bit of the first octet set.
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+
+    /// Returns whether the address is locally administered, with the
+    /// second least significant bit of the first octet set.
+    pub fn is_locally_administered(&self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+
+    /// Returns a random locally administered unicast address.
+    #[cfg(feature = "proptest")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "proptest")))]
+    pub fn random() -> Self {
+        use proptest::arbitrary::any;
+        use proptest::strategy::{Strategy, ValueTree};
+        use proptest::test_runner::{Config, TestRunner};
+
+        let mut runner = TestRunner::new(Config::default());
+        let octets = any::<[u8; 6]>()
+            .new_tree(&mut runner)
+            .expect("No value can be generated")
+            .current();
+        MacAddr(octets).into_local_unicast()
+    }
+
+    /// Returns the `index`-th locally administered unicast address after
+    /// `base`.
+    ///
+    /// The index is added to the low 4 octets of the base address. The
+    /// same base and index always return the same address, so distinct
+    /// indices return distinct addresses for up to 2^32 addresses.
+    pub fn sequential(base: MacAddr, index: u32) -> Self {
+        let mut octets = base.0;
+        let low = u32::from_be_bytes([octets[2], octets[3], octets[4], octets[5]]);
+        octets[2..].copy_from_slice(&low.wrapping_add(index).to_be_bytes());
+        MacAddr(octets).into_local_unicast()
+    }
+
+    fn into_local_unicast(self) -> Self {
+        let mut octets = self.0;
+        octets[0] = (octets[0] | 0x02) & !0x01;
+        MacAddr(octets)
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            self.0[0], self.0[1], self.0[2], self.0[3], self.0[4], self.0[5]
+        )
+    }
+}
+
+impl From<[u8; 6]> for MacAddr {
+    fn from(octets: [u8; 6]) -> MacAddr {
+        MacAddr(octets)
+    }
+}
+
+/// Error returned when parsing a malformed MAC address.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[derive(Debug, Error)]
+#[error("Failed to parse '{0}' as MAC address.")]
+pub struct MacParseError(String);
+
+#[cfg(feature = "std")]
+impl FromStr for MacAddr {
+    type Err = MacParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let u8s = s
+            .split(&[':', '-'][..])
+            .map(|s| u8::from_str_radix(s, 16))
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+
+        if u8s.len() == 6 {
+            let mut octets = [0; 6];
+            octets.copy_from_slice(u8s.as_slice());
+            Ok(octets.into())
+        } else {
+            Err(MacParseError(s.to_owned()))
+        }
+    }
+}
+
+// make `MacAddr` serde deserializable.
+#[cfg(all(feature = "serde", feature = "std"))]
+impl<'de> serde::Deserialize<'de> for MacAddr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        MacAddr::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mac_addr_to_string() {
+        assert_eq!(
+            "00:00:00:00:00:00",
+            MacAddr::new(0, 0, 0, 0, 0, 0).to_string()
+        );
+        assert_eq!(
+            "ff:ff:ff:ff:ff:ff",
+            MacAddr::new(255, 255, 255, 255, 255, 255).to_string()
+        );
+        assert_eq!(
+            "12:34:56:ab:cd:ef",
+            MacAddr::new(0x12, 0x34, 0x56, 0xAB, 0xCD, 0xEF).to_string()
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn string_to_mac_addr() {
+        assert_eq!(
+            MacAddr::new(0, 0, 0, 0, 0, 0),
+            "00:00:00:00:00:00".parse().unwrap()
+        );
+        assert_eq!(
+            MacAddr::new(255, 255, 255, 255, 255, 255),
+            "ff:ff:ff:ff:ff:ff".parse().unwrap()
+        );
+        assert_eq!(
+            MacAddr::new(0x12, 0x34, 0x56, 0xAB, 0xCD, 0xEF),
+            "12:34:56:ab:cd:ef".parse().unwrap()
+        );
+    }
+
+    #[cfg(feature = "proptest")]
+    #[test]
+    fn generate_random_mac_addrs() {
+        for _ in 0..100 {
+            let mac = MacAddr::random();
+            assert!(!mac.is_multicast());
+            assert!(mac.is_locally_administered());
+        }
+    }
+
+    #[test]
+    fn generate_sequential_mac_addrs() {
+        let base = MacAddr::new(0x01, 0x00, 0x00, 0x00, 0x00, 0xfe);
+        let macs = (0..4)
+            .map(|index| MacAddr::sequential(base, index))
+            .collect::<Vec<_>>();
+        assert_eq!("02:00:00:00:00:fe", macs[0].to_string());
+        assert_eq!("02:00:00:00:00:ff", macs[1].to_string());
+        assert_eq!("02:00:00:00:01:00", macs[2].to_string());
+        assert_eq!("02:00:00:00:01:01", macs[3].to_string());
+        assert!(macs.iter().all(|mac| mac.is_locally_administered()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn ipv4_multicast_to_mac_addr() {
+        assert_eq!(
+            Some(MacAddr::new(0x01, 0x00, 0x5e, 0x00, 0x00, 0x01)),
+            MacAddr::from_ipv4_multicast(Ipv4Addr::new(224, 0, 0, 1))
+        );
+        assert_eq!(
+            Some(MacAddr::new(0x01, 0x00, 0x5e, 0x01, 0x02, 0x03)),
+            MacAddr::from_ipv4_multicast(Ipv4Addr::new(239, 1, 2, 3))
+        );
+        // the high bit of the second octet is dropped.
+        assert_eq!(
+            Some(MacAddr::new(0x01, 0x00, 0x5e, 0x7f, 0xff, 0xfa)),
+            MacAddr::from_ipv4_multicast(Ipv4Addr::new(239, 255, 255, 250))
+        );
+        assert_eq!(
+            None,
+            MacAddr::from_ipv4_multicast(Ipv4Addr::new(192, 168, 0, 1))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn ipv6_multicast_to_mac_addr() {
+        assert_eq!(
+            Some(MacAddr::new(0x33, 0x33, 0x00, 0x00, 0x00, 0x01)),
+            MacAddr::from_ipv6_multicast("ff02::1".parse().unwrap())
+        );
+        assert_eq!(
+            Some(MacAddr::new(0x33, 0x33, 0xff, 0x12, 0x34, 0x56)),
+            MacAddr::from_ipv6_multicast("ff02::1:ff12:3456".parse().unwrap())
+        );
+        assert_eq!(
+            None,
+            MacAddr::from_ipv6_multicast("fe80::1".parse().unwrap())
+        );
+    }
+}
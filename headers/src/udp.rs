@@ -0,0 +1,105 @@
+/*
+* Copyright 2019 Comcast Cable Communications Management, LLC
+*
+* Licensed under the Apache License, Version 2.0 (the "License");
+* you may not use this file except in compliance with the License.
+* You may obtain a copy of the License at
+*
+* http://www.apache.org/licenses/LICENSE-2.0
+*
+* Unless required by applicable law or agreed to in writing, software
+* distributed under the License is distributed on an "AS IS" BASIS,
+* WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+* See the License for the specific language governing permissions and
+* limitations under the License.
+*
+* SPDX-License-Identifier: Apache-2.0
+*/
+
+use crate::types::u16be;
+
+/// UDP header.
+///
+/// The accessors convert the fields to and from network byte order. A
+/// checksum of 0 means the sender computed no checksum, the header does
+/// not map a computed checksum of 0 to `0xFFFF` on its own.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct UdpHeader {
+    src_port: u16be,
+    dst_port: u16be,
+    length: u16be,
+    checksum: u16be,
+}
+
+impl UdpHeader {
+    /// Returns the source port.
+    #[inline]
+    pub fn src_port(&self) -> u16 {
+        self.src_port.into()
+    }
+
+    /// Sets the source port.
+    #[inline]
+    pub fn set_src_port(&mut self, src_port: u16) {
+        self.src_port = src_port.into();
+    }
+
+    /// Returns the destination port.
+    #[inline]
+    pub fn dst_port(&self) -> u16 {
+        self.dst_port.into()
+    }
+
+    /// Sets the destination port.
+    #[inline]
+    pub fn set_dst_port(&mut self, dst_port: u16) {
+        self.dst_port = dst_port.into();
+    }
+
+    /// Returns the length in octets of this user datagram, including the
+    /// header and the data.
+    #[inline]
+    pub fn length(&self) -> u16 {
+        self.length.into()
+    }
+
+    /// Sets the length.
+    #[inline]
+    pub fn set_length(&mut self, length: u16) {
+        self.length = length.into();
+    }
+
+    /// Returns the checksum.
+    #[inline]
+    pub fn checksum(&self) -> u16 {
+        self.checksum.into()
+    }
+
+    /// Sets the checksum as is.
+    #[inline]
+    pub fn set_checksum(&mut self, checksum: u16) {
+        self.checksum = checksum.into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_of_udp_header() {
+        assert_eq!(8, core::mem::size_of::<UdpHeader>());
+    }
+
+    #[test]
+    fn udp_header_in_network_byte_order() {
+        let mut header = UdpHeader::default();
+        header.set_src_port(39376);
+        header.set_length(18);
+        assert_eq!(39376, header.src_port());
+        assert_eq!(18, header.length());
+        assert_eq!(u16be::from(39376), header.src_port);
+        assert_eq!(0x99d0, u16::from_be(header.src_port.0));
+    }
+}
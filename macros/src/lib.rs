@@ -41,9 +41,7 @@ pub fn derive_size_of(input: TokenStream) -> TokenStream {
 
     let expanded = quote! {
         impl #impl_generics SizeOf for #name #ty_generics #where_clause {
-            fn size_of() -> usize {
-                std::mem::size_of::<Self>()
-            }
+            const SIZE: usize = std::mem::size_of::<Self>();
         }
     };
 